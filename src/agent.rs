@@ -5,13 +5,44 @@ use super::csv_processor::{
     row_printer::RowPrinter,
 };
 use crate::{
-    cli::Cli,
-    cli_handler::*,
+    audio_cd::{
+        self,
+        media::{
+            MediaSource,
+            MediaStatus,
+        },
+        AudioFormat,
+        ItemTags,
+    },
+    cli::{
+        Cli,
+        Command,
+    },
+    cli_handler::{
+        drive_identity::DriveIdentity,
+        *,
+    },
+    config::Config,
     csv_processor::path_validator::{
         DirectoryStatus,
         PathValidationOptions,
         PathValidator,
     },
+    disc_image::{
+        self,
+        blu_ray::BluRay,
+        dvd_video::DvdVideo,
+    },
+    metadata::{
+        ExpectedContent,
+        ItemMetadata,
+        Provenance,
+    },
+    preflight::{
+        self,
+        Problem,
+    },
+    watch::LabelPattern,
 };
 use inquire::{
     Confirm,
@@ -19,13 +50,22 @@ use inquire::{
 };
 use log::info;
 use std::{
+    env,
     error::Error,
     fs,
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 // use tempfile::TempDir;
 
-fn get_cli_handler() -> Box<dyn CliHandler>
+fn get_platform_cli_handler() -> Box<dyn CliHandler>
 {
     #[cfg(target_os = "macos")]
     return Box::new(MacosCliHandler {});
@@ -34,9 +74,24 @@ fn get_cli_handler() -> Box<dyn CliHandler>
     return Box::new(LinuxCliHandler {});
 }
 
+/// The platform's handler, with any commands the configuration gives used in
+/// place of the platform's tools.
+fn get_cli_handler(config: &Config) -> Box<dyn CliHandler>
+{
+    let platform = get_platform_cli_handler();
+
+    match config.tools.is_empty() {
+        | true => platform,
+        | false => {
+            Box::new(ConfiguredCliHandler::new(config.tools.clone(), platform))
+        }
+    }
+}
+
 pub struct Agent
 {
     args: Cli,
+    config: Config,
     cli_handler: Box<dyn CliHandler>,
 }
 
@@ -44,9 +99,321 @@ impl Agent
 {
     pub fn new(args: Cli) -> Result<Agent, Box<dyn Error>>
     {
-        let cli_handler = get_cli_handler();
+        let config = match &args.config {
+            | Some(path) => Config::load(&PathBuf::from(path))?,
+            | None => Config::default(),
+        };
+        let cli_handler = get_cli_handler(&config);
+
+        Ok(Agent {
+            args,
+            config,
+            cli_handler,
+        })
+    }
+
+    /// Whether to import whichever disk is inserted, matched by its label,
+    /// rather than asking for each in turn.
+    pub fn watching(&self) -> bool
+    {
+        self.args.watch
+    }
+
+    /// What finds the identifier in a disk's label while watching, from the
+    /// config file.
+    pub fn label_pattern(&self) -> Option<&LabelPattern>
+    {
+        self.config.watch.label_pattern.as_ref()
+    }
+
+    /// The subcommand to run instead of the CSV import, if one was given.
+    pub fn command(&self) -> Option<Command>
+    {
+        self.args.command.clone()
+    }
+
+    pub fn run_command(
+        &self,
+        command: Command,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        match command {
+            | Command::ListImage {
+                image_path,
+                session,
+            } => self.list_image(&PathBuf::from(image_path), session),
+            | Command::FsInfo { image_path } => {
+                self.print_filesystem_report(&PathBuf::from(image_path))
+            }
+            | Command::DvdInfo { image_path } => {
+                self.print_dvd_video(&PathBuf::from(image_path))
+            }
+            | Command::BdInfo { image_path } => {
+                self.print_blu_ray(&PathBuf::from(image_path))
+            }
+            | Command::VerifyItem { item_path } => {
+                match self.verify_item(&PathBuf::from(&item_path))? {
+                    | true => Ok(()),
+                    | false => {
+                        Err(format!(
+                            "The file-system copy in {item_path} does not \
+                             match its ISO."
+                        )
+                        .into())
+                    }
+                }
+            }
+            | Command::SplitAudio {
+                cue_path,
+                audio_format,
+            } => {
+                let cue = PathBuf::from(cue_path);
+                let to = cue
+                    .parent()
+                    .map(PathBuf::from)
+                    .unwrap_or(PathBuf::from("."));
+
+                self.split_audio(&cue, &to, audio_format, &ItemTags::default())
+            }
+        }
+    }
+
+    pub fn list_image(
+        &self,
+        image_path: &PathBuf,
+        session: Option<usize>,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        image_path.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsNotDirectory,
+        ))?;
+
+        let volume = match session {
+            | Some(number) => {
+                let sessions = disc_image::read_iso9660_sessions(image_path)?;
+                let session = sessions
+                    .iter()
+                    .find(|s| s.number == number)
+                    .ok_or(format!(
+                        "{image_path:?} has {} ISO 9660 sessions, not \
+                         {number}.",
+                        sessions.len()
+                    ))?;
+
+                disc_image::open_iso9660_session(image_path, session.start)?
+            }
+            | None => disc_image::open_filesystem(image_path)?,
+        };
+
+        let mut volume = volume.ok_or(format!(
+            "No readable filesystem could be found in {image_path:?}."
+        ))?;
+
+        let entries = volume.entries()?;
+
+        println!(
+            "{} volume '{}':",
+            volume.filesystem_name(),
+            volume.volume_name()
+        );
+        println!("{}", disc_image::format_listing(&entries));
+
+        Ok(())
+    }
+
+    pub fn print_filesystem_report(
+        &self,
+        image_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        image_path.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsNotDirectory,
+        ))?;
+
+        let report = disc_image::probe::probe(image_path)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+
+        for warning in &report.warnings {
+            eprintln!("WARNING: {warning}");
+        }
+
+        Ok(())
+    }
+
+    pub fn print_dvd_video(
+        &self,
+        image_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        image_path.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsNotDirectory,
+        ))?;
+
+        let dvd = self
+            .read_dvd_video(image_path)?
+            .ok_or(format!("{image_path:?} has no VIDEO_TS/VIDEO_TS.IFO."))?;
+        println!("{}", serde_json::to_string_pretty(&dvd)?);
+
+        Ok(())
+    }
+
+    /// Records the titles and streams of a DVD-Video image in the item's
+    /// `metadata`, with a warning wherever they disagree with what its row
+    /// `expected`.  Other discs are left alone.
+    pub fn write_dvd_video(
+        &self,
+        image_path: &PathBuf,
+        metadata: &PathBuf,
+        expected: &ExpectedContent,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping DVD-Video analysis.");
+            return Ok(());
+        }
+
+        let Some(dvd) = self.read_dvd_video(image_path)? else {
+            info!("No DVD-Video structure found in {image_path:?}.");
+            return Ok(());
+        };
+
+        println!(
+            "Recording the {} titles of the DVD-Video in {metadata:?}.",
+            dvd.titles.len()
+        );
+
+        let warnings = disc_image::dvd_video::check_against(&dvd, expected);
+        for warning in &warnings {
+            println!("WARNING: {warning}");
+        }
+
+        ItemMetadata::update(metadata, |m| {
+            m.dvd_video = Some(dvd);
+            m.warnings.extend(warnings);
+        })
+    }
+
+    pub fn print_blu_ray(
+        &self,
+        image_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        image_path.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsNotDirectory,
+        ))?;
+
+        let blu_ray = self
+            .read_blu_ray(image_path)?
+            .ok_or(format!("{image_path:?} has no BDMV/index.bdmv."))?;
+        println!("{}", serde_json::to_string_pretty(&blu_ray)?);
+
+        Ok(())
+    }
+
+    /// Records the titles and playlists of a Blu-ray image in the item's
+    /// `metadata`.  Other discs are left alone.
+    pub fn write_blu_ray(
+        &self,
+        image_path: &PathBuf,
+        metadata: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping Blu-ray analysis.");
+            return Ok(());
+        }
+
+        let Some(blu_ray) = self.read_blu_ray(image_path)? else {
+            info!("No BDMV structure found in {image_path:?}.");
+            return Ok(());
+        };
+
+        println!(
+            "Recording the {} playlists of the Blu-ray in {metadata:?}.",
+            blu_ray.playlists.len()
+        );
+
+        ItemMetadata::update(metadata, |m| m.blu_ray = Some(blu_ray))
+    }
+
+    fn read_blu_ray(
+        &self,
+        image_path: &PathBuf,
+    ) -> Result<Option<BluRay>, Box<dyn Error>>
+    {
+        match disc_image::open_file_copy_volume(image_path)? {
+            | Some(mut volume) => {
+                disc_image::blu_ray::read_blu_ray(volume.as_mut())
+            }
+            | None => Ok(None),
+        }
+    }
+
+    /// Checks the running time of the titles or tracks recorded in the
+    /// item's `metadata` against the row's, and records a warning if they
+    /// are further apart than the tolerance.  `video_cd` says whether the
+    /// disc's MPEG tracks can be timed from their length, as a VCD's can.
+    pub fn check_running_time(
+        &self,
+        metadata: &PathBuf,
+        expected: &ExpectedContent,
+        video_cd: bool,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping running time check.");
+            return Ok(());
+        }
+
+        let running_times =
+            ItemMetadata::load(metadata)?.running_times(video_cd);
+        let tolerance = self.args.duration_tolerance;
+
+        match expected.check_running_time(&running_times, tolerance) {
+            | Some(warning) => {
+                println!("WARNING: {warning}");
+                ItemMetadata::update(metadata, |m| m.warnings.push(warning))
+            }
+            | None => Ok(()),
+        }
+    }
+
+    fn read_dvd_video(
+        &self,
+        image_path: &PathBuf,
+    ) -> Result<Option<DvdVideo>, Box<dyn Error>>
+    {
+        match disc_image::open_file_copy_volume(image_path)? {
+            | Some(mut volume) => {
+                disc_image::dvd_video::read_dvd_video(volume.as_mut())
+            }
+            | None => Ok(None),
+        }
+    }
+
+    /// Writes the filesystem detection report for the image to `to` as JSON.
+    pub fn write_filesystem_report(
+        &self,
+        image_path: &PathBuf,
+        to: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        println!("Writing filesystem report for {image_path:?} to {to:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping filesystem report.");
+            return Ok(());
+        }
 
-        Ok(Agent { args, cli_handler })
+        let report = disc_image::probe::probe(image_path)?;
+        fs::write(to, serde_json::to_string_pretty(&report)?)
+            .map_err(|e| format!("Could not write {to:?}: {e}"))?;
+
+        for warning in &report.warnings {
+            println!("WARNING: {warning}");
+        }
+
+        Ok(())
     }
 
     pub fn create_dir_or_prompt_if_exists(
@@ -216,23 +583,256 @@ impl Agent
         Ok(())
     }
 
-    pub fn select_rom_device(&self) -> Result<String, Box<dyn Error>>
+    /// The drives to image from, as given on the command line, or in the
+    /// config file, or else the one the user selects, each found as it is
+    /// named now.
+    pub fn select_rom_devices(
+        &self
+    ) -> Result<Vec<DriveIdentity>, Box<dyn Error>>
     {
-        match &self.args.rom_device {
-            | Some(d) => Ok(d.clone()),
-            | None => {
-                self.cli_handler.select_rom_device().map_err(|e| {
-                    format!("Error while selecting ROM device: {e}").into()
-                })
+        let selected = self.args.rom_devices.is_empty()
+            && self.config.rom_devices.is_empty();
+
+        let given = if !self.args.rom_devices.is_empty() {
+            self.args.rom_devices.clone()
+        } else if !self.config.rom_devices.is_empty() {
+            info!("Using the ROM devices from the config file.");
+            self.config.rom_devices.clone()
+        } else {
+            let dev = self.cli_handler.select_rom_device().map_err(|e| {
+                format!("Error while selecting ROM device: {e}")
+            })?;
+
+            vec![dev]
+        };
+
+        let mut drives: Vec<DriveIdentity> = Vec::new();
+        for dev in &given {
+            let drive = self.cli_handler.identify_drive(dev).map_err(|e| {
+                format!("Error while finding ROM device {dev}: {e}")
+            })?;
+
+            if let Some(other) = drives.iter().find(|d| d.name == drive.name) {
+                return Err(format!(
+                    "ROM devices {} and {dev} are both {}.",
+                    other.given, drive.name
+                )
+                .into());
+            }
+
+            drives.push(drive);
+        }
+
+        for drive in &drives {
+            let stable_name = drive.stable_name();
+
+            if selected && stable_name != drive.name {
+                println!(
+                    "To use {} in later sessions, even if its name changes, \
+                     give {stable_name} as the ROM Device, or list it in the \
+                     config file's rom_devices.",
+                    drive.name
+                );
+            }
+        }
+
+        Ok(drives)
+    }
+
+    /// Checks, before the first item, that each of `drives` can be read,
+    /// that files can be created in `output`, and that the external programs
+    /// the handler runs can be found, printing how to fix each problem.
+    pub fn preflight(
+        &self,
+        output: &Path,
+        drives: &[DriveIdentity],
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let mut problems = preflight::check_tools(
+            &self.cli_handler.required_tools(),
+            env::var_os("PATH").as_deref(),
+        );
+        problems.extend(preflight::check_writable(output));
+        for drive in drives {
+            let (dev_path, required) = self.cli_handler.raw_device(&drive.name);
+            problems.extend(preflight::check_device(&dev_path).map(|p| {
+                Problem {
+                    blocking: required,
+                    ..p
+                }
+            }));
+        }
+
+        for problem in &problems {
+            match problem.blocking {
+                | true => println!("Problem: {problem}"),
+                | false => println!("Warning: {problem}"),
             }
         }
+
+        let blocking = problems.iter().filter(|p| p.blocking).count();
+        if blocking == 0 {
+            return Ok(());
+        }
+        if self.args.dry_run {
+            println!("Dry Run: Continuing despite {blocking} problem(s).");
+            return Ok(());
+        }
+
+        Err(format!(
+            "{blocking} problem(s) must be fixed before importing.  See above \
+             for how."
+        )
+        .into())
+    }
+
+    /// Records the drive the item was read in, in its `metadata`.
+    pub fn write_provenance(
+        &self,
+        metadata: &PathBuf,
+        drive: &DriveIdentity,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping provenance.");
+            return Ok(());
+        }
+
+        ItemMetadata::update(metadata, |m| {
+            m.provenance = Some(Provenance {
+                drive: drive.clone(),
+            })
+        })
     }
 
-    pub fn eject_tray(&self) -> Result<(), Box<dyn Error>>
+    /// Ejects the media in `dev`, first unlocking its door in case an
+    /// earlier run left it locked.
+    pub fn eject_tray(
+        &self,
+        dev: &String,
+    ) -> Result<(), Box<dyn Error>>
     {
+        info!("Ejecting {dev}.");
+
+        if let Err(e) = self.cli_handler.unlock_door(dev) {
+            info!("Could not unlock the door of {dev}: {e}");
+        }
+
         self.cli_handler
-            .eject_tray()
-            .map_err(|e| format!("Error while ejecting: {e}").into())
+            .eject(dev)
+            .map_err(|e| format!("Error while ejecting {dev}: {e}").into())
+    }
+
+    pub fn close_tray(
+        &self,
+        dev: &String,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        info!("Closing the tray of {dev}.");
+
+        self.cli_handler.close_tray(dev).map_err(|e| {
+            format!("Error while closing the tray of {dev}: {e}").into()
+        })
+    }
+
+    /// Locks the door of `dev` until the returned guard is dropped.  Drives
+    /// without a lockable door are imaged anyway, with a warning.
+    pub fn lock_door<'a>(
+        &'a self,
+        dev: &'a String,
+    ) -> DoorLock<'a>
+    {
+        let mut lock = DoorLock {
+            cli_handler: self.cli_handler.as_ref(),
+            dev: None,
+        };
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping locking the door of {dev}.");
+            return lock;
+        }
+
+        match self.cli_handler.lock_door(dev) {
+            | Ok(()) => lock.dev = Some(dev),
+            | Err(e) => {
+                println!("WARNING: Could not lock the door of {dev}: {e}")
+            }
+        }
+
+        lock
+    }
+
+    /// What the drive `dev` reports about the media in it, asking it once.
+    /// A drive which cannot be asked reports `Unknown`.
+    pub fn media_status(
+        &self,
+        dev: &String,
+    ) -> MediaStatus
+    {
+        let mut drive = ReopeningDrive {
+            cli_handler: self.cli_handler.as_ref(),
+            dev,
+        };
+
+        drive.media_status().unwrap_or_else(|e| {
+            info!("Could not read the status of {dev}: {e}");
+            MediaStatus::Unknown
+        })
+    }
+
+    /// Whether the drive `dev` holds media which is ready to read, asking it
+    /// once.
+    pub fn media_ready(
+        &self,
+        dev: &String,
+    ) -> bool
+    {
+        self.media_status(dev) == MediaStatus::Ready
+    }
+
+    /// Waits until the drive `dev` holds media which is ready to read, for
+    /// up to `--media-timeout` seconds.
+    pub fn wait_for_media(
+        &self,
+        dev: &String,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let mut drive = ReopeningDrive {
+            cli_handler: self.cli_handler.as_ref(),
+            dev,
+        };
+
+        audio_cd::media::wait_for_media(
+            &mut drive,
+            self.media_timeout(),
+            audio_cd::media::POLL_INTERVAL,
+        )
+    }
+
+    /// The label of the media in `dev`, waiting up to `--media-timeout`
+    /// seconds for the system to have read it.
+    pub fn wait_for_rom_device_label(
+        &self,
+        dev: &String,
+    ) -> Result<String, Box<dyn Error>>
+    {
+        let deadline = Instant::now() + self.media_timeout();
+
+        loop {
+            match self.get_rom_device_label(dev) {
+                | Ok(label) => return Ok(label),
+                | Err(e) if Instant::now() >= deadline => return Err(e),
+                | Err(e) => {
+                    info!("Waiting for the disk label: {e}");
+                    thread::sleep(audio_cd::media::POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    fn media_timeout(&self) -> Duration
+    {
+        Duration::from_secs(self.args.media_timeout)
     }
 
     pub fn get_rom_device_label(
@@ -249,6 +849,7 @@ impl Agent
 
     pub fn dump_iso(
         &self,
+        dev: &str,
         from: &PathBuf,
         to: &PathBuf,
     ) -> Result<(), Box<dyn Error>>
@@ -261,91 +862,660 @@ impl Agent
         }
 
         println!("Please wait...");
-        self.cli_handler.dump_iso(from, to)?;
+        self.cli_handler.dump_iso(dev, from, to)?;
         println!("ISO dump finished.");
 
         Ok(())
     }
 
-    pub fn fix_permissions(
+    /// Whether the disc in `dev` is an audio CD, judged from its table of
+    /// contents.  A drive which cannot be opened is taken to hold a data disc.
+    pub fn is_audio_disc(
         &self,
-        in_path: &PathBuf,
+        dev: &String,
+    ) -> bool
+    {
+        match self.cli_handler.open_cd_drive(dev) {
+            | Ok(mut drive) => audio_cd::is_audio_disc(drive.as_mut()),
+            | Err(e) => {
+                info!("Could not read the table of contents of {dev}: {e}");
+                false
+            }
+        }
+    }
+
+    /// Whether the disc in `dev` has both audio and data tracks, as an
+    /// Enhanced CD does.
+    pub fn is_mixed_disc(
+        &self,
+        dev: &String,
+    ) -> bool
+    {
+        match self.cli_handler.open_cd_drive(dev) {
+            | Ok(mut drive) => audio_cd::is_mixed_disc(drive.as_mut()),
+            | Err(e) => {
+                info!("Could not read the table of contents of {dev}: {e}");
+                false
+            }
+        }
+    }
+
+    /// Why the data disc in `dev`, visible at `mount_point`, must be read as
+    /// raw sectors rather than imaged as an ISO, if it must.  Video CDs, and
+    /// any disc with Mode 2 Form 2 sectors, lose data in a 2048-byte image.
+    pub fn raw_capture_reason(
+        &self,
+        dev: &String,
+        mount_point: &PathBuf,
+    ) -> Option<String>
+    {
+        if let Some(kind) = self.video_cd_kind(dev, mount_point) {
+            return Some(format!(
+                "This disc is a {kind}, whose MPEG tracks are Mode 2 Form 2 \
+                 sectors."
+            ));
+        }
+
+        match self.cli_handler.open_cd_drive(dev) {
+            | Ok(mut drive) => audio_cd::raw_capture_reason(drive.as_mut()),
+            | Err(e) => {
+                info!("Could not read the table of contents of {dev}: {e}");
+                None
+            }
+        }
+    }
+
+    /// The Video CD standard ("VCD" or "SVCD") of the disc in `dev`, visible
+    /// at `mount_point`, if it is a Video CD.
+    pub fn video_cd_kind(
+        &self,
+        dev: &String,
+        mount_point: &PathBuf,
+    ) -> Option<&'static str>
+    {
+        match mount_point.is_dir() {
+            | true => disc_image::video_cd::kind_in_directory(mount_point),
+            | false => {
+                disc_image::video_cd::kind_in_image(mount_point).unwrap_or_else(
+                    |e| {
+                        info!("Could not look for a Video CD on {dev}: {e}");
+                        None
+                    },
+                )
+            }
+        }
+    }
+
+    /// Writes the CD in `dev` to `bin` as raw 2352-byte sectors, with a cue
+    /// sheet at `cue`, and records its track layout in the item's
+    /// `metadata`.  Used for audio and mixed-mode CDs, and for data discs
+    /// which cannot be imaged as an ISO.
+    pub fn rip_raw(
+        &self,
+        dev: &String,
+        bin: &PathBuf,
+        cue: &PathBuf,
+        metadata: &PathBuf,
     ) -> Result<(), Box<dyn Error>>
     {
-        info!("Fixing permissions in {in_path:?}.");
+        println!("Reading raw sectors from {dev} to {bin:?}.");
 
         if self.args.dry_run {
-            info!("Dry run: Skipping permissions fix.");
+            info!("Dry run: Skipping raw CD read.");
             return Ok(());
         }
 
-        self.cli_handler.fix_permissions(in_path)
+        let mut drive = self.cli_handler.open_cd_drive(dev)?;
+
+        println!("Please wait...");
+        let report = audio_cd::rip(drive.as_mut(), bin, cue)?;
+
+        println!(
+            "Raw CD read finished: {} tracks, {} sectors.",
+            report.toc.tracks.len(),
+            report.sectors
+        );
+
+        if !report.unreadable.is_empty() {
+            println!(
+                "Warning: {} sectors could not be read and were written as \
+                 silence or zeros: {:?}",
+                report.unreadable.len(),
+                report.unreadable
+            );
+        }
+
+        ItemMetadata::update(metadata, |m| {
+            m.track_layout = Some(report.toc.layout())
+        })?;
+
+        Ok(())
     }
 
-    pub fn copy_rec(
+    /// Writes the data track of the BIN/CUE at `cue` to `to` as an image
+    /// which the file copy can read.
+    pub fn extract_data_track(
         &self,
-        from: &PathBuf,
+        cue: &PathBuf,
+        to: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        println!("Extracting the data track of {cue:?} to {to:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping data track extraction.");
+            return Ok(());
+        }
+
+        let report = audio_cd::data_track::extract_data_track(cue, to)?
+            .ok_or(format!("{cue:?} lists no data track."))?;
+
+        println!(
+            "Data track {} extracted: {} sectors.",
+            report.track, report.sectors
+        );
+
+        if !report.unconverted.is_empty() {
+            println!(
+                "Warning: {} sectors hold no 2048 byte data and were written \
+                 as zeros: {:?}",
+                report.unconverted.len(),
+                report.unconverted
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The format audio CD tracks are split into.
+    pub fn audio_format(&self) -> AudioFormat
+    {
+        self.args.audio_format
+    }
+
+    /// Records the AccurateRip and CTDB checksums and disc IDs of the BIN/CUE
+    /// rip at `cue`, of the disc in `dev`, in the item's metadata sidecar at
+    /// `metadata`.  The disc IDs come from the drive's table of contents,
+    /// as the cue of an Enhanced CD holds only its first session.
+    pub fn write_audio_checksums(
+        &self,
+        dev: &str,
+        cue: &PathBuf,
+        metadata: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        println!("Writing audio checksums to {metadata:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping audio checksums.");
+            return Ok(());
+        }
+
+        let disc = self.cli_handler.open_cd_drive(dev)?.read_toc()?;
+        let checksums = audio_cd::checksums::disc_checksums(cue, &disc)?;
+
+        println!("AccurateRip ID: {}", checksums.accuraterip_id);
+        for track in &checksums.tracks {
+            println!(
+                "Track {:02}: AccurateRip v1 {}, v2 {}",
+                track.number, track.accuraterip_v1, track.accuraterip_v2
+            );
+        }
+        println!("CTDB CRC: {}", checksums.ctdb_crc);
+
+        ItemMetadata::update(metadata, |m| m.audio_checksums = Some(checksums))
+    }
+
+    /// Writes each audio track of the BIN/CUE at `cue` to `to` as a WAV or
+    /// FLAC file, named after the cue sheet with the track number appended.
+    pub fn split_audio(
+        &self,
+        cue: &PathBuf,
         to: &PathBuf,
+        format: AudioFormat,
+        tags: &ItemTags,
     ) -> Result<(), Box<dyn Error>>
     {
-        println!("Copying files from {from:?} to {to:?}.");
+        println!("Splitting {cue:?} into tracks in {to:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping audio track split.");
+            return Ok(());
+        }
+
+        cue.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsNotDirectory,
+        ))?;
+
+        let prefix = cue
+            .file_stem()
+            .ok_or(format!("{cue:?} has no file name."))?
+            .to_string_lossy();
+
+        let written =
+            audio_cd::split::split_tracks(cue, to, &prefix, format, tags)?;
+
+        println!("Wrote {} track files.", written.len());
+
+        Ok(())
+    }
+
+    /// Fills `to` with the files on the media, extracted from the image
+    /// with their recorded modification times.  Files which cannot be read
+    /// are reported, and do not stop the rest of the extraction.  Returns
+    /// `false` if the image holds no filesystem which can be extracted.
+    pub fn copy_files(
+        &self,
+        image_path: &PathBuf,
+        to: &PathBuf,
+    ) -> Result<bool, Box<dyn Error>>
+    {
+        println!("Extracting files from {image_path:?} to {to:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping file extraction.");
+            return Ok(true);
+        }
+
+        let mut volume = match disc_image::open_file_copy_volume(image_path)? {
+            | Some(v) => v,
+            | None => {
+                println!(
+                    "No ISO 9660 or FAT filesystem was found in \
+                     {image_path:?}, so no files were extracted."
+                );
+                return Ok(false);
+            }
+        };
 
+        println!("Reading the {} volume.", volume.filesystem_name());
         println!("Please wait...");
+        let failures = disc_image::extract(volume.as_mut(), to)?;
+        print_extract_failures(&failures);
+        println!("File extraction finished.");
+
+        Ok(true)
+    }
+
+    /// Fills `to` with the files of the media in `dev` as the system mounts
+    /// it, for filesystems, such as UDF, which cannot be extracted from the
+    /// image.  The media is mounted if it is not already.
+    pub fn copy_mounted_files(
+        &self,
+        dev: &String,
+        to: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        println!("Copying files from the mounted media in {dev} to {to:?}.");
+
         if self.args.dry_run {
-            info!("Dry run: Skipping Copy.");
+            info!("Dry run: Skipping file copy.");
             return Ok(());
         }
 
-        self.cli_handler.copy_rec(from, to)?;
+        let from = match self.cli_handler.get_mount_directory(dev)? {
+            | Some(dir) => dir,
+            | None => {
+                println!("{dev} is not mounted.  Mounting it...");
+                self.cli_handler.mount_media(dev).map_err(|e| {
+                    format!(
+                        "The media in {dev} is not mounted, and mounting it \
+                         failed: {e}"
+                    )
+                })?
+            }
+        };
+
+        println!("Copying from {from:?}.");
+        println!("Please wait...");
+        if !self.cli_handler.copy_files(&from, to)? {
+            let failures = disc_image::copy_tree(&from, to)?;
+            print_extract_failures(&failures);
+        }
         println!("File copy finished.");
 
         Ok(())
     }
+
+    /// Lists the files of each session of a multisession ISO before the last,
+    /// whose files the file-system copy already holds, to
+    /// `<image name>_session<N>.txt` in `to`.  With `--extract-sessions`, the
+    /// files are also extracted to `<image name>_session<N>`.
+    pub fn copy_earlier_sessions(
+        &self,
+        image_path: &PathBuf,
+        to: &Path,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping earlier sessions.");
+            return Ok(());
+        }
+
+        let sessions = disc_image::read_iso9660_sessions(image_path)?;
+        if sessions.len() < 2 {
+            return Ok(());
+        }
+
+        let name = image_path
+            .file_stem()
+            .ok_or(format!("{image_path:?} has no file name."))?
+            .to_string_lossy()
+            .to_string();
+
+        println!(
+            "{image_path:?} has {} sessions.  Recording the files of the \
+             earlier ones.",
+            sessions.len()
+        );
+
+        for session in &sessions[..sessions.len() - 1] {
+            let mut volume =
+                disc_image::open_iso9660_session(image_path, session.start)?
+                    .ok_or(format!(
+                        "Session {} of {image_path:?} has no volume.",
+                        session.number
+                    ))?;
+
+            let listing =
+                to.join(format!("{name}_session{}.txt", session.number));
+            fs::write(
+                &listing,
+                disc_image::format_listing(&volume.entries()?) + "\n",
+            )
+            .map_err(|e| format!("Could not write {listing:?}: {e}"))?;
+
+            if self.args.extract_sessions {
+                let dir = to.join(format!("{name}_session{}", session.number));
+
+                println!("Extracting session {} to {dir:?}.", session.number);
+                let failures = disc_image::extract(volume.as_mut(), &dir)?;
+                print_extract_failures(&failures);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compares the file-system copy in an item's folder with the files in
+    /// the item's ISO (`<folder name>.iso`), and prints what differs.  The
+    /// ISO, its sidecars and the Mac volume folder are not counted as extra
+    /// files.  Returns whether the copy matched.
+    pub fn verify_item(
+        &self,
+        item_path: &PathBuf,
+    ) -> Result<bool, Box<dyn Error>>
+    {
+        item_path.validate_path(PathValidationOptions::Exists(
+            DirectoryStatus::IsDirectory,
+        ))?;
+
+        let name = item_path
+            .file_name()
+            .ok_or(format!("{item_path:?} has no folder name."))?
+            .to_string_lossy()
+            .to_string();
+
+        let mut image_path = item_path.clone();
+        image_path.push(format!("{name}.iso"));
+
+        println!(
+            "Verifying the files in {item_path:?} against {image_path:?}."
+        );
+
+        let mut volume = disc_image::open_file_copy_volume(&image_path)?
+            .ok_or(format!(
+                "No ISO 9660 or FAT filesystem could be found in \
+                 {image_path:?}."
+            ))?;
+
+        let is_item_file = |n: &str| {
+            n.strip_prefix(&name)
+                .is_some_and(|rest| rest.starts_with(['.', '_']))
+        };
+
+        let report = disc_image::verify::verify(
+            volume.as_mut(),
+            item_path,
+            &is_item_file,
+        )?;
+
+        println!("Verification finished: {report}");
+
+        Ok(report.is_ok())
+    }
+
+    /// Verifies a freshly extracted file-system copy, warning rather than
+    /// failing when it does not match (or cannot be checked) so the rest of
+    /// the import can go on.
+    pub fn verify_files(
+        &self,
+        item_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping verification.");
+            return Ok(());
+        }
+
+        match self.verify_item(item_path) {
+            | Ok(true) => (),
+            | Ok(false) => {
+                println!(
+                    "Warning: the file-system copy in {item_path:?} does not \
+                     match its ISO.  See the differences listed above."
+                )
+            }
+            | Err(e) => {
+                println!("Warning: {item_path:?} could not be verified: {e}")
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn extract_mac_volume(
+        &self,
+        image_path: &PathBuf,
+        to: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        if self.args.dry_run {
+            info!("Dry run: Skipping Mac volume extraction.");
+            return Ok(());
+        }
+
+        let mut volume = match disc_image::open_mac_volume(image_path)? {
+            | Some(v) => v,
+            | None => {
+                info!("No HFS or HFS+ volume found in {image_path:?}.");
+                return Ok(());
+            }
+        };
+
+        println!(
+            "Extracting the {} volume from {image_path:?} to {to:?}.",
+            volume.filesystem_name()
+        );
+
+        println!("Please wait...");
+        let failures = disc_image::extract(volume.as_mut(), to)?;
+        print_extract_failures(&failures);
+        println!("Mac volume extraction finished.");
+
+        Ok(())
+    }
+
+    pub fn fix_permissions(
+        &self,
+        in_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        info!("Fixing permissions in {in_path:?}.");
+
+        if self.args.dry_run {
+            info!("Dry run: Skipping permissions fix.");
+            return Ok(());
+        }
+
+        self.cli_handler.fix_permissions(in_path)
+    }
+}
+
+/// A drive which is opened afresh each time its status is asked for, as a
+/// device node may only exist, or only report its media, while the drive
+/// holds a disc.  A drive which cannot be opened holds no media yet.
+struct ReopeningDrive<'a>
+{
+    cli_handler: &'a dyn CliHandler,
+    dev: &'a String,
+}
+
+/// Keeps the door of a drive locked until it is dropped, so that the disc
+/// cannot be ejected part way through imaging, even when imaging fails.
+pub struct DoorLock<'a>
+{
+    cli_handler: &'a dyn CliHandler,
+    dev: Option<&'a String>,
+}
+
+impl Drop for DoorLock<'_>
+{
+    fn drop(&mut self)
+    {
+        if let Some(dev) = self.dev {
+            if let Err(e) = self.cli_handler.unlock_door(dev) {
+                println!("WARNING: Could not unlock the door of {dev}: {e}");
+            }
+        }
+    }
+}
+
+impl MediaSource for ReopeningDrive<'_>
+{
+    fn media_status(&mut self) -> Result<MediaStatus, Box<dyn Error>>
+    {
+        match self.cli_handler.open_cd_drive(self.dev) {
+            | Ok(mut drive) => drive.media_status(),
+            | Err(e) => {
+                info!("Could not open {}: {e}", self.dev);
+                Ok(MediaStatus::NoDisc)
+            }
+        }
+    }
+}
+
+/// Lists the files which could not be extracted, if there were any.
+fn print_extract_failures(failures: &[disc_image::ExtractFailure])
+{
+    if failures.is_empty() {
+        return;
+    }
+
+    println!("{} files could not be extracted:", failures.len());
+    for failure in failures {
+        println!("  {:?}: {}", failure.path, failure.error);
+    }
 }
 
 #[cfg(test)]
 mod tests
 {
     use super::*;
+    use crate::disc_image::iso9660::tests::{
+        build_iso,
+        build_multisession,
+    };
+    use clap::Parser;
+    use tempfile::TempDir;
 
     #[test]
-    fn test_cli_handler_copy_single_file()
+    fn test_copy_files_from_iso()
     {
-        let from = PathBuf::from("./demo/out_exists.csv");
-        assert!(from.exists());
-        assert!(!from.is_dir());
+        let out = TempDir::new().unwrap();
+        let image = out.path().join("item.iso");
+        fs::write(&image, build_iso(true, false)).unwrap();
+
+        let to = out.path().join("item");
+        let agent = Agent::new(Cli::parse_from(["carroh"])).unwrap();
+
+        agent.copy_files(&image, &to).unwrap();
+
+        assert_eq!(
+            "hello",
+            fs::read_to_string(to.join("Read Me.txt")).unwrap()
+        );
+        assert_eq!(
+            "notes",
+            fs::read_to_string(to.join("Docs/Notes.txt")).unwrap()
+        );
+    }
 
-        let to = PathBuf::from("./demo/out/out_exists_copy.csv");
-        assert!(!to.exists());
+    #[test]
+    fn test_verify_item()
+    {
+        let out = TempDir::new().unwrap();
+        let item = out.path().join("CR001_DISC");
+        fs::create_dir(&item).unwrap();
+        fs::write(item.join("CR001_DISC.iso"), build_iso(true, false)).unwrap();
+        fs::write(item.join("CR001_DISC.fsinfo.json"), "{}").unwrap();
 
-        let clih = get_cli_handler();
+        let agent = Agent::new(Cli::parse_from(["carroh"])).unwrap();
+        agent
+            .copy_files(&item.join("CR001_DISC.iso"), &item)
+            .unwrap();
 
-        clih.copy_rec(&from, &to).unwrap();
-        assert!(to.exists());
-        assert!(!to.is_dir());
+        assert!(agent.verify_item(&item).unwrap());
 
-        fs::remove_file(to).unwrap();
+        fs::write(item.join("Read Me.txt"), "changed").unwrap();
+        assert!(!agent.verify_item(&item).unwrap());
     }
 
     #[test]
-    fn test_cli_handler_copy_dir()
+    fn test_copy_earlier_sessions()
     {
-        let from = PathBuf::from("./demo/ram_disk_template_contents");
-        assert!(from.exists());
-        assert!(from.is_dir());
-
-        let to = PathBuf::from("./demo/out/ram_disk_template_contents");
-        assert!(!to.exists());
+        let out = TempDir::new().unwrap();
+        let item = out.path().join("CR001_DISC_2");
+        fs::create_dir(&item).unwrap();
+        let image = item.join("CR001_DISC_2.iso");
+        fs::write(&image, build_multisession()).unwrap();
+
+        let agent =
+            Agent::new(Cli::parse_from(["carroh", "--extract-sessions"]))
+                .unwrap();
+        agent.copy_files(&image, &item).unwrap();
+        agent.copy_earlier_sessions(&image, &item).unwrap();
+
+        assert_eq!("new", fs::read_to_string(item.join("NEW.TXT")).unwrap());
+        assert!(!item.join("DOCS").exists());
+        assert!(fs::read_to_string(item.join("CR001_DISC_2_session1.txt"))
+            .unwrap()
+            .contains("DOCS/NOTES.TXT"));
+        assert_eq!(
+            "notes",
+            fs::read_to_string(
+                item.join("CR001_DISC_2_session1/DOCS/NOTES.TXT")
+            )
+            .unwrap()
+        );
+        assert!(!item.join("CR001_DISC_2_session2.txt").exists());
+        assert!(agent.verify_item(&item).unwrap());
+    }
 
-        let clih = get_cli_handler();
+    #[test]
+    fn test_copy_files_dry_run()
+    {
+        let out = TempDir::new().unwrap();
+        let image = out.path().join("item.iso");
+        fs::write(&image, build_iso(true, false)).unwrap();
 
-        clih.copy_rec(&from, &to).unwrap();
-        assert!(to.exists());
-        assert!(to.is_dir());
+        let to = out.path().join("item");
+        let agent =
+            Agent::new(Cli::parse_from(["carroh", "--dry-run"])).unwrap();
 
-        assert_eq!(1, to.read_dir().unwrap().collect::<Vec<_>>().len());
+        agent.copy_files(&image, &to).unwrap();
 
-        fs::remove_dir_all(to).unwrap();
+        assert!(!to.exists());
     }
 
     // #[test]
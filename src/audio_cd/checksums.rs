@@ -0,0 +1,482 @@
+//! Checksums of ripped audio which can be compared against the AccurateRip
+//! and CUETools (CTDB) databases, and disc IDs from the table of contents for
+//! looking discs up in them (and in FreeDB and MusicBrainz).
+
+use super::{
+    cue::parse_cue_sheet,
+    toc::{
+        Toc,
+        TocTrack,
+        CONTROL_DATA,
+        FRAMES_PER_SECOND,
+        LEAD_IN_FRAMES,
+        RAW_SECTOR_SIZE,
+    },
+};
+use base64::{
+    engine::general_purpose::STANDARD,
+    Engine,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha1::{
+    Digest,
+    Sha1,
+};
+use std::{
+    error::Error,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        BufReader,
+        Read,
+        Seek,
+        SeekFrom,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Bytes read from the BIN at once.
+const READ_CHUNK_SIZE: usize = 75 * RAW_SECTOR_SIZE;
+
+/// Stereo samples in a sector.
+const SAMPLES_PER_SECTOR: u64 = RAW_SECTOR_SIZE as u64 / 4;
+
+/// AccurateRip leaves out the first five sectors of the first track and the
+/// last five of the last, which drives with different offsets cannot all
+/// read.
+const ACCURATERIP_SKIPPED_SAMPLES: u64 = 5 * SAMPLES_PER_SECTOR;
+
+/// The CTDB CRC leaves out the first and last ten sectors of the disc.
+const CTDB_SKIPPED_BYTES: u64 = 10 * RAW_SECTOR_SIZE as u64;
+
+/// Checksums and disc IDs for an audio rip.  Checksums are hexadecimal, as
+/// the databases and ripping logs show them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscChecksums
+{
+    /// As used in AccurateRip database paths:
+    /// `dBAR-<tracks>-<id 1>-<id 2>-<FreeDB id>`.
+    pub accuraterip_id: String,
+    pub freedb_id: String,
+    pub musicbrainz_id: String,
+    pub ctdb_toc_id: String,
+    /// CRC-32 of the disc's audio, as stored by CTDB.
+    pub ctdb_crc: String,
+    pub tracks: Vec<TrackChecksums>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackChecksums
+{
+    pub number: u8,
+    pub accuraterip_v1: String,
+    pub accuraterip_v2: String,
+}
+
+/// The MusicBrainz and CTDB variant of base64, which is safe in URLs.
+fn url_base64(bytes: &[u8]) -> String
+{
+    STANDARD
+        .encode(bytes)
+        .replace('+', ".")
+        .replace('/', "_")
+        .replace('=', "-")
+}
+
+/// The AccurateRip disc ID, as `dBAR-<tracks>-<id 1>-<id 2>-<FreeDB id>`.
+pub fn accuraterip_id(toc: &Toc) -> String
+{
+    let (mut id1, mut id2) = (0u32, 0u32);
+
+    for (i, track) in toc.tracks.iter().enumerate() {
+        id1 = id1.wrapping_add(track.start);
+        id2 = id2.wrapping_add(track.start.max(1).wrapping_mul(i as u32 + 1));
+    }
+
+    id1 = id1.wrapping_add(toc.lead_out);
+    id2 = id2
+        .wrapping_add(toc.lead_out.wrapping_mul(toc.tracks.len() as u32 + 1));
+
+    format!(
+        "dBAR-{:03}-{id1:08x}-{id2:08x}-{}",
+        toc.tracks.len(),
+        freedb_id(toc)
+    )
+}
+
+/// The FreeDB (CDDB) disc ID.
+pub fn freedb_id(toc: &Toc) -> String
+{
+    let seconds = |lba: u32| (lba + LEAD_IN_FRAMES) / FRAMES_PER_SECOND;
+    let digit_sum = |mut n: u32| {
+        let mut sum = 0;
+        while n > 0 {
+            sum += n % 10;
+            n /= 10;
+        }
+        sum
+    };
+
+    let n: u32 = toc.tracks.iter().map(|t| digit_sum(seconds(t.start))).sum();
+    let first = toc.tracks.first().map(|t| seconds(t.start)).unwrap_or(0);
+    let length = seconds(toc.lead_out) - first;
+
+    format!(
+        "{:08x}",
+        (n % 0xFF) << 24 | length << 8 | toc.tracks.len() as u32
+    )
+}
+
+/// The MusicBrainz disc ID.
+pub fn musicbrainz_id(toc: &Toc) -> String
+{
+    let first = toc.tracks.first().map(|t| t.number).unwrap_or(1);
+    let last = toc.tracks.last().map(|t| t.number).unwrap_or(0);
+
+    let mut offsets = [0; 100];
+    offsets[0] = toc.lead_out + LEAD_IN_FRAMES;
+    for t in &toc.tracks {
+        offsets[t.number as usize] = t.start + LEAD_IN_FRAMES;
+    }
+
+    let text = format!("{first:02X}{last:02X}")
+        + &offsets
+            .iter()
+            .map(|o| format!("{o:08X}"))
+            .collect::<String>();
+
+    url_base64(&Sha1::digest(text.as_bytes()))
+}
+
+/// The CUETools database TOC ID, from the audio tracks' positions relative
+/// to the first.
+pub fn ctdb_toc_id(toc: &Toc) -> String
+{
+    let audio: Vec<&TocTrack> =
+        toc.tracks.iter().filter(|t| t.is_audio()).collect();
+    let first = audio.first().map(|t| t.start).unwrap_or(0);
+    let end = audio_end(toc);
+
+    let mut text: String = audio
+        .iter()
+        .skip(1)
+        .map(|t| format!("{:08X}", t.start - first))
+        .collect();
+    text += &format!("{:08X}", end - first);
+    text += &"0".repeat((100 - audio.len()) * 8);
+
+    url_base64(&Sha1::digest(text.as_bytes()))
+}
+
+/// The end of the last audio track.
+fn audio_end(toc: &Toc) -> u32
+{
+    toc.tracks
+        .iter()
+        .rposition(|t| t.is_audio())
+        .map(|i| toc.track_end(i))
+        .unwrap_or(toc.lead_out)
+}
+
+/// The AccurateRip v1 and v2 CRCs of a track's samples read from `from`.
+fn accuraterip_crcs(
+    from: &mut impl Read,
+    samples: u64,
+    first: bool,
+    last: bool,
+) -> Result<(u32, u32), Box<dyn Error>>
+{
+    let check_from = match first {
+        | true => ACCURATERIP_SKIPPED_SAMPLES,
+        | false => 0,
+    };
+    let check_to = match last {
+        | true => samples.saturating_sub(ACCURATERIP_SKIPPED_SAMPLES),
+        | false => samples,
+    };
+
+    let (mut v1, mut v2) = (0u32, 0u32);
+    let mut buffer = vec![0; READ_CHUNK_SIZE];
+    let mut position = 1u64;
+
+    while position <= samples {
+        let count = (samples - position + 1).min(buffer.len() as u64 / 4);
+        let chunk = &mut buffer[..count as usize * 4];
+        from.read_exact(chunk)?;
+
+        for sample in chunk.chunks_exact(4) {
+            if (check_from..=check_to).contains(&position) {
+                let sample = u32::from_le_bytes([
+                    sample[0], sample[1], sample[2], sample[3],
+                ]);
+                let product = sample as u64 * position;
+
+                v1 = v1.wrapping_add(product as u32);
+                v2 = v2
+                    .wrapping_add(product as u32)
+                    .wrapping_add((product >> 32) as u32);
+            }
+            position += 1;
+        }
+    }
+
+    Ok((v1, v2))
+}
+
+/// The table of contents described by a cue sheet for a single BIN file
+/// holding a disc (or, on an Enhanced CD, its first session) from LBA 0, as
+/// written by `rip`, with the path of that file.  Files of later sessions,
+/// which hold no audio, are left out.
+fn toc_from_cue(cue: &PathBuf) -> Result<(Toc, PathBuf), Box<dyn Error>>
+{
+    let text = fs::read_to_string(cue)
+        .map_err(|e| format!("Could not read {cue:?}: {e}"))?;
+    let files = parse_cue_sheet(&text)?;
+
+    let [file, later @ ..] = &files[..] else {
+        return Err(format!("{cue:?} names no files.").into());
+    };
+
+    if later.iter().flat_map(|f| &f.tracks).any(|t| t.audio) {
+        return Err(format!(
+            "{cue:?} names several files holding audio; checksums need a \
+             single image of the disc's audio."
+        )
+        .into());
+    }
+
+    let bin = cue.parent().unwrap_or(Path::new(".")).join(&file.name);
+    let size = fs::metadata(&bin)
+        .map_err(|e| format!("Could not read {bin:?}: {e}"))?
+        .len();
+
+    let toc = Toc {
+        tracks: file
+            .tracks
+            .iter()
+            .map(|t| {
+                TocTrack {
+                    number: t.number,
+                    control: match t.audio {
+                        | true => 0,
+                        | false => CONTROL_DATA,
+                    },
+                    start: t.start,
+                    pregap_start: t.pregap_start,
+                    data_mode: None,
+                }
+            })
+            .collect(),
+        lead_out: (size / RAW_SECTOR_SIZE as u64) as u32,
+        session_lead_outs: vec![],
+    };
+    toc.validate()?;
+
+    Ok((toc, bin))
+}
+
+/// Computes the checksums and disc IDs of the BIN/CUE rip at `cue`, with
+/// `disc` the table of contents read from the drive.  The AccurateRip and
+/// FreeDB IDs count every track up to the disc's lead-out, which the cue of
+/// an Enhanced CD leaves out with its later sessions, so they are taken from
+/// `disc`.  The MusicBrainz and CTDB IDs count only the audio.
+pub fn disc_checksums(
+    cue: &PathBuf,
+    disc: &Toc,
+) -> Result<DiscChecksums, Box<dyn Error>>
+{
+    let (toc, bin) = toc_from_cue(cue)?;
+    let mut reader = BufReader::new(File::open(&bin)?);
+
+    let audio: Vec<usize> = (0..toc.tracks.len())
+        .filter(|i| toc.tracks[*i].is_audio())
+        .collect();
+
+    let mut tracks = vec![];
+
+    for (n, i) in audio.iter().enumerate() {
+        let track = &toc.tracks[*i];
+        // Unlike splitting, AccurateRip counts each track up to the next
+        // track's index 01.
+        let end = match toc.tracks.get(i + 1) {
+            | Some(next) if next.is_audio() => next.start,
+            | _ => audio_end(&toc),
+        };
+
+        reader.seek(SeekFrom::Start(
+            track.start as u64 * RAW_SECTOR_SIZE as u64,
+        ))?;
+        let (v1, v2) = accuraterip_crcs(
+            &mut reader,
+            (end - track.start) as u64 * SAMPLES_PER_SECTOR,
+            n == 0,
+            n == audio.len() - 1,
+        )?;
+
+        tracks.push(TrackChecksums {
+            number: track.number,
+            accuraterip_v1: format!("{v1:08X}"),
+            accuraterip_v2: format!("{v2:08X}"),
+        });
+    }
+
+    let first = audio.first().map(|i| toc.tracks[*i].start).unwrap_or(0);
+    let start = first as u64 * RAW_SECTOR_SIZE as u64 + CTDB_SKIPPED_BYTES;
+    let end = (audio_end(&toc) as u64 * RAW_SECTOR_SIZE as u64)
+        .saturating_sub(CTDB_SKIPPED_BYTES)
+        .max(start);
+
+    reader.seek(SeekFrom::Start(start))?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; READ_CHUNK_SIZE];
+    let mut remaining = end - start;
+    while remaining > 0 {
+        let chunk = &mut buffer[..(remaining as usize).min(READ_CHUNK_SIZE)];
+        reader.read_exact(chunk)?;
+        hasher.update(chunk);
+        remaining -= chunk.len() as u64;
+    }
+
+    Ok(DiscChecksums {
+        accuraterip_id: accuraterip_id(disc),
+        freedb_id: freedb_id(disc),
+        musicbrainz_id: musicbrainz_id(&toc),
+        ctdb_toc_id: ctdb_toc_id(&toc),
+        ctdb_crc: format!("{:08X}", hasher.finalize()),
+        tracks,
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::{
+        rip,
+        simulated::SimulatedDrive,
+        toc::TocSource,
+    };
+    use tempfile::TempDir;
+
+    /// The six track disc used as the example in MusicBrainz's description
+    /// of its disc IDs.
+    fn known_toc() -> Toc
+    {
+        let starts = [0, 15_213, 32_164, 46_442, 63_264, 80_339];
+
+        Toc {
+            tracks: starts
+                .iter()
+                .enumerate()
+                .map(|(i, start)| {
+                    TocTrack {
+                        number: i as u8 + 1,
+                        control: 0,
+                        start: *start,
+                        pregap_start: None,
+                        data_mode: None,
+                    }
+                })
+                .collect(),
+            lead_out: 95_312,
+            session_lead_outs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_disc_ids()
+    {
+        let toc = known_toc();
+
+        assert_eq!("49HHV7Eb8UKF3aQiNmu1GR8vKTY-", musicbrainz_id(&toc));
+        assert_eq!("dBAR-006-000513be-001b2231-3404f606", accuraterip_id(&toc));
+        assert_eq!("3404f606", freedb_id(&toc));
+        assert_eq!(28, ctdb_toc_id(&toc).len());
+    }
+
+    #[test]
+    fn test_accuraterip_crcs()
+    {
+        // Each sample's value is its index, so the CRC is a sum of
+        // index * position.
+        let data: Vec<u8> = (0..SAMPLES_PER_SECTOR * 20)
+            .flat_map(|i| (i as u32).to_le_bytes())
+            .collect();
+        let samples = SAMPLES_PER_SECTOR * 20;
+
+        let (v1, v2) =
+            accuraterip_crcs(&mut &data[..], samples, false, false).unwrap();
+
+        let expected: u64 = (0..samples).map(|i| i * (i + 1)).sum();
+        assert_eq!(expected as u32, v1);
+        assert_eq!(v1, v2);
+
+        // As the only track, the first and last five sectors are skipped.
+        let (first, _) =
+            accuraterip_crcs(&mut &data[..], samples, true, true).unwrap();
+        let expected: u64 = (ACCURATERIP_SKIPPED_SAMPLES - 1
+            ..samples - ACCURATERIP_SKIPPED_SAMPLES)
+            .map(|i| i * (i + 1))
+            .sum();
+        assert_eq!(expected as u32, first);
+    }
+
+    #[test]
+    fn test_disc_checksums()
+    {
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("a.bin");
+        let cue = out.path().join("a.cue");
+        let mut drive = SimulatedDrive::audio(
+            &[(1, 0, None), (2, 3000, Some(2850)), (3, 6000, None)],
+            9000,
+        );
+        let disc = drive.read_toc().unwrap();
+        rip(&mut drive, &bin, &cue).unwrap();
+
+        let checksums = disc_checksums(&cue, &disc).unwrap();
+
+        assert_eq!(3, checksums.tracks.len());
+        assert!(checksums.accuraterip_id.starts_with("dBAR-003-"));
+        assert_eq!(8, checksums.ctdb_crc.len());
+
+        // A re-rip of the same disc gives the same checksums, and a change to
+        // one track changes only that track's.
+        let mut damaged = drive.clone();
+        damaged.unreadable = vec![4000];
+        rip(&mut damaged, &bin, &cue).unwrap();
+        let rerip = disc_checksums(&cue, &disc).unwrap();
+
+        assert_eq!(checksums.tracks[0], rerip.tracks[0]);
+        assert_ne!(checksums.tracks[1], rerip.tracks[1]);
+        assert_eq!(checksums.tracks[2], rerip.tracks[2]);
+        assert_ne!(checksums.ctdb_crc, rerip.ctdb_crc);
+    }
+    #[test]
+    fn test_enhanced_cd_checksums()
+    {
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("a.bin");
+        let cue = out.path().join("a.cue");
+        let mut drive = SimulatedDrive::enhanced_cd();
+        let disc = drive.read_toc().unwrap();
+        rip(&mut drive, &bin, &cue).unwrap();
+
+        let checksums = disc_checksums(&cue, &disc).unwrap();
+
+        // The data track in the second session is counted in the
+        // AccurateRip and FreeDB IDs, but only the audio is checksummed.
+        assert_eq!(accuraterip_id(&disc), checksums.accuraterip_id);
+        assert!(checksums.accuraterip_id.starts_with("dBAR-003-"));
+        assert_eq!(freedb_id(&disc), checksums.freedb_id);
+        assert_eq!(2, checksums.tracks.len());
+    }
+}
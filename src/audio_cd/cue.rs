@@ -0,0 +1,431 @@
+use super::toc::{
+    Msf,
+    Toc,
+    CONTROL_COPY_PERMITTED,
+    CONTROL_FOUR_CHANNEL,
+    CONTROL_PRE_EMPHASIS,
+    FRAMES_PER_SECOND,
+};
+use std::error::Error;
+
+/// How the samples in a cue sheet's data file are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueFileType
+{
+    /// Little-endian samples, as read from the drive.
+    Binary,
+    /// Big-endian samples.
+    Motorola,
+}
+
+/// A data file named by a cue sheet, with the tracks stored in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueFile
+{
+    pub name: String,
+    pub file_type: CueFileType,
+    pub tracks: Vec<CueTrack>,
+}
+
+/// A track in a cue sheet.  Positions are in sectors from the start of the
+/// track's file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CueTrack
+{
+    pub number: u8,
+    pub audio: bool,
+    /// INDEX 00, where the pregap starts.
+    pub pregap_start: Option<u32>,
+    /// INDEX 01, where the track proper starts.
+    pub start: u32,
+}
+
+impl CueFile
+{
+    /// The sector after the end of the track with the given index in
+    /// `tracks`, or `None` if it runs to the end of the file.  The gap before
+    /// an audio track is kept at the end of the track before it, as CD
+    /// players play it; the gap before a data track is not audio, so is
+    /// left out.
+    pub fn track_end(
+        &self,
+        i: usize,
+    ) -> Option<u32>
+    {
+        self.tracks.get(i + 1).map(|next| {
+            match next.audio {
+                | true => next.start,
+                | false => next.pregap_start.unwrap_or(next.start),
+            }
+        })
+    }
+}
+
+/// Builds a cue sheet for a single BIN file holding the disc from LBA 0 to
+/// the lead-out, as written by `rip`.  Index positions are relative to the
+/// start of the file.  On a multisession disc, `bin_name` holds the first
+/// session, and each later session is in a file of its own, named by
+/// `session_file_name`, starting at its first track.
+pub fn cue_sheet(
+    toc: &Toc,
+    bin_name: &str,
+) -> String
+{
+    let sessions = toc.sessions();
+    let mut lines = vec![];
+
+    for (i, track) in toc.tracks.iter().enumerate() {
+        let session = toc.session(track.start);
+        let session_start = sessions[session - 1].start;
+
+        if i == 0 || session != toc.session(toc.tracks[i - 1].start) {
+            if sessions.len() > 1 {
+                lines.push(format!("REM SESSION {session:02}"));
+            }
+
+            lines.push(format!(
+                "FILE \"{}\" BINARY",
+                escape(&session_file_name(bin_name, session))
+            ));
+        }
+
+        lines.push(format!(
+            "  TRACK {:02} {}",
+            track.number,
+            track.track_type()
+        ));
+
+        let flags: Vec<&str> = [
+            (CONTROL_COPY_PERMITTED, "DCP"),
+            (CONTROL_FOUR_CHANNEL, "4CH"),
+            (CONTROL_PRE_EMPHASIS, "PRE"),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| track.is_audio() && track.control & bit != 0)
+        .map(|(_, flag)| flag)
+        .collect();
+
+        if !flags.is_empty() {
+            lines.push(format!("    FLAGS {}", flags.join(" ")));
+        }
+
+        // Audio before the first track's index 01 (a "hidden" track) is
+        // marked as track 1's pregap.
+        let pregap_start = match i {
+            | 0 if track.start > 0 => Some(0),
+            | _ => track.pregap_start.filter(|p| *p < track.start),
+        };
+
+        if let Some(pregap_start) = pregap_start {
+            lines.push(format!(
+                "    INDEX 00 {}",
+                Msf::from_frames(pregap_start - session_start)
+            ));
+        }
+
+        lines.push(format!(
+            "    INDEX 01 {}",
+            Msf::from_frames(track.start - session_start)
+        ));
+    }
+
+    lines.push(String::new());
+    lines.join("\r\n")
+}
+
+/// The name of the file holding `session` of a disc ripped to `bin_name`:
+/// `bin_name` itself for the first session, and for later ones, such as the
+/// data session of an Enhanced CD, `bin_name` with "_SESSION_02" (and so on)
+/// before its extension.
+pub fn session_file_name(
+    bin_name: &str,
+    session: usize,
+) -> String
+{
+    match (session, bin_name.rsplit_once('.')) {
+        | (1, _) => bin_name.to_string(),
+        | (_, Some((stem, extension))) => {
+            format!("{stem}_SESSION_{session:02}.{extension}")
+        }
+        | (_, None) => format!("{bin_name}_SESSION_{session:02}"),
+    }
+}
+
+/// Reads the files and tracks from a cue sheet, whether written by `cue_sheet`
+/// or by another program.  Commands other than FILE, TRACK and INDEX are
+/// ignored.
+pub fn parse_cue_sheet(text: &str) -> Result<Vec<CueFile>, Box<dyn Error>>
+{
+    let mut files: Vec<CueFile> = vec![];
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error =
+            |message: &str| format!("Cue sheet line {}: {message}", n + 1);
+
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command.to_uppercase().as_str() {
+            | "FILE" => {
+                let (name, file_type) = file_name_and_type(rest.trim())
+                    .ok_or_else(|| error("FILE has no type."))?;
+
+                let file_type = match file_type.to_uppercase().as_str() {
+                    | "BINARY" => CueFileType::Binary,
+                    | "MOTOROLA" => CueFileType::Motorola,
+                    | other => {
+                        return Err(error(&format!(
+                            "{other} files are not supported."
+                        ))
+                        .into())
+                    }
+                };
+
+                files.push(CueFile {
+                    name,
+                    file_type,
+                    tracks: vec![],
+                });
+            }
+            | "TRACK" => {
+                let mut words = rest.split_whitespace();
+                let number = words
+                    .next()
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| error("TRACK has no number."))?;
+                let audio = words
+                    .next()
+                    .ok_or_else(|| error("TRACK has no type."))?
+                    .eq_ignore_ascii_case("AUDIO");
+
+                files
+                    .last_mut()
+                    .ok_or_else(|| error("TRACK comes before any FILE."))?
+                    .tracks
+                    .push(CueTrack {
+                        number,
+                        audio,
+                        pregap_start: None,
+                        start: 0,
+                    });
+            }
+            | "INDEX" => {
+                let mut words = rest.split_whitespace();
+                let index: u8 = words
+                    .next()
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| error("INDEX has no number."))?;
+                let position = words
+                    .next()
+                    .and_then(parse_msf)
+                    .ok_or_else(|| error("INDEX has no valid position."))?;
+
+                let track = files
+                    .last_mut()
+                    .and_then(|f| f.tracks.last_mut())
+                    .ok_or_else(|| error("INDEX comes before any TRACK."))?;
+
+                match index {
+                    | 0 => track.pregap_start = Some(position),
+                    | 1 => track.start = position,
+                    | _ => (),
+                }
+            }
+            | _ => (),
+        }
+    }
+
+    if files.iter().all(|f| f.tracks.is_empty()) {
+        return Err("The cue sheet lists no tracks.".into());
+    }
+
+    Ok(files)
+}
+
+/// Splits the argument of a FILE command into the (possibly quoted) name and
+/// the file type after it.
+fn file_name_and_type(rest: &str) -> Option<(String, &str)>
+{
+    let (name, file_type) = match rest.strip_prefix('"') {
+        | Some(quoted) => {
+            let (name, after) = quoted.split_once('"')?;
+            (name, after.trim())
+        }
+        | None => rest.rsplit_once(' ')?,
+    };
+
+    match file_type.is_empty() {
+        | true => None,
+        | false => Some((name.trim().to_string(), file_type)),
+    }
+}
+
+/// Parses an "MM:SS:FF" position as a count of sectors.
+fn parse_msf(text: &str) -> Option<u32>
+{
+    let parts: Vec<u32> = text
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match parts[..] {
+        | [m, s, f] if s < 60 && f < FRAMES_PER_SECOND => {
+            Some((m * 60 + s) * FRAMES_PER_SECOND + f)
+        }
+        | _ => None,
+    }
+}
+
+/// Cue sheets have no escape for quotes within names.
+fn escape(name: &str) -> String
+{
+    name.replace('"', "'")
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::toc::TocTrack;
+
+    #[test]
+    fn test_cue_sheet()
+    {
+        let toc = Toc {
+            tracks: vec![
+                TocTrack {
+                    number: 1,
+                    control: CONTROL_PRE_EMPHASIS,
+                    start: 0,
+                    pregap_start: None,
+                    data_mode: None,
+                },
+                TocTrack {
+                    number: 2,
+                    control: 0,
+                    start: 18_000,
+                    pregap_start: Some(17_850),
+                    data_mode: None,
+                },
+                TocTrack {
+                    number: 3,
+                    control: 0,
+                    start: 30_000,
+                    pregap_start: None,
+                    data_mode: None,
+                },
+            ],
+            lead_out: 40_000,
+            session_lead_outs: vec![],
+        };
+
+        assert_eq!(
+            [
+                "FILE \"CR001_AUDIO_CD.bin\" BINARY",
+                "  TRACK 01 AUDIO",
+                "    FLAGS PRE",
+                "    INDEX 01 00:00:00",
+                "  TRACK 02 AUDIO",
+                "    INDEX 00 03:58:00",
+                "    INDEX 01 04:00:00",
+                "  TRACK 03 AUDIO",
+                "    INDEX 01 06:40:00",
+                "",
+            ]
+            .join("\r\n"),
+            cue_sheet(&toc, "CR001_AUDIO_CD.bin")
+        );
+    }
+
+    #[test]
+    fn test_hidden_track()
+    {
+        let toc = Toc {
+            tracks: vec![TocTrack {
+                number: 1,
+                control: 0,
+                start: 750,
+                pregap_start: None,
+                data_mode: None,
+            }],
+            lead_out: 10_000,
+            session_lead_outs: vec![],
+        };
+
+        let cue = cue_sheet(&toc, "a.bin");
+
+        assert!(cue.contains("INDEX 00 00:00:00\r\n    INDEX 01 00:10:00"));
+    }
+
+    #[test]
+    fn test_parse_cue_sheet()
+    {
+        let toc = Toc {
+            tracks: vec![
+                TocTrack {
+                    number: 1,
+                    control: 0,
+                    start: 150,
+                    pregap_start: None,
+                    data_mode: None,
+                },
+                TocTrack {
+                    number: 2,
+                    control: 0,
+                    start: 1000,
+                    pregap_start: Some(850),
+                    data_mode: None,
+                },
+            ],
+            lead_out: 2000,
+            session_lead_outs: vec![],
+        };
+
+        let files =
+            parse_cue_sheet(&cue_sheet(&toc, "CR001 AUDIO.bin")).unwrap();
+
+        assert_eq!(1, files.len());
+        assert_eq!("CR001 AUDIO.bin", files[0].name);
+        assert_eq!(CueFileType::Binary, files[0].file_type);
+        assert_eq!(
+            vec![
+                CueTrack {
+                    number: 1,
+                    audio: true,
+                    pregap_start: Some(0),
+                    start: 150,
+                },
+                CueTrack {
+                    number: 2,
+                    audio: true,
+                    pregap_start: Some(850),
+                    start: 1000,
+                },
+            ],
+            files[0].tracks
+        );
+        assert_eq!(Some(1000), files[0].track_end(0));
+        assert_eq!(None, files[0].track_end(1));
+    }
+
+    #[test]
+    fn test_parse_foreign_cue_sheet()
+    {
+        let text = "REM GENRE Rock\nPERFORMER \"Someone\"\nFILE track01.bin \
+                    BINARY\n\tTRACK 01 AUDIO\n\t\tTITLE \"One\"\n\t\tINDEX 01 \
+                    00:00:00\nFILE \"track02.bin\" MOTOROLA\n\tTRACK 02 \
+                    MODE1/2352\n\t\tINDEX 00 00:00:00\n\t\tINDEX 01 00:02:00\n";
+
+        let files = parse_cue_sheet(text).unwrap();
+
+        assert_eq!(2, files.len());
+        assert_eq!("track01.bin", files[0].name);
+        assert_eq!(CueFileType::Motorola, files[1].file_type);
+        assert!(!files[1].tracks[0].audio);
+        assert_eq!(150, files[1].tracks[0].start);
+
+        assert!(parse_cue_sheet("FILE a.wav WAVE\n  TRACK 01 AUDIO").is_err());
+        assert!(parse_cue_sheet("  TRACK 01 AUDIO").is_err());
+        assert!(parse_cue_sheet("REM nothing").is_err());
+    }
+}
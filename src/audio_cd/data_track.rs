@@ -0,0 +1,282 @@
+use super::{
+    cue::parse_cue_sheet,
+    toc::{
+        sector_address,
+        sector_mode,
+        SectorMode,
+        RAW_SECTOR_SIZE,
+    },
+};
+use crate::disc_image::iso9660::SECTOR_SIZE;
+use std::{
+    error::Error,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        BufReader,
+        BufWriter,
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The system area and volume descriptor set at the start of an ISO 9660
+/// track, in sectors.
+const DESCRIPTOR_AREA_SECTORS: u32 = 32;
+
+/// What `extract_data_track` wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataTrackReport
+{
+    pub track: u8,
+    /// Logical block address of the track's first sector.
+    pub start: u32,
+    pub sectors: u32,
+    /// Sectors without 2048 bytes of user data (neither Mode 1 nor Mode 2
+    /// Form 1), which were written as zeros.
+    pub unconverted: Vec<u32>,
+}
+
+/// Writes the user data of the first data track in the BIN/CUE at `cue` to
+/// `to` as a 2048-byte image which can be read like an ISO.  Returns `None`
+/// if the cue sheet lists no data track.
+///
+/// Each sector is written at its disc address, so that the filesystem's
+/// absolute block numbers still hold.  The address is read from the header
+/// of the track's first sector, or, if it has none, taken to be the track's
+/// position in the BIN, as in the rips carroh makes of single session discs.
+/// When the track does not start the disc, as on an Enhanced CD, the space
+/// before it is left as a hole, and the track's volume descriptors are repeated
+/// at the start of the image where readers look for them.
+pub fn extract_data_track(
+    cue: &PathBuf,
+    to: &PathBuf,
+) -> Result<Option<DataTrackReport>, Box<dyn Error>>
+{
+    let text = fs::read_to_string(cue)
+        .map_err(|e| format!("Could not read {cue:?}: {e}"))?;
+    let files = parse_cue_sheet(&text)?;
+    let cue_dir = cue.parent().unwrap_or(Path::new("."));
+
+    let Some((file, i)) = files
+        .iter()
+        .find_map(|f| f.tracks.iter().position(|t| !t.audio).map(|i| (f, i)))
+    else {
+        return Ok(None);
+    };
+    let track = file.tracks[i];
+
+    let bin_path = cue_dir.join(&file.name);
+    let bin = File::open(&bin_path)
+        .map_err(|e| format!("Could not open {bin_path:?}: {e}"))?;
+    let bin_sectors = (bin.metadata()?.len() / RAW_SECTOR_SIZE as u64) as u32;
+
+    // Unlike the audio split, the gap before the next track is not part of
+    // this one.
+    let end = file
+        .tracks
+        .get(i + 1)
+        .map(|next| next.pregap_start.unwrap_or(next.start))
+        .unwrap_or(bin_sectors)
+        .min(bin_sectors);
+
+    if track.start >= end {
+        return Err(format!(
+            "Track {} of {bin_path:?} holds no sectors.",
+            track.number
+        )
+        .into());
+    }
+
+    let track_offset = track.start as u64 * RAW_SECTOR_SIZE as u64;
+    let mut bin = BufReader::new(bin);
+    let mut raw = vec![0; RAW_SECTOR_SIZE];
+    bin.seek(SeekFrom::Start(track_offset))?;
+    bin.read_exact(&mut raw)?;
+    bin.seek(SeekFrom::Start(track_offset))?;
+
+    let start = sector_address(&raw).unwrap_or(track.start);
+
+    let out = File::create(to)
+        .map_err(|e| format!("Could not create {to:?}: {e}"))?;
+    out.set_len(start as u64 * SECTOR_SIZE)?;
+    let mut out = BufWriter::new(out);
+    out.seek(SeekFrom::End(0))?;
+
+    let repeat_descriptors = start >= DESCRIPTOR_AREA_SECTORS;
+    let mut descriptor_area = vec![];
+    let mut unconverted = vec![];
+
+    for lba in start..start + (end - track.start) {
+        bin.read_exact(&mut raw)?;
+
+        let data = match user_data(&raw) {
+            | Some(data) => data,
+            | None => {
+                unconverted.push(lba);
+                &[0; SECTOR_SIZE as usize]
+            }
+        };
+
+        out.write_all(data)?;
+
+        if repeat_descriptors && lba - start < DESCRIPTOR_AREA_SECTORS {
+            descriptor_area.extend_from_slice(data);
+        }
+    }
+
+    if repeat_descriptors {
+        let mut out = out.into_inner().map_err(|e| e.into_error())?;
+        out.seek(SeekFrom::Start(0))?;
+        out.write_all(&descriptor_area)?;
+    } else {
+        out.flush()?;
+    }
+
+    Ok(Some(DataTrackReport {
+        track: track.number,
+        start,
+        sectors: end - track.start,
+        unconverted,
+    }))
+}
+
+/// The 2048 bytes of user data in a raw Mode 1 or Mode 2 Form 1 sector.
+fn user_data(raw: &[u8]) -> Option<&[u8]>
+{
+    let offset = match sector_mode(raw)? {
+        | SectorMode::Mode0 | SectorMode::Mode1 => 16,
+        | SectorMode::Mode2Form1 => 24,
+        | SectorMode::Mode2 | SectorMode::Mode2Form2 => return None,
+    };
+
+    Some(&raw[offset..offset + SECTOR_SIZE as usize])
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        audio_cd::toc::tests::data_sector,
+        disc_image::{
+            iso9660::tests::build_iso,
+            open_iso9660_volume,
+        },
+    };
+    use tempfile::TempDir;
+
+    /// `data` as raw Mode 1 sectors starting at `lba`.
+    fn mode_1_sectors(
+        data: &[u8],
+        lba: u32,
+    ) -> Vec<u8>
+    {
+        data.chunks(SECTOR_SIZE as usize)
+            .zip(lba..)
+            .flat_map(|(chunk, lba)| {
+                let mut sector = data_sector(lba, 1, 0);
+                sector[16..16 + chunk.len()].copy_from_slice(chunk);
+                sector
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_data_track_first()
+    {
+        // A mixed-mode game disc: the ISO 9660 track, then audio.
+        let iso = build_iso(false, false);
+        let sectors = (iso.len() / SECTOR_SIZE as usize) as u32;
+
+        let out = TempDir::new().unwrap();
+        let mut bin = mode_1_sectors(&iso, 0);
+        bin.extend(vec![0x55; 300 * RAW_SECTOR_SIZE]);
+        fs::write(out.path().join("game.bin"), bin).unwrap();
+
+        let cue = out.path().join("game.cue");
+        fs::write(
+            &cue,
+            format!(
+                "FILE \"game.bin\" BINARY\n  TRACK 01 MODE1/2352\n    INDEX \
+                 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 00 00:00:{:02}\n    \
+                 INDEX 01 00:02:00\n",
+                sectors
+            ),
+        )
+        .unwrap();
+
+        let iso_path = out.path().join("game.iso");
+        let report = extract_data_track(&cue, &iso_path).unwrap().unwrap();
+
+        assert_eq!(1, report.track);
+        assert_eq!(sectors, report.sectors);
+        assert!(report.unconverted.is_empty());
+        assert_eq!(iso, fs::read(&iso_path).unwrap());
+        assert!(open_iso9660_volume(&iso_path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_data_track_last()
+    {
+        // An Enhanced CD: audio, then a data track in the second session.
+        let out = TempDir::new().unwrap();
+        let mut bin = vec![0x55; 100 * RAW_SECTOR_SIZE];
+        let data: Vec<u8> =
+            (0..40 * SECTOR_SIZE).map(|i| (i / 2048) as u8).collect();
+        bin.extend(mode_1_sectors(&data, 100));
+        bin.extend(data_sector(140, 2, 0x20));
+        fs::write(out.path().join("enhanced.bin"), bin).unwrap();
+
+        let cue = out.path().join("enhanced.cue");
+        fs::write(
+            &cue,
+            "FILE \"enhanced.bin\" BINARY\n  TRACK 01 AUDIO\n    INDEX 01 \
+             00:00:00\n  TRACK 02 MODE1/2352\n    INDEX 01 00:01:25\n",
+        )
+        .unwrap();
+
+        let iso_path = out.path().join("enhanced.iso");
+        let report = extract_data_track(&cue, &iso_path).unwrap().unwrap();
+
+        assert_eq!(2, report.track);
+        assert_eq!(100, report.start);
+        assert_eq!(41, report.sectors);
+        assert_eq!(vec![140], report.unconverted);
+
+        let iso = fs::read(&iso_path).unwrap();
+        let sector = |n: usize| &iso[n * 2048..(n + 1) * 2048];
+        assert_eq!(141 * 2048, iso.len());
+        assert_eq!(&data[16 * 2048..17 * 2048], sector(16));
+        assert_eq!(&data[..2048], sector(100));
+        assert_eq!(&data[39 * 2048..], sector(139));
+        assert!(sector(50).iter().all(|b| *b == 0));
+        assert!(sector(140).iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_no_data_track()
+    {
+        let out = TempDir::new().unwrap();
+        fs::write(out.path().join("a.bin"), vec![0; RAW_SECTOR_SIZE]).unwrap();
+        let cue = out.path().join("a.cue");
+        fs::write(
+            &cue,
+            "FILE a.bin BINARY\n  TRACK 01 AUDIO\n  INDEX 01 00:00:00",
+        )
+        .unwrap();
+
+        assert_eq!(
+            None,
+            extract_data_track(&cue, &out.path().join("a.iso")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,712 @@
+//! Reading the TOC and raw sectors of the CD in a real drive, through the
+//! operating system's CD-ROM ioctls.
+
+use super::{
+    media::{
+        MediaSource,
+        MediaStatus,
+    },
+    toc::{
+        Toc,
+        TocSource,
+        TocTrack,
+        RAW_SECTOR_SIZE,
+    },
+    CdDrive,
+};
+use std::{
+    error::Error,
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::fs::OpenOptionsExt,
+    },
+    path::PathBuf,
+};
+
+/// A CD drive opened for reading through its device node.
+pub struct DeviceDrive
+{
+    path: PathBuf,
+    file: File,
+    /// The last table of contents read, which says how each sector must be
+    /// read.
+    toc: Option<Toc>,
+}
+
+impl DeviceDrive
+{
+    /// Opens the device node of a drive, such as `/dev/sr0` on Linux or
+    /// `/dev/rdisk4` on macOS.
+    pub fn open(path: &PathBuf) -> Result<DeviceDrive, Box<dyn Error>>
+    {
+        // Non-blocking, so that opening a drive with no disc (or an open
+        // tray) fails fast rather than waiting for media.
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|e| format!("Could not open drive {path:?}: {e}"))?;
+
+        Ok(DeviceDrive {
+            path: path.clone(),
+            file,
+            toc: None,
+        })
+    }
+
+    fn ioctl<T>(
+        &self,
+        request: u64,
+        arg: &mut T,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        // SAFETY: each request is paired with the structure the kernel
+        // expects for it, and `arg` outlives the call.
+        let result = unsafe {
+            libc::ioctl(self.file.as_raw_fd(), request as _, arg as *mut T)
+        };
+
+        match result {
+            | -1 => {
+                Err(format!(
+                    "Drive {:?} request failed: {}",
+                    self.path,
+                    io::Error::last_os_error()
+                )
+                .into())
+            }
+            | _ => Ok(()),
+        }
+    }
+}
+
+impl TocSource for DeviceDrive
+{
+    fn read_toc(&mut self) -> Result<Toc, Box<dyn Error>>
+    {
+        let toc = self.read_platform_toc()?;
+        self.toc = Some(toc.clone());
+
+        Ok(toc)
+    }
+}
+
+impl MediaSource for DeviceDrive
+{
+    fn media_status(&mut self) -> Result<MediaStatus, Box<dyn Error>>
+    {
+        self.platform_media_status()
+    }
+}
+
+impl CdDrive for DeviceDrive
+{
+    /// Audio and data sectors are read by different requests, so the range
+    /// is split where it crosses between audio and data tracks.
+    fn read_raw_sectors(
+        &mut self,
+        lba: u32,
+        count: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        let toc = match &self.toc {
+            | Some(toc) => toc.clone(),
+            | None => self.read_toc()?,
+        };
+        let is_data = |s: u32| toc.track_at(s).is_some_and(|t| !t.is_audio());
+
+        let end = lba + count;
+        let mut out = Vec::with_capacity(count as usize * RAW_SECTOR_SIZE);
+        let mut sector = lba;
+
+        while sector < end {
+            let data = is_data(sector);
+            let run_end = (sector + 1..end)
+                .find(|s| is_data(*s) != data)
+                .unwrap_or(end);
+
+            match data {
+                | true => {
+                    for s in sector..run_end {
+                        out.extend(self.read_data_sector(s)?);
+                    }
+                }
+                | false => {
+                    out.extend(
+                        self.read_audio_sectors(sector, run_end - sector)?,
+                    )
+                }
+            }
+
+            sector = run_end;
+        }
+
+        Ok(out)
+    }
+
+    fn read_q_subchannel(
+        &mut self,
+        lba: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        self.read_platform_q_subchannel(lba)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform
+{
+    use super::*;
+    use crate::audio_cd::toc::{
+        Msf,
+        Q_SUBCHANNEL_SIZE,
+        SESSION_GAP_SECTORS,
+    };
+    use log::debug;
+    use std::{
+        fs,
+        path::Path,
+    };
+
+    const CDROMREADTOCHDR: u64 = 0x5305;
+    const CDROMREADTOCENTRY: u64 = 0x5306;
+    const CDROMMULTISESSION: u64 = 0x5310;
+    const CDROMREADAUDIO: u64 = 0x530E;
+    const CDROMREADRAW: u64 = 0x5314;
+    const CDROM_DRIVE_STATUS: u64 = 0x5326;
+    const CDROM_LBA: u8 = 0x01;
+    const CDROM_LEADOUT: u8 = 0xAA;
+
+    /// The drive status of the current slot, rather than of one in a
+    /// changer.
+    const CDSL_CURRENT: libc::c_int = libc::c_int::MAX;
+    const CDS_NO_DISC: libc::c_int = 1;
+    const CDS_TRAY_OPEN: libc::c_int = 2;
+    const CDS_DRIVE_NOT_READY: libc::c_int = 3;
+    const CDS_DISC_OK: libc::c_int = 4;
+
+    /// Where Linux gives each block device's size, in 512 byte sectors.
+    const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+
+    /// The kernel will not read more audio frames than this at once.
+    const MAX_AUDIO_FRAMES: u32 = 75;
+
+    /// Sends a SCSI command to the drive.
+    const SG_IO: u64 = 0x2285;
+    const SG_DXFER_FROM_DEV: libc::c_int = -3;
+    const SG_TIMEOUT_MS: libc::c_uint = 10_000;
+    /// The MMC READ CD command.
+    const READ_CD: u8 = 0xBE;
+    /// Asks READ CD for the whole 2352 bytes of each sector, of any type.
+    const READ_CD_ALL_MAIN_CHANNEL: u8 = 0xF8;
+    /// Asks READ CD for each sector's formatted Q sub-channel after it.
+    const READ_CD_FORMATTED_Q: u8 = 0x02;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct TocHeader
+    {
+        first_track: u8,
+        last_track: u8,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct TocEntry
+    {
+        track: u8,
+        /// ADR in the low nibble, control in the high nibble.
+        adr_control: u8,
+        format: u8,
+        lba: i32,
+        data_mode: u8,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct MultiSession
+    {
+        /// The start of the first track of the last session.
+        lba: i32,
+        /// Set when the disc has more than one session.
+        xa_flag: u8,
+        address_format: u8,
+    }
+
+    /// `sg_io_hdr` from `<scsi/sg.h>`.
+    #[repr(C)]
+    struct SgIoHeader
+    {
+        interface_id: libc::c_int,
+        dxfer_direction: libc::c_int,
+        cmd_len: u8,
+        mx_sb_len: u8,
+        iovec_count: libc::c_ushort,
+        dxfer_len: libc::c_uint,
+        dxferp: *mut u8,
+        cmdp: *const u8,
+        sbp: *mut u8,
+        timeout: libc::c_uint,
+        flags: libc::c_uint,
+        pack_id: libc::c_int,
+        usr_ptr: *mut libc::c_void,
+        status: u8,
+        masked_status: u8,
+        msg_status: u8,
+        sb_len_wr: u8,
+        host_status: libc::c_ushort,
+        driver_status: libc::c_ushort,
+        resid: libc::c_int,
+        duration: libc::c_uint,
+        info: libc::c_uint,
+    }
+
+    #[repr(C)]
+    struct ReadAudio
+    {
+        lba: i32,
+        address_format: u8,
+        frames: i32,
+        buffer: *mut u8,
+    }
+
+    impl DeviceDrive
+    {
+        fn toc_entry(
+            &self,
+            track: u8,
+        ) -> Result<TocEntry, Box<dyn Error>>
+        {
+            let mut entry = TocEntry {
+                track,
+                format: CDROM_LBA,
+                ..Default::default()
+            };
+            self.ioctl(CDROMREADTOCENTRY, &mut entry)?;
+
+            Ok(entry)
+        }
+    }
+
+    impl DeviceDrive
+    {
+        /// Asks a CD drive for its status, or, for other drives such as
+        /// floppy drives, takes media to be present when the device has a
+        /// size.
+        pub(super) fn platform_media_status(
+            &self
+        ) -> Result<MediaStatus, Box<dyn Error>>
+        {
+            // SAFETY: CDROM_DRIVE_STATUS takes a slot number rather than a
+            // pointer.
+            let status = unsafe {
+                libc::ioctl(
+                    self.file.as_raw_fd(),
+                    CDROM_DRIVE_STATUS as _,
+                    CDSL_CURRENT,
+                )
+            };
+
+            match status {
+                | CDS_NO_DISC => return Ok(MediaStatus::NoDisc),
+                | CDS_TRAY_OPEN => return Ok(MediaStatus::TrayOpen),
+                | CDS_DRIVE_NOT_READY => return Ok(MediaStatus::NotReady),
+                | CDS_DISC_OK => return Ok(MediaStatus::Ready),
+                | _ => (),
+            }
+
+            let device = fs::canonicalize(&self.path)?;
+            let name = device
+                .file_name()
+                .ok_or(format!("{device:?} is not a device."))?;
+            let size = fs::read_to_string(
+                Path::new(SYS_CLASS_BLOCK).join(name).join("size"),
+            )?;
+
+            match size.trim().parse::<u64>() {
+                | Ok(sectors) if sectors > 0 => Ok(MediaStatus::Ready),
+                | Ok(_) => Ok(MediaStatus::NoDisc),
+                | Err(_) => Ok(MediaStatus::Unknown),
+            }
+        }
+    }
+
+    impl DeviceDrive
+    {
+        pub(super) fn read_platform_toc(
+            &mut self
+        ) -> Result<Toc, Box<dyn Error>>
+        {
+            let mut header = TocHeader::default();
+            self.ioctl(CDROMREADTOCHDR, &mut header)?;
+
+            let tracks = (header.first_track..=header.last_track)
+                .map(|number| {
+                    let entry = self.toc_entry(number)?;
+
+                    Ok(TocTrack {
+                        number,
+                        control: entry.adr_control >> 4,
+                        start: entry.lba.max(0) as u32,
+                        pregap_start: None,
+                        data_mode: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+            let lead_out = self.toc_entry(CDROM_LEADOUT)?.lba.max(0) as u32;
+
+            Ok(Toc {
+                tracks,
+                lead_out,
+                session_lead_outs: self.session_lead_outs(),
+            })
+        }
+
+        /// Linux only reports where the last session starts, so a disc is
+        /// taken to have at most two sessions, as Enhanced CDs do, with the
+        /// standard gap between them.
+        fn session_lead_outs(&self) -> Vec<u32>
+        {
+            let mut request = MultiSession {
+                address_format: CDROM_LBA,
+                ..Default::default()
+            };
+
+            if let Err(e) = self.ioctl(CDROMMULTISESSION, &mut request) {
+                debug!("The drive reported no sessions: {e}");
+                return vec![];
+            }
+
+            match request.xa_flag {
+                | 0 => vec![],
+                | _ => {
+                    (request.lba.max(0) as u32)
+                        .checked_sub(SESSION_GAP_SECTORS)
+                        .into_iter()
+                        .collect()
+                }
+            }
+        }
+    }
+
+    impl DeviceDrive
+    {
+        pub(super) fn read_audio_sectors(
+            &mut self,
+            lba: u32,
+            count: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            let mut out = vec![0; count as usize * RAW_SECTOR_SIZE];
+
+            for (i, chunk) in out
+                .chunks_mut(MAX_AUDIO_FRAMES as usize * RAW_SECTOR_SIZE)
+                .enumerate()
+            {
+                let mut request = ReadAudio {
+                    lba: (lba + i as u32 * MAX_AUDIO_FRAMES) as i32,
+                    address_format: CDROM_LBA,
+                    frames: (chunk.len() / RAW_SECTOR_SIZE) as i32,
+                    buffer: chunk.as_mut_ptr(),
+                };
+                self.ioctl(CDROMREADAUDIO, &mut request)?;
+            }
+
+            Ok(out)
+        }
+
+        /// Reads a data sector whole: sync, header, subheader, data and
+        /// error correction.
+        pub(super) fn read_data_sector(
+            &mut self,
+            lba: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            // The request takes the sector's position in its first bytes,
+            // and is overwritten with the sector.
+            let msf = Msf::from_lba(lba);
+            let mut out = vec![0; RAW_SECTOR_SIZE];
+            out[..3].copy_from_slice(&[
+                msf.minutes as u8,
+                msf.seconds as u8,
+                msf.frames as u8,
+            ]);
+
+            self.ioctl(CDROMREADRAW, &mut out[0])?;
+
+            Ok(out)
+        }
+
+        /// Linux has no ioctl for a sector's sub-channel, so READ CD is sent
+        /// to the drive directly.
+        pub(super) fn read_platform_q_subchannel(
+            &mut self,
+            lba: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            let mut command = [0u8; 12];
+            command[0] = READ_CD;
+            command[2..6].copy_from_slice(&lba.to_be_bytes());
+            command[8] = 1;
+            command[9] = READ_CD_ALL_MAIN_CHANNEL;
+            command[10] = READ_CD_FORMATTED_Q;
+
+            let mut out = vec![0; RAW_SECTOR_SIZE + Q_SUBCHANNEL_SIZE];
+            let mut sense = [0u8; 32];
+            let mut request = SgIoHeader {
+                interface_id: b'S' as libc::c_int,
+                dxfer_direction: SG_DXFER_FROM_DEV,
+                cmd_len: command.len() as u8,
+                mx_sb_len: sense.len() as u8,
+                iovec_count: 0,
+                dxfer_len: out.len() as libc::c_uint,
+                dxferp: out.as_mut_ptr(),
+                cmdp: command.as_ptr(),
+                sbp: sense.as_mut_ptr(),
+                timeout: SG_TIMEOUT_MS,
+                flags: 0,
+                pack_id: 0,
+                usr_ptr: std::ptr::null_mut(),
+                status: 0,
+                masked_status: 0,
+                msg_status: 0,
+                sb_len_wr: 0,
+                host_status: 0,
+                driver_status: 0,
+                resid: 0,
+                duration: 0,
+                info: 0,
+            };
+            self.ioctl(SG_IO, &mut request)?;
+
+            if request.status != 0
+                || request.host_status != 0
+                || request.driver_status != 0
+            {
+                return Err(format!(
+                    "Drive {:?} could not read the sub-channel of sector \
+                     {lba}.",
+                    self.path
+                )
+                .into());
+            }
+
+            Ok(out.split_off(RAW_SECTOR_SIZE))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform
+{
+    use super::*;
+    use crate::audio_cd::toc::Q_SUBCHANNEL_SIZE;
+    use std::mem::size_of;
+
+    /// `_IOWR('d', number, T)` from `<sys/ioccom.h>`.
+    const fn iowr(
+        number: u64,
+        size: usize,
+    ) -> u64
+    {
+        0xC000_0000
+            | ((size as u64 & 0x1FFF) << 16)
+            | ((b'd' as u64) << 8)
+            | number
+    }
+
+    const DKIOCCDREAD: u64 = iowr(96, size_of::<CdRead>());
+    const DKIOCCDREADTOC: u64 = iowr(100, size_of::<CdReadToc>());
+
+    const TOC_FORMAT_FULL: u8 = 0x02;
+    const SECTOR_AREA_USER: u8 = 0x10;
+    const SECTOR_AREA_Q: u8 = 0x04;
+    /// Sync, header, subheader, user data and auxiliary (error correction)
+    /// areas: the whole 2352 bytes of a data sector.
+    const SECTOR_AREA_RAW: u8 = 0xF8;
+    const SECTOR_TYPE_UNKNOWN: u8 = 0x00;
+    const SECTOR_TYPE_CDDA: u8 = 0x01;
+    const POINT_LEAD_OUT: u8 = 0xA2;
+
+    /// `dk_cd_read_toc_t` from `<IOKit/storage/IOCDMediaBSDClient.h>`.
+    #[repr(C)]
+    struct CdReadToc
+    {
+        format: u8,
+        format_as_time: u8,
+        reserved0: [u8; 5],
+        address: u8,
+        reserved1: [u8; 6],
+        buffer_length: u16,
+        buffer: *mut u8,
+    }
+
+    /// `dk_cd_read_t` from `<IOKit/storage/IOCDMediaBSDClient.h>`.
+    #[repr(C)]
+    struct CdRead
+    {
+        offset: u64,
+        sector_area: u8,
+        sector_type: u8,
+        reserved0: [u8; 10],
+        buffer_length: u32,
+        buffer: *mut u8,
+    }
+
+    fn msf_lba(b: &[u8]) -> u32
+    {
+        ((b[0] as u32 * 60 + b[1] as u32) * 75 + b[2] as u32)
+            .saturating_sub(150)
+    }
+
+    impl DeviceDrive
+    {
+        /// macOS only gives a drive a device node while it holds media, so a
+        /// drive which could be opened is ready.
+        pub(super) fn platform_media_status(
+            &self
+        ) -> Result<MediaStatus, Box<dyn Error>>
+        {
+            Ok(MediaStatus::Ready)
+        }
+    }
+
+    impl DeviceDrive
+    {
+        pub(super) fn read_platform_toc(
+            &mut self
+        ) -> Result<Toc, Box<dyn Error>>
+        {
+            let mut buffer = vec![0u8; 2048];
+            let mut request = CdReadToc {
+                format: TOC_FORMAT_FULL,
+                format_as_time: 1,
+                reserved0: [0; 5],
+                address: 0,
+                reserved1: [0; 6],
+                buffer_length: buffer.len() as u16,
+                buffer: buffer.as_mut_ptr(),
+            };
+            self.ioctl(DKIOCCDREADTOC, &mut request)?;
+
+            // A four byte header, then eleven byte descriptors.
+            let length =
+                u16::from_be_bytes([buffer[0], buffer[1]]) as usize + 2;
+            let mut tracks = vec![];
+            // Each session has a lead-out, given with its session number.
+            let mut lead_outs = vec![];
+
+            for d in buffer[4..length.min(buffer.len())].chunks_exact(11) {
+                match d[3] {
+                    | point @ 1..=99 => {
+                        tracks.push(TocTrack {
+                            number: point,
+                            control: d[1] & 0x0F,
+                            start: msf_lba(&d[8..11]),
+                            pregap_start: None,
+                            data_mode: None,
+                        })
+                    }
+                    | POINT_LEAD_OUT => {
+                        lead_outs.push((d[0], msf_lba(&d[8..11])))
+                    }
+                    | _ => (),
+                }
+            }
+
+            tracks.sort_by_key(|t| t.number);
+            lead_outs.sort();
+
+            let (_, lead_out) =
+                lead_outs.pop().ok_or("The TOC has no lead-out.")?;
+
+            Ok(Toc {
+                tracks,
+                lead_out,
+                session_lead_outs: lead_outs
+                    .into_iter()
+                    .map(|(_, l)| l)
+                    .collect(),
+            })
+        }
+    }
+
+    impl DeviceDrive
+    {
+        /// Reads the `sector_area` of `count` sectors, each of which gives
+        /// `sector_size` bytes.
+        fn read_sectors(
+            &mut self,
+            lba: u32,
+            count: u32,
+            sector_area: u8,
+            sector_type: u8,
+            sector_size: usize,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            let mut out = vec![0; count as usize * sector_size];
+            let mut request = CdRead {
+                offset: lba as u64 * RAW_SECTOR_SIZE as u64,
+                sector_area,
+                sector_type,
+                reserved0: [0; 10],
+                buffer_length: out.len() as u32,
+                buffer: out.as_mut_ptr(),
+            };
+            self.ioctl(DKIOCCDREAD, &mut request)?;
+
+            Ok(out)
+        }
+
+        pub(super) fn read_audio_sectors(
+            &mut self,
+            lba: u32,
+            count: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            self.read_sectors(
+                lba,
+                count,
+                SECTOR_AREA_USER,
+                SECTOR_TYPE_CDDA,
+                RAW_SECTOR_SIZE,
+            )
+        }
+
+        /// Reads a data sector whole: sync, header, subheader, data and
+        /// error correction.
+        pub(super) fn read_data_sector(
+            &mut self,
+            lba: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            self.read_sectors(
+                lba,
+                1,
+                SECTOR_AREA_RAW,
+                SECTOR_TYPE_UNKNOWN,
+                RAW_SECTOR_SIZE,
+            )
+        }
+
+        pub(super) fn read_platform_q_subchannel(
+            &mut self,
+            lba: u32,
+        ) -> Result<Vec<u8>, Box<dyn Error>>
+        {
+            self.read_sectors(
+                lba,
+                1,
+                SECTOR_AREA_Q,
+                SECTOR_TYPE_UNKNOWN,
+                Q_SUBCHANNEL_SIZE,
+            )
+        }
+    }
+}
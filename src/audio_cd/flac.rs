@@ -0,0 +1,684 @@
+//! A FLAC encoder for CD-DA audio (44.1 kHz, 16-bit stereo), using the fixed
+//! predictors, stereo decorrelation and partitioned Rice coding of the
+//! format.  It compresses a little less than the reference encoder, but needs
+//! no external tools.
+
+use super::wav::{
+    BITS_PER_SAMPLE,
+    CHANNELS,
+    SAMPLE_RATE,
+};
+use md5::{
+    Digest,
+    Md5,
+};
+use std::{
+    error::Error,
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+};
+
+/// Samples per channel in each frame.
+pub const BLOCK_SIZE: usize = 4096;
+
+const BYTES_PER_FRAME: usize = (CHANNELS * BITS_PER_SAMPLE / 8) as usize;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: u32 = 8;
+const MAX_RICE_PARAMETER: u32 = 30;
+const STREAMINFO_OFFSET: u64 = 8;
+
+const METADATA_STREAMINFO: u8 = 0;
+const METADATA_VORBIS_COMMENT: u8 = 4;
+
+const CHANNELS_INDEPENDENT: u64 = 0b0001;
+const CHANNELS_LEFT_SIDE: u64 = 0b1000;
+const CHANNELS_RIGHT_SIDE: u64 = 0b1001;
+const CHANNELS_MID_SIDE: u64 = 0b1010;
+
+/// Writes bits most significant first.
+#[derive(Default)]
+struct BitWriter
+{
+    bytes: Vec<u8>,
+    accumulator: u64,
+    bits: u32,
+}
+
+impl BitWriter
+{
+    /// Writes the low `count` bits of `value`, where `count` is at most 32.
+    fn write(
+        &mut self,
+        count: u32,
+        value: u64,
+    )
+    {
+        if count == 0 {
+            return;
+        }
+
+        self.accumulator =
+            (self.accumulator << count) | (value & ((1 << count) - 1));
+        self.bits += count;
+
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.accumulator >> self.bits) as u8);
+        }
+    }
+
+    /// Writes `value` in two's complement in `count` bits.
+    fn write_signed(
+        &mut self,
+        count: u32,
+        value: i32,
+    )
+    {
+        self.write(count, value as u32 as u64);
+    }
+
+    fn write_unary(
+        &mut self,
+        mut zeros: u32,
+    )
+    {
+        while zeros >= 32 {
+            self.write(32, 0);
+            zeros -= 32;
+        }
+        self.write(zeros, 0);
+        self.write(1, 1);
+    }
+
+    fn align(&mut self)
+    {
+        if self.bits > 0 {
+            self.write(8 - self.bits, 0);
+        }
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8
+{
+    bytes.iter().fold(0, |crc, b| {
+        (0..8).fold(crc ^ b, |c, _| {
+            match c & 0x80 {
+                | 0 => c << 1,
+                | _ => (c << 1) ^ 0x07,
+            }
+        })
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16
+{
+    bytes.iter().fold(0, |crc, b| {
+        (0..8).fold(crc ^ ((*b as u16) << 8), |c, _| {
+            match c & 0x8000 {
+                | 0 => c << 1,
+                | _ => (c << 1) ^ 0x8005,
+            }
+        })
+    })
+}
+
+/// How one channel of a frame is coded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Subframe
+{
+    Constant,
+    Verbatim,
+    Fixed
+    {
+        order: usize,
+        partition_order: u32,
+        parameters: Vec<u32>,
+    },
+}
+
+fn zigzag(residual: i32) -> u32
+{
+    ((residual << 1) ^ (residual >> 31)) as u32
+}
+
+/// The residual of the fixed predictor of `order` for every sample after the
+/// first `order`.
+fn fixed_residual(
+    samples: &[i32],
+    order: usize,
+) -> Vec<i32>
+{
+    (order..samples.len())
+        .map(|i| {
+            let s = &samples[i - order..=i];
+            match order {
+                | 0 => s[0],
+                | 1 => s[1] - s[0],
+                | 2 => s[2] - 2 * s[1] + s[0],
+                | 3 => s[3] - 3 * s[2] + 3 * s[1] - s[0],
+                | _ => s[4] - 4 * s[3] + 6 * s[2] - 4 * s[1] + s[0],
+            }
+        })
+        .collect()
+}
+
+/// The Rice parameter and estimated size in bits for a partition of `count`
+/// residuals whose zigzag values add up to `sum`.
+fn rice_parameter(
+    sum: u64,
+    count: u64,
+) -> (u32, u64)
+{
+    let guess = match sum > count {
+        | true => (sum / count).ilog2(),
+        | false => 0,
+    };
+
+    (guess.saturating_sub(1)..=(guess + 1).min(MAX_RICE_PARAMETER))
+        .map(|k| (k, count * (k as u64 + 1) + (sum >> k)))
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap_or((0, u64::MAX))
+}
+
+/// Picks the partition order and Rice parameters which code `residual`
+/// (following `order` warm-up samples in a block of `block_size`) in the
+/// fewest bits, returning them with that size.
+fn partition_residual(
+    residual: &[i32],
+    order: usize,
+    block_size: usize,
+) -> (u32, Vec<u32>, u64)
+{
+    // Partition sums at the finest order the block allows, merged pairwise
+    // for each coarser order.
+    let finest = (0..=MAX_PARTITION_ORDER)
+        .rev()
+        .find(|p| {
+            block_size.is_multiple_of(1 << p) && (block_size >> p) > order
+        })
+        .unwrap_or(0);
+
+    let size = block_size >> finest;
+    let mut sums: Vec<(u64, u64)> = (0..1 << finest)
+        .map(|j| {
+            let start = (j * size).max(order) - order;
+            let end = (j + 1) * size - order;
+            let sum =
+                residual[start..end].iter().map(|r| zigzag(*r) as u64).sum();
+            (sum, (end - start) as u64)
+        })
+        .collect();
+
+    let mut best: Option<(u32, Vec<u32>, u64)> = None;
+
+    for p in (0..=finest).rev() {
+        let (parameters, bits): (Vec<u32>, Vec<u64>) = sums
+            .iter()
+            .map(|(sum, count)| rice_parameter(*sum, *count))
+            .unzip();
+        let parameter_bits = match parameters.iter().any(|k| *k > 14) {
+            | true => 5,
+            | false => 4,
+        };
+        let total = 6
+            + bits.iter().sum::<u64>()
+            + parameters.len() as u64 * parameter_bits;
+
+        if best.as_ref().is_none_or(|(_, _, b)| total < *b) {
+            best = Some((p, parameters, total));
+        }
+
+        sums = sums
+            .chunks(2)
+            .map(|pair| {
+                pair.iter()
+                    .fold((0, 0), |(s, c), (ps, pc)| (s + ps, c + pc))
+            })
+            .collect();
+    }
+
+    best.unwrap_or((0, vec![0], u64::MAX))
+}
+
+/// Chooses how to code a channel, returning the choice and its size in bits.
+fn choose_subframe(
+    samples: &[i32],
+    bps: u32,
+) -> (Subframe, u64)
+{
+    if samples.iter().all(|s| *s == samples[0]) {
+        return (Subframe::Constant, 8 + bps as u64);
+    }
+
+    let verbatim = (Subframe::Verbatim, 8 + samples.len() as u64 * bps as u64);
+
+    // The order with the smallest residual is nearly always the one which
+    // codes smallest, so only that one is partitioned.
+    let order = (0..=MAX_FIXED_ORDER.min(samples.len() - 1))
+        .min_by_key(|order| {
+            fixed_residual(samples, *order)
+                .iter()
+                .map(|r| r.unsigned_abs() as u64)
+                .sum::<u64>()
+        })
+        .unwrap_or(0);
+
+    let residual = fixed_residual(samples, order);
+    let (partition_order, parameters, residual_bits) =
+        partition_residual(&residual, order, samples.len());
+    let bits = 8 + order as u64 * bps as u64 + residual_bits;
+
+    match bits < verbatim.1 {
+        | true => {
+            (
+                Subframe::Fixed {
+                    order,
+                    partition_order,
+                    parameters,
+                },
+                bits,
+            )
+        }
+        | false => verbatim,
+    }
+}
+
+fn write_subframe(
+    out: &mut BitWriter,
+    samples: &[i32],
+    bps: u32,
+    subframe: &Subframe,
+)
+{
+    out.write(1, 0);
+
+    match subframe {
+        | Subframe::Constant => {
+            out.write(6, 0b000000);
+            out.write(1, 0);
+            out.write_signed(bps, samples[0]);
+        }
+        | Subframe::Verbatim => {
+            out.write(6, 0b000001);
+            out.write(1, 0);
+            samples.iter().for_each(|s| out.write_signed(bps, *s));
+        }
+        | Subframe::Fixed {
+            order,
+            partition_order,
+            parameters,
+        } => {
+            out.write(6, 0b001000 | *order as u64);
+            out.write(1, 0);
+            samples[..*order]
+                .iter()
+                .for_each(|s| out.write_signed(bps, *s));
+
+            let parameter_bits = match parameters.iter().any(|k| *k > 14) {
+                | true => {
+                    out.write(2, 1);
+                    5
+                }
+                | false => {
+                    out.write(2, 0);
+                    4
+                }
+            };
+            out.write(4, *partition_order as u64);
+
+            let residual = fixed_residual(samples, *order);
+            let size = samples.len() >> partition_order;
+
+            for (j, k) in parameters.iter().enumerate() {
+                out.write(parameter_bits, *k as u64);
+
+                let start = (j * size).max(*order) - order;
+                let end = (j + 1) * size - order;
+
+                for r in &residual[start..end] {
+                    let u = zigzag(*r);
+                    out.write_unary(u >> k);
+                    out.write(*k, u as u64);
+                }
+            }
+        }
+    }
+}
+
+/// The frame number as coded in frame headers: like UTF-8, extended to 36
+/// bits.
+fn coded_number(number: u64) -> Vec<u8>
+{
+    if number < 0x80 {
+        return vec![number as u8];
+    }
+
+    let continuation = match number {
+        | n if n < 1 << 11 => 1,
+        | n if n < 1 << 16 => 2,
+        | n if n < 1 << 21 => 3,
+        | n if n < 1 << 26 => 4,
+        | n if n < 1 << 31 => 5,
+        | _ => 6,
+    };
+
+    let lead_marker = (0xFF00u16 >> (continuation + 1)) as u8;
+    let mut bytes = vec![lead_marker | (number >> (6 * continuation)) as u8];
+    bytes.extend(
+        (0..continuation)
+            .rev()
+            .map(|i| 0x80 | ((number >> (6 * i)) & 0x3F) as u8),
+    );
+
+    bytes
+}
+
+/// Codes one frame of left and right samples.
+fn encode_frame(
+    number: u64,
+    left: &[i32],
+    right: &[i32],
+) -> Vec<u8>
+{
+    let bps = BITS_PER_SAMPLE as u32;
+    let mid: Vec<i32> =
+        left.iter().zip(right).map(|(l, r)| (l + r) >> 1).collect();
+    let side: Vec<i32> = left.iter().zip(right).map(|(l, r)| l - r).collect();
+
+    let l = choose_subframe(left, bps);
+    let r = choose_subframe(right, bps);
+    let m = choose_subframe(&mid, bps);
+    let s = choose_subframe(&side, bps + 1);
+
+    let (assignment, channels) = [
+        (CHANNELS_INDEPENDENT, [(left, &l, bps), (right, &r, bps)]),
+        (
+            CHANNELS_LEFT_SIDE,
+            [(left, &l, bps), (&side[..], &s, bps + 1)],
+        ),
+        (
+            CHANNELS_RIGHT_SIDE,
+            [(&side[..], &s, bps + 1), (right, &r, bps)],
+        ),
+        (
+            CHANNELS_MID_SIDE,
+            [(&mid[..], &m, bps), (&side[..], &s, bps + 1)],
+        ),
+    ]
+    .into_iter()
+    .min_by_key(|(_, channels)| channels.iter().map(|c| c.1 .1).sum::<u64>())
+    .unwrap();
+
+    let mut out = BitWriter::default();
+
+    out.write(14, 0b11111111111110);
+    out.write(1, 0);
+    // Fixed block size, with frames counted rather than samples.
+    out.write(1, 0);
+    let block_size_code = match left.len() {
+        | BLOCK_SIZE => 0b1100,
+        | _ => 0b0111,
+    };
+    out.write(4, block_size_code);
+    // 44.1 kHz
+    out.write(4, 0b1001);
+    out.write(4, assignment);
+    // 16 bits per sample
+    out.write(3, 0b100);
+    out.write(1, 0);
+    coded_number(number)
+        .iter()
+        .for_each(|b| out.write(8, *b as u64));
+    if block_size_code == 0b0111 {
+        out.write(16, left.len() as u64 - 1);
+    }
+    let header_crc = crc8(&out.bytes);
+    out.write(8, header_crc as u64);
+
+    for (samples, (subframe, _), bps) in channels {
+        write_subframe(&mut out, samples, bps, subframe);
+    }
+
+    out.align();
+    let frame_crc = crc16(&out.bytes);
+    out.write(16, frame_crc as u64);
+
+    out.bytes
+}
+
+fn metadata_header(
+    last: bool,
+    block_type: u8,
+    length: usize,
+) -> Vec<u8>
+{
+    let mut out = BitWriter::default();
+    out.write(1, last as u64);
+    out.write(7, block_type as u64);
+    out.write(24, length as u64);
+    out.bytes
+}
+
+fn vorbis_comment(comments: &[(String, String)]) -> Vec<u8>
+{
+    let vendor = concat!("carroh ", env!("CARGO_PKG_VERSION"));
+
+    let mut out = vec![];
+    out.extend((vendor.len() as u32).to_le_bytes());
+    out.extend(vendor.as_bytes());
+    out.extend((comments.len() as u32).to_le_bytes());
+
+    for (field, value) in comments {
+        let comment = format!("{field}={value}");
+        out.extend((comment.len() as u32).to_le_bytes());
+        out.extend(comment.as_bytes());
+    }
+
+    out
+}
+
+fn stream_info(
+    block_size: u64,
+    frame_sizes: (u64, u64),
+    total_samples: u64,
+    md5: &[u8],
+) -> Vec<u8>
+{
+    let mut out = BitWriter::default();
+    out.write(16, block_size);
+    out.write(16, block_size);
+    out.write(24, frame_sizes.0);
+    out.write(24, frame_sizes.1);
+    out.write(20, SAMPLE_RATE as u64);
+    out.write(3, CHANNELS as u64 - 1);
+    out.write(5, BITS_PER_SAMPLE as u64 - 1);
+    out.write(4, total_samples >> 32);
+    out.write(32, total_samples);
+    md5.iter().for_each(|b| out.write(8, *b as u64));
+    out.bytes
+}
+
+/// Encodes `length` bytes of CD-DA samples (little-endian, interleaved) from
+/// `from` as a FLAC stream in `to`, with the given Vorbis comments.
+pub fn encode(
+    from: &mut impl Read,
+    length: u64,
+    to: &mut (impl Write + Seek),
+    comments: &[(String, String)],
+) -> Result<(), Box<dyn Error>>
+{
+    if !length.is_multiple_of(BYTES_PER_FRAME as u64) {
+        return Err(format!(
+            "{length} bytes is not a whole number of stereo samples."
+        )
+        .into());
+    }
+
+    let total_samples = length / BYTES_PER_FRAME as u64;
+    let block_size = total_samples.min(BLOCK_SIZE as u64);
+
+    let start = to.stream_position()?;
+    to.write_all(b"fLaC")?;
+    to.write_all(&metadata_header(false, METADATA_STREAMINFO, 34))?;
+    // Filled in once the frame sizes and checksum are known.
+    to.write_all(&[0; 34])?;
+
+    let comment = vorbis_comment(comments);
+    to.write_all(&metadata_header(
+        true,
+        METADATA_VORBIS_COMMENT,
+        comment.len(),
+    ))?;
+    to.write_all(&comment)?;
+
+    let mut md5 = Md5::new();
+    let mut frame_sizes: Option<(u64, u64)> = None;
+    let mut buffer = vec![0; BLOCK_SIZE * BYTES_PER_FRAME];
+    let mut remaining = length;
+    let mut number = 0;
+
+    while remaining > 0 {
+        let chunk = &mut buffer
+            [..(remaining as usize).min(BLOCK_SIZE * BYTES_PER_FRAME)];
+        from.read_exact(chunk)?;
+        md5.update(&chunk[..]);
+
+        let (left, right): (Vec<i32>, Vec<i32>) = chunk
+            .chunks_exact(BYTES_PER_FRAME)
+            .map(|s| {
+                (
+                    i16::from_le_bytes([s[0], s[1]]) as i32,
+                    i16::from_le_bytes([s[2], s[3]]) as i32,
+                )
+            })
+            .unzip();
+
+        let frame = encode_frame(number, &left, &right);
+        to.write_all(&frame)?;
+
+        let size = frame.len() as u64;
+        frame_sizes = Some(match frame_sizes {
+            | Some((min, max)) => (min.min(size), max.max(size)),
+            | None => (size, size),
+        });
+
+        remaining -= chunk.len() as u64;
+        number += 1;
+    }
+
+    let end = to.stream_position()?;
+    to.seek(SeekFrom::Start(start + STREAMINFO_OFFSET))?;
+    to.write_all(&stream_info(
+        block_size,
+        frame_sizes.unwrap_or((0, 0)),
+        total_samples,
+        &md5.finalize(),
+    ))?;
+    to.seek(SeekFrom::Start(end))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Cursor;
+
+    /// A second of a tone on the left, and of noise on the right, with a
+    /// stretch of silence, as little-endian interleaved samples.
+    fn samples(count: usize) -> Vec<u8>
+    {
+        let mut noise: u32 = 1;
+
+        (0..count)
+            .flat_map(|i| {
+                noise = noise.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let (left, right) = match i {
+                    | 5000..=9999 => (0, 0),
+                    | _ => {
+                        (
+                            ((i as f64 * 0.05).sin() * 20_000.0) as i16,
+                            (noise >> 16) as i16,
+                        )
+                    }
+                };
+                [left.to_le_bytes(), right.to_le_bytes()].concat()
+            })
+            .collect()
+    }
+
+    fn round_trip(
+        data: &[u8],
+        comments: &[(String, String)],
+    ) -> claxon::FlacReader<Cursor<Vec<u8>>>
+    {
+        let mut out = Cursor::new(vec![]);
+        encode(&mut &data[..], data.len() as u64, &mut out, comments).unwrap();
+
+        out.set_position(0);
+        claxon::FlacReader::new(out).unwrap()
+    }
+
+    #[test]
+    fn test_encode()
+    {
+        let data = samples(44_100);
+        let tags = [("TITLE".to_string(), "Side A".to_string())];
+        let mut reader = round_trip(&data, &tags);
+
+        let info = reader.streaminfo();
+        assert_eq!(44_100, info.sample_rate);
+        assert_eq!(2, info.channels);
+        assert_eq!(16, info.bits_per_sample);
+        assert_eq!(Some(44_100), info.samples);
+        assert_eq!(Md5::digest(&data)[..], info.md5sum);
+        assert_eq!(Some("Side A"), reader.get_tag("TITLE").next());
+
+        let decoded: Vec<u8> = reader
+            .samples()
+            .flat_map(|s| (s.unwrap() as i16).to_le_bytes())
+            .collect();
+        assert_eq!(data, decoded);
+    }
+
+    #[test]
+    fn test_compresses()
+    {
+        // A pure tone codes far smaller than its samples.
+        let data: Vec<u8> = (0..BLOCK_SIZE * 4)
+            .flat_map(|i| {
+                let s = ((i as f64 * 0.01).sin() * 10_000.0) as i16;
+                [s.to_le_bytes(), s.to_le_bytes()].concat()
+            })
+            .collect();
+
+        let mut out = Cursor::new(vec![]);
+        encode(&mut &data[..], data.len() as u64, &mut out, &[]).unwrap();
+
+        assert!(out.get_ref().len() < data.len() / 3);
+    }
+
+    #[test]
+    fn test_short_track()
+    {
+        let data = samples(588);
+        let mut reader = round_trip(&data, &[]);
+
+        assert_eq!(Some(588), reader.streaminfo().samples);
+        assert_eq!(588 * 2, reader.samples().count());
+    }
+
+    #[test]
+    fn test_coded_number()
+    {
+        assert_eq!(vec![0x7F], coded_number(0x7F));
+        assert_eq!(vec![0xC2, 0x80], coded_number(0x80));
+        assert_eq!(vec![0xEF, 0xBF, 0xBF], coded_number(0xFFFF));
+        assert_eq!(vec![0xF0, 0x90, 0x80, 0x80], coded_number(0x10000));
+    }
+}
@@ -0,0 +1,97 @@
+//! Waiting for media to be inserted into a drive, by asking the drive for its
+//! status until it reports a disc which is ready to read.
+
+use log::debug;
+use std::{
+    error::Error,
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How often a drive is asked for its status while waiting for media.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a drive reports about the media in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaStatus
+{
+    /// The drive cannot tell.
+    Unknown,
+    NoDisc,
+    TrayOpen,
+    /// There is media, but the drive is still spinning it up or reading it.
+    NotReady,
+    Ready,
+}
+
+/// A drive which can report whether it holds media.
+pub trait MediaSource
+{
+    fn media_status(&mut self) -> Result<MediaStatus, Box<dyn Error>>;
+}
+
+/// Asks `source` for its status every `poll_interval` until it reports media
+/// which is ready to read, or until `timeout` passes.  A drive which cannot
+/// be asked is taken to hold no media yet.
+pub fn wait_for_media(
+    source: &mut dyn MediaSource,
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<(), Box<dyn Error>>
+{
+    let deadline = Instant::now() + timeout;
+    let mut last = None;
+
+    loop {
+        let status = source.media_status().unwrap_or_else(|e| {
+            debug!("Could not read the drive's status: {e}");
+            MediaStatus::Unknown
+        });
+
+        if status == MediaStatus::Ready {
+            return Ok(());
+        }
+
+        if last != Some(status) {
+            debug!("Waiting for media: {status:?}");
+            last = Some(status);
+        }
+
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "No readable media was found within {} seconds (the drive \
+                 last reported {status:?}).",
+                timeout.as_secs()
+            )
+            .into());
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::simulated::SimulatedDrive;
+
+    #[test]
+    fn test_wait_for_media()
+    {
+        let mut drive = SimulatedDrive::audio(&[(1, 0, None)], 1000);
+        drive.not_ready_polls = 3;
+
+        wait_for_media(&mut drive, Duration::from_secs(5), Duration::ZERO)
+            .unwrap();
+        assert_eq!(0, drive.not_ready_polls);
+
+        let mut empty = SimulatedDrive::no_disc();
+        let error = wait_for_media(&mut empty, Duration::ZERO, Duration::ZERO)
+            .unwrap_err();
+        assert!(error.to_string().contains("NoDisc"));
+    }
+}
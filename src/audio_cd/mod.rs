@@ -0,0 +1,630 @@
+pub mod checksums;
+pub mod cue;
+pub mod data_track;
+pub mod device;
+pub mod flac;
+pub mod media;
+pub mod simulated;
+pub mod split;
+pub mod toc;
+pub mod wav;
+
+use clap::ValueEnum;
+use log::{
+    debug,
+    warn,
+};
+use media::MediaSource;
+use std::{
+    error::Error,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        BufWriter,
+        Write,
+    },
+    ops::Range,
+    path::PathBuf,
+};
+use toc::{
+    SectorMode,
+    Toc,
+    TocSource,
+    TocTrack,
+    RAW_SECTOR_SIZE,
+};
+
+/// Audio discs have no volume label, so this stands in for the system's disk
+/// label when naming their files.
+pub const AUDIO_CD_LABEL: &str = "AUDIO_CD";
+
+/// Sectors requested from the drive at once.  Linux will not read more than
+/// 75 audio frames per request.
+const READ_CHUNK_SECTORS: u32 = 75;
+
+/// Attempts at reading a sector before it is written as silence.
+const READ_ATTEMPTS: u32 = 3;
+
+/// Sectors tried when looking for a position in the Q sub-channel.
+const Q_ATTEMPTS: usize = 3;
+
+/// The file format audio tracks are split into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AudioFormat
+{
+    /// Uncompressed PCM WAV.
+    #[default]
+    Wav,
+    /// Lossless FLAC, tagged with the item's metadata.
+    Flac,
+}
+
+impl AudioFormat
+{
+    pub fn extension(&self) -> &'static str
+    {
+        match self {
+            | AudioFormat::Wav => "wav",
+            | AudioFormat::Flac => "flac",
+        }
+    }
+}
+
+/// Descriptive metadata about an item, from its CSV row, for tagging its
+/// audio tracks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemTags
+{
+    /// From the `label` column.
+    pub title: Option<String>,
+    /// From the `obj_partner_name` column.
+    pub organization: Option<String>,
+    /// From the `obj_call_number` column.
+    pub call_number: Option<String>,
+    /// From the `obj_created_date__date_free` column.
+    pub date: Option<String>,
+}
+
+impl ItemTags
+{
+    /// Vorbis comments for track `number` of `total`.
+    pub fn vorbis_comments(
+        &self,
+        number: u8,
+        total: usize,
+    ) -> Vec<(String, String)>
+    {
+        [
+            ("TITLE", &self.title),
+            ("ORGANIZATION", &self.organization),
+            ("CATALOGNUMBER", &self.call_number),
+            ("DATE", &self.date),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            value.as_ref().map(|v| (field.to_string(), v.clone()))
+        })
+        .chain([
+            ("TRACKNUMBER".to_string(), number.to_string()),
+            ("TRACKTOTAL".to_string(), total.to_string()),
+        ])
+        .collect()
+    }
+}
+
+/// A drive holding a CD which can be read as raw 2352 byte sectors.
+pub trait CdDrive: TocSource + MediaSource
+{
+    /// Reads `count` raw sectors starting at `lba`.
+    fn read_raw_sectors(
+        &mut self,
+        lba: u32,
+        count: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    /// Reads the formatted Q sub-channel of the sector at `lba`, which gives
+    /// the track and index it lies in.
+    fn read_q_subchannel(
+        &mut self,
+        lba: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+/// What was written by `rip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RipReport
+{
+    pub toc: Toc,
+    pub sectors: u32,
+    /// Sectors which could not be read, and were written as silence.
+    pub unreadable: Vec<u32>,
+}
+
+/// Reads the disc's table of contents and reports whether it is an audio
+/// (CD-DA) disc.  A drive which cannot report a TOC is taken to hold
+/// something else.
+pub fn is_audio_disc(source: &mut dyn TocSource) -> bool
+{
+    match source.read_toc() {
+        | Ok(toc) => toc.is_audio(),
+        | Err(e) => {
+            debug!("No table of contents could be read: {e}");
+            false
+        }
+    }
+}
+
+/// Reads the disc's table of contents and reports whether it mixes audio
+/// and data tracks, as an Enhanced CD does.
+pub fn is_mixed_disc(source: &mut dyn TocSource) -> bool
+{
+    match source.read_toc() {
+        | Ok(toc) => toc.is_mixed(),
+        | Err(e) => {
+            debug!("No table of contents could be read: {e}");
+            false
+        }
+    }
+}
+
+/// Reads the mode of each data track from the header of its first sector.
+/// Tracks whose first sector cannot be read are left unknown.
+pub fn read_data_modes(drive: &mut dyn CdDrive) -> Result<Toc, Box<dyn Error>>
+{
+    let mut toc = drive.read_toc()?;
+
+    for track in toc.tracks.iter_mut().filter(|t| !t.is_audio()) {
+        match read_with_retries(drive, track.start, 1) {
+            | Ok(sector) => track.data_mode = toc::sector_mode(&sector),
+            | Err(e) => {
+                debug!("Track {} header could not be read: {e}", track.number)
+            }
+        }
+    }
+
+    Ok(toc)
+}
+
+/// Finds where the pregap (index 00) before each track after the first
+/// starts, which the TOC does not give, from the Q sub-channel.  The sectors
+/// of a pregap give the number of the track after them, so the first such
+/// sector is searched for between the start of the track before and the
+/// track's own start.  Pregaps which cannot be found are left unknown.  The
+/// tracks must be in order, as `Toc::validate` checks.
+pub fn find_pregaps(
+    drive: &mut dyn CdDrive,
+    toc: &mut Toc,
+)
+{
+    for i in 1..toc.tracks.len() {
+        let previous = toc.tracks[i - 1].start;
+        let track = toc.tracks[i];
+
+        // The first track of a later session follows the gap between
+        // sessions rather than another track.
+        if toc.session(previous) != toc.session(track.start) {
+            continue;
+        }
+
+        let mut probe = |lba| in_track(drive, lba, &track);
+
+        let pregap_start = match probe(track.start - 1) {
+            | Ok(false) => continue,
+            | Ok(true) => {
+                let (mut low, mut high) = (previous + 1, track.start - 1);
+
+                while low < high {
+                    let middle = low + (high - low) / 2;
+
+                    match probe(middle) {
+                        | Ok(true) => high = middle,
+                        | Ok(false) => low = middle + 1,
+                        | Err(e) => {
+                            debug!(
+                                "Track {} pregap could not be found: {e}",
+                                track.number
+                            );
+                            break;
+                        }
+                    }
+                }
+
+                match low == high {
+                    | true => high,
+                    | false => continue,
+                }
+            }
+            | Err(e) => {
+                // Drives which cannot read the Q sub-channel fail at every
+                // sector alike.
+                debug!("No pregaps could be found: {e}");
+                return;
+            }
+        };
+
+        toc.tracks[i].pregap_start = Some(pregap_start);
+    }
+}
+
+/// Whether the Q sub-channel puts the sector at `lba` in `track` (or its
+/// pregap) rather than in the track before.  A sector whose Q sub-channel
+/// gives something other than its position is judged by the next few,
+/// which can misplace a pregap's start by as many sectors.
+fn in_track(
+    drive: &mut dyn CdDrive,
+    lba: u32,
+    track: &TocTrack,
+) -> Result<bool, Box<dyn Error>>
+{
+    for sector in (lba..track.start).take(Q_ATTEMPTS) {
+        let q = drive.read_q_subchannel(sector)?;
+
+        if let Some(position) = toc::q_position(&q) {
+            return Ok(position.track >= track.number);
+        }
+    }
+
+    Err(format!("Sector {lba} has no position in its Q sub-channel.").into())
+}
+
+/// Why the disc in `drive` must be captured as raw sectors rather than as a
+/// 2048-byte ISO, if it must: a data track in CD-ROM XA Mode 2 Form 2, as on
+/// Video CDs, whose sectors a 2048-byte read drops or corrupts.
+pub fn raw_capture_reason(drive: &mut dyn CdDrive) -> Option<String>
+{
+    let toc = match read_data_modes(drive) {
+        | Ok(toc) => toc,
+        | Err(e) => {
+            debug!("No table of contents could be read: {e}");
+            return None;
+        }
+    };
+
+    toc.tracks
+        .iter()
+        .find(|t| t.data_mode == Some(SectorMode::Mode2Form2))
+        .map(|t| {
+            format!(
+                "Track {} holds Mode 2 Form 2 sectors, which a 2048-byte \
+                 image drops or corrupts.",
+                t.number
+            )
+        })
+}
+
+/// Writes the disc from LBA 0 to the lead-out to `bin` as raw sectors, with
+/// a cue sheet describing its tracks at `cue`.  Sectors which cannot be read
+/// after several attempts are written as silence (or zeros, in data tracks)
+/// and listed in the report, as `dd conv=noerror,sync` does for data discs.
+///
+/// On a multisession disc, such as an Enhanced CD, `bin` ends with the first
+/// session, and each later session is written to a file of its own beside
+/// it, as named by `cue::session_file_name`.  The gaps between sessions hold
+/// no readable sectors, so are skipped.
+pub fn rip(
+    drive: &mut dyn CdDrive,
+    bin: &PathBuf,
+    cue: &PathBuf,
+) -> Result<RipReport, Box<dyn Error>>
+{
+    let mut toc = read_data_modes(drive)?;
+    toc.validate()?;
+    find_pregaps(drive, &mut toc);
+
+    let bin_name = bin
+        .file_name()
+        .ok_or(format!("{bin:?} has no file name."))?
+        .to_string_lossy();
+
+    let mut unreadable = vec![];
+    let mut sectors = 0;
+
+    for (i, session) in toc.sessions().into_iter().enumerate() {
+        let path = bin.with_file_name(cue::session_file_name(&bin_name, i + 1));
+        let mut out = BufWriter::new(
+            File::create(&path)
+                .map_err(|e| format!("Could not create {path:?}: {e}"))?,
+        );
+
+        sectors += session.len() as u32;
+        unreadable.extend(read_sectors(drive, session, &mut out)?);
+        out.flush()?;
+    }
+
+    fs::write(cue, cue::cue_sheet(&toc, &bin_name))
+        .map_err(|e| format!("Could not write {cue:?}: {e}"))?;
+
+    Ok(RipReport {
+        toc,
+        sectors,
+        unreadable,
+    })
+}
+
+/// Writes the sectors in `range` to `out`, returning those which could not
+/// be read and were written as zeros.
+fn read_sectors(
+    drive: &mut dyn CdDrive,
+    range: Range<u32>,
+    out: &mut impl Write,
+) -> Result<Vec<u32>, Box<dyn Error>>
+{
+    let mut unreadable = vec![];
+
+    let mut lba = range.start;
+    while lba < range.end {
+        let count = READ_CHUNK_SECTORS.min(range.end - lba);
+
+        match read_with_retries(drive, lba, count) {
+            | Ok(data) => out.write_all(&data)?,
+            | Err(e) => {
+                debug!("Reading {count} sectors at {lba} failed: {e}");
+
+                // Fall back to single sectors to lose as little as possible.
+                for sector in lba..lba + count {
+                    match read_with_retries(drive, sector, 1) {
+                        | Ok(data) => out.write_all(&data)?,
+                        | Err(e) => {
+                            warn!("Sector {sector} could not be read: {e}");
+                            unreadable.push(sector);
+                            out.write_all(&[0; RAW_SECTOR_SIZE])?;
+                        }
+                    }
+                }
+            }
+        }
+
+        lba += count;
+    }
+
+    Ok(unreadable)
+}
+
+fn read_with_retries(
+    drive: &mut dyn CdDrive,
+    lba: u32,
+    count: u32,
+) -> Result<Vec<u8>, Box<dyn Error>>
+{
+    let mut attempt = 1;
+
+    loop {
+        match drive.read_raw_sectors(lba, count) {
+            | Ok(data) if data.len() == count as usize * RAW_SECTOR_SIZE => {
+                return Ok(data)
+            }
+            | Ok(data) => {
+                if attempt >= READ_ATTEMPTS {
+                    return Err(format!(
+                        "Read returned {} bytes rather than {}.",
+                        data.len(),
+                        count as usize * RAW_SECTOR_SIZE
+                    )
+                    .into());
+                }
+            }
+            | Err(e) if attempt >= READ_ATTEMPTS => return Err(e),
+            | Err(_) => (),
+        }
+
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use simulated::SimulatedDrive;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rip()
+    {
+        let mut drive =
+            SimulatedDrive::audio(&[(1, 0, None), (2, 100, Some(90))], 160);
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("CR001_AUDIO_CD.bin");
+        let cue = out.path().join("CR001_AUDIO_CD.cue");
+
+        let report = rip(&mut drive, &bin, &cue).unwrap();
+
+        assert_eq!(160, report.sectors);
+        assert!(report.unreadable.is_empty());
+
+        let data = fs::read(&bin).unwrap();
+        assert_eq!(160 * RAW_SECTOR_SIZE, data.len());
+        assert_eq!(
+            &drive.sector(101)[..],
+            &data[101 * RAW_SECTOR_SIZE..102 * RAW_SECTOR_SIZE]
+        );
+
+        let cue = fs::read_to_string(&cue).unwrap();
+        assert!(cue.starts_with("FILE \"CR001_AUDIO_CD.bin\" BINARY"));
+        assert!(cue.contains("TRACK 02 AUDIO\r\n    INDEX 00 00:01:15"));
+    }
+
+    #[test]
+    fn test_find_pregaps()
+    {
+        let mut drive = SimulatedDrive::audio(
+            &[
+                (1, 0, None),
+                (2, 1000, Some(850)),
+                (3, 2000, None),
+                (4, 3000, Some(2999)),
+            ],
+            4000,
+        );
+        let mut toc = drive.read_toc().unwrap();
+        assert!(toc.tracks.iter().all(|t| t.pregap_start.is_none()));
+
+        find_pregaps(&mut drive, &mut toc);
+        assert_eq!(drive.toc.as_ref().unwrap(), &toc);
+
+        // Without the Q sub-channel, pregaps are left unknown.
+        drive.unreadable = (0..4000).collect();
+        let mut toc = drive.read_toc().unwrap();
+        find_pregaps(&mut drive, &mut toc);
+        assert!(toc.tracks.iter().all(|t| t.pregap_start.is_none()));
+    }
+
+    #[test]
+    fn test_unreadable_sectors()
+    {
+        let mut drive = SimulatedDrive::audio(&[(1, 0, None)], 100);
+        drive.unreadable = vec![80, 81];
+
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("a.bin");
+        let cue = out.path().join("a.cue");
+
+        let report = rip(&mut drive, &bin, &cue).unwrap();
+
+        assert_eq!(vec![80, 81], report.unreadable);
+
+        let data = fs::read(&bin).unwrap();
+        assert_eq!(100 * RAW_SECTOR_SIZE, data.len());
+        assert!(data[80 * RAW_SECTOR_SIZE..82 * RAW_SECTOR_SIZE]
+            .iter()
+            .all(|b| *b == 0));
+        assert_eq!(
+            &drive.sector(82)[..],
+            &data[82 * RAW_SECTOR_SIZE..83 * RAW_SECTOR_SIZE]
+        );
+    }
+
+    #[test]
+    fn test_is_audio_disc()
+    {
+        assert!(is_audio_disc(&mut SimulatedDrive::audio(
+            &[(1, 0, None)],
+            10
+        )));
+        assert!(!is_audio_disc(&mut SimulatedDrive::no_disc()));
+    }
+
+    #[test]
+    fn test_mixed_disc()
+    {
+        let mut drive = SimulatedDrive::audio(&[(1, 0, None)], 200);
+        assert!(!is_mixed_disc(&mut drive));
+
+        drive.toc.as_mut().unwrap().tracks.push(toc::TocTrack {
+            number: 2,
+            control: toc::CONTROL_DATA,
+            start: 100,
+            pregap_start: None,
+            data_mode: Some(SectorMode::Mode1),
+        });
+        assert!(is_mixed_disc(&mut drive));
+        assert!(!is_audio_disc(&mut drive));
+
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("CR001_GAME.bin");
+        let cue = out.path().join("CR001_GAME.cue");
+        let report = rip(&mut drive, &bin, &cue).unwrap();
+
+        assert_eq!("MODE1/2352", report.toc.layout()[1].track_type);
+
+        let iso = out.path().join("CR001_GAME.iso");
+        let extracted =
+            data_track::extract_data_track(&cue, &iso).unwrap().unwrap();
+
+        assert_eq!(100, extracted.sectors);
+        assert!(extracted.unconverted.is_empty());
+        assert_eq!(200 * 2048, fs::metadata(&iso).unwrap().len());
+    }
+
+    #[test]
+    fn test_enhanced_cd()
+    {
+        let mut drive = SimulatedDrive::enhanced_cd();
+        assert!(is_mixed_disc(&mut drive));
+
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("CR001_AUDIO_CD.bin");
+        let cue = out.path().join("CR001_AUDIO_CD.cue");
+        let report = rip(&mut drive, &bin, &cue).unwrap();
+
+        // The gap between the sessions is neither read nor written.
+        assert!(report.unreadable.is_empty());
+        assert_eq!(700, report.sectors);
+        assert_eq!(600, report.toc.layout()[1].end);
+
+        let audio = fs::read(&bin).unwrap();
+        assert_eq!(600 * RAW_SECTOR_SIZE, audio.len());
+
+        let data =
+            fs::read(out.path().join("CR001_AUDIO_CD_SESSION_02.bin")).unwrap();
+        assert_eq!(100 * RAW_SECTOR_SIZE, data.len());
+        assert_eq!(&drive.sector(12_000)[..], &data[..RAW_SECTOR_SIZE]);
+
+        let text = fs::read_to_string(&cue).unwrap();
+        assert_eq!(
+            [
+                "REM SESSION 01",
+                "FILE \"CR001_AUDIO_CD.bin\" BINARY",
+                "  TRACK 01 AUDIO",
+                "    INDEX 01 00:00:00",
+                "  TRACK 02 AUDIO",
+                "    INDEX 01 00:04:00",
+                "REM SESSION 02",
+                "FILE \"CR001_AUDIO_CD_SESSION_02.bin\" BINARY",
+                "  TRACK 03 MODE1/2352",
+                "    INDEX 01 00:00:00",
+                "",
+            ]
+            .join("\r\n"),
+            text
+        );
+
+        let iso = out.path().join("CR001_AUDIO_CD.iso");
+        let extracted =
+            data_track::extract_data_track(&cue, &iso).unwrap().unwrap();
+
+        assert_eq!(12_000, extracted.start);
+        assert_eq!(100, extracted.sectors);
+        assert_eq!(12_100 * 2048, fs::metadata(&iso).unwrap().len());
+    }
+
+    #[test]
+    fn test_video_cd()
+    {
+        let mut drive = SimulatedDrive::video_cd();
+        assert!(!is_audio_disc(&mut drive));
+        assert!(raw_capture_reason(&mut drive)
+            .unwrap()
+            .starts_with("Track 2 holds Mode 2 Form 2"));
+
+        let out = TempDir::new().unwrap();
+        let bin = out.path().join("CR001_VIDEO.bin");
+        let cue = out.path().join("CR001_VIDEO.cue");
+        rip(&mut drive, &bin, &cue).unwrap();
+
+        let data = fs::read(&bin).unwrap();
+        assert_eq!(1200 * RAW_SECTOR_SIZE, data.len());
+        assert_eq!(
+            Some(SectorMode::Mode2Form2),
+            toc::sector_mode(&data[700 * RAW_SECTOR_SIZE..])
+        );
+
+        let cue = fs::read_to_string(&cue).unwrap();
+        assert!(cue.contains("TRACK 01 MODE2/2352"));
+        assert!(cue.contains("TRACK 02 MODE2/2352"));
+    }
+
+    #[test]
+    fn test_mode_1_disc()
+    {
+        let mut drive = SimulatedDrive::video_cd();
+        for track in &mut drive.toc.as_mut().unwrap().tracks {
+            track.data_mode = Some(SectorMode::Mode1);
+        }
+
+        assert_eq!(None, raw_capture_reason(&mut drive));
+        assert_eq!(None, raw_capture_reason(&mut SimulatedDrive::no_disc()));
+    }
+}
@@ -0,0 +1,267 @@
+use super::{
+    media::{
+        MediaSource,
+        MediaStatus,
+    },
+    toc::{
+        Msf,
+        SectorMode,
+        Toc,
+        TocSource,
+        TocTrack,
+        CONTROL_DATA,
+        Q_SUBCHANNEL_SIZE,
+        RAW_SECTOR_SIZE,
+        SESSION_GAP_SECTORS,
+        SYNC,
+    },
+    CdDrive,
+};
+use std::error::Error;
+
+/// A drive which serves a made-up disc from memory, so that the intake of
+/// audio discs can be exercised without hardware.  Each sector is filled
+/// with a pattern derived from its address.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedDrive
+{
+    /// `None` when the drive is empty or the disc has no readable TOC.
+    pub toc: Option<Toc>,
+    /// Sectors which fail to read, as on a scratched disc.
+    pub unreadable: Vec<u32>,
+    /// How many more times the drive will report its disc as not ready, as
+    /// while it spins up.
+    pub not_ready_polls: u32,
+}
+
+impl SimulatedDrive
+{
+    /// An audio disc with the given (track number, start, pregap start)
+    /// tracks, ending at `lead_out`.
+    pub fn audio(
+        tracks: &[(u8, u32, Option<u32>)],
+        lead_out: u32,
+    ) -> SimulatedDrive
+    {
+        let tracks = tracks
+            .iter()
+            .map(|(number, start, pregap_start)| {
+                TocTrack {
+                    number: *number,
+                    control: 0,
+                    start: *start,
+                    pregap_start: *pregap_start,
+                    data_mode: None,
+                }
+            })
+            .collect();
+
+        SimulatedDrive {
+            toc: Some(Toc {
+                tracks,
+                lead_out,
+                session_lead_outs: vec![],
+            }),
+            unreadable: vec![],
+            not_ready_polls: 0,
+        }
+    }
+
+    /// A drive with no disc in it.
+    pub fn no_disc() -> SimulatedDrive
+    {
+        SimulatedDrive::default()
+    }
+
+    /// A Video CD: an ISO 9660 track in Mode 2 Form 1, then an MPEG track in
+    /// Mode 2 Form 2.
+    pub fn video_cd() -> SimulatedDrive
+    {
+        let track = |number, start, data_mode| {
+            TocTrack {
+                number,
+                control: CONTROL_DATA,
+                start,
+                pregap_start: None,
+                data_mode: Some(data_mode),
+            }
+        };
+
+        SimulatedDrive {
+            toc: Some(Toc {
+                tracks: vec![
+                    track(1, 0, SectorMode::Mode2Form1),
+                    track(2, 600, SectorMode::Mode2Form2),
+                ],
+                lead_out: 1200,
+                session_lead_outs: vec![],
+            }),
+            unreadable: vec![],
+            not_ready_polls: 0,
+        }
+    }
+
+    /// An Enhanced CD (CD-Extra): two audio tracks in the first session, then
+    /// a Mode 1 data track in a second session.
+    pub fn enhanced_cd() -> SimulatedDrive
+    {
+        let mut drive =
+            SimulatedDrive::audio(&[(1, 0, None), (2, 300, None)], 600);
+        let toc = drive.toc.as_mut().unwrap();
+
+        toc.tracks.push(TocTrack {
+            number: 3,
+            control: CONTROL_DATA,
+            start: 600 + SESSION_GAP_SECTORS,
+            pregap_start: None,
+            data_mode: Some(SectorMode::Mode1),
+        });
+        toc.session_lead_outs = vec![600];
+        toc.lead_out = 700 + SESSION_GAP_SECTORS;
+
+        drive
+    }
+
+    /// The contents of the sector at `lba`.  Sectors of data tracks start
+    /// with a header giving the track's `data_mode`.
+    pub fn sector(
+        &self,
+        lba: u32,
+    ) -> Vec<u8>
+    {
+        let seed = lba.to_le_bytes();
+
+        let mut sector: Vec<u8> = (0..RAW_SECTOR_SIZE)
+            .map(|i| seed[i % 4].wrapping_add((i / 4) as u8))
+            .collect();
+
+        let data_mode = self
+            .toc
+            .as_ref()
+            .and_then(|toc| toc.track_at(lba))
+            .filter(|t| !t.is_audio())
+            .and_then(|t| t.data_mode);
+
+        if let Some(mode) = data_mode {
+            let (mode, submode) = match mode {
+                | SectorMode::Mode0 => (0, 0),
+                | SectorMode::Mode1 => (1, 0),
+                | SectorMode::Mode2 => (2, 0),
+                | SectorMode::Mode2Form1 => (2, 0x08),
+                | SectorMode::Mode2Form2 => (2, 0x20),
+            };
+
+            let msf = Msf::from_lba(lba);
+            let bcd = |n: u32| (((n / 10) << 4) | (n % 10)) as u8;
+            sector[..12].copy_from_slice(&SYNC);
+            sector[12..16].copy_from_slice(&[
+                bcd(msf.minutes),
+                bcd(msf.seconds),
+                bcd(msf.frames),
+                mode,
+            ]);
+            if mode == 2 && submode != 0 {
+                sector[16..20].copy_from_slice(&[0, 1, submode, 0]);
+                sector[20..24].copy_from_slice(&[0, 1, submode, 0]);
+            }
+        }
+
+        sector
+    }
+}
+
+impl TocSource for SimulatedDrive
+{
+    fn read_toc(&mut self) -> Result<Toc, Box<dyn Error>>
+    {
+        let mut toc =
+            self.toc.clone().ok_or("The simulated drive has no disc.")?;
+
+        // As from a real drive, data modes are only found in the sectors,
+        // and pregaps in the Q sub-channel.
+        toc.tracks.iter_mut().for_each(|t| {
+            t.data_mode = None;
+            t.pregap_start = None;
+        });
+
+        Ok(toc)
+    }
+}
+
+impl MediaSource for SimulatedDrive
+{
+    fn media_status(&mut self) -> Result<MediaStatus, Box<dyn Error>>
+    {
+        if self.toc.is_none() {
+            return Ok(MediaStatus::NoDisc);
+        }
+
+        match self.not_ready_polls {
+            | 0 => Ok(MediaStatus::Ready),
+            | _ => {
+                self.not_ready_polls -= 1;
+                Ok(MediaStatus::NotReady)
+            }
+        }
+    }
+}
+
+impl CdDrive for SimulatedDrive
+{
+    fn read_raw_sectors(
+        &mut self,
+        lba: u32,
+        count: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        let toc = self.read_toc()?;
+
+        if lba + count > toc.lead_out {
+            return Err(format!(
+                "Sector {} is past the lead-out.",
+                lba + count
+            )
+            .into());
+        }
+
+        // As on a real disc, the gap between sessions cannot be read.
+        let sessions = toc.sessions();
+        if let Some(gap) = (lba..lba + count)
+            .find(|s| !sessions.iter().any(|session| session.contains(s)))
+        {
+            return Err(format!("Sector {gap} lies between sessions.").into());
+        }
+
+        if let Some(bad) = self
+            .unreadable
+            .iter()
+            .find(|s| (lba..lba + count).contains(s))
+        {
+            return Err(format!("Sector {bad} is unreadable.").into());
+        }
+
+        Ok((lba..lba + count).flat_map(|s| self.sector(s)).collect())
+    }
+
+    fn read_q_subchannel(
+        &mut self,
+        lba: u32,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        self.read_raw_sectors(lba, 1)?;
+
+        let track = self
+            .toc
+            .as_ref()
+            .and_then(|toc| toc.track_at(lba))
+            .ok_or("The simulated drive has no disc.")?;
+        let bcd = |n: u8| ((n / 10) << 4) | (n % 10);
+
+        let mut q = vec![0; Q_SUBCHANNEL_SIZE];
+        q[0] = track.control << 4 | 1;
+        q[1] = bcd(track.number);
+        q[2] = bcd((lba >= track.start) as u8);
+
+        Ok(q)
+    }
+}
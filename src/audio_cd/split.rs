@@ -0,0 +1,285 @@
+use super::{
+    cue::{
+        parse_cue_sheet,
+        CueFileType,
+    },
+    flac,
+    toc::RAW_SECTOR_SIZE,
+    wav::{
+        self,
+        BLOCK_ALIGN,
+    },
+    AudioFormat,
+    ItemTags,
+};
+use std::{
+    error::Error,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        BufWriter,
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Reads one track's samples from a BIN file as little-endian samples,
+/// swapping the bytes of big-endian (MOTOROLA) files.
+struct TrackSamples<'a>
+{
+    bin: &'a mut File,
+    remaining: u64,
+    swap: bool,
+}
+
+impl Read for TrackSamples<'_>
+{
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> io::Result<usize>
+    {
+        // Whole samples only, so that no sample is split between reads.
+        let count = (buf.len() as u64).min(self.remaining) as usize & !1;
+        if count == 0 {
+            return Ok(0);
+        }
+
+        self.bin.read_exact(&mut buf[..count])?;
+        self.remaining -= count as u64;
+
+        if self.swap {
+            buf[..count].chunks_exact_mut(2).for_each(|s| s.swap(0, 1));
+        }
+
+        Ok(count)
+    }
+}
+
+/// Writes each audio track listed in the cue sheet at `cue` to
+/// `to/<prefix>_tNN.wav` (or `.flac`), returning the paths written.  Data
+/// files are found relative to the cue sheet, and data tracks are skipped.
+/// FLAC files are tagged from `tags`.
+pub fn split_tracks(
+    cue: &PathBuf,
+    to: &Path,
+    prefix: &str,
+    format: AudioFormat,
+    tags: &ItemTags,
+) -> Result<Vec<PathBuf>, Box<dyn Error>>
+{
+    let text = fs::read_to_string(cue)
+        .map_err(|e| format!("Could not read {cue:?}: {e}"))?;
+    let files = parse_cue_sheet(&text)?;
+    let cue_dir = cue.parent().unwrap_or(Path::new("."));
+
+    let track_total = files
+        .iter()
+        .flat_map(|f| &f.tracks)
+        .filter(|t| t.audio)
+        .count();
+
+    let mut written = vec![];
+
+    for file in &files {
+        let bin_path = cue_dir.join(&file.name);
+        let mut bin = File::open(&bin_path)
+            .map_err(|e| format!("Could not open {bin_path:?}: {e}"))?;
+        let bin_size = bin.metadata()?.len();
+
+        for (i, track) in file.tracks.iter().enumerate() {
+            if !track.audio {
+                continue;
+            }
+
+            let start = track.start as u64 * RAW_SECTOR_SIZE as u64;
+            let end = file
+                .track_end(i)
+                .map(|e| e as u64 * RAW_SECTOR_SIZE as u64)
+                .unwrap_or(bin_size)
+                .min(bin_size);
+
+            if end <= start {
+                return Err(format!(
+                    "Track {} lies outside {bin_path:?}.",
+                    track.number
+                )
+                .into());
+            }
+
+            // Drop any partial sample frame at the end of a short file.
+            let length =
+                (end - start) / BLOCK_ALIGN as u64 * BLOCK_ALIGN as u64;
+
+            bin.seek(SeekFrom::Start(start))?;
+            let mut samples = TrackSamples {
+                bin: &mut bin,
+                remaining: length,
+                swap: file.file_type == CueFileType::Motorola,
+            };
+
+            let path = to.join(format!(
+                "{prefix}_t{:02}.{}",
+                track.number,
+                format.extension()
+            ));
+
+            match format {
+                | AudioFormat::Wav => {
+                    let length = u32::try_from(length).map_err(|_| {
+                        format!(
+                            "Track {} is too long for a WAV file.",
+                            track.number
+                        )
+                    })?;
+
+                    wav::write_wav(&mut samples, length, &path)?;
+                }
+                | AudioFormat::Flac => {
+                    let mut out =
+                        BufWriter::new(File::create(&path).map_err(|e| {
+                            format!("Could not create {path:?}: {e}")
+                        })?);
+
+                    flac::encode(
+                        &mut samples,
+                        length,
+                        &mut out,
+                        &tags.vorbis_comments(track.number, track_total),
+                    )?;
+                    out.flush()?;
+                }
+            }
+
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::{
+        rip,
+        simulated::SimulatedDrive,
+        wav::wav_header,
+    };
+    use tempfile::TempDir;
+
+    fn ripped_disc(out: &Path) -> (SimulatedDrive, PathBuf)
+    {
+        let drive =
+            SimulatedDrive::audio(&[(1, 0, None), (2, 100, Some(90))], 160);
+        let bin = out.join("CR001_AUDIO_CD.bin");
+        let cue = out.join("CR001_AUDIO_CD.cue");
+        rip(&mut drive.clone(), &bin, &cue).unwrap();
+
+        (drive, cue)
+    }
+
+    #[test]
+    fn test_split_tracks()
+    {
+        let out = TempDir::new().unwrap();
+        let (drive, cue) = ripped_disc(out.path());
+
+        let written = split_tracks(
+            &cue,
+            out.path(),
+            "CR001_AUDIO_CD",
+            AudioFormat::Wav,
+            &ItemTags::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                out.path().join("CR001_AUDIO_CD_t01.wav"),
+                out.path().join("CR001_AUDIO_CD_t02.wav"),
+            ],
+            written
+        );
+
+        // The pregap of track 2 stays at the end of track 1.
+        let t1 = fs::read(&written[0]).unwrap();
+        assert_eq!(44 + 100 * RAW_SECTOR_SIZE, t1.len());
+        assert_eq!(wav_header(100 * RAW_SECTOR_SIZE as u32), t1[..44]);
+
+        let t2 = fs::read(&written[1]).unwrap();
+        assert_eq!(44 + 60 * RAW_SECTOR_SIZE, t2.len());
+        assert_eq!(&drive.sector(100)[..], &t2[44..44 + RAW_SECTOR_SIZE]);
+    }
+
+    #[test]
+    fn test_split_flac()
+    {
+        let out = TempDir::new().unwrap();
+        let (drive, cue) = ripped_disc(out.path());
+        let tags = ItemTags {
+            title: Some("Oral histories, disc 1".to_string()),
+            organization: Some("Example Library".to_string()),
+            call_number: Some("CR001".to_string()),
+            date: Some("circa 1998".to_string()),
+        };
+
+        let written =
+            split_tracks(&cue, out.path(), "CR001", AudioFormat::Flac, &tags)
+                .unwrap();
+
+        assert_eq!(out.path().join("CR001_t02.flac"), written[1]);
+
+        let mut reader = claxon::FlacReader::open(&written[1]).unwrap();
+        assert_eq!(Some("2"), reader.get_tag("TRACKNUMBER").next());
+        assert_eq!(Some("2"), reader.get_tag("TRACKTOTAL").next());
+        assert_eq!(
+            Some("Example Library"),
+            reader.get_tag("ORGANIZATION").next()
+        );
+
+        let decoded: Vec<u8> = reader
+            .samples()
+            .flat_map(|s| (s.unwrap() as i16).to_le_bytes())
+            .collect();
+        let expected: Vec<u8> =
+            (100..160).flat_map(|s| drive.sector(s)).collect();
+        assert_eq!(expected, decoded);
+    }
+
+    #[test]
+    fn test_split_motorola()
+    {
+        let out = TempDir::new().unwrap();
+        fs::write(out.path().join("a.bin"), [1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let cue = out.path().join("a.cue");
+        fs::write(
+            &cue,
+            "FILE \"a.bin\" MOTOROLA\n  TRACK 01 AUDIO\n    INDEX 01 \
+             00:00:00\n",
+        )
+        .unwrap();
+
+        let written = split_tracks(
+            &cue,
+            out.path(),
+            "a",
+            AudioFormat::Wav,
+            &ItemTags::default(),
+        )
+        .unwrap();
+
+        let t1 = fs::read(&written[0]).unwrap();
+        assert_eq!([2, 1, 4, 3, 6, 5, 8, 7], t1[44..]);
+    }
+}
@@ -0,0 +1,638 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    error::Error,
+    fmt,
+    ops::Range,
+};
+
+/// Size of a raw CD sector: 2352 bytes of audio, or of a data sector with
+/// its sync, header and error correction.
+pub const RAW_SECTOR_SIZE: usize = 2352;
+
+/// CD sectors (frames) per second of audio.
+pub const FRAMES_PER_SECOND: u32 = 75;
+
+/// The two second gap before track 1 which is not addressable, so LBA 0 is
+/// MSF 00:02:00.
+pub const LEAD_IN_FRAMES: u32 = 150;
+
+/// Sectors between a session's lead-out and the first track of the next
+/// session: the lead-out (6750 sectors, for a first session), the next lead-in
+/// (4500) and the two second pregap.  None of them can be read as a track.
+pub const SESSION_GAP_SECTORS: u32 = 11_400;
+
+/// Q sub-channel control bits.
+pub const CONTROL_PRE_EMPHASIS: u8 = 0x1;
+pub const CONTROL_COPY_PERMITTED: u8 = 0x2;
+pub const CONTROL_DATA: u8 = 0x4;
+pub const CONTROL_FOUR_CHANNEL: u8 = 0x8;
+
+/// A track as listed in the table of contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TocTrack
+{
+    pub number: u8,
+    /// Q sub-channel control nibble.
+    pub control: u8,
+    /// Logical block address of index 01, where the track proper starts.
+    pub start: u32,
+    /// Logical block address of index 00, where the pregap before the track
+    /// starts, when the source knows it.  The TOC itself does not record
+    /// pregaps; they come from the Q sub-channel.
+    pub pregap_start: Option<u32>,
+    /// For data tracks, the mode given in the header of the track's first
+    /// sector, once it has been read.
+    pub data_mode: Option<SectorMode>,
+}
+
+impl TocTrack
+{
+    pub fn is_audio(&self) -> bool
+    {
+        self.control & CONTROL_DATA == 0
+    }
+
+    /// The track type used in cue sheets.  Data tracks of unknown mode are
+    /// taken to be Mode 1.
+    pub fn track_type(&self) -> &'static str
+    {
+        match self.is_audio() {
+            | true => "AUDIO",
+            | false => {
+                self.data_mode.map(|m| m.cue_type()).unwrap_or("MODE1/2352")
+            }
+        }
+    }
+}
+
+/// Where a track lies on the disc, as recorded in the item metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackLayout
+{
+    pub number: u8,
+    /// The cue sheet track type, such as "AUDIO" or "MODE1/2352".
+    pub track_type: String,
+    /// Logical block addresses of index 00 (if known) and index 01, and of
+    /// the first sector after the track.
+    pub pregap_start: Option<u32>,
+    pub start: u32,
+    pub end: u32,
+}
+
+/// How a data sector lays out its 2352 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorMode
+{
+    /// All zeros, as in some pregaps.
+    Mode0,
+    /// 2048 bytes of data with error correction, as on most CD-ROMs.
+    Mode1,
+    /// Mode 2 without the CD-ROM XA subheader: 2336 bytes of data.
+    Mode2,
+    /// CD-ROM XA Form 1: 2048 bytes of data with error correction.
+    Mode2Form1,
+    /// CD-ROM XA Form 2: 2324 bytes of data without error correction, as
+    /// used for Video CD MPEG streams.  A 2048-byte read loses these.
+    Mode2Form2,
+}
+
+impl SectorMode
+{
+    /// The track type used in cue sheets.
+    pub fn cue_type(&self) -> &'static str
+    {
+        match self {
+            | SectorMode::Mode0 | SectorMode::Mode1 => "MODE1/2352",
+            | _ => "MODE2/2352",
+        }
+    }
+}
+
+/// The sync pattern which starts every raw data sector.
+pub const SYNC: [u8; 12] = [
+    0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00,
+];
+
+/// The submode bit of a CD-ROM XA subheader which marks Form 2.
+const SUBMODE_FORM_2: u8 = 0x20;
+
+/// Reads the mode from the header of a raw 2352 byte data sector, or `None`
+/// if it does not start with a sync pattern (as audio sectors do not).
+pub fn sector_mode(raw: &[u8]) -> Option<SectorMode>
+{
+    if raw.len() < RAW_SECTOR_SIZE || raw[..12] != SYNC {
+        return None;
+    }
+
+    match raw[15] {
+        | 0 => Some(SectorMode::Mode0),
+        | 1 => Some(SectorMode::Mode1),
+        // XA repeats the subheader; data which is not a subheader rarely
+        // does.
+        | 2 if raw[16..20] == raw[20..24] => {
+            match raw[18] & SUBMODE_FORM_2 {
+                | 0 => Some(SectorMode::Mode2Form1),
+                | _ => Some(SectorMode::Mode2Form2),
+            }
+        }
+        | 2 => Some(SectorMode::Mode2),
+        | _ => None,
+    }
+}
+
+/// Reads the disc address from the header of a raw 2352 byte data sector,
+/// or `None` if it does not start with a sync pattern.
+pub fn sector_address(raw: &[u8]) -> Option<u32>
+{
+    if raw.len() < RAW_SECTOR_SIZE || raw[..12] != SYNC {
+        return None;
+    }
+
+    let bcd = |b: u8| (b >> 4) as u32 * 10 + (b & 0x0F) as u32;
+
+    Msf {
+        minutes: bcd(raw[12]),
+        seconds: bcd(raw[13]),
+        frames: bcd(raw[14]),
+    }
+    .to_lba()
+}
+
+/// Size of the formatted Q sub-channel of a sector, as drives return it.
+pub const Q_SUBCHANNEL_SIZE: usize = 16;
+
+/// Where a sector lies, from its Q sub-channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QPosition
+{
+    pub track: u8,
+    /// 0 in the pregap before a track, 1 from where the track proper starts.
+    pub index: u8,
+}
+
+/// Reads the track and index from a sector's formatted Q sub-channel, or
+/// `None` if it gives something other than the position (ADR 1), as about
+/// one sector in a hundred gives the catalogue number or an ISRC instead.
+pub fn q_position(q: &[u8]) -> Option<QPosition>
+{
+    if q.len() < Q_SUBCHANNEL_SIZE || q[0] & 0x0F != 1 {
+        return None;
+    }
+
+    let bcd = |b: u8| (b >> 4) * 10 + (b & 0x0F);
+
+    Some(QPosition {
+        track: bcd(q[1]),
+        index: bcd(q[2]),
+    })
+}
+
+/// The table of contents of a disc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toc
+{
+    pub tracks: Vec<TocTrack>,
+    /// Logical block address of the lead-out, one past the last sector of
+    /// the last track.
+    pub lead_out: u32,
+    /// On multisession discs, such as Enhanced CDs, the lead-outs of the
+    /// sessions before the last, in order.  Empty for single session discs.
+    pub session_lead_outs: Vec<u32>,
+}
+
+impl Toc
+{
+    /// Whether every track holds audio, as on a CD-DA disc.
+    pub fn is_audio(&self) -> bool
+    {
+        !self.tracks.is_empty() && self.tracks.iter().all(|t| t.is_audio())
+    }
+
+    /// Whether the disc mixes audio and data tracks, as Enhanced CDs
+    /// (CD-Extra) and mixed-mode game discs do.
+    pub fn is_mixed(&self) -> bool
+    {
+        self.tracks.iter().any(|t| t.is_audio())
+            && self.tracks.iter().any(|t| !t.is_audio())
+    }
+
+    /// The place and type of each track.
+    pub fn layout(&self) -> Vec<TrackLayout>
+    {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                TrackLayout {
+                    number: t.number,
+                    track_type: t.track_type().to_string(),
+                    pregap_start: t.pregap_start,
+                    start: t.start,
+                    end: self.track_end(i),
+                }
+            })
+            .collect()
+    }
+
+    /// The track holding the sector at `lba`.  Sectors before the first
+    /// track belong to it.
+    pub fn track_at(
+        &self,
+        lba: u32,
+    ) -> Option<&TocTrack>
+    {
+        self.tracks
+            .iter()
+            .rev()
+            .find(|t| t.pregap_start.unwrap_or(t.start) <= lba)
+            .or(self.tracks.first())
+    }
+
+    /// The first sector after the track with the given index in `tracks`.
+    /// The last track of a session ends at the session's lead-out.
+    pub fn track_end(
+        &self,
+        i: usize,
+    ) -> u32
+    {
+        let next = self
+            .tracks
+            .get(i + 1)
+            .map(|t| t.pregap_start.unwrap_or(t.start))
+            .unwrap_or(self.lead_out);
+
+        next.min(self.session_lead_out(self.tracks[i].start))
+    }
+
+    /// The session, numbered from 1, holding the sector at `lba`.
+    pub fn session(
+        &self,
+        lba: u32,
+    ) -> usize
+    {
+        1 + self.session_lead_outs.iter().filter(|l| **l <= lba).count()
+    }
+
+    /// The lead-out of the session holding the sector at `lba`.
+    fn session_lead_out(
+        &self,
+        lba: u32,
+    ) -> u32
+    {
+        self.session_lead_outs
+            .iter()
+            .copied()
+            .find(|l| *l > lba)
+            .unwrap_or(self.lead_out)
+    }
+
+    /// The sectors of each session which can be read: from LBA 0, or from
+    /// the first track of a later session, to the session's lead-out.  The
+    /// gaps between sessions are left out.
+    pub fn sessions(&self) -> Vec<Range<u32>>
+    {
+        let mut sessions = vec![];
+        let mut start = 0;
+
+        for lead_out in self.session_lead_outs.iter().chain([&self.lead_out]) {
+            sessions.push(start..*lead_out);
+
+            start = self
+                .tracks
+                .iter()
+                .map(|t| t.pregap_start.unwrap_or(t.start))
+                .find(|s| s >= lead_out)
+                .unwrap_or(self.lead_out);
+        }
+
+        sessions
+    }
+
+    /// Checks that tracks are numbered and placed in order before the
+    /// lead-out, so that nothing downstream reads a nonsensical range.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>>
+    {
+        if self.tracks.is_empty() {
+            return Err("The table of contents lists no tracks.".into());
+        }
+
+        let mut previous_start = None;
+
+        for t in &self.tracks {
+            if !(1..=99).contains(&t.number) {
+                return Err(
+                    format!("Track number {} is invalid.", t.number).into()
+                );
+            }
+
+            if t.pregap_start.is_some_and(|p| p > t.start) {
+                return Err(format!(
+                    "Track {} pregap starts after the track.",
+                    t.number
+                )
+                .into());
+            }
+
+            let first = t.pregap_start.unwrap_or(t.start);
+            if previous_start.is_some_and(|p| first <= p)
+                || t.start >= self.lead_out
+            {
+                return Err(
+                    format!("Track {} is out of order.", t.number).into()
+                );
+            }
+
+            previous_start = Some(t.start);
+        }
+
+        let mut previous_lead_out = 0;
+
+        for (i, lead_out) in self.session_lead_outs.iter().enumerate() {
+            if *lead_out <= previous_lead_out
+                || !self.tracks.iter().any(|t| t.start >= *lead_out)
+            {
+                return Err(format!(
+                    "The lead-out of session {} is out of order.",
+                    i + 1
+                )
+                .into());
+            }
+
+            previous_lead_out = *lead_out;
+        }
+
+        Ok(())
+    }
+}
+
+/// Something which can report a disc's table of contents: a real drive, or a
+/// simulated one in tests.
+pub trait TocSource
+{
+    fn read_toc(&mut self) -> Result<Toc, Box<dyn Error>>;
+}
+
+/// A position on the disc in minutes, seconds and frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Msf
+{
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+}
+
+impl Msf
+{
+    /// A length or offset of `frames` sectors, as used in cue sheets.
+    pub fn from_frames(frames: u32) -> Msf
+    {
+        Msf {
+            minutes: frames / (60 * FRAMES_PER_SECOND),
+            seconds: frames / FRAMES_PER_SECOND % 60,
+            frames: frames % FRAMES_PER_SECOND,
+        }
+    }
+
+    /// The absolute disc position of a logical block address.
+    pub fn from_lba(lba: u32) -> Msf
+    {
+        Msf::from_frames(lba + LEAD_IN_FRAMES)
+    }
+
+    /// The logical block address of an absolute disc position.
+    pub fn to_lba(self) -> Option<u32>
+    {
+        ((self.minutes * 60 + self.seconds) * FRAMES_PER_SECOND + self.frames)
+            .checked_sub(LEAD_IN_FRAMES)
+    }
+}
+
+impl fmt::Display for Msf
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result
+    {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+
+    fn track(
+        number: u8,
+        start: u32,
+    ) -> TocTrack
+    {
+        TocTrack {
+            number,
+            control: 0,
+            start,
+            pregap_start: None,
+            data_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_msf()
+    {
+        assert_eq!("00:00:00", Msf::from_frames(0).to_string());
+        assert_eq!("00:02:00", Msf::from_lba(0).to_string());
+        assert_eq!("01:00:74", Msf::from_frames(60 * 75 + 74).to_string());
+        assert_eq!(Some(1000), Msf::from_lba(1000).to_lba());
+        assert_eq!(None, Msf::from_frames(10).to_lba());
+    }
+
+    #[test]
+    fn test_is_audio()
+    {
+        let mut toc = Toc {
+            tracks: vec![track(1, 0), track(2, 1000)],
+            lead_out: 2000,
+            session_lead_outs: vec![],
+        };
+        assert!(toc.is_audio());
+
+        assert!(!toc.is_mixed());
+
+        toc.tracks[0].control = CONTROL_DATA;
+        assert!(!toc.is_audio());
+        assert!(toc.is_mixed());
+
+        toc.tracks[1].control = CONTROL_DATA;
+        assert!(!toc.is_mixed());
+    }
+
+    #[test]
+    fn test_layout()
+    {
+        let mut toc = Toc {
+            tracks: vec![track(1, 0), track(2, 1000)],
+            lead_out: 2000,
+            session_lead_outs: vec![],
+        };
+        toc.tracks[1].control = CONTROL_DATA;
+        toc.tracks[1].pregap_start = Some(850);
+        toc.tracks[1].data_mode = Some(SectorMode::Mode2Form1);
+
+        assert_eq!(
+            vec![
+                TrackLayout {
+                    number: 1,
+                    track_type: "AUDIO".to_string(),
+                    pregap_start: None,
+                    start: 0,
+                    end: 850,
+                },
+                TrackLayout {
+                    number: 2,
+                    track_type: "MODE2/2352".to_string(),
+                    pregap_start: Some(850),
+                    start: 1000,
+                    end: 2000,
+                },
+            ],
+            toc.layout()
+        );
+    }
+
+    #[test]
+    fn test_validate()
+    {
+        let mut toc = Toc {
+            tracks: vec![track(1, 0), track(2, 1000)],
+            lead_out: 2000,
+            session_lead_outs: vec![],
+        };
+        assert!(toc.validate().is_ok());
+        assert_eq!(1000, toc.track_end(0));
+        assert_eq!(2000, toc.track_end(1));
+
+        toc.tracks[1].pregap_start = Some(850);
+        assert!(toc.validate().is_ok());
+        assert_eq!(850, toc.track_end(0));
+
+        toc.tracks[1].start = 2000;
+        assert!(toc.validate().is_err());
+    }
+
+    #[test]
+    fn test_sessions()
+    {
+        let mut toc = Toc {
+            tracks: vec![track(1, 0), track(2, 1000), track(3, 13_400)],
+            lead_out: 14_000,
+            session_lead_outs: vec![],
+        };
+        assert_eq!(vec![0..14_000], toc.sessions());
+        assert_eq!(13_400, toc.track_end(1));
+
+        toc.session_lead_outs = vec![2000];
+        assert!(toc.validate().is_ok());
+        assert_eq!(vec![0..2000, 13_400..14_000], toc.sessions());
+        assert_eq!(2000, toc.track_end(1));
+        assert_eq!(14_000, toc.track_end(2));
+        assert_eq!(1, toc.session(1999));
+        assert_eq!(2, toc.session(13_400));
+
+        toc.session_lead_outs = vec![14_000];
+        assert!(toc.validate().is_err());
+    }
+
+    /// A raw data sector header at `lba`, with an XA subheader whose submode
+    /// is `submode` for Mode 2.
+    pub(crate) fn data_sector(
+        lba: u32,
+        mode: u8,
+        submode: u8,
+    ) -> Vec<u8>
+    {
+        let msf = Msf::from_lba(lba);
+        let bcd = |n: u32| (((n / 10) << 4) | (n % 10)) as u8;
+
+        let mut sector = vec![0; RAW_SECTOR_SIZE];
+        sector[..12].copy_from_slice(&SYNC);
+        sector[12..16].copy_from_slice(&[
+            bcd(msf.minutes),
+            bcd(msf.seconds),
+            bcd(msf.frames),
+            mode,
+        ]);
+        if mode == 2 {
+            sector[16..20].copy_from_slice(&[1, 1, submode, 0]);
+            sector[20..24].copy_from_slice(&[1, 1, submode, 0]);
+        }
+        sector
+    }
+
+    #[test]
+    fn test_sector_mode()
+    {
+        assert_eq!(
+            Some(SectorMode::Mode1),
+            sector_mode(&data_sector(16, 1, 0))
+        );
+        assert_eq!(
+            Some(SectorMode::Mode2Form1),
+            sector_mode(&data_sector(16, 2, 0x08))
+        );
+        assert_eq!(
+            Some(SectorMode::Mode2Form2),
+            sector_mode(&data_sector(16, 2, 0x64))
+        );
+
+        let mut formless = data_sector(16, 2, 0);
+        formless[20] = 9;
+        assert_eq!(Some(SectorMode::Mode2), sector_mode(&formless));
+
+        assert_eq!(None, sector_mode(&[0x55; RAW_SECTOR_SIZE]));
+        assert_eq!(Some(12_345), sector_address(&data_sector(12_345, 1, 0)));
+        assert_eq!(None, sector_address(&[0x55; RAW_SECTOR_SIZE]));
+        assert_eq!("MODE2/2352", SectorMode::Mode2Form2.cue_type());
+    }
+
+    #[test]
+    fn test_q_position()
+    {
+        let mut q = [0; Q_SUBCHANNEL_SIZE];
+        q[..3].copy_from_slice(&[0x01, 0x12, 0x00]);
+        assert_eq!(
+            Some(QPosition {
+                track: 12,
+                index: 0,
+            }),
+            q_position(&q)
+        );
+
+        // The catalogue number.
+        q[0] = 0x02;
+        assert_eq!(None, q_position(&q));
+        assert_eq!(None, q_position(&q[..10]));
+    }
+
+    #[test]
+    fn test_track_at()
+    {
+        let mut toc = Toc {
+            tracks: vec![track(1, 150), track(2, 1000)],
+            lead_out: 2000,
+            session_lead_outs: vec![],
+        };
+        toc.tracks[1].pregap_start = Some(850);
+
+        assert_eq!(1, toc.track_at(0).unwrap().number);
+        assert_eq!(1, toc.track_at(849).unwrap().number);
+        assert_eq!(2, toc.track_at(850).unwrap().number);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Read,
+        Write,
+    },
+    path::PathBuf,
+};
+
+/// CD-DA audio: 44.1 kHz, 16-bit, stereo.
+pub const SAMPLE_RATE: u32 = 44_100;
+pub const CHANNELS: u16 = 2;
+pub const BITS_PER_SAMPLE: u16 = 16;
+
+/// Bytes in each stereo sample frame.
+pub const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+
+/// The 44 byte header of a PCM WAV file holding `data_size` bytes of CD-DA
+/// samples.
+pub fn wav_header(data_size: u32) -> [u8; 44]
+{
+    let mut header = [0; 44];
+
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(36 + data_size).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    // PCM
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&CHANNELS.to_le_bytes());
+    header[24..28].copy_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header[28..32]
+        .copy_from_slice(&(SAMPLE_RATE * BLOCK_ALIGN as u32).to_le_bytes());
+    header[32..34].copy_from_slice(&BLOCK_ALIGN.to_le_bytes());
+    header[34..36].copy_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_size.to_le_bytes());
+
+    header
+}
+
+/// Writes `length` bytes of CD-DA samples (little-endian, interleaved) from
+/// `from` to `to` as a WAV file.
+pub fn write_wav(
+    from: &mut impl Read,
+    length: u32,
+    to: &PathBuf,
+) -> Result<(), Box<dyn Error>>
+{
+    let mut wav = BufWriter::new(
+        File::create(to)
+            .map_err(|e| format!("Could not create {to:?}: {e}"))?,
+    );
+
+    wav.write_all(&wav_header(length))?;
+
+    let copied = io::copy(&mut from.take(length as u64), &mut wav)?;
+    if copied < length as u64 {
+        return Err(format!("{to:?} is missing samples.").into());
+    }
+
+    wav.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::toc::RAW_SECTOR_SIZE;
+
+    #[test]
+    fn test_wav_header()
+    {
+        let header = wav_header(4 * RAW_SECTOR_SIZE as u32);
+
+        assert_eq!(b"RIFF", &header[0..4]);
+        assert_eq!(
+            (36 + 4 * RAW_SECTOR_SIZE as u32).to_le_bytes(),
+            header[4..8]
+        );
+        assert_eq!(b"WAVEfmt ", &header[8..16]);
+        assert_eq!(44_100u32.to_le_bytes(), header[24..28]);
+        assert_eq!(176_400u32.to_le_bytes(), header[28..32]);
+        assert_eq!(4u16.to_le_bytes(), header[32..34]);
+        assert_eq!(16u16.to_le_bytes(), header[34..36]);
+    }
+}
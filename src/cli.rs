@@ -1,4 +1,8 @@
-use clap::Parser;
+use crate::audio_cd::AudioFormat;
+use clap::{
+    Parser,
+    Subcommand,
+};
 use clap_verbosity_flag::Verbosity;
 
 #[derive(Parser, Debug)]
@@ -7,10 +11,14 @@ use clap_verbosity_flag::Verbosity;
     version,
     about,
     long_about = "A tool to automate the process of collecting and storing \
-                  California Revealed's digital assets"
+                  California Revealed's digital assets",
+    args_conflicts_with_subcommands = true
 )]
 pub struct Cli
 {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to the CSV file we want to process.
 
     #[arg(value_name = "Input CSV")]
@@ -20,10 +28,43 @@ pub struct Cli
     #[arg(value_name = "Output Parent Directory")]
     pub output_parent_path: Option<String>,
 
-    /// Device to use as ISO generation source.  If none is provided, the user
-    /// will be prompted to select a device.
+    /// Devices to use as ISO generation sources.  Given several, the disks
+    /// are imaged from all of them at once, each drive taking the next disk
+    /// as it becomes free.  If none is provided, the user will be prompted
+    /// to select a device.
     #[arg(value_name = "ROM Device")]
-    pub rom_device: Option<String>,
+    pub rom_devices: Vec<String>,
+
+    /// Format to split audio CD tracks into.  FLAC files are tagged from the
+    /// item's row.
+    #[arg(long, value_enum, default_value_t)]
+    pub audio_format: AudioFormat,
+
+    /// Extract the files of each earlier session of a multisession disc to
+    /// its own folder, rather than only listing them.
+    #[arg(long)]
+    pub extract_sessions: bool,
+
+    /// How many seconds a disc's running time may differ from its row's
+    /// obj_prsv_duration_string before the item is flagged.
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub duration_tolerance: u32,
+
+    /// How many seconds to wait for a disc to be inserted, and for the
+    /// system to read its label, before asking whether to keep waiting.
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    pub media_timeout: u64,
+
+    /// Import whichever disc is inserted into a drive, as the remaining
+    /// identifier its label matches, and eject it, asking only when the
+    /// label matches several.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// TOML file of settings, such as external commands to list, label,
+    /// eject, image or copy discs with.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
 
     /// Don't actually create or modify any files
     #[arg(long, short)]
@@ -37,3 +78,70 @@ pub struct Cli
     #[command(flatten)]
     pub verbose: Verbosity,
 }
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command
+{
+    /// List the files in a disc or media image (FAT12/16/32, HFS, HFS+ or
+    /// ISO 9660), with Mac type and creator codes where present.
+    ListImage
+    {
+        /// Path to the disc image.
+        #[arg(value_name = "Image")]
+        image_path: String,
+
+        /// List this session of a multisession ISO, counting from 1, rather
+        /// than the last.
+        #[arg(long)]
+        session: Option<usize>,
+    },
+
+    /// Compare an item's file-system copy with the files in its ISO,
+    /// reporting missing, extra and differing files.
+    VerifyItem
+    {
+        /// Path to the item's folder, which holds the ISO.
+        #[arg(value_name = "Item Directory")]
+        item_path: String,
+    },
+
+    /// Print the filesystems (ISO 9660, Joliet, Rock Ridge, UDF, HFS/HFS+,
+    /// FAT) and El Torito boot record found in an image as JSON.
+    FsInfo
+    {
+        /// Path to the disc image.
+        #[arg(value_name = "Image")]
+        image_path: String,
+    },
+
+    /// Print the titles, chapters, running times, streams and regions of a
+    /// DVD-Video image as JSON.
+    DvdInfo
+    {
+        /// Path to the disc image.
+        #[arg(value_name = "Image")]
+        image_path: String,
+    },
+
+    /// Print the titles and playlists of a Blu-ray image, with each
+    /// playlist's running time, chapters, clips and streams, as JSON.
+    BdInfo
+    {
+        /// Path to the disc image.
+        #[arg(value_name = "Image")]
+        image_path: String,
+    },
+
+    /// Split an audio CD's BIN/CUE into a WAV or FLAC file for each audio
+    /// track, written next to the cue sheet.
+    SplitAudio
+    {
+        /// Path to the cue sheet.
+        #[arg(value_name = "Cue Sheet")]
+        cue_path: String,
+
+        /// Format to write the tracks in.
+        #[arg(long, value_enum, default_value_t)]
+        audio_format: AudioFormat,
+    },
+}
@@ -0,0 +1,274 @@
+use serde_json::Value;
+use std::{
+    error::Error,
+    fmt,
+    fs,
+    path::Path,
+};
+
+/// Where Linux describes each block device.
+pub const SYS_BLOCK_PATH: &str = "/sys/block";
+
+/// The SCSI peripheral device type of CD, DVD and Blu-ray drives.
+const SCSI_TYPE_OPTICAL: &str = "5";
+
+/// The columns `parse_lsblk` expects from `lsblk --json --output`.
+pub const LSBLK_COLUMNS: &str = "NAME,TYPE,MODEL,VENDOR,SERIAL,LABEL,SIZE,RM";
+
+/// A whole block device, as listed by `lsblk` and `/sys/block`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Device
+{
+    /// The name under `/dev`, such as "sr0".
+    pub name: String,
+    /// The lsblk type, such as "rom" or "disk".
+    pub device_type: String,
+    pub model: Option<String>,
+    pub vendor: Option<String>,
+    pub serial: Option<String>,
+    /// The label of the device's filesystem, or else of its first labelled
+    /// partition.
+    pub label: Option<String>,
+    pub size: Option<String>,
+    pub removable: bool,
+    /// Whether the drive holds a disc or other media.
+    pub has_media: bool,
+    /// Whether `/sys/block` reports the drive as a CD, DVD or Blu-ray drive.
+    pub optical: bool,
+}
+
+impl Device
+{
+    /// Whether media could be imaged from the device: optical drives, and
+    /// removable drives such as floppy and flash drives.
+    pub fn is_candidate(&self) -> bool
+    {
+        self.device_type == "rom" || self.optical || self.removable
+    }
+}
+
+impl fmt::Display for Device
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result
+    {
+        let kind = match self.device_type == "rom" || self.optical {
+            | true => "optical drive",
+            | false => "removable drive",
+        };
+        let description: Vec<&str> = [&self.vendor, &self.model]
+            .into_iter()
+            .filter_map(|s| s.as_deref())
+            .collect();
+
+        write!(f, "{} ({kind}", self.name)?;
+        if !description.is_empty() {
+            write!(f, ", {}", description.join(" "))?;
+        }
+        write!(f, ")")?;
+
+        match (self.has_media, &self.label) {
+            | (false, _) => write!(f, ": no media"),
+            | (true, Some(label)) => write!(f, ": \"{label}\""),
+            | (true, None) => write!(f, ": unlabelled media"),
+        }?;
+
+        match (&self.size, self.has_media) {
+            | (Some(size), true) => write!(f, ", {size}"),
+            | _ => Ok(()),
+        }
+    }
+}
+
+/// Reads the whole devices from the output of `lsblk --json --output
+/// LSBLK_COLUMNS`.  Partitions lend their labels to their device.  Media is
+/// taken to be present when the device has a size, until `read_sysfs`
+/// says otherwise.
+pub fn parse_lsblk(json: &str) -> Result<Vec<Device>, Box<dyn Error>>
+{
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| format!("Could not parse the lsblk output: {e}"))?;
+
+    let devices = value["blockdevices"]
+        .as_array()
+        .ok_or("The lsblk output lists no block devices.")?;
+
+    Ok(devices.iter().map(device).collect())
+}
+
+/// Fills in the optical, removable and media flags of each device from the
+/// files under `sys_block`, normally `/sys/block`.  Devices missing from it
+/// are left as `lsblk` described them.
+pub fn read_sysfs(
+    devices: &mut [Device],
+    sys_block: &Path,
+)
+{
+    for device in devices {
+        let dir = sys_block.join(&device.name);
+        let read = |file: &str| {
+            fs::read_to_string(dir.join(file))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+
+        if let Some(scsi_type) = read("device/type") {
+            device.optical = scsi_type == SCSI_TYPE_OPTICAL;
+        }
+        if let Some(removable) = read("removable") {
+            device.removable = removable == "1";
+        }
+        if let Some(sectors) = read("size") {
+            device.has_media = sectors.parse::<u64>().is_ok_and(|s| s > 0);
+        }
+    }
+}
+
+fn device(value: &Value) -> Device
+{
+    let text = |key: &str| {
+        value[key]
+            .as_str()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+
+    let children = value["children"].as_array();
+    let label = text("label").or_else(|| {
+        children?.iter().find_map(|c| {
+            c["label"]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+        })
+    });
+
+    // Older versions of lsblk write flags as "0" and "1".
+    let removable = match &value["rm"] {
+        | Value::Bool(rm) => *rm,
+        | Value::String(rm) => rm == "1",
+        | _ => false,
+    };
+
+    let size = text("size").filter(|s| s != "0B" && s != "0");
+
+    Device {
+        name: text("name").unwrap_or_default(),
+        device_type: text("type").unwrap_or_default(),
+        model: text("model"),
+        vendor: text("vendor"),
+        serial: text("serial"),
+        label,
+        has_media: size.is_some(),
+        size,
+        removable,
+        optical: false,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use tempfile::TempDir;
+
+    const LSBLK: &str = r#"{
+        "blockdevices": [
+            {"name": "sda", "type": "disk", "model": "Samsung SSD 870",
+             "vendor": "ATA     ", "label": null, "size": "465.8G",
+             "rm": false, "children": [
+                {"name": "sda1", "type": "part", "model": null,
+                 "vendor": null, "label": "Slow", "size": "465.8G",
+                 "rm": false}
+            ]},
+            {"name": "sdb", "type": "disk", "model": "Flash Disk",
+             "vendor": "Generic ", "label": null, "size": "1.4M",
+             "rm": "1", "children": [
+                {"name": "sdb1", "type": "part", "model": null,
+                 "vendor": null, "label": "FLOPPY", "size": "1.4M",
+                 "rm": "1"}
+            ]},
+            {"name": "sr0", "type": "rom", "model": "DVDRAM GP65NB60",
+             "vendor": "HL-DT-ST", "serial": "KZ9G1234", "label": "MY_DISC",
+             "size": "4.2G", "rm": true},
+            {"name": "sr1", "type": "rom", "model": "BD-RE BU40N",
+             "vendor": "HL-DT-ST", "label": null, "size": "1024M",
+             "rm": true}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_lsblk()
+    {
+        let devices = parse_lsblk(LSBLK).unwrap();
+
+        assert_eq!(4, devices.len());
+        assert_eq!(
+            Device {
+                name: "sr0".to_string(),
+                device_type: "rom".to_string(),
+                model: Some("DVDRAM GP65NB60".to_string()),
+                vendor: Some("HL-DT-ST".to_string()),
+                serial: Some("KZ9G1234".to_string()),
+                label: Some("MY_DISC".to_string()),
+                size: Some("4.2G".to_string()),
+                removable: true,
+                has_media: true,
+                optical: false,
+            },
+            devices[2]
+        );
+        assert_eq!(Some("ATA".to_string()), devices[0].vendor);
+        assert_eq!(Some("FLOPPY".to_string()), devices[1].label);
+        assert!(devices[1].removable);
+
+        let candidates: Vec<&str> = devices
+            .iter()
+            .filter(|d| d.is_candidate())
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(vec!["sdb", "sr0", "sr1"], candidates);
+
+        assert!(parse_lsblk("{}").is_err());
+        assert!(parse_lsblk("sda disk").is_err());
+    }
+
+    #[test]
+    fn test_read_sysfs()
+    {
+        let sys = TempDir::new().unwrap();
+        for (name, scsi_type, removable, size) in
+            [("sda", "0", "0", "976773168"), ("sr1", "5", "1", "0")]
+        {
+            let dir = sys.path().join(name);
+            fs::create_dir_all(dir.join("device")).unwrap();
+            fs::write(dir.join("device/type"), format!("{scsi_type}\n"))
+                .unwrap();
+            fs::write(dir.join("removable"), format!("{removable}\n")).unwrap();
+            fs::write(dir.join("size"), format!("{size}\n")).unwrap();
+        }
+
+        let mut devices = parse_lsblk(LSBLK).unwrap();
+        read_sysfs(&mut devices, sys.path());
+
+        assert!(!devices[0].optical && !devices[0].removable);
+        assert!(devices[0].has_media);
+        // The drive's default size is reported without a disc in it.
+        assert!(devices[3].optical && !devices[3].has_media);
+        assert_eq!(
+            "sr1 (optical drive, HL-DT-ST BD-RE BU40N): no media",
+            devices[3].to_string()
+        );
+        assert_eq!(
+            "sr0 (optical drive, HL-DT-ST DVDRAM GP65NB60): \"MY_DISC\", 4.2G",
+            devices[2].to_string()
+        );
+        assert_eq!(
+            "sdb (removable drive, Generic Flash Disk): \"FLOPPY\", 1.4M",
+            devices[1].to_string()
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use log::debug;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    ffi::{
+        OsStr,
+        OsString,
+    },
+    fmt,
+    process::Command,
+};
+
+/// A command line from the configuration, such as
+/// "ddrescue -b 2048 {device} {iso} {iso}.map", whose placeholders are filled
+/// in each time it is run.  The line is split into arguments before anything
+/// is filled in, and is run without a shell, so a value holding spaces,
+/// quotes or semicolons is always passed as part of a single argument.
+///
+/// Arguments are split at whitespace, except within single or double quotes.
+/// Within double quotes, a backslash keeps the next character as it is.
+/// "{{" and "}}" stand for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CommandTemplate
+{
+    line: String,
+    words: Vec<Vec<Part>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part
+{
+    Text(String),
+    Placeholder(String),
+}
+
+impl CommandTemplate
+{
+    pub fn parse(line: &str) -> Result<CommandTemplate, Box<dyn Error>>
+    {
+        let mut words = Vec::new();
+        let mut word: Option<Vec<Part>> = None;
+        let mut quote = None;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let text = match (quote, c) {
+                | (None, c) if c.is_whitespace() => {
+                    words.extend(word.take());
+                    continue;
+                }
+                | (None, '\'' | '"') => {
+                    quote = Some(c);
+                    word.get_or_insert_with(Vec::new);
+                    continue;
+                }
+                | (Some(q), c) if c == q => {
+                    quote = None;
+                    continue;
+                }
+                | (Some('"'), '\\') => {
+                    chars.next().ok_or(format!(
+                        "The command \"{line}\" ends with a backslash."
+                    ))?
+                }
+                | (_, '{') if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    '{'
+                }
+                | (_, '}') if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    '}'
+                }
+                | (_, '{') => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            | '}' => {
+                                closed = true;
+                                break;
+                            }
+                            | c => name.push(c),
+                        }
+                    }
+
+                    if !closed
+                        || name.is_empty()
+                        || !name
+                            .chars()
+                            .all(|c| c.is_ascii_lowercase() || c == '_')
+                    {
+                        return Err(format!(
+                            "The command \"{line}\" has an unfinished or \
+                             misspelt placeholder, {{{name}."
+                        )
+                        .into());
+                    }
+
+                    word.get_or_insert_with(Vec::new)
+                        .push(Part::Placeholder(name));
+                    continue;
+                }
+                | (_, '}') => {
+                    return Err(format!(
+                        "The command \"{line}\" has a \"}}\" which closes no \
+                         placeholder.  Write \"}}}}\" for a literal brace."
+                    )
+                    .into());
+                }
+                | (_, c) => c,
+            };
+
+            let word = word.get_or_insert_with(Vec::new);
+            match word.last_mut() {
+                | Some(Part::Text(s)) => s.push(text),
+                | _ => word.push(Part::Text(text.to_string())),
+            }
+        }
+
+        if let Some(q) = quote {
+            return Err(
+                format!("The command \"{line}\" has an unclosed {q}.").into()
+            );
+        }
+        words.extend(word);
+
+        if words.is_empty() {
+            return Err("A configured command is empty.".into());
+        }
+
+        Ok(CommandTemplate {
+            line: line.to_string(),
+            words,
+        })
+    }
+
+    /// Checks that the template only uses the placeholders in `available`,
+    /// naming the template `name` in the error.
+    pub fn check_placeholders(
+        &self,
+        name: &str,
+        available: &[&str],
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let unknown = self.words.iter().flatten().find_map(|part| {
+            match part {
+                | Part::Placeholder(p) if !available.contains(&p.as_str()) => {
+                    Some(p)
+                }
+                | _ => None,
+            }
+        });
+
+        match unknown {
+            | None => Ok(()),
+            | Some(p) => {
+                let available: Vec<String> =
+                    available.iter().map(|a| format!("{{{a}}}")).collect();
+
+                Err(format!(
+                    "The {name} command uses {{{p}}}, but only {} can be used \
+                     there.",
+                    match available.is_empty() {
+                        | true => "no placeholders".to_string(),
+                        | false => available.join(", "),
+                    }
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Whether the template uses the placeholder `name`.
+    pub fn uses(
+        &self,
+        name: &str,
+    ) -> bool
+    {
+        self.words
+            .iter()
+            .flatten()
+            .any(|part| *part == Part::Placeholder(name.to_string()))
+    }
+
+    /// The program the command runs, unless it is given by a placeholder.
+    pub fn program(&self) -> Option<&str>
+    {
+        match &self.words[0][..] {
+            | [Part::Text(program)] => Some(program),
+            | _ => None,
+        }
+    }
+
+    /// The command with each placeholder replaced by its value in `values`.
+    pub fn command(
+        &self,
+        values: &[(&str, &OsStr)],
+    ) -> Result<Command, Box<dyn Error>>
+    {
+        let mut args = Vec::new();
+
+        for word in &self.words {
+            let mut arg = OsString::new();
+
+            for part in word {
+                match part {
+                    | Part::Text(text) => arg.push(text),
+                    | Part::Placeholder(p) => {
+                        let value = values
+                            .iter()
+                            .find(|(name, _)| name == p)
+                            .ok_or(format!(
+                            "The command \"{}\" uses {{{p}}}, which has no \
+                             value here.",
+                            self.line
+                        ))?;
+                        arg.push(value.1);
+                    }
+                }
+            }
+
+            args.push(arg);
+        }
+
+        let mut command = Command::new(&args[0]);
+        command.args(&args[1..]);
+
+        Ok(command)
+    }
+
+    /// Runs the command with its placeholders filled in from `values`, and
+    /// returns what it printed.  A command which exits unsuccessfully is an
+    /// error.
+    pub fn run(
+        &self,
+        values: &[(&str, &OsStr)],
+    ) -> Result<String, Box<dyn Error>>
+    {
+        let mut command = self.command(values)?;
+        debug!("Running configured command: '{command:?}'");
+
+        let output = command
+            .output()
+            .map_err(|e| format!("Could not run {command:?}: {e}"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        debug!("Command '{command:?}' produced output:\n{stdout}");
+
+        if !output.status.success() {
+            return Err(format!(
+                "{command:?} failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        Ok(stdout)
+    }
+}
+
+impl TryFrom<String> for CommandTemplate
+{
+    type Error = String;
+
+    fn try_from(line: String) -> Result<CommandTemplate, String>
+    {
+        CommandTemplate::parse(&line).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for CommandTemplate
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result
+    {
+        write!(f, "{}", self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn args(command: &Command) -> Vec<&OsStr>
+    {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .collect()
+    }
+
+    #[test]
+    fn test_command()
+    {
+        let template =
+            CommandTemplate::parse("ddrescue -b 2048 {device} {iso} {iso}.map")
+                .unwrap();
+        let iso = OsStr::new("/archive/My Disc; rm -rf ~.iso");
+        let command = template
+            .command(&[("device", OsStr::new("/dev/sr0")), ("iso", iso)])
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                "ddrescue",
+                "-b",
+                "2048",
+                "/dev/sr0",
+                "/archive/My Disc; rm -rf ~.iso",
+                "/archive/My Disc; rm -rf ~.iso.map",
+            ],
+            args(&command)
+        );
+
+        assert!(template.command(&[("iso", iso)]).is_err());
+        assert_eq!(Some("ddrescue"), template.program());
+        assert_eq!(None, CommandTemplate::parse("{x} -a").unwrap().program());
+    }
+
+    #[test]
+    fn test_parse_quotes()
+    {
+        let template = CommandTemplate::parse(
+            r#"sh -c 'cp -a "$0"/. "$1"' "{from}" x"{{{to}}}"y """#,
+        )
+        .unwrap();
+        let command = template
+            .command(&[
+                ("from", OsStr::new("/Volumes/Disc: Part 1")),
+                ("to", OsStr::new("out")),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                "sh",
+                "-c",
+                r#"cp -a "$0"/. "$1""#,
+                "/Volumes/Disc: Part 1",
+                "x{out}y",
+                "",
+            ],
+            args(&command)
+        );
+
+        for bad in [
+            "",
+            "  ",
+            "eject '{device}",
+            "eject {device",
+            "eject }",
+            "a {Dev}",
+        ] {
+            assert!(CommandTemplate::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_check_placeholders()
+    {
+        let template = CommandTemplate::parse("eject {device}").unwrap();
+
+        assert!(template.check_placeholders("eject", &["device"]).is_ok());
+        assert!(template.uses("device") && !template.uses("name"));
+        assert_eq!(
+            "The eject command uses {device}, but only {from}, {to} can be \
+             used there.",
+            template
+                .check_placeholders("eject", &["from", "to"])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_run()
+    {
+        let template = CommandTemplate::parse("echo {name}").unwrap();
+        assert_eq!(
+            "MY_DISC\n",
+            template.run(&[("name", OsStr::new("MY_DISC"))]).unwrap()
+        );
+
+        assert!(CommandTemplate::parse("false").unwrap().run(&[]).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use super::{
+    command_template::CommandTemplate,
+    drive_identity::DriveIdentity,
+    CliHandler,
+};
+use crate::{
+    audio_cd::CdDrive,
+    preflight::Tool,
+};
+use inquire::Select;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    ffi::OsStr,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The external commands a configuration may give in its `[tools]` table.
+/// Each operation without one is left to the platform's own tools.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCommands
+{
+    /// Prints the names of the drives to choose from, one per line.
+    pub list: Option<CommandTemplate>,
+    /// Prints the label of the media in `{device}` (or `{name}`).
+    pub label: Option<CommandTemplate>,
+    /// Ejects the media in `{device}` (or `{name}`).
+    pub eject: Option<CommandTemplate>,
+    /// Images `{device}` (or the media mounted at `{mount}`) to the ISO
+    /// `{iso}`.
+    pub image: Option<CommandTemplate>,
+    /// Copies the files of the media mounted at `{from}` into `{to}`.
+    pub copy: Option<CommandTemplate>,
+}
+
+impl ToolCommands
+{
+    /// Checks that each command only uses the placeholders which are filled
+    /// in for it.
+    pub fn check(&self) -> Result<(), Box<dyn Error>>
+    {
+        let tools = [
+            ("list", &self.list, &[][..]),
+            ("label", &self.label, &["device", "name"][..]),
+            ("eject", &self.eject, &["device", "name"][..]),
+            ("image", &self.image, &["device", "mount", "iso"][..]),
+            ("copy", &self.copy, &["from", "to"][..]),
+        ];
+
+        for (name, template, available) in tools {
+            if let Some(template) = template {
+                template.check_placeholders(name, available)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Each operation, as named in the `[tools]` table, with its command.
+    fn commands(&self) -> [(&'static str, &Option<CommandTemplate>); 5]
+    {
+        [
+            ("list", &self.list),
+            ("label", &self.label),
+            ("eject", &self.eject),
+            ("image", &self.image),
+            ("copy", &self.copy),
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        *self == ToolCommands::default()
+    }
+}
+
+/// Runs the configured commands in place of the platform's tools, and
+/// passes everything else to `platform`.
+pub struct ConfiguredCliHandler
+{
+    tools: ToolCommands,
+    platform: Box<dyn CliHandler>,
+}
+
+impl ConfiguredCliHandler
+{
+    pub fn new(
+        tools: ToolCommands,
+        platform: Box<dyn CliHandler>,
+    ) -> ConfiguredCliHandler
+    {
+        ConfiguredCliHandler { tools, platform }
+    }
+}
+
+/// The path of the drive `dev`, for `{device}`, such as "/dev/sr0".  Its
+/// name, such as "sr0", is given for `{name}`.
+fn device_path(dev: &str) -> PathBuf
+{
+    let mut dev_path = PathBuf::from("/dev");
+    dev_path.push(dev);
+    dev_path
+}
+
+impl CliHandler for ConfiguredCliHandler
+{
+    fn select_rom_device(&self) -> Result<String, Box<dyn Error>>
+    {
+        let Some(list) = &self.tools.list else {
+            return self.platform.select_rom_device();
+        };
+
+        let devices: Vec<String> = list
+            .run(&[])?
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from)
+            .collect();
+
+        if devices.is_empty() {
+            return Err(
+                format!("The list command, {list}, listed no drives.").into()
+            );
+        }
+
+        let dev = Select::new(
+            "Select the drive you would like to image from for this session:",
+            devices,
+        )
+        .prompt()?;
+
+        Ok(dev)
+    }
+
+    fn identify_drive(
+        &self,
+        given: &str,
+    ) -> Result<DriveIdentity, Box<dyn Error>>
+    {
+        self.platform.identify_drive(given)
+    }
+
+    fn eject(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let Some(eject) = &self.tools.eject else {
+            return self.platform.eject(dev);
+        };
+
+        let dev_path = device_path(dev);
+        eject
+            .run(&[("device", dev_path.as_os_str()), ("name", OsStr::new(dev))])
+            .map(|_| ())
+    }
+
+    fn close_tray(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        self.platform.close_tray(dev)
+    }
+
+    fn lock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        self.platform.lock_door(dev)
+    }
+
+    fn unlock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        self.platform.unlock_door(dev)
+    }
+
+    fn get_rom_device_label(
+        &self,
+        dev: &String,
+    ) -> Result<String, Box<dyn Error>>
+    {
+        let Some(label) = &self.tools.label else {
+            return self.platform.get_rom_device_label(dev);
+        };
+
+        let dev_path = device_path(dev);
+        let output = label.run(&[
+            ("device", dev_path.as_os_str()),
+            ("name", OsStr::new(dev)),
+        ])?;
+
+        match output.trim() {
+            | "" => {
+                Err(format!("Device '{dev}' label could not be found.").into())
+            }
+            | label => Ok(label.to_string()),
+        }
+    }
+
+    /// `{device}` is always the drive's path, even on macOS, where the
+    /// platform's tools image the mount; `{mount}` is where its media is
+    /// mounted, which is only looked up when the command uses it.
+    fn dump_iso(
+        &self,
+        dev: &str,
+        from: &PathBuf,
+        to: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let Some(image) = &self.tools.image else {
+            return self.platform.dump_iso(dev, from, to);
+        };
+
+        let mount = match image.uses("mount") {
+            | true => {
+                self.platform.get_mount_directory(dev)?.ok_or(format!(
+                    "Device '{dev}' is not mounted, but the image command \
+                     uses {{mount}}."
+                ))?
+            }
+            | false => PathBuf::new(),
+        };
+
+        let dev_path = device_path(dev);
+        image
+            .run(&[
+                ("device", dev_path.as_os_str()),
+                ("mount", mount.as_os_str()),
+                ("iso", to.as_os_str()),
+            ])
+            .map(|_| ())
+            .map_err(|e| {
+                format!("Failure while dumping ISO. Details:\n{e}").into()
+            })
+    }
+
+    fn fix_permissions(
+        &self,
+        in_path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        self.platform.fix_permissions(in_path)
+    }
+
+    fn get_mount_directory(
+        &self,
+        dev: &str,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>>
+    {
+        self.platform.get_mount_directory(dev)
+    }
+
+    fn mount_media(
+        &self,
+        dev: &str,
+    ) -> Result<PathBuf, Box<dyn Error>>
+    {
+        self.platform.mount_media(dev)
+    }
+
+    fn copy_files(
+        &self,
+        from: &Path,
+        to: &Path,
+    ) -> Result<bool, Box<dyn Error>>
+    {
+        let Some(copy) = &self.tools.copy else {
+            return self.platform.copy_files(from, to);
+        };
+
+        copy.run(&[("from", from.as_os_str()), ("to", to.as_os_str())])
+            .map_err(|e| format!("Failure while copying files: {e}"))?;
+
+        Ok(true)
+    }
+
+    fn open_cd_drive(
+        &self,
+        dev: &str,
+    ) -> Result<Box<dyn CdDrive>, Box<dyn Error>>
+    {
+        self.platform.open_cd_drive(dev)
+    }
+
+    /// A configured image command which takes `{device}` is given the
+    /// device node for every disc.
+    fn raw_device(
+        &self,
+        dev: &str,
+    ) -> (PathBuf, bool)
+    {
+        match &self.tools.image {
+            | Some(image) if image.uses("device") => (device_path(dev), true),
+            | _ => self.platform.raw_device(dev),
+        }
+    }
+
+    /// The platform's tools for the operations left to it, and the program
+    /// of each configured command.
+    fn required_tools(&self) -> Vec<Tool>
+    {
+        let commands = self.tools.commands();
+        let configured = |operation: &str| {
+            commands.iter().any(|(o, c)| *o == operation && c.is_some())
+        };
+
+        let mut tools: Vec<Tool> = self
+            .platform
+            .required_tools()
+            .into_iter()
+            .filter(|tool| !configured(tool.operation))
+            .collect();
+
+        for (operation, command) in commands {
+            let Some(program) = command.as_ref().and_then(|c| c.program())
+            else {
+                continue;
+            };
+
+            tools.push(Tool {
+                program: program.to_string(),
+                operation,
+                purpose: format!("the configured {operation} command"),
+                required: true,
+                fix: format!(
+                    "Install {program}, or change the {operation} command in \
+                     the config file."
+                ),
+            });
+        }
+
+        tools
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::cli_handler::{
+        LinuxCliHandler,
+        MacosCliHandler,
+    };
+
+    fn tools(toml: &str) -> Result<ToolCommands, Box<dyn Error>>
+    {
+        let tools: ToolCommands = toml::from_str(toml)?;
+        tools.check()?;
+
+        Ok(tools)
+    }
+
+    #[test]
+    fn test_tool_commands()
+    {
+        let tools = tools(
+            r#"
+            image = "ddrescue -b 2048 {device} {iso} {iso}.map"
+            label = "echo 'Disc: {name}'"
+            "#,
+        )
+        .unwrap();
+        assert!(tools.eject.is_none() && !tools.is_empty());
+        assert!(ToolCommands::default().is_empty());
+
+        let handler =
+            ConfiguredCliHandler::new(tools, Box::new(LinuxCliHandler {}));
+        assert_eq!(
+            "Disc: sr0",
+            handler.get_rom_device_label(&"sr0".to_string()).unwrap()
+        );
+
+        let programs: Vec<(&str, String)> = handler
+            .required_tools()
+            .into_iter()
+            .map(|t| (t.operation, t.program))
+            .collect();
+        assert!(programs.contains(&("image", "ddrescue".to_string())));
+        assert!(programs.contains(&("label", "echo".to_string())));
+        assert!(!programs.contains(&("image", "dd".to_string())));
+        assert!(programs.contains(&("eject", "eject".to_string())));
+        assert_eq!(
+            (PathBuf::from("/dev/sr0"), true),
+            handler.raw_device("sr0")
+        );
+
+        assert!(tools_error("image = \"ddrescue {device} {to}\"")
+            .contains("only {device}, {mount}, {iso}"));
+        assert!(
+            tools_error("list = \"lsblk {name}\"").contains("no placeholders")
+        );
+        assert!(tools_error("rip = \"cdparanoia\"").contains("rip"));
+        let unclosed = tools_error("eject = \"eject '{device}\"");
+        assert!(unclosed.contains("unclosed"));
+    }
+
+    #[test]
+    fn test_image_device()
+    {
+        let handler = |toml| {
+            ConfiguredCliHandler::new(
+                tools(toml).unwrap(),
+                Box::new(LinuxCliHandler {}),
+            )
+        };
+        let out = tempfile::TempDir::new().unwrap();
+        let iso = out.path().join("a.iso");
+
+        // The device, rather than the mount the platform images from.
+        handler(r#"image = "sh -c 'echo \"$0\" > \"$1\"' {device} {iso}""#)
+            .dump_iso("sr0", &PathBuf::from("/Volumes/A"), &iso)
+            .unwrap();
+        assert_eq!("/dev/sr0\n", std::fs::read_to_string(&iso).unwrap());
+        assert_eq!(
+            (PathBuf::from("/dev/sr0"), true),
+            handler("image = \"dd if={device} of={iso}\"").raw_device("sr0")
+        );
+
+        // An image command without {device} reads what the platform would.
+        let mount = ConfiguredCliHandler::new(
+            tools("image = \"cp -R {mount} {iso}\"").unwrap(),
+            Box::new(MacosCliHandler {}),
+        );
+        assert_eq!(
+            (PathBuf::from("/dev/rdisk4"), false),
+            mount.raw_device("disk4")
+        );
+
+        // A drive which is not mounted has no {mount}.
+        let error = handler("image = \"cp -R {mount} {iso}\"")
+            .dump_iso("null", &PathBuf::new(), &iso)
+            .unwrap_err();
+        assert!(error.to_string().contains("is not mounted"));
+    }
+
+    fn tools_error(toml: &str) -> String
+    {
+        tools(toml).unwrap_err().to_string()
+    }
+}
@@ -0,0 +1,243 @@
+use super::block_device::Device;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    path::PathBuf,
+};
+
+/// What `diskutil list -plist` says about the disks.  Only whole disks are
+/// listed at the top level; their partitions are listed within them.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DiskList
+{
+    /// The identifiers of every disk, each followed by its partitions.
+    #[serde(default)]
+    all_disks: Vec<String>,
+    all_disks_and_partitions: Vec<ListedDisk>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListedDisk
+{
+    device_identifier: String,
+    size: Option<u64>,
+    volume_name: Option<String>,
+    #[serde(default)]
+    partitions: Vec<ListedVolume>,
+    #[serde(default, rename = "APFSVolumes")]
+    apfs_volumes: Vec<ListedVolume>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListedVolume
+{
+    volume_name: Option<String>,
+}
+
+/// What `diskutil info -plist <disk>` says about a disk or partition.  Keys
+/// the disk does not have are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DiskInfo
+{
+    /// Such as "disk4" or "disk4s1".
+    pub device_identifier: String,
+    pub whole_disk: bool,
+    /// The disk holding a partition, or the disk itself.
+    pub parent_whole_disk: Option<String>,
+    /// The drive's vendor and model, such as "HL-DT-ST DVDRAM GP65NB60".
+    pub media_name: Option<String>,
+    pub volume_name: Option<String>,
+    /// Empty when the volume is not mounted.
+    pub mount_point: Option<String>,
+    pub removable: bool,
+    pub removable_media: bool,
+    pub ejectable: bool,
+    /// Only present for CD, DVD and Blu-ray drives, such as "DVD-RW".
+    pub optical_device_type: Option<String>,
+    /// The kind of disc in an optical drive, such as "DVD-ROM".
+    pub optical_media_type: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl DiskInfo
+{
+    pub fn mount_point(&self) -> Option<PathBuf>
+    {
+        self.mount_point
+            .as_deref()
+            .filter(|mp| !mp.is_empty())
+            .map(PathBuf::from)
+    }
+}
+
+/// Reads the whole disks from the output of `diskutil list -plist`, as
+/// `lsblk` would describe them.  Partitions and APFS volumes lend their
+/// names to their disk.  Whether a disk is optical or removable is only
+/// known once `apply_info` has been given its `diskutil info`.
+pub fn parse_list(plist: &[u8]) -> Result<Vec<Device>, Box<dyn Error>>
+{
+    let list: DiskList = plist::from_bytes(plist)
+        .map_err(|e| format!("Could not parse the diskutil list: {e}"))?;
+
+    let devices = list
+        .all_disks_and_partitions
+        .into_iter()
+        .map(|disk| {
+            let label = disk
+                .volume_name
+                .into_iter()
+                .chain(
+                    disk.partitions.into_iter().filter_map(|p| p.volume_name),
+                )
+                .chain(
+                    disk.apfs_volumes.into_iter().filter_map(|v| v.volume_name),
+                )
+                .find(|name| !name.is_empty());
+            let size = disk.size.filter(|s| *s > 0);
+
+            Device {
+                name: disk.device_identifier,
+                device_type: "disk".to_string(),
+                label,
+                has_media: size.is_some(),
+                size: size.map(format_size),
+                ..Device::default()
+            }
+        })
+        .collect();
+
+    Ok(devices)
+}
+
+/// Reads the identifiers of every disk and partition, each disk before its
+/// partitions, from the output of `diskutil list -plist`.
+pub fn parse_identifiers(plist: &[u8]) -> Result<Vec<String>, Box<dyn Error>>
+{
+    let list: DiskList = plist::from_bytes(plist)
+        .map_err(|e| format!("Could not parse the diskutil list: {e}"))?;
+
+    Ok(list.all_disks)
+}
+
+/// Reads the output of `diskutil info -plist`.
+pub fn parse_info(plist: &[u8]) -> Result<DiskInfo, Box<dyn Error>>
+{
+    plist::from_bytes(plist).map_err(|e| {
+        format!("Could not parse the diskutil information: {e}").into()
+    })
+}
+
+/// Fills in the model, optical and removable flags of `device` from its
+/// `info`.  Optical drives take the "rom" type which `lsblk` gives them.
+pub fn apply_info(
+    device: &mut Device,
+    info: &DiskInfo,
+)
+{
+    device.model = info.media_name.clone().or(device.model.take());
+    device.removable = info.removable || info.removable_media || info.ejectable;
+    device.optical =
+        info.optical_device_type.is_some() || info.optical_media_type.is_some();
+
+    if device.optical {
+        device.device_type = "rom".to_string();
+    }
+}
+
+/// Writes a size in bytes as `lsblk` does, such as "4.2G" or "1.4M".
+fn format_size(bytes: u64) -> String
+{
+    let mut size = bytes as f64;
+    let mut units = ["B", "K", "M", "G", "T", "P"].iter().peekable();
+
+    while size >= 1024.0 && units.len() > 1 {
+        size /= 1024.0;
+        units.next();
+    }
+
+    let unit = units.peek().unwrap_or(&&"P");
+    match size.fract() < 0.05 {
+        | true => format!("{size:.0}{unit}"),
+        | false => format!("{size:.1}{unit}"),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const LIST: &str = include_str!("fixtures/diskutil_list.plist");
+    const INFO_DVD: &str = include_str!("fixtures/diskutil_info_dvd.plist");
+    const INFO_INTERNAL: &str =
+        include_str!("fixtures/diskutil_info_internal.plist");
+
+    #[test]
+    fn test_parse_list()
+    {
+        let devices = parse_list(LIST.as_bytes()).unwrap();
+
+        let names: Vec<&str> =
+            devices.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(vec!["disk0", "disk3", "disk4", "disk5"], names);
+
+        // The colon in the volume name is kept.
+        assert_eq!(Some("Disc: Part 1".to_string()), devices[2].label);
+        assert_eq!(Some("4.2G".to_string()), devices[2].size);
+        assert_eq!(Some("EFI".to_string()), devices[0].label);
+        assert_eq!(Some("Data".to_string()), devices[1].label);
+        assert_eq!(Some("FLOPPY".to_string()), devices[3].label);
+        assert_eq!(Some("1.4M".to_string()), devices[3].size);
+        assert!(devices.iter().all(|d| d.has_media));
+
+        assert!(parse_list(b"disk0").is_err());
+
+        let identifiers = parse_identifiers(LIST.as_bytes()).unwrap();
+        assert_eq!(8, identifiers.len());
+        assert_eq!(["disk5", "disk5s1"], identifiers[6..]);
+    }
+
+    #[test]
+    fn test_parse_info()
+    {
+        let dvd = parse_info(INFO_DVD.as_bytes()).unwrap();
+        assert_eq!("disk4", dvd.device_identifier);
+        assert!(dvd.whole_disk);
+        assert_eq!(Some("DVD-ROM".to_string()), dvd.optical_media_type);
+        assert_eq!(
+            Some(PathBuf::from("/Volumes/Disc: Part 1")),
+            dvd.mount_point()
+        );
+
+        let internal = parse_info(INFO_INTERNAL.as_bytes()).unwrap();
+        assert_eq!(None, internal.mount_point());
+        assert_eq!(None, internal.volume_name);
+
+        let mut devices = parse_list(LIST.as_bytes()).unwrap();
+        apply_info(&mut devices[0], &internal);
+        apply_info(&mut devices[2], &dvd);
+
+        assert!(!devices[0].is_candidate());
+        assert_eq!(
+            format!(
+                "disk4 (optical drive, HL-DT-ST DVDRAM GP65NB60): {:?}, 4.2G",
+                "Disc: Part 1"
+            ),
+            devices[2].to_string()
+        );
+    }
+
+    #[test]
+    fn test_format_size()
+    {
+        assert_eq!("512B", format_size(512));
+        assert_eq!("1.4M", format_size(1474560));
+        assert_eq!("4.2G", format_size(4510973952));
+        assert_eq!("465.9G", format_size(500277790720));
+        assert_eq!("1G", format_size(1 << 30));
+    }
+}
@@ -0,0 +1,327 @@
+use super::block_device::Device;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    error::Error,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Where Linux keeps links to each disk named by what it is, or by where it
+/// is plugged in, which last across reboots.
+pub const DEV_DISK_PATH: &str = "/dev/disk";
+
+/// A drive as it was given, and what it was found to be at the start of the
+/// session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriveIdentity
+{
+    /// The drive as it was given, such as "sr0",
+    /// "/dev/disk/by-id/usb-HL-DT-ST_DVDRAM_GP65NB60_KZ9G1234-0:0", or
+    /// "serial=KZ9G1234".
+    pub given: String,
+    /// The system's current name for the drive, such as "sr0" or "disk4".
+    pub name: String,
+    /// A link in `/dev/disk/by-id`, named for the drive's model and serial.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_id: Option<PathBuf>,
+    /// A link in `/dev/disk/by-path`, named for the port the drive is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+impl DriveIdentity
+{
+    /// The way to give the drive which is least likely to change: its
+    /// by-id link, its serial number, or else its current name.
+    pub fn stable_name(&self) -> String
+    {
+        match (&self.by_id, &self.serial) {
+            | (Some(by_id), _) => by_id.to_string_lossy().to_string(),
+            | (None, Some(serial)) => format!("serial={serial}"),
+            | (None, None) => self.name.clone(),
+        }
+    }
+}
+
+/// How a drive may be given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveSpec
+{
+    /// A name such as "sr0".
+    Name(String),
+    /// A path such as "/dev/sr0", or a link to one, such as those in
+    /// `/dev/disk/by-id` and `/dev/disk/by-path`.
+    Path(PathBuf),
+    /// What the drive reports about itself, such as
+    /// "vendor=HL-DT-ST,model=DVDRAM GP65NB60" or "serial=KZ9G1234".  Each
+    /// given value must match.
+    Attributes
+    {
+        vendor: Option<String>,
+        model: Option<String>,
+        serial: Option<String>,
+    },
+}
+
+impl DriveSpec
+{
+    pub fn parse(spec: &str) -> Result<DriveSpec, Box<dyn Error>>
+    {
+        if spec.starts_with('/') {
+            return Ok(DriveSpec::Path(PathBuf::from(spec)));
+        }
+        if !spec.contains('=') {
+            return Ok(DriveSpec::Name(spec.to_string()));
+        }
+
+        let (mut vendor, mut model, mut serial) = (None, None, None);
+        for pair in spec.split(',') {
+            let (key, value) = pair.split_once('=').ok_or(format!(
+                "Drive '{spec}' should be given as key=value pairs, such as \
+                 serial=KZ9G1234, separated by commas."
+            ))?;
+            let value = Some(value.trim().to_string());
+
+            match key.trim() {
+                | "vendor" => vendor = value,
+                | "model" => model = value,
+                | "serial" => serial = value,
+                | key => {
+                    return Err(format!(
+                        "Drive '{spec}' has the key {key}, but only vendor, \
+                         model and serial can be given."
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(DriveSpec::Attributes {
+            vendor,
+            model,
+            serial,
+        })
+    }
+
+    /// Whether `device` reports each of the attributes given, ignoring case
+    /// and surrounding spaces.  Names and paths match no device here.
+    pub fn matches(
+        &self,
+        device: &Device,
+    ) -> bool
+    {
+        let DriveSpec::Attributes {
+            vendor,
+            model,
+            serial,
+        } = self
+        else {
+            return false;
+        };
+
+        let same = |given: &Option<String>, found: &Option<String>| {
+            match given {
+                | None => true,
+                | Some(given) => {
+                    found
+                        .as_deref()
+                        .is_some_and(|f| f.trim().eq_ignore_ascii_case(given))
+                }
+            }
+        };
+
+        same(vendor, &device.vendor)
+            && same(model, &device.model)
+            && same(serial, &device.serial)
+    }
+}
+
+/// The one device in `devices` which has the attributes of `spec`.
+pub fn find_device<'a>(
+    devices: &'a [Device],
+    spec: &DriveSpec,
+    given: &str,
+) -> Result<&'a Device, Box<dyn Error>>
+{
+    let matching: Vec<&Device> =
+        devices.iter().filter(|d| spec.matches(d)).collect();
+
+    match matching[..] {
+        | [device] => Ok(device),
+        | [] => Err(format!("No drive matches '{given}'.").into()),
+        | _ => {
+            let names: Vec<&str> =
+                matching.iter().map(|d| d.name.as_str()).collect();
+
+            Err(format!(
+                "Drives {} all match '{given}'.  Give the serial number to \
+                 choose one.",
+                names.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+/// The name of the device that `path`, or the link it is, leads to, such as
+/// "sr0" for "/dev/disk/by-id/usb-HL-DT-ST_DVDRAM_GP65NB60_KZ9G1234-0:0".
+pub fn resolve_path(path: &Path) -> Result<String, Box<dyn Error>>
+{
+    let device = fs::canonicalize(path)
+        .map_err(|e| format!("Drive {path:?} could not be found: {e}"))?;
+
+    device
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or(format!("{path:?} does not lead to a device.").into())
+}
+
+/// The first link, in name order, in the `kind` folder of `dev_disk` (such
+/// as "by-id" in `/dev/disk`) which leads to the device `name`.  Links
+/// named for the drive's World Wide Name are only used if there is no other.
+pub fn find_link(
+    dev_disk: &Path,
+    kind: &str,
+    name: &str,
+) -> Option<PathBuf>
+{
+    let mut links: Vec<PathBuf> = fs::read_dir(dev_disk.join(kind))
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|link| {
+            fs::canonicalize(link)
+                .is_ok_and(|target| target.file_name() == Some(name.as_ref()))
+        })
+        .collect();
+
+    links.sort_by_key(|link| {
+        let wwn = link
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with("wwn-"));
+        (wwn, link.clone())
+    });
+
+    links.into_iter().next()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    fn drive(
+        name: &str,
+        vendor: &str,
+        model: &str,
+        serial: Option<&str>,
+    ) -> Device
+    {
+        Device {
+            name: name.to_string(),
+            vendor: Some(vendor.to_string()),
+            model: Some(model.to_string()),
+            serial: serial.map(String::from),
+            ..Device::default()
+        }
+    }
+
+    #[test]
+    fn test_parse()
+    {
+        assert_eq!(
+            DriveSpec::Name("sr0".to_string()),
+            DriveSpec::parse("sr0").unwrap()
+        );
+        assert_eq!(
+            DriveSpec::Path(PathBuf::from("/dev/disk/by-path/pci-0:0:0")),
+            DriveSpec::parse("/dev/disk/by-path/pci-0:0:0").unwrap()
+        );
+        assert_eq!(
+            DriveSpec::Attributes {
+                vendor: None,
+                model: Some("DVDRAM GP65NB60".to_string()),
+                serial: Some("KZ9G1234".to_string()),
+            },
+            DriveSpec::parse("model=DVDRAM GP65NB60, serial=KZ9G1234").unwrap()
+        );
+
+        assert!(DriveSpec::parse("colour=beige").is_err());
+        assert!(DriveSpec::parse("serial=1,sr0").is_err());
+    }
+
+    #[test]
+    fn test_find_device()
+    {
+        let devices = vec![
+            drive("sr0", "HL-DT-ST", "DVDRAM GP65NB60", Some("KZ9G1234")),
+            drive("sr1", "HL-DT-ST", "DVDRAM GP65NB60", Some("KZ9G5678")),
+            drive("sr2", "ASUS", "BW-16D1HT", None),
+        ];
+        let find = |spec: &str| {
+            find_device(&devices, &DriveSpec::parse(spec).unwrap(), spec)
+                .map(|d| d.name.clone())
+        };
+
+        assert_eq!("sr1", find("serial=kz9g5678").unwrap());
+        assert_eq!("sr2", find("vendor=asus").unwrap());
+        assert!(find("model=DVDRAM GP65NB60")
+            .unwrap_err()
+            .to_string()
+            .contains("sr0, sr1"));
+        assert!(find("serial=nope").is_err());
+        assert!(find("sr0").is_err());
+    }
+
+    #[test]
+    fn test_links()
+    {
+        let dev = TempDir::new().unwrap();
+        let by_id = dev.path().join("disk/by-id");
+        let by_path = dev.path().join("disk/by-path");
+        fs::create_dir_all(&by_id).unwrap();
+        fs::create_dir_all(&by_path).unwrap();
+        for name in ["sr0", "sr1"] {
+            fs::write(dev.path().join(name), "").unwrap();
+        }
+
+        let usb = by_id.join("usb-HL-DT-ST_DVDRAM_GP65NB60_KZ9G1234-0:0");
+        symlink("../../sr0", &usb).unwrap();
+        symlink("../../sr0", by_id.join("wwn-0x5001480000000000")).unwrap();
+        symlink("../../sr1", by_id.join("ata-ASUS_BW-16D1HT")).unwrap();
+        symlink("../../sr0", by_path.join("pci-0000:00:14.0-usb-0:2:1.0"))
+            .unwrap();
+
+        assert_eq!("sr0", resolve_path(&usb).unwrap());
+        assert!(resolve_path(&by_id.join("missing")).is_err());
+
+        let dev_disk = dev.path().join("disk");
+        assert_eq!(Some(usb), find_link(&dev_disk, "by-id", "sr0"));
+        assert_eq!(
+            Some(by_path.join("pci-0000:00:14.0-usb-0:2:1.0")),
+            find_link(&dev_disk, "by-path", "sr0")
+        );
+        assert_eq!(None, find_link(&dev_disk, "by-path", "sr1"));
+        assert_eq!(None, find_link(&dev_disk, "by-label", "sr0"));
+
+        let identity = DriveIdentity {
+            name: "sr1".to_string(),
+            serial: Some("KZ9G5678".to_string()),
+            ..DriveIdentity::default()
+        };
+        assert_eq!("serial=KZ9G5678", identity.stable_name());
+    }
+}
@@ -0,0 +1,40 @@
+/// The numbers `drutil list` gives the attached drives, which its `-drive`
+/// option takes.
+pub fn parse_drive_numbers(list: &str) -> Vec<u32>
+{
+    list.lines()
+        .filter_map(|line| line.split_whitespace().next()?.parse().ok())
+        .collect()
+}
+
+/// The disk identifier, such as "disk4", which `drutil status` gives the
+/// disc in a drive.  Drives without a disc have none.
+pub fn parse_status_disk(status: &str) -> Option<String>
+{
+    let (_, name) = status.split_once("Name: /dev/")?;
+
+    name.split_whitespace().next().map(|n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const LIST: &str = include_str!("fixtures/drutil_list.txt");
+    const STATUS: &str = include_str!("fixtures/drutil_status.txt");
+
+    #[test]
+    fn test_parse_drive_numbers()
+    {
+        assert_eq!(vec![1, 2], parse_drive_numbers(LIST));
+        assert!(parse_drive_numbers("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_status_disk()
+    {
+        assert_eq!(Some("disk4".to_string()), parse_status_disk(STATUS));
+        assert_eq!(None, parse_status_disk("No Media Inserted"));
+    }
+}
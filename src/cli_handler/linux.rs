@@ -1,45 +1,163 @@
 use super::{
+    block_device::{
+        self,
+        Device,
+    },
+    drive_identity::{
+        self,
+        DriveIdentity,
+        DriveSpec,
+    },
+    mountinfo,
     CliHandler,
     CliHandlerExtras,
 };
-use inquire::Text;
+use crate::{
+    audio_cd::{
+        device::DeviceDrive,
+        CdDrive,
+    },
+    preflight::Tool,
+};
+use inquire::{
+    Select,
+    Text,
+};
 use log::debug;
 use std::{
     error::Error,
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::fs::OpenOptionsExt,
+    },
     path::{
         self,
+        Path,
         PathBuf,
     },
     process::Command,
 };
 
+/// Requests from `<linux/cdrom.h>` which take an integer argument.
+const CDROMEJECT: u64 = 0x5309;
+const CDROMCLOSETRAY: u64 = 0x5319;
+/// The lock outlasts the open file, until it is released with an argument
+/// of 0.
+const CDROM_LOCKDOOR: u64 = 0x5329;
+
 pub struct LinuxCliHandler {}
 
 impl CliHandler for LinuxCliHandler
 {
     fn select_rom_device(&self) -> Result<String, Box<dyn Error>>
     {
-        println!(
-            "{}",
-            Command::new("lsblk")
-                .arg("--all")
-                .arg("-o")
-                .arg("name,label,size")
-                .run()?
-        );
+        let mut devices = list_block_devices()?;
+        devices.retain(Device::is_candidate);
 
-        let dev = Text::new(
-            "Enter the device NAME you would like to image from for this \
-             session:",
+        if devices.is_empty() {
+            println!(
+                "No optical or removable drives were found.  Listing every \
+                 device instead."
+            );
+            return prompt_for_device_name();
+        }
+
+        let device = Select::new(
+            "Select the drive you would like to image from for this session:",
+            devices,
         )
         .prompt()?;
 
-        Ok(dev)
+        Ok(device.name)
+    }
+
+    fn identify_drive(
+        &self,
+        given: &str,
+    ) -> Result<DriveIdentity, Box<dyn Error>>
+    {
+        let spec = DriveSpec::parse(given)?;
+        let devices = list_block_devices();
+
+        let name = match &spec {
+            | DriveSpec::Name(name) => name.clone(),
+            | DriveSpec::Path(path) => drive_identity::resolve_path(path)?,
+            | DriveSpec::Attributes { .. } => {
+                let devices = devices.as_deref().map_err(|e| {
+                    format!("Could not list the drives to find {given}: {e}")
+                })?;
+
+                drive_identity::find_device(devices, &spec, given)?
+                    .name
+                    .clone()
+            }
+        };
+
+        // The drive's details are only recorded, so a drive lsblk cannot
+        // describe is still used.
+        let device = devices
+            .unwrap_or_default()
+            .into_iter()
+            .find(|d| d.name == name)
+            .unwrap_or_default();
+        let dev_disk = Path::new(drive_identity::DEV_DISK_PATH);
+
+        Ok(DriveIdentity {
+            given: given.to_string(),
+            by_id: drive_identity::find_link(dev_disk, "by-id", &name),
+            by_path: drive_identity::find_link(dev_disk, "by-path", &name),
+            vendor: device.vendor,
+            model: device.model,
+            serial: device.serial,
+            name,
+        })
+    }
+
+    fn eject(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        // The request fails while the media is mounted or is not a CD, so
+        // fall back to the eject command, which unmounts it first.
+        if let Err(e) = cdrom_ioctl(dev, CDROMEJECT, 0) {
+            debug!("{e}  Trying the eject command instead.");
+
+            let mut dev_path = PathBuf::from("/dev");
+            dev_path.push(dev);
+            Command::new("eject").arg(dev_path).run()?;
+        }
+
+        Ok(())
+    }
+
+    fn close_tray(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        cdrom_ioctl(dev, CDROMCLOSETRAY, 0)
+    }
+
+    fn lock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        cdrom_ioctl(dev, CDROM_LOCKDOOR, 1)
     }
 
-    fn eject_tray(&self) -> Result<(), Box<dyn Error>>
+    fn unlock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
     {
-        Command::new("eject").run().map(|_| ())
+        cdrom_ioctl(dev, CDROM_LOCKDOOR, 0)
     }
 
     fn get_rom_device_label(
@@ -57,6 +175,7 @@ impl CliHandler for LinuxCliHandler
 
     fn dump_iso(
         &self,
+        _dev: &str,
         from: &PathBuf,
         to: &PathBuf,
     ) -> Result<(), Box<dyn Error>>
@@ -77,11 +196,7 @@ impl CliHandler for LinuxCliHandler
             .run()
             .map(|_| ())
             .map_err(|e| {
-                format!(
-                    "Failure while trying to run dd.  Should this program be \
-                     running as root? Details:\n{e}"
-                )
-                .into()
+                format!("Failure while trying to run dd.  Details:\n{e}").into()
             })
     }
 
@@ -105,6 +220,127 @@ impl CliHandler for LinuxCliHandler
             })
     }
 
+    fn get_mount_directory(
+        &self,
+        dev: &str,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>>
+    {
+        // Resolve names such as disk/by-id/usb-... to the device node, as
+        // mountinfo lists it.
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(dev);
+        let device = fs::canonicalize(&dev_path)
+            .map_err(|e| format!("Could not find device '{dev}': {e}"))?;
+
+        let text =
+            fs::read_to_string(mountinfo::MOUNTINFO_PATH).map_err(|e| {
+                format!("Could not read {}: {e}", mountinfo::MOUNTINFO_PATH)
+            })?;
+
+        let mut mounts = mountinfo::parse_mountinfo(&text);
+        for mount in &mut mounts {
+            if let Ok(source) = fs::canonicalize(&mount.source) {
+                mount.source = source;
+            }
+        }
+
+        debug!("Searching for the mount of {device:?}");
+
+        Ok(mountinfo::find_mount(&mounts, &device)
+            .map(|m| m.mount_point.clone()))
+    }
+
+    fn mount_media(
+        &self,
+        dev: &str,
+    ) -> Result<PathBuf, Box<dyn Error>>
+    {
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(dev);
+
+        Command::new("udisksctl")
+            .arg("mount")
+            .arg("--block-device")
+            .arg(&dev_path)
+            .run()?;
+
+        self.get_mount_directory(dev)?.ok_or(
+            format!(
+                "Device '{dev}' could not be mounted with udisksctl.  Mount \
+                 it by hand and try again."
+            )
+            .into(),
+        )
+    }
+
+    fn copy_files(
+        &self,
+        _from: &Path,
+        _to: &Path,
+    ) -> Result<bool, Box<dyn Error>>
+    {
+        Ok(false)
+    }
+
+    fn open_cd_drive(
+        &self,
+        dev: &str,
+    ) -> Result<Box<dyn CdDrive>, Box<dyn Error>>
+    {
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(dev);
+
+        Ok(Box::new(DeviceDrive::open(&dev_path)?))
+    }
+
+    /// dd images every data disc straight from the device.
+    fn raw_device(
+        &self,
+        dev: &str,
+    ) -> (PathBuf, bool)
+    {
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(dev);
+
+        (dev_path, true)
+    }
+
+    fn required_tools(&self) -> Vec<Tool>
+    {
+        let tool =
+            |program: &str, operation, purpose: &str, required, package| {
+                Tool {
+                    program: program.to_string(),
+                    operation,
+                    purpose: purpose.to_string(),
+                    required,
+                    fix: format!(
+                        "Install the {package} package, or add the folder \
+                         holding {program} to PATH."
+                    ),
+                }
+            };
+
+        vec![
+            tool("lsblk", "label", "reading disc labels", true, "util-linux"),
+            tool("dd", "image", "imaging data discs", true, "coreutils"),
+            tool(
+                "eject",
+                "eject",
+                "ejecting drives which are not CD, DVD or Blu-ray drives",
+                false,
+                "eject",
+            ),
+            tool(
+                "udisksctl",
+                "copy",
+                "mounting discs whose files are copied",
+                false,
+                "udisks2",
+            ),
+        ]
+    }
+
     // fn mount_iso(
     //     &self,
     //     iso_path: &PathBuf,
@@ -127,25 +363,78 @@ impl CliHandler for LinuxCliHandler
     //             format!("Failure while trying to mount ISO: {e}").into()
     //         })
     // }
+}
 
-    fn copy_rec(
-        &self,
-        from: &PathBuf,
-        to: &PathBuf,
-    ) -> Result<(), Box<dyn Error>>
-    {
-        debug!("Copying files from {from:?} to {to:?}");
+/// The whole block devices, as `lsblk` and `/sys/block` describe them.
+fn list_block_devices() -> Result<Vec<Device>, Box<dyn Error>>
+{
+    let lsblk = Command::new("lsblk")
+        .arg("--json")
+        .arg("--output")
+        .arg(block_device::LSBLK_COLUMNS)
+        .run()?;
 
-        Command::new("cp")
-            .arg("--recursive")
-            .arg(from)
-            .arg(to)
-            .run()
-            .map(|_| ())
-            .map_err(|e| format!("Failure while copying files: {e}").into())
+    let mut devices = block_device::parse_lsblk(&lsblk)?;
+    block_device::read_sysfs(
+        &mut devices,
+        Path::new(block_device::SYS_BLOCK_PATH),
+    );
+
+    Ok(devices)
+}
+
+/// Sends `request`, which takes an integer argument, to the drive `dev`.
+fn cdrom_ioctl(
+    dev: &str,
+    request: u64,
+    arg: libc::c_int,
+) -> Result<(), Box<dyn Error>>
+{
+    let mut dev_path = PathBuf::from("/dev");
+    dev_path.push(dev);
+
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&dev_path)
+        .map_err(|e| format!("Could not open drive {dev_path:?}: {e}"))?;
+
+    // SAFETY: these requests take their argument by value, not by pointer.
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg) };
+
+    match result {
+        | -1 => {
+            Err(format!(
+                "Drive {dev_path:?} request failed: {}",
+                io::Error::last_os_error()
+            )
+            .into())
+        }
+        | _ => Ok(()),
     }
 }
 
+/// Prints every block device and asks for the name of one, for drives which
+/// are not recognised as optical or removable.
+fn prompt_for_device_name() -> Result<String, Box<dyn Error>>
+{
+    println!(
+        "{}",
+        Command::new("lsblk")
+            .arg("--all")
+            .arg("-o")
+            .arg("name,label,size")
+            .run()?
+    );
+
+    let dev = Text::new(
+        "Enter the device NAME you would like to image from for this session:",
+    )
+    .prompt()?;
+
+    Ok(dev)
+}
+
 fn lsblk_dev_label(dev: &String) -> Result<String, Box<dyn Error>>
 {
     let dev_path = {
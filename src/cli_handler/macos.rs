@@ -1,13 +1,32 @@
 use super::{
+    block_device::Device,
+    diskutil,
+    drive_identity::{
+        self,
+        DriveIdentity,
+        DriveSpec,
+    },
+    drutil,
     CliHandler,
     CliHandlerExtras,
 };
-use inquire::Text;
+use crate::{
+    audio_cd::{
+        device::DeviceDrive,
+        CdDrive,
+    },
+    preflight::Tool,
+};
+use inquire::{
+    Select,
+    Text,
+};
 use log::debug;
 use std::{
     error::Error,
     path::{
         self,
+        Path,
         PathBuf,
     },
     process::Command,
@@ -19,46 +38,121 @@ impl CliHandler for MacosCliHandler
 {
     fn select_rom_device(&self) -> Result<String, Box<dyn Error>>
     {
-        println!("{}", Command::new("diskutil").arg("list").run()?);
+        let mut devices = list_disks()?;
+        devices.retain(Device::is_candidate);
+
+        if devices.is_empty() {
+            println!(
+                "No optical or removable drives were found.  Listing every \
+                 device instead."
+            );
+            return prompt_for_device_name();
+        }
 
-        let dev = Text::new(
-            "Enter the DISK identifier you would like to image from for \
-             this session.  (Do not enter the partition identifier.  For example, disk4 is correct, but disk4s1 is not.):",
+        let device = Select::new(
+            "Select the drive you would like to image from for this session:",
+            devices,
         )
         .prompt()?;
 
-        Ok(dev)
+        Ok(device.name)
     }
 
-    fn eject_tray(&self) -> Result<(), Box<dyn Error>>
+    /// macOS has no lasting links to disks, and diskutil gives a drive's
+    /// vendor and model together, as its model, and no serial number.
+    fn identify_drive(
+        &self,
+        given: &str,
+    ) -> Result<DriveIdentity, Box<dyn Error>>
+    {
+        let spec = DriveSpec::parse(given)?;
+
+        let name = match &spec {
+            | DriveSpec::Name(name) => name.clone(),
+            | DriveSpec::Path(path) => drive_identity::resolve_path(path)?,
+            | DriveSpec::Attributes { .. } => {
+                drive_identity::find_device(&list_disks()?, &spec, given)?
+                    .name
+                    .clone()
+            }
+        };
+
+        Ok(DriveIdentity {
+            given: given.to_string(),
+            model: diskutil_info(&name).ok().and_then(|i| i.media_name),
+            name,
+            ..DriveIdentity::default()
+        })
+    }
+
+    fn eject(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        Command::new("diskutil")
+            .arg("eject")
+            .arg(dev)
+            .run()
+            .map(|_| ())
+    }
+
+    fn close_tray(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>
     {
         Command::new("drutil")
             .arg("tray")
-            .arg("eject")
+            .arg("close")
+            .arg("-drive")
+            .arg(drutil_drive(dev)?.to_string())
             .run()
             .map(|_| ())
     }
 
+    /// macOS keeps the tray of a drive with a mounted disc closed itself.
+    fn lock_door(
+        &self,
+        _dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        Ok(())
+    }
+
+    fn unlock_door(
+        &self,
+        _dev: &str,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        Ok(())
+    }
+
     fn get_rom_device_label(
         &self,
         dev: &String,
     ) -> Result<String, Box<dyn Error>>
     {
-        diskutil_dev_label(dev)?
-            .lines()
+        debug!("Searching for label for device {dev:?}");
+
+        // Listing the disk gives the names of its partitions as well, which
+        // hold the label of a partitioned disc.
+        let list = Command::new("diskutil")
+            .arg("list")
+            .arg("-plist")
+            .arg(dev)
+            .run()?;
+
+        diskutil::parse_list(list.as_bytes())?
             .into_iter()
-            .filter_map(|l| {
-                l.trim()
-                    .strip_prefix("Volume Name:")
-                    .map(|vn| vn.trim())
-            })
-            .next()
+            .find(|d| &d.name == dev)
+            .and_then(|d| d.label)
             .ok_or(format!("Device '{dev}' label could not be found.").into())
-            .map(|vn| vn.into())
     }
 
     fn dump_iso(
         &self,
+        _dev: &str,
         from: &PathBuf,
         to: &PathBuf,
     ) -> Result<(), Box<dyn Error>>
@@ -81,10 +175,7 @@ impl CliHandler for MacosCliHandler
             .run()
             .map(|_| ())
             .map_err(|e| {
-                format!(
-                    "Failure while dumping ISO. Details:\n{e}"
-                )
-                .into()
+                format!("Failure while dumping ISO. Details:\n{e}").into()
             })
     }
 
@@ -108,6 +199,104 @@ impl CliHandler for MacosCliHandler
             })
     }
 
+    fn get_mount_directory(
+        &self,
+        dev: &str,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>>
+    {
+        // A partitioned disc is mounted from its partition, so try the
+        // partitions in turn when the disk itself is not mounted.
+        let list = Command::new("diskutil")
+            .arg("list")
+            .arg("-plist")
+            .arg(dev)
+            .run()?;
+        let identifiers = diskutil::parse_identifiers(list.as_bytes())?;
+
+        for identifier in identifiers {
+            if let Some(mount_point) = diskutil_info(&identifier)?.mount_point()
+            {
+                return Ok(Some(mount_point));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn mount_media(
+        &self,
+        dev: &str,
+    ) -> Result<PathBuf, Box<dyn Error>>
+    {
+        Command::new("diskutil").arg("mountDisk").arg(dev).run()?;
+
+        self.get_mount_directory(dev)?.ok_or(
+            format!(
+                "Device '{dev}' could not be mounted with diskutil.  Mount it \
+                 by hand and try again."
+            )
+            .into(),
+        )
+    }
+
+    fn copy_files(
+        &self,
+        _from: &Path,
+        _to: &Path,
+    ) -> Result<bool, Box<dyn Error>>
+    {
+        Ok(false)
+    }
+
+    fn open_cd_drive(
+        &self,
+        dev: &str,
+    ) -> Result<Box<dyn CdDrive>, Box<dyn Error>>
+    {
+        // The raw device node allows sector-sized reads.
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(format!("r{dev}"));
+
+        Ok(Box::new(DeviceDrive::open(&dev_path)?))
+    }
+
+    /// Data discs are imaged from their mount in /Volumes, so only audio CDs,
+    /// and waiting for media, read the raw device, which only root may.
+    fn raw_device(
+        &self,
+        dev: &str,
+    ) -> (PathBuf, bool)
+    {
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(format!("r{dev}"));
+
+        (dev_path, false)
+    }
+
+    /// Each of these comes with macOS, so a missing one means PATH has lost
+    /// /usr/sbin or /usr/bin.
+    fn required_tools(&self) -> Vec<Tool>
+    {
+        let tool = |program: &str, operation, purpose: &str, required| {
+            Tool {
+                program: program.to_string(),
+                operation,
+                purpose: purpose.to_string(),
+                required,
+                fix: format!(
+                    "{program} comes with macOS.  Add /usr/sbin and /usr/bin \
+                     to PATH."
+                ),
+            }
+        };
+
+        vec![
+            tool("diskutil", "label", "reading disc labels", true),
+            tool("hdiutil", "image", "imaging data discs", true),
+            tool("drutil", "eject", "closing drive trays", false),
+        ]
+    }
+
     // fn mount_iso(
     //     &self,
     //     iso_path: &PathBuf,
@@ -127,28 +316,80 @@ impl CliHandler for MacosCliHandler
     //             format!("Failure while trying to mount ISO: {e}").into()
     //         })
     // }
+}
 
-    fn copy_rec(
-        &self,
-        from: &PathBuf,
-        to: &PathBuf,
-    ) -> Result<(), Box<dyn Error>>
-    {
-        debug!("Copying files from {from:?} to {to:?}");
+/// The whole disks, as `diskutil` describes them.
+fn list_disks() -> Result<Vec<Device>, Box<dyn Error>>
+{
+    let list = Command::new("diskutil").arg("list").arg("-plist").run()?;
 
-        Command::new("cp")
-            .arg("-R")
-            .arg(from)
-            .arg(to)
-            .run()
-            .map(|_| ())
-            .map_err(|e| format!("Failure while copying files: {e}").into())
+    let mut devices = diskutil::parse_list(list.as_bytes())?;
+    for device in &mut devices {
+        match diskutil_info(&device.name) {
+            | Ok(info) => diskutil::apply_info(device, &info),
+            | Err(e) => debug!("Skipping details of {}: {e}", device.name),
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Prints every disk, with its partitions, and asks for the disk to image.
+fn prompt_for_device_name() -> Result<String, Box<dyn Error>>
+{
+    println!("{}", Command::new("diskutil").arg("list").run()?);
+
+    let dev = Text::new(
+        "Enter the DISK identifier you would like to image from for this \
+         session.  (Do not enter the partition identifier.  For example, \
+         disk4 is correct, but disk4s1 is not.):",
+    )
+    .prompt()?;
+
+    Ok(dev)
+}
+
+/// The number drutil gives the drive holding the disk `dev`.  drutil knows
+/// drives by its own numbers, and only names the disk of a drive with a disc
+/// in it, so a drive whose tray is open cannot be found this way; it is
+/// taken to be the only drive, if there is just one.
+fn drutil_drive(dev: &str) -> Result<u32, Box<dyn Error>>
+{
+    let list = Command::new("drutil").arg("list").run()?;
+    let numbers = drutil::parse_drive_numbers(&list);
+
+    for number in &numbers {
+        let status = Command::new("drutil")
+            .arg("status")
+            .arg("-drive")
+            .arg(number.to_string())
+            .run()?;
+
+        if drutil::parse_status_disk(&status).as_deref() == Some(dev) {
+            return Ok(*number);
+        }
+    }
+
+    match numbers[..] {
+        | [only] => Ok(only),
+        | _ => {
+            Err(format!(
+                "No drive holds device '{dev}', and {} drives are attached, \
+                 so which tray to close is not known.  Close it by hand.",
+                numbers.len()
+            )
+            .into())
+        }
     }
 }
 
-fn diskutil_dev_label(dev: &String) -> Result<String, Box<dyn Error>>
+fn diskutil_info(dev: &String) -> Result<diskutil::DiskInfo, Box<dyn Error>>
 {
-    debug!("Searching for label for device {dev:?}");
+    let info = Command::new("diskutil")
+        .arg("info")
+        .arg("-plist")
+        .arg(dev)
+        .run()?;
 
-    Command::new("diskutil").arg("information").arg(dev).run()
+    diskutil::parse_info(info.as_bytes())
 }
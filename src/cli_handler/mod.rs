@@ -1,28 +1,77 @@
+pub mod block_device;
+pub mod command_template;
+pub mod configured;
+pub mod diskutil;
+pub mod drive_identity;
+pub mod drutil;
 pub mod linux;
 pub mod macos;
+pub mod mountinfo;
 
+use crate::{
+    audio_cd::CdDrive,
+    preflight::Tool,
+};
+pub use configured::ConfiguredCliHandler;
+use drive_identity::DriveIdentity;
 pub use linux::LinuxCliHandler;
 use log::debug;
 pub use macos::MacosCliHandler;
 use std::{
     error::Error,
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
     process::Command,
 };
 
-pub trait CliHandler
+pub trait CliHandler: Sync
 {
     fn select_rom_device(&self) -> Result<String, Box<dyn Error>>;
 
-    fn eject_tray(&self) -> Result<(), Box<dyn Error>>;
+    /// Finds the drive `given` as a name, a path (such as a by-id link), or
+    /// its vendor, model and serial number, along with what identifies it
+    /// across reboots.
+    fn identify_drive(
+        &self,
+        given: &str,
+    ) -> Result<DriveIdentity, Box<dyn Error>>;
+
+    /// Ejects the media in `dev`, opening its tray if it has one.
+    fn eject(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Closes the tray of `dev`.
+    fn close_tray(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Stops the eject button of `dev` from opening its tray, until
+    /// `unlock_door` is called.
+    fn lock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>;
+
+    fn unlock_door(
+        &self,
+        dev: &str,
+    ) -> Result<(), Box<dyn Error>>;
 
     fn get_rom_device_label(
         &self,
         dev: &String,
     ) -> Result<String, Box<dyn Error>>;
 
+    /// Images the media in the drive `dev`, read from `from` (the device on
+    /// Linux, and the mount on macOS), to `to`.
     fn dump_iso(
         &self,
+        dev: &str,
         from: &PathBuf,
         to: &PathBuf,
     ) -> Result<(), Box<dyn Error>>;
@@ -32,17 +81,51 @@ pub trait CliHandler
         in_path: &PathBuf,
     ) -> Result<(), Box<dyn Error>>;
 
+    /// The directory the media in `dev`, or one of its partitions, is
+    /// mounted at, or `None` if it is not mounted.
+    fn get_mount_directory(
+        &self,
+        dev: &str,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>>;
+
+    /// Mounts the media in `dev` where the system chooses, returning the
+    /// directory.
+    fn mount_media(
+        &self,
+        dev: &str,
+    ) -> Result<PathBuf, Box<dyn Error>>;
+
+    /// Copies the files of the media mounted at `from` into `to` with an
+    /// external tool, returning false when there is none, so that the files
+    /// are copied without one.
+    fn copy_files(
+        &self,
+        from: &Path,
+        to: &Path,
+    ) -> Result<bool, Box<dyn Error>>;
+
+    /// Opens the drive for reading its TOC and raw (audio) sectors.
+    fn open_cd_drive(
+        &self,
+        dev: &str,
+    ) -> Result<Box<dyn CdDrive>, Box<dyn Error>>;
+
+    /// The external programs this handler runs, so that any missing ones can
+    /// be reported before the first item.
+    fn required_tools(&self) -> Vec<Tool>;
+
+    /// The device node this handler reads `dev` through, and whether every
+    /// disc needs it read, rather than only some, such as audio CDs.
+    fn raw_device(
+        &self,
+        dev: &str,
+    ) -> (PathBuf, bool);
+
     // fn mount_iso(
     //     &self,
     //     iso_path: &PathBuf,
     //     mount_point: &PathBuf,
     // ) -> Result<(), Box<dyn Error>>;
-
-    fn copy_rec(
-        &self,
-        from: &PathBuf,
-        to: &PathBuf,
-    ) -> Result<(), Box<dyn Error>>;
 }
 
 pub trait CliHandlerExtras
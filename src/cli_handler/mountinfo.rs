@@ -0,0 +1,173 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// Where Linux lists the mounts visible to the current process.
+pub const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// A line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount
+{
+    /// The major and minor numbers of the mounted device.
+    pub device_number: (u32, u32),
+    /// The directory of the filesystem which is mounted, "/" for all of it.
+    pub root: PathBuf,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    /// The mounted device, such as "/dev/sr0", or a name such as "tmpfs".
+    pub source: PathBuf,
+}
+
+/// Reads the mounts from the text of a mountinfo file.  Lines which do not
+/// have the expected fields are skipped.
+pub fn parse_mountinfo(text: &str) -> Vec<Mount>
+{
+    text.lines().filter_map(parse_line).collect()
+}
+
+/// The mount of `device`, or else of one of its partitions, preferring a
+/// mount of the whole filesystem to one of a directory within it.  `device`
+/// and the mounts' sources should already have had symlinks, such as those
+/// in `/dev/disk/by-id`, resolved.
+pub fn find_mount<'a>(
+    mounts: &'a [Mount],
+    device: &Path,
+) -> Option<&'a Mount>
+{
+    let device = device.to_string_lossy();
+    let is_partition = |source: &Path| {
+        source
+            .to_string_lossy()
+            .strip_prefix(&*device)
+            .map(|rest| rest.strip_prefix('p').unwrap_or(rest))
+            .is_some_and(|n| {
+                !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+            })
+    };
+
+    let mut candidates: Vec<&Mount> = mounts
+        .iter()
+        .filter(|m| m.source.to_string_lossy() == device)
+        .collect();
+    if candidates.is_empty() {
+        candidates =
+            mounts.iter().filter(|m| is_partition(&m.source)).collect();
+    }
+
+    candidates
+        .iter()
+        .find(|m| m.root == Path::new("/"))
+        .or(candidates.first())
+        .copied()
+}
+
+/// Parses "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw".
+/// The optional fields between the mount options and the "-" vary in
+/// number.
+fn parse_line(line: &str) -> Option<Mount>
+{
+    let (mount, filesystem) = line.split_once(" - ")?;
+
+    let fields: Vec<&str> = mount.split(' ').collect();
+    let (major, minor) = fields.get(2)?.split_once(':')?;
+
+    let mut filesystem = filesystem.split(' ');
+    let fs_type = filesystem.next()?;
+    let source = filesystem.next()?;
+
+    Some(Mount {
+        device_number: (major.parse().ok()?, minor.parse().ok()?),
+        root: PathBuf::from(unescape(fields.get(3)?)),
+        mount_point: PathBuf::from(unescape(fields.get(4)?)),
+        fs_type: unescape(fs_type),
+        source: PathBuf::from(unescape(source)),
+    })
+}
+
+/// Undoes the octal escapes, such as "\040" for a space, which the kernel
+/// writes for whitespace and backslashes in names.
+fn unescape(field: &str) -> String
+{
+    let mut out = String::new();
+    let mut rest = field;
+
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        match rest.get(1..4).and_then(|o| u8::from_str_radix(o, 8).ok()) {
+            | Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[4..];
+            }
+            | None => {
+                out.push('\\');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+25 22 0:5 / /dev rw,nosuid shared:2 - devtmpfs udev rw,size=8000000k
+40 22 0:35 / /tmp rw,nosuid,nodev shared:20 - tmpfs tmpfs rw
+97 22 8:17 /backups /srv/backups rw,relatime shared:50 - vfat /dev/sdb1 rw
+98 22 8:17 / /media/archivist/FLOPPY rw,nosuid shared:51 - vfat /dev/sdb1 rw
+103 22 11:0 / /media/archivist/MY\\040DISC ro shared:55 master:3 - iso9660 \
+                             /dev/sr0 ro
+";
+
+    #[test]
+    fn test_parse_mountinfo()
+    {
+        let mounts = parse_mountinfo(&format!("{MOUNTINFO}garbage\n"));
+
+        assert_eq!(6, mounts.len());
+        assert_eq!(
+            Mount {
+                device_number: (11, 0),
+                root: PathBuf::from("/"),
+                mount_point: PathBuf::from("/media/archivist/MY DISC"),
+                fs_type: "iso9660".to_string(),
+                source: PathBuf::from("/dev/sr0"),
+            },
+            mounts[5]
+        );
+        assert_eq!(PathBuf::from("tmpfs"), mounts[2].source);
+        assert_eq!("a\\b c\\x", unescape("a\\134b\\040c\\x"));
+    }
+
+    #[test]
+    fn test_find_mount()
+    {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        let mount_point = |device: &str| {
+            find_mount(&mounts, Path::new(device))
+                .map(|m| m.mount_point.clone())
+        };
+
+        assert_eq!(
+            Some(PathBuf::from("/media/archivist/MY DISC")),
+            mount_point("/dev/sr0")
+        );
+        // A partition, mounted both in part and in whole.
+        assert_eq!(
+            Some(PathBuf::from("/media/archivist/FLOPPY")),
+            mount_point("/dev/sdb")
+        );
+        assert_eq!(Some(PathBuf::from("/")), mount_point("/dev/nvme0n1"));
+        assert_eq!(None, mount_point("/dev/sr1"));
+        assert_eq!(None, mount_point("/dev/sd"));
+    }
+}
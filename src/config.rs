@@ -0,0 +1,86 @@
+//! Settings read from the TOML file given with `--config`.
+
+use crate::{
+    cli_handler::configured::ToolCommands,
+    watch::WatchSettings,
+};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config
+{
+    /// Drives to image from when none are given on the command line, in any
+    /// form the command line takes, such as by-id links.
+    #[serde(default)]
+    pub rom_devices: Vec<String>,
+    /// External commands to use in place of the platform's tools.
+    #[serde(default)]
+    pub tools: ToolCommands,
+    /// How `--watch` matches disc labels to identifiers.
+    #[serde(default)]
+    pub watch: WatchSettings,
+}
+
+impl Config
+{
+    pub fn load(path: &PathBuf) -> Result<Config, Box<dyn Error>>
+    {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config file {path:?}: {e}"))?;
+
+        let config = Config::parse(&text)
+            .map_err(|e| format!("Config file {path:?} is invalid: {e}"))?;
+
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, Box<dyn Error>>
+    {
+        let config: Config = toml::from_str(text)?;
+        config.tools.check()?;
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load()
+    {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("carroh.toml");
+        fs::write(
+            &path,
+            "rom_devices = [\"serial=KZ9G1234\"]\n[tools]\nimage = \"ddrescue \
+             -b 2048 {device} {iso} {iso}.map\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert!(config.tools.image.is_some());
+        assert!(config.tools.copy.is_none());
+        assert_eq!(vec!["serial=KZ9G1234"], config.rom_devices);
+
+        let config =
+            Config::parse("[watch]\nlabel_pattern = '^(?P<id>CSJ_\\d+)'\n")
+                .unwrap();
+        let pattern = config.watch.label_pattern.unwrap();
+        assert_eq!(Some("CSJ_12"), pattern.find("CSJ_12_D1"));
+        assert!(Config::parse("[watch]\nlabel_pattern = '('\n").is_err());
+
+        assert_eq!(Config::default(), Config::parse("").unwrap());
+        assert!(Config::parse("[tool]\n").is_err());
+        assert!(Config::load(&dir.path().join("missing.toml")).is_err());
+    }
+}
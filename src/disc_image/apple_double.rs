@@ -0,0 +1,151 @@
+use super::FinderInfo;
+use std::{
+    error::Error,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+const MAGIC: u32 = 0x0005_1607;
+const VERSION: u32 = 0x0002_0000;
+
+const RESOURCE_FORK_ENTRY: u32 = 2;
+const FILE_DATES_ENTRY: u32 = 8;
+const FINDER_INFO_ENTRY: u32 = 9;
+
+/// AppleDouble dates count seconds from 2000-01-01 00:00:00 GMT.
+const APPLE_DOUBLE_EPOCH: Duration = Duration::from_secs(946_684_800);
+
+/// "Unknown" value for AppleDouble dates.
+const UNKNOWN_DATE: i32 = i32::MIN;
+
+/// The location of the AppleDouble header file for `file`, which is the
+/// file's name prefixed with "._" in the same directory.
+pub fn header_path(file: &Path) -> Option<PathBuf>
+{
+    let name = file.file_name()?.to_string_lossy();
+
+    Some(file.with_file_name(format!("._{name}")))
+}
+
+/// Writes an AppleDouble header file holding the Finder information, dates,
+/// and resource fork of a Mac file.
+pub fn write(
+    path: &PathBuf,
+    finder_info: Option<&FinderInfo>,
+    resource: &[u8],
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> Result<(), Box<dyn Error>>
+{
+    fs::write(path, encode(finder_info, resource, created, modified))
+        .map_err(|e| format!("Could not write {path:?}: {e}").into())
+}
+
+pub fn encode(
+    finder_info: Option<&FinderInfo>,
+    resource: &[u8],
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> Vec<u8>
+{
+    let mut dates = vec![];
+    dates.extend(apple_double_date(created).to_be_bytes());
+    dates.extend(apple_double_date(modified).to_be_bytes());
+    dates.extend(UNKNOWN_DATE.to_be_bytes());
+    dates.extend(UNKNOWN_DATE.to_be_bytes());
+
+    let finder_info = finder_info.map(|fi| fi.raw).unwrap_or([0; 32]);
+
+    // The resource fork goes last so it can be of any length.
+    let entries: Vec<(u32, &[u8])> = vec![
+        (FILE_DATES_ENTRY, &dates),
+        (FINDER_INFO_ENTRY, &finder_info),
+        (RESOURCE_FORK_ENTRY, resource),
+    ];
+
+    let mut out = vec![];
+    out.extend(MAGIC.to_be_bytes());
+    out.extend(VERSION.to_be_bytes());
+    out.extend(b"Mac OS X        ");
+    out.extend((entries.len() as u16).to_be_bytes());
+
+    let mut offset = (out.len() + entries.len() * 12) as u32;
+    for (id, contents) in &entries {
+        out.extend(id.to_be_bytes());
+        out.extend(offset.to_be_bytes());
+        out.extend((contents.len() as u32).to_be_bytes());
+        offset += contents.len() as u32;
+    }
+
+    for (_, contents) in entries {
+        out.extend(contents);
+    }
+
+    out
+}
+
+fn apple_double_date(time: Option<SystemTime>) -> i32
+{
+    let epoch = UNIX_EPOCH + APPLE_DOUBLE_EPOCH;
+
+    match time {
+        | None => UNKNOWN_DATE,
+        | Some(t) => {
+            match t.duration_since(epoch) {
+                | Ok(d) => d.as_secs().min(i32::MAX as u64) as i32,
+                | Err(e) => {
+                    -(e.duration().as_secs().min(i32::MAX as u64) as i32)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_header_path()
+    {
+        assert_eq!(
+            Some(PathBuf::from("out/Folder/._Read Me")),
+            header_path(&PathBuf::from("out/Folder/Read Me"))
+        );
+    }
+
+    #[test]
+    fn test_encode()
+    {
+        let mut raw = [0; 32];
+        raw[..8].copy_from_slice(b"APPLdemo");
+
+        let encoded = encode(
+            Some(&FinderInfo { raw }),
+            b"resource",
+            None,
+            Some(UNIX_EPOCH + APPLE_DOUBLE_EPOCH + Duration::from_secs(5)),
+        );
+
+        // Header, three entry descriptors, dates, Finder info, resource.
+        assert_eq!(26 + 36 + 16 + 32 + 8, encoded.len());
+        assert_eq!(&encoded[0..8], &[0, 5, 0x16, 7, 0, 2, 0, 0]);
+        assert_eq!(&encoded[24..26], &[0, 3]);
+
+        let dates = &encoded[62..78];
+        assert_eq!(&dates[0..4], &i32::MIN.to_be_bytes());
+        assert_eq!(&dates[4..8], &5i32.to_be_bytes());
+
+        assert_eq!(&encoded[78..86], b"APPLdemo");
+        assert!(encoded.ends_with(b"resource"));
+    }
+}
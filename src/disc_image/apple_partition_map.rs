@@ -0,0 +1,169 @@
+use super::{
+    be_u16,
+    be_u32,
+    DiscImage,
+};
+use log::debug;
+use std::error::Error;
+
+/// "ER", the signature of the driver descriptor record in block zero.
+const DRIVER_DESCRIPTOR_SIGNATURE: u16 = 0x4552;
+
+/// "PM", the signature of each partition map entry.
+const PARTITION_ENTRY_SIGNATURE: u16 = 0x504D;
+
+/// A partition described by an Apple partition map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplePartition
+{
+    pub name: String,
+    pub partition_type: String,
+    /// Byte offset of the partition within the image.
+    pub offset: u64,
+    /// Length of the partition in bytes.
+    pub length: u64,
+}
+
+impl ApplePartition
+{
+    pub fn is_hfs(&self) -> bool
+    {
+        self.partition_type.eq("Apple_HFS")
+    }
+}
+
+/// Reads the Apple partition map at the start of the image.  Images without
+/// one (including plain ISO 9660 discs) produce an empty list.
+///
+/// The map is read the same way as the Linux kernel does: entries are
+/// spaced by the block size in the driver descriptor, which is 2048 on
+/// most hybrid CDs, and partition starts and lengths are counted in those
+/// blocks.
+pub fn read_partitions(
+    image: &mut DiscImage
+) -> Result<Vec<ApplePartition>, Box<dyn Error>>
+{
+    if image.size() < 512 {
+        return Ok(vec![]);
+    }
+
+    let ddm = image.read_at(0, 512)?;
+
+    if be_u16(&ddm, 0) != DRIVER_DESCRIPTOR_SIGNATURE {
+        return Ok(vec![]);
+    }
+
+    let block_size = match be_u16(&ddm, 2) as u64 {
+        | 0 => 512,
+        | bs => bs,
+    };
+
+    debug!("Found Apple partition map with block size {block_size}");
+
+    let mut partitions = vec![];
+    let mut map_blocks = 1;
+    let mut block = 1;
+
+    while block <= map_blocks {
+        let pos = block * block_size;
+
+        if pos + 512 > image.size() {
+            break;
+        }
+
+        let entry = image.read_at(pos, 512)?;
+
+        if be_u16(&entry, 0) != PARTITION_ENTRY_SIGNATURE {
+            break;
+        }
+
+        map_blocks = be_u32(&entry, 4) as u64;
+
+        partitions.push(ApplePartition {
+            name: c_string(&entry[16..48]),
+            partition_type: c_string(&entry[48..80]),
+            offset: be_u32(&entry, 8) as u64 * block_size,
+            length: be_u32(&entry, 12) as u64 * block_size,
+        });
+
+        block += 1;
+    }
+
+    debug!("Apple partitions: {partitions:?}");
+
+    Ok(partitions)
+}
+
+fn c_string(b: &[u8]) -> String
+{
+    let end = b.iter().position(|c| *c == 0).unwrap_or(b.len());
+
+    String::from_utf8_lossy(&b[..end]).to_string()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::{
+        io::Write,
+        path::PathBuf,
+    };
+    use tempfile::NamedTempFile;
+
+    fn entry(
+        map_blocks: u32,
+        start: u32,
+        count: u32,
+        name: &str,
+        partition_type: &str,
+    ) -> Vec<u8>
+    {
+        let mut e = vec![0; 2048];
+        e[0..2].copy_from_slice(&PARTITION_ENTRY_SIGNATURE.to_be_bytes());
+        e[4..8].copy_from_slice(&map_blocks.to_be_bytes());
+        e[8..12].copy_from_slice(&start.to_be_bytes());
+        e[12..16].copy_from_slice(&count.to_be_bytes());
+        e[16..16 + name.len()].copy_from_slice(name.as_bytes());
+        e[48..48 + partition_type.len()]
+            .copy_from_slice(partition_type.as_bytes());
+        e
+    }
+
+    #[test]
+    fn test_hybrid_cd_map()
+    {
+        let mut ddm = vec![0; 2048];
+        ddm[0..2].copy_from_slice(&DRIVER_DESCRIPTOR_SIGNATURE.to_be_bytes());
+        ddm[2..4].copy_from_slice(&2048u16.to_be_bytes());
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&ddm).unwrap();
+        file.write_all(&entry(2, 1, 2, "Apple", "Apple_partition_map"))
+            .unwrap();
+        file.write_all(&entry(2, 16, 300, "Disc", "Apple_HFS"))
+            .unwrap();
+        file.write_all(&vec![0; 2048]).unwrap();
+
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        let partitions = read_partitions(&mut image).unwrap();
+
+        assert_eq!(2, partitions.len());
+        assert!(!partitions[0].is_hfs());
+        assert!(partitions[1].is_hfs());
+        assert_eq!("Disc", partitions[1].name);
+        assert_eq!(16 * 2048, partitions[1].offset);
+        assert_eq!(300 * 2048, partitions[1].length);
+    }
+
+    #[test]
+    fn test_no_map()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&vec![0; 4096]).unwrap();
+
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+
+        assert!(read_partitions(&mut image).unwrap().is_empty());
+    }
+}
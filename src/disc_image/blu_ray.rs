@@ -0,0 +1,587 @@
+//! The structure of a Blu-ray disc, read from its `BDMV` folder: `index.bdmv`
+//! lists the titles chosen from the disc's menus, and each playlist in
+//! `PLAYLIST` strings together clips from `STREAM` with their streams and
+//! chapter marks.
+
+use super::{
+    be_u16,
+    be_u32,
+    DiscEntry,
+    DiscFilesystem,
+};
+use crate::metadata::format_duration;
+use log::warn;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::error::Error;
+
+const INDEX_IDENTIFIER: &[u8] = b"INDX";
+const PLAYLIST_IDENTIFIER: &[u8] = b"MPLS";
+
+/// Playlist times count ticks of a 45 kHz clock.
+const TICKS_PER_SECOND: u32 = 45_000;
+
+/// The playlist mark type of an entry mark, which players use as a chapter.
+const ENTRY_MARK: u8 = 1;
+
+/// What `index.bdmv` and the playlists say about a Blu-ray disc.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BluRay
+{
+    /// The BDMV version, such as "0200".
+    pub version: String,
+    pub titles: Vec<BluRayTitle>,
+    pub playlists: Vec<BluRayPlaylist>,
+}
+
+/// A title, as chosen from the disc's menus.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BluRayTitle
+{
+    pub number: usize,
+    /// "HDMV" for a movie object, or "BD-J" for a Java program.
+    pub object_type: String,
+    /// The movie object's number, or the BD-J object's name.
+    pub object: String,
+}
+
+/// A playlist file, such as `00800.mpls`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BluRayPlaylist
+{
+    pub name: String,
+    /// The running time of the playlist's play items, as "H:MM:SS".
+    pub duration: String,
+    pub duration_seconds: u32,
+    pub chapters: usize,
+    /// The clips played, such as "00001.m2ts", in order of first use.
+    pub clips: Vec<String>,
+    /// The streams of the first play item.
+    pub video_streams: Vec<BluRayStream>,
+    pub audio_streams: Vec<BluRayStream>,
+    pub subtitle_streams: Vec<BluRayStream>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BluRayStream
+{
+    /// Such as "H.264", "DTS-HD Master Audio" or "PGS".
+    pub coding: String,
+    /// For video, the resolution and frame rate, such as "1080p 23.976".
+    pub format: Option<String>,
+    /// The ISO 639-2 code recorded on the disc, such as "eng".
+    pub language: Option<String>,
+}
+
+/// Reads the Blu-ray structure of the volume, or `None` if it has no
+/// `BDMV/index.bdmv`.  The copies in `BDMV/BACKUP` are read when the main
+/// files cannot be, and a playlist which cannot be read from either is left
+/// out.
+pub fn read_blu_ray(
+    volume: &mut dyn DiscFilesystem
+) -> Result<Option<BluRay>, Box<dyn Error>>
+{
+    let entries = volume.entries()?;
+    let find = |path: &str| -> Option<&DiscEntry> {
+        entries.iter().find(|e| {
+            !e.is_dir && e.path.to_string_lossy().eq_ignore_ascii_case(path)
+        })
+    };
+
+    let Some(index) = find("BDMV/index.bdmv") else {
+        return Ok(None);
+    };
+
+    let (version, titles) = match parse_index(&volume.read_data(index)?) {
+        | Ok(index) => index,
+        | Err(e) => {
+            let backup = find("BDMV/BACKUP/index.bdmv").ok_or(e)?;
+            parse_index(&volume.read_data(backup)?)?
+        }
+    };
+
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter_map(|e| {
+            let path = e.path.to_string_lossy();
+            let (dir, name) = path.rsplit_once('/')?;
+
+            match !e.is_dir
+                && dir.eq_ignore_ascii_case("BDMV/PLAYLIST")
+                && name.to_lowercase().ends_with(".mpls")
+            {
+                | true => Some(name.to_string()),
+                | false => None,
+            }
+        })
+        .collect();
+    names.sort();
+
+    let mut playlists = vec![];
+    for name in names {
+        let read = |volume: &mut dyn DiscFilesystem, dir: &str| {
+            let entry = find(&format!("{dir}/{name}"))
+                .ok_or(format!("There is no {dir}/{name}."))?;
+            parse_playlist(&name, &volume.read_data(entry)?)
+        };
+
+        match read(volume, "BDMV/PLAYLIST")
+            .or_else(|_| read(volume, "BDMV/BACKUP/PLAYLIST"))
+        {
+            | Ok(playlist) => playlists.push(playlist),
+            | Err(e) => warn!("{name} could not be read: {e}"),
+        }
+    }
+
+    Ok(Some(BluRay {
+        version,
+        titles,
+        playlists,
+    }))
+}
+
+/// Reads the version and titles from `index.bdmv`.
+fn parse_index(
+    index: &[u8]
+) -> Result<(String, Vec<BluRayTitle>), Box<dyn Error>>
+{
+    let version = check_header(index, INDEX_IDENTIFIER, 12)?;
+
+    // The first playback and top menu objects come before the titles.
+    let at = be_u32(index, 8) as usize + 4 + 12 + 12;
+    let count = be_u16(
+        index
+            .get(at..at + 2)
+            .ok_or("The index table runs past the end of index.bdmv.")?,
+        0,
+    ) as usize;
+    let entries = index
+        .get(at + 2..at + 2 + count * 12)
+        .ok_or("The title table runs past the end of index.bdmv.")?;
+
+    let titles = entries
+        .chunks(12)
+        .enumerate()
+        .map(|(i, e)| {
+            let (object_type, object) = match e[0] >> 6 {
+                | 2 => ("BD-J", String::from_utf8_lossy(&e[6..11]).to_string()),
+                | _ => ("HDMV", be_u16(e, 6).to_string()),
+            };
+
+            BluRayTitle {
+                number: i + 1,
+                object_type: object_type.to_string(),
+                object,
+            }
+        })
+        .collect();
+
+    Ok((version, titles))
+}
+
+/// Reads the play items, streams and chapter marks of a `.mpls` file.
+fn parse_playlist(
+    name: &str,
+    mpls: &[u8],
+) -> Result<BluRayPlaylist, Box<dyn Error>>
+{
+    check_header(mpls, PLAYLIST_IDENTIFIER, 20)?;
+    let overrun = || format!("The playlist runs past the end of {name}.");
+
+    let playlist = be_u32(mpls, 8) as usize;
+    let header = mpls.get(playlist..playlist + 10).ok_or_else(overrun)?;
+    let item_count = be_u16(header, 6) as usize;
+
+    let mut ticks = 0;
+    let mut clips: Vec<String> = vec![];
+    let mut streams = None;
+    let mut at = playlist + 10;
+
+    for _ in 0..item_count {
+        let length = be_u16(mpls.get(at..at + 2).ok_or_else(overrun)?, 0);
+        let item = mpls
+            .get(at + 2..at + 2 + length as usize)
+            .filter(|i| i.len() >= 20)
+            .ok_or_else(overrun)?;
+
+        let clip = format!("{}.m2ts", String::from_utf8_lossy(&item[0..5]));
+        if !clips.contains(&clip) {
+            clips.push(clip);
+        }
+
+        let (in_time, out_time) = (be_u32(item, 12), be_u32(item, 16));
+        ticks += out_time.saturating_sub(in_time) as u64;
+
+        if streams.is_none() {
+            streams = Some(stream_table(item).ok_or_else(overrun)?);
+        }
+
+        at += 2 + length as usize;
+    }
+
+    let marks = be_u32(mpls, 12) as usize;
+    let chapters = match mpls.get(marks..marks + 6) {
+        | Some(header) => {
+            (0..be_u16(header, 4) as usize)
+                .filter(|i| {
+                    mpls.get(marks + 6 + i * 14 + 1) == Some(&ENTRY_MARK)
+                })
+                .count()
+        }
+        | None => 0,
+    };
+
+    let seconds = (ticks / TICKS_PER_SECOND as u64) as u32;
+    let (video_streams, audio_streams, subtitle_streams) =
+        streams.unwrap_or_default();
+
+    Ok(BluRayPlaylist {
+        name: name.to_string(),
+        duration: format_duration(seconds),
+        duration_seconds: seconds,
+        chapters,
+        clips,
+        video_streams,
+        audio_streams,
+        subtitle_streams,
+    })
+}
+
+/// The video, audio and subtitle streams of the play item's stream number
+/// table, or `None` if it runs past the end of the item.
+fn stream_table(item: &[u8]) -> Option<StreamTable>
+{
+    // Multi-angle items list the clips of the other angles first.
+    let mut at = 32;
+    if item.get(10)? & 0x10 != 0 {
+        at += 2 + (*item.get(at)? as usize).saturating_sub(1) * 10;
+    }
+
+    let table = item.get(at..)?;
+    let counts: Vec<usize> =
+        table.get(4..11)?.iter().map(|c| *c as usize).collect();
+    let mut at = 16;
+
+    // Picture-in-picture subtitles are listed with the others, and
+    // interactive graphics, which are menus, are skipped.
+    let kinds = [
+        (Kind::Video, counts[0]),
+        (Kind::Audio, counts[1]),
+        (Kind::Subtitle, counts[2] + counts[6]),
+        (Kind::Menu, counts[3]),
+        (Kind::SecondaryAudio, counts[4]),
+        (Kind::SecondaryVideo, counts[5]),
+    ];
+
+    let mut streams: [Vec<BluRayStream>; 3] = Default::default();
+    for (kind, count) in kinds {
+        for _ in 0..count {
+            at += 1 + *table.get(at)? as usize;
+            let length = *table.get(at)? as usize;
+            let attributes = table.get(at + 1..at + 1 + length)?;
+            at += 1 + length;
+
+            // Secondary streams list the primary streams they go with, in
+            // lists padded to an even length.
+            let references = match kind {
+                | Kind::SecondaryAudio => 1,
+                | Kind::SecondaryVideo => 2,
+                | _ => 0,
+            };
+            for _ in 0..references {
+                let n = *table.get(at)? as usize;
+                at += 2 + n + n % 2;
+            }
+
+            let list = match kind {
+                | Kind::Video | Kind::SecondaryVideo => 0,
+                | Kind::Audio | Kind::SecondaryAudio => 1,
+                | Kind::Subtitle => 2,
+                | Kind::Menu => continue,
+            };
+
+            streams[list].push(stream(attributes)?);
+        }
+    }
+
+    let [video, audio, subtitles] = streams;
+    Some((video, audio, subtitles))
+}
+
+/// The groups of streams in a stream number table, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind
+{
+    Video,
+    Audio,
+    Subtitle,
+    Menu,
+    SecondaryAudio,
+    SecondaryVideo,
+}
+
+/// The video, audio and subtitle streams of a play item.
+type StreamTable = (Vec<BluRayStream>, Vec<BluRayStream>, Vec<BluRayStream>);
+
+fn stream(attributes: &[u8]) -> Option<BluRayStream>
+{
+    let coding_type = *attributes.first()?;
+    let language = |at: usize| {
+        attributes
+            .get(at..at + 3)
+            .filter(|code| code.iter().all(u8::is_ascii_alphabetic))
+            .map(|code| String::from_utf8_lossy(code).to_lowercase())
+    };
+
+    let coding = match coding_type {
+        | 0x01 => "MPEG-1",
+        | 0x02 => "MPEG-2",
+        | 0x1B => "H.264",
+        | 0x20 => "H.264 MVC",
+        | 0x24 => "HEVC",
+        | 0xEA => "VC-1",
+        | 0x03 | 0x04 => "MPEG Audio",
+        | 0x80 => "LPCM",
+        | 0x81 => "AC-3",
+        | 0x82 => "DTS",
+        | 0x83 => "Dolby TrueHD",
+        | 0x84 | 0xA1 => "E-AC-3",
+        | 0x85 => "DTS-HD High Resolution",
+        | 0x86 => "DTS-HD Master Audio",
+        | 0xA2 => "DTS Express",
+        | 0x90 => "PGS",
+        | 0x92 => "Text",
+        | _ => "Unknown",
+    };
+
+    let (format, language) = match coding_type {
+        | 0x01 | 0x02 | 0x1B | 0x20 | 0x24 | 0xEA => {
+            (video_format(*attributes.get(1)?), None)
+        }
+        | 0x90 => (None, language(1)),
+        | 0x92 => (None, language(2)),
+        | _ => (None, language(2)),
+    };
+
+    Some(BluRayStream {
+        coding: coding.to_string(),
+        format,
+        language,
+    })
+}
+
+/// Describes a video stream's format and frame rate byte, such as
+/// "1080p 23.976".
+fn video_format(b: u8) -> Option<String>
+{
+    let format = match b >> 4 {
+        | 1 => "480i",
+        | 2 => "576i",
+        | 3 => "480p",
+        | 4 => "1080i",
+        | 5 => "720p",
+        | 6 => "1080p",
+        | 7 => "576p",
+        | 8 => "2160p",
+        | _ => return None,
+    };
+
+    let rate = match b & 0xF {
+        | 1 => "23.976",
+        | 2 => "24",
+        | 3 => "25",
+        | 4 => "29.97",
+        | 6 => "50",
+        | 7 => "59.94",
+        | _ => return Some(format.to_string()),
+    };
+
+    Some(format!("{format} {rate}"))
+}
+
+/// Checks a BDMV file's identifier and that it is long enough to hold the
+/// fixed fields before `min_len`, returning its version.
+fn check_header(
+    file: &[u8],
+    identifier: &[u8],
+    min_len: usize,
+) -> Result<String, Box<dyn Error>>
+{
+    if !file.starts_with(identifier) {
+        return Err(format!(
+            "The file does not start with {}.",
+            String::from_utf8_lossy(identifier)
+        )
+        .into());
+    }
+
+    if file.len() < min_len {
+        return Err(format!("The file is only {} bytes.", file.len()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&file[4..8]).to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// An `index.bdmv` with an HDMV title and a BD-J title.
+    fn index_bdmv() -> Vec<u8>
+    {
+        let mut index = vec![0; 120];
+        index[..8].copy_from_slice(b"INDX0200");
+        index[8..12].copy_from_slice(&60u32.to_be_bytes());
+
+        let titles = 60 + 4 + 24;
+        index[titles..titles + 2].copy_from_slice(&2u16.to_be_bytes());
+        index[titles + 2] = 0x40;
+        index[titles + 8..titles + 10].copy_from_slice(&3u16.to_be_bytes());
+        index[titles + 14] = 0x80;
+        index[titles + 20..titles + 25].copy_from_slice(b"00001");
+
+        index
+    }
+
+    /// A play item from `clip`, running from `seconds_in` to `seconds_out`,
+    /// with `streams` in its stream number table, each as (kind, coding
+    /// attributes).
+    fn play_item(
+        clip: &[u8],
+        seconds_in: u32,
+        seconds_out: u32,
+        streams: &[(usize, &[u8])],
+    ) -> Vec<u8>
+    {
+        let mut item = vec![0; 32];
+        item[0..5].copy_from_slice(clip);
+        item[5..9].copy_from_slice(b"M2TS");
+        item[12..16].copy_from_slice(&(seconds_in * 45_000).to_be_bytes());
+        item[16..20].copy_from_slice(&(seconds_out * 45_000).to_be_bytes());
+
+        let mut table = vec![0; 16];
+        for kind in 0..7 {
+            for (_, attributes) in streams.iter().filter(|(k, _)| *k == kind) {
+                table[4 + kind] += 1;
+                table.extend([9, 1, 0x10, 0x11, 0, 0, 0, 0, 0, 0]);
+                table.push(attributes.len() as u8);
+                table.extend(*attributes);
+            }
+        }
+        let table_length = (table.len() - 2) as u16;
+        table[0..2].copy_from_slice(&table_length.to_be_bytes());
+        item.extend(table);
+
+        let mut with_length = (item.len() as u16).to_be_bytes().to_vec();
+        with_length.extend(item);
+        with_length
+    }
+
+    /// A playlist of `items`, with three chapter marks and a link point.
+    fn mpls(items: &[Vec<u8>]) -> Vec<u8>
+    {
+        let mut mpls = vec![0; 58];
+        mpls[..8].copy_from_slice(b"MPLS0200");
+        mpls[8..12].copy_from_slice(&58u32.to_be_bytes());
+
+        mpls.extend([0; 6]);
+        mpls.extend((items.len() as u16).to_be_bytes());
+        mpls.extend([0; 2]);
+        for item in items {
+            mpls.extend(item);
+        }
+
+        let marks = mpls.len() as u32;
+        mpls[12..16].copy_from_slice(&marks.to_be_bytes());
+        mpls.extend([0, 0, 0, 0, 0, 4]);
+        for mark_type in [1, 1, 2, 1] {
+            let mut mark = [0; 14];
+            mark[1] = mark_type;
+            mpls.extend(mark);
+        }
+
+        mpls
+    }
+
+    #[test]
+    fn test_index()
+    {
+        let (version, titles) = parse_index(&index_bdmv()).unwrap();
+
+        assert_eq!("0200", version);
+        assert_eq!(
+            vec![
+                BluRayTitle {
+                    number: 1,
+                    object_type: "HDMV".to_string(),
+                    object: "3".to_string(),
+                },
+                BluRayTitle {
+                    number: 2,
+                    object_type: "BD-J".to_string(),
+                    object: "00001".to_string(),
+                },
+            ],
+            titles
+        );
+
+        assert!(parse_index(b"MPLS0200").is_err());
+        assert!(parse_index(&index_bdmv()[..80]).is_err());
+    }
+
+    #[test]
+    fn test_playlist()
+    {
+        let streams: [(usize, &[u8]); 5] = [
+            (0, &[0x1B, 0x61]),
+            (1, &[0x86, 0x61, b'e', b'n', b'g']),
+            (1, &[0x81, 0x31, b's', b'p', b'a']),
+            (2, &[0x90, b'e', b'n', b'g', 0]),
+            (3, &[0x91, b'e', b'n', b'g']),
+        ];
+        let items = [
+            play_item(b"00001", 10, 3610, &streams),
+            play_item(b"00002", 0, 1807, &streams[..1]),
+            play_item(b"00001", 3610, 3620, &streams[..1]),
+        ];
+
+        let playlist = parse_playlist("00800.mpls", &mpls(&items)).unwrap();
+
+        assert_eq!("00800.mpls", playlist.name);
+        assert_eq!(5417, playlist.duration_seconds);
+        assert_eq!("1:30:17", playlist.duration);
+        assert_eq!(3, playlist.chapters);
+        assert_eq!(vec!["00001.m2ts", "00002.m2ts"], playlist.clips);
+        assert_eq!(
+            vec![BluRayStream {
+                coding: "H.264".to_string(),
+                format: Some("1080p 23.976".to_string()),
+                language: None,
+            }],
+            playlist.video_streams
+        );
+        assert_eq!(
+            vec![
+                BluRayStream {
+                    coding: "DTS-HD Master Audio".to_string(),
+                    format: None,
+                    language: Some("eng".to_string()),
+                },
+                BluRayStream {
+                    coding: "AC-3".to_string(),
+                    format: None,
+                    language: Some("spa".to_string()),
+                },
+            ],
+            playlist.audio_streams
+        );
+        assert_eq!(1, playlist.subtitle_streams.len());
+        assert_eq!("PGS", playlist.subtitle_streams[0].coding);
+
+        let truncated = &mpls(&items)[..100];
+        assert!(parse_playlist("00800.mpls", truncated).is_err());
+    }
+}
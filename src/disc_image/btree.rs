@@ -0,0 +1,204 @@
+use super::{
+    be_u16,
+    be_u32,
+};
+use std::error::Error;
+
+/// Node kind of a leaf node in the node descriptor.
+const LEAF_NODE: i8 = -1;
+
+/// A whole HFS or HFS+ B-tree file (catalog or extents overflow) read into
+/// memory.  Both filesystems share the node and header layout; only the
+/// record keys differ.
+pub struct BTree
+{
+    data: Vec<u8>,
+    node_size: usize,
+    first_leaf: u32,
+    total_nodes: u32,
+}
+
+impl BTree
+{
+    pub fn parse(data: Vec<u8>) -> Result<BTree, Box<dyn Error>>
+    {
+        if data.len() < 512 {
+            return Err(
+                "B-tree file is too small to hold a header node.".into()
+            );
+        }
+
+        // The header record immediately follows the 14 byte node
+        // descriptor of node zero.
+        let first_leaf = be_u32(&data, 24);
+        let node_size = be_u16(&data, 32) as usize;
+        let total_nodes = be_u32(&data, 36);
+
+        if node_size < 512 || !node_size.is_power_of_two() {
+            return Err(format!("Invalid B-tree node size {node_size}.").into());
+        }
+
+        Ok(BTree {
+            data,
+            node_size,
+            first_leaf,
+            total_nodes,
+        })
+    }
+
+    /// Every record in the leaf nodes, in key order.
+    pub fn leaf_records(&self) -> Result<Vec<&[u8]>, Box<dyn Error>>
+    {
+        let mut records = vec![];
+        let mut node_number = self.first_leaf;
+        let mut visited = 0;
+
+        while node_number != 0 {
+            visited += 1;
+            if visited > self.total_nodes {
+                return Err("B-tree leaf chain contains a loop.".into());
+            }
+
+            let node = self.node(node_number)?;
+
+            if node[8] as i8 != LEAF_NODE {
+                return Err(format!(
+                    "B-tree node {node_number} is in the leaf chain but is \
+                     not a leaf."
+                )
+                .into());
+            }
+
+            // The offset table at the end of the node holds one offset per
+            // record plus that of the free space, and must not run into the
+            // node descriptor.
+            let num_records = be_u16(node, 10) as usize;
+            if 2 * (num_records + 1) > self.node_size - 14 {
+                return Err(format!(
+                    "B-tree node {node_number} claims {num_records} records, \
+                     more than it can hold."
+                )
+                .into());
+            }
+
+            for i in 0..num_records {
+                let start = be_u16(node, self.node_size - 2 * (i + 1)) as usize;
+                let end = be_u16(node, self.node_size - 2 * (i + 2)) as usize;
+
+                if start < 14 || start > end || end > self.node_size {
+                    return Err(format!(
+                        "B-tree node {node_number} record {i} has invalid \
+                         bounds."
+                    )
+                    .into());
+                }
+
+                records.push(&node[start..end]);
+            }
+
+            node_number = be_u32(node, 0);
+        }
+
+        Ok(records)
+    }
+
+    fn node(
+        &self,
+        node_number: u32,
+    ) -> Result<&[u8], Box<dyn Error>>
+    {
+        (node_number as usize)
+            .checked_mul(self.node_size)
+            .and_then(|start| self.data.get(start..start + self.node_size))
+            .ok_or(format!("B-tree node {node_number} is out of range.").into())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+
+    /// Builds a two node B-tree (header plus a single leaf) holding the
+    /// given records.
+    pub(crate) fn build_btree(
+        node_size: usize,
+        records: &[Vec<u8>],
+    ) -> Vec<u8>
+    {
+        let mut data = vec![0; node_size * 2];
+
+        // Header node.
+        data[8] = 1;
+        data[10..12].copy_from_slice(&3u16.to_be_bytes());
+        data[14..16].copy_from_slice(&1u16.to_be_bytes());
+        data[16..20].copy_from_slice(&1u32.to_be_bytes());
+        data[20..24].copy_from_slice(&(records.len() as u32).to_be_bytes());
+        data[24..28].copy_from_slice(&1u32.to_be_bytes());
+        data[28..32].copy_from_slice(&1u32.to_be_bytes());
+        data[32..34].copy_from_slice(&(node_size as u16).to_be_bytes());
+        data[36..40].copy_from_slice(&2u32.to_be_bytes());
+
+        // Leaf node.
+        let leaf = &mut data[node_size..];
+        leaf[8] = 0xFF;
+        leaf[9] = 1;
+        leaf[10..12].copy_from_slice(&(records.len() as u16).to_be_bytes());
+
+        let mut offset = 14;
+        for (i, r) in records.iter().enumerate() {
+            leaf[offset..offset + r.len()].copy_from_slice(r);
+            leaf[node_size - 2 * (i + 1)..node_size - 2 * i]
+                .copy_from_slice(&(offset as u16).to_be_bytes());
+            offset += r.len();
+        }
+        let i = records.len();
+        leaf[node_size - 2 * (i + 1)..node_size - 2 * i]
+            .copy_from_slice(&(offset as u16).to_be_bytes());
+
+        data
+    }
+
+    #[test]
+    fn test_leaf_records()
+    {
+        let records = vec![vec![1, 2, 3, 4], vec![5, 6]];
+        let tree = BTree::parse(build_btree(512, &records)).unwrap();
+
+        let found = tree.leaf_records().unwrap();
+
+        assert_eq!(found, vec![&[1u8, 2, 3, 4][..], &[5u8, 6][..]]);
+    }
+
+    #[test]
+    fn test_corrupt_leaf_node()
+    {
+        let node_size = 512;
+        // More records than the offset table at the end of the node can
+        // hold.
+        let mut data = build_btree(node_size, &[vec![1, 2]]);
+        data[node_size + 10..node_size + 12]
+            .copy_from_slice(&u16::MAX.to_be_bytes());
+        assert!(BTree::parse(data).unwrap().leaf_records().is_err());
+
+        // A record which starts inside the node descriptor.
+        let mut data = build_btree(node_size, &[vec![1, 2]]);
+        data[2 * node_size - 2..].copy_from_slice(&4u16.to_be_bytes());
+        assert!(BTree::parse(data).unwrap().leaf_records().is_err());
+
+        // A record which ends past the end of the node.
+        let mut data = build_btree(node_size, &[vec![1, 2]]);
+        data[2 * node_size - 4..2 * node_size - 2]
+            .copy_from_slice(&(node_size as u16 + 1).to_be_bytes());
+        assert!(BTree::parse(data).unwrap().leaf_records().is_err());
+    }
+
+    #[test]
+    fn test_bad_node_size()
+    {
+        let mut data = build_btree(512, &[]);
+        data[32..34].copy_from_slice(&100u16.to_be_bytes());
+
+        assert!(BTree::parse(data).is_err());
+    }
+}
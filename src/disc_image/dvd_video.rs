@@ -0,0 +1,711 @@
+//! The structure of a DVD-Video disc, read from the IFO files in its
+//! `VIDEO_TS` folder: `VIDEO_TS.IFO` lists the titles and the regions the
+//! disc plays in, and each title set's `VTS_xx_0.IFO` gives its streams,
+//! chapters and running times.
+
+use super::{
+    be_u16,
+    be_u32,
+    DiscEntry,
+    DiscFilesystem,
+};
+use crate::metadata::ExpectedContent;
+use log::warn;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::error::Error;
+
+const VMG_IDENTIFIER: &[u8] = b"DVDVIDEO-VMG";
+const VTS_IDENTIFIER: &[u8] = b"DVDVIDEO-VTS";
+
+/// IFO tables are addressed in 2048 byte sectors from the start of the file.
+const IFO_SECTOR_SIZE: usize = 2048;
+
+/// How far apart two aspect ratios may be and still count as the same, so
+/// that "1.78:1" matches 16:9.
+const ASPECT_RATIO_TOLERANCE: f64 = 0.05;
+
+/// ISO 639-1 codes, as DVDs record languages, with the ISO 639-2
+/// bibliographic and terminology codes used in the catalog.
+const LANGUAGES: [(&str, &[&str]); 44] = [
+    ("ar", &["ara"]),
+    ("bg", &["bul"]),
+    ("ca", &["cat"]),
+    ("cs", &["cze", "ces"]),
+    ("da", &["dan"]),
+    ("de", &["ger", "deu"]),
+    ("el", &["gre", "ell"]),
+    ("en", &["eng"]),
+    ("es", &["spa"]),
+    ("et", &["est"]),
+    ("eu", &["baq", "eus"]),
+    ("fa", &["per", "fas"]),
+    ("fi", &["fin"]),
+    ("fr", &["fre", "fra"]),
+    ("he", &["heb"]),
+    ("hi", &["hin"]),
+    ("hr", &["hrv"]),
+    ("hu", &["hun"]),
+    ("hy", &["arm", "hye"]),
+    ("id", &["ind"]),
+    ("is", &["ice", "isl"]),
+    ("it", &["ita"]),
+    ("ja", &["jpn"]),
+    ("km", &["khm"]),
+    ("ko", &["kor"]),
+    ("lo", &["lao"]),
+    ("lt", &["lit"]),
+    ("lv", &["lav"]),
+    ("ms", &["may", "msa"]),
+    ("nl", &["dut", "nld"]),
+    ("no", &["nor"]),
+    ("pl", &["pol"]),
+    ("pt", &["por"]),
+    ("ro", &["rum", "ron"]),
+    ("ru", &["rus"]),
+    ("sk", &["slo", "slk"]),
+    ("sl", &["slv"]),
+    ("sr", &["srp"]),
+    ("sv", &["swe"]),
+    ("th", &["tha"]),
+    ("tl", &["tgl"]),
+    ("tr", &["tur"]),
+    ("uk", &["ukr"]),
+    ("vi", &["vie"]),
+];
+
+/// What the IFO files say about a DVD-Video disc.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DvdVideo
+{
+    /// The regions (1-8) the disc may be played in.
+    pub regions: Vec<u8>,
+    pub provider_id: String,
+    pub titles: Vec<DvdTitle>,
+    pub title_sets: Vec<DvdTitleSet>,
+}
+
+/// A title, as chosen from the disc's menus or a player's title search.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DvdTitle
+{
+    pub number: usize,
+    /// The title set holding the title's video.
+    pub title_set: u8,
+    pub chapters: u16,
+    pub angles: u8,
+    /// The running time of the title's first program chain, as "H:MM:SS".
+    pub duration: Option<String>,
+    pub duration_seconds: Option<u32>,
+}
+
+/// The attributes shared by the titles in one `VTS_xx_0.IFO`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DvdTitleSet
+{
+    pub number: u8,
+    /// "MPEG-1" or "MPEG-2".
+    pub video_coding: String,
+    /// "NTSC" or "PAL".
+    pub video_standard: String,
+    /// "4:3" or "16:9".
+    pub aspect_ratio: String,
+    pub resolution: String,
+    pub audio_streams: Vec<DvdAudioStream>,
+    pub subpicture_streams: Vec<DvdSubpictureStream>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DvdAudioStream
+{
+    /// Such as "AC-3", "LPCM" or "DTS".
+    pub coding: String,
+    pub channels: u8,
+    pub sample_rate: u32,
+    /// The ISO 639-1 code recorded on the disc, such as "en".
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DvdSubpictureStream
+{
+    /// The ISO 639-1 code recorded on the disc, such as "en".
+    pub language: Option<String>,
+}
+
+/// The video manager's contents, from `VIDEO_TS.IFO`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VideoManager
+{
+    regions: Vec<u8>,
+    provider_id: String,
+    title_set_count: u16,
+    /// (title set, title within the set, chapters, angles) for each title.
+    titles: Vec<(u8, u8, u16, u8)>,
+}
+
+/// A running time in whole seconds, and as "H:MM:SS".
+type RunningTime = (u32, String);
+
+/// The title set attributes, and each of its titles' running time, from a
+/// `VTS_xx_0.IFO`.
+type TitleSetInfo = (DvdTitleSet, Vec<Option<RunningTime>>);
+
+/// Reads the DVD-Video structure of the volume, or `None` if it has no
+/// `VIDEO_TS/VIDEO_TS.IFO`.  A title set whose IFO is missing or unreadable
+/// is left out, with its titles' running times unknown.
+pub fn read_dvd_video(
+    volume: &mut dyn DiscFilesystem
+) -> Result<Option<DvdVideo>, Box<dyn Error>>
+{
+    let entries = volume.entries()?;
+    let find = |name: &str| -> Option<&DiscEntry> {
+        entries.iter().find(|e| {
+            !e.is_dir
+                && e.path
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(&format!("VIDEO_TS/{name}"))
+        })
+    };
+
+    let Some(vmg) = find("VIDEO_TS.IFO") else {
+        return Ok(None);
+    };
+    let vmg = parse_video_manager(&volume.read_data(vmg)?)?;
+
+    let mut title_sets = vec![];
+    for number in 1..=vmg.title_set_count.min(99) as u8 {
+        let name = format!("VTS_{number:02}_0.IFO");
+
+        let Some(entry) = find(&name) else {
+            warn!("The disc lists title set {number}, but has no {name}.");
+            continue;
+        };
+
+        match parse_title_set(number, &volume.read_data(entry)?) {
+            | Ok(info) => title_sets.push(info),
+            | Err(e) => warn!("{name} could not be read: {e}"),
+        }
+    }
+
+    Ok(Some(assemble(vmg, title_sets)))
+}
+
+/// Compares the disc with what its CSV row says, returning a description of
+/// each disagreement.  Menus are not counted, only the titles' title sets.
+pub fn check_against(
+    dvd: &DvdVideo,
+    expected: &ExpectedContent,
+) -> Vec<String>
+{
+    let mut problems = vec![];
+
+    if let Some(expected_ratio) = &expected.aspect_ratio {
+        let mut on_disc: Vec<&str> = dvd
+            .title_sets
+            .iter()
+            .map(|s| s.aspect_ratio.as_str())
+            .collect();
+        on_disc.sort();
+        on_disc.dedup();
+
+        let matches = match parse_ratio(expected_ratio) {
+            | Some(expected) => {
+                on_disc.iter().any(|r| {
+                    parse_ratio(r).is_some_and(|r| {
+                        (r - expected).abs() < ASPECT_RATIO_TOLERANCE
+                    })
+                })
+            }
+            | None => true,
+        };
+
+        if !matches && !on_disc.is_empty() {
+            problems.push(format!(
+                "The row gives an aspect ratio of {expected_ratio}, but the \
+                 disc's titles are {}.",
+                on_disc.join(", ")
+            ));
+        }
+    }
+
+    if let Some(expected_languages) = &expected.language {
+        let mut on_disc: Vec<&str> = dvd
+            .title_sets
+            .iter()
+            .flat_map(|s| &s.audio_streams)
+            .filter_map(|a| a.language.as_deref())
+            .collect();
+        on_disc.sort();
+        on_disc.dedup();
+
+        for language in expected_languages
+            .split([';', ',', '|'])
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+        {
+            if !on_disc.is_empty()
+                && !on_disc.iter().any(|code| same_language(code, language))
+            {
+                problems.push(format!(
+                    "The row gives the language {language}, but the disc's \
+                     audio is in {}.",
+                    on_disc.join(", ")
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn parse_video_manager(ifo: &[u8]) -> Result<VideoManager, Box<dyn Error>>
+{
+    check_ifo(ifo, VMG_IDENTIFIER, 0x100)?;
+
+    // Each set bit of the region mask is a region the disc may not play in.
+    let region_mask = ifo[0x23];
+    let regions = (1..=8u8)
+        .filter(|r| region_mask & (1 << (r - 1)) == 0)
+        .collect();
+
+    let provider_id = String::from_utf8_lossy(&ifo[0x40..0x60])
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+
+    let table = table_at(ifo, be_u32(ifo, 0xC4), 8)?;
+    let count = be_u16(table, 0) as usize;
+    let entries = table
+        .get(8..8 + count * 12)
+        .ok_or("The title table runs past the end of VIDEO_TS.IFO.")?;
+
+    let titles = entries
+        .chunks(12)
+        .map(|e| (e[6], e[7], be_u16(e, 2), e[1]))
+        .collect();
+
+    Ok(VideoManager {
+        regions,
+        provider_id,
+        title_set_count: be_u16(ifo, 0x3E),
+        titles,
+    })
+}
+
+fn parse_title_set(
+    number: u8,
+    ifo: &[u8],
+) -> Result<TitleSetInfo, Box<dyn Error>>
+{
+    check_ifo(ifo, VTS_IDENTIFIER, 0x318)?;
+
+    let video = ifo[0x200];
+    let video_coding = match video >> 6 {
+        | 0 => "MPEG-1",
+        | _ => "MPEG-2",
+    };
+    let (video_standard, lines) = match (video >> 4) & 0x3 {
+        | 0 => ("NTSC", 480),
+        | _ => ("PAL", 576),
+    };
+    let aspect_ratio = match (video >> 2) & 0x3 {
+        | 3 => "16:9",
+        | _ => "4:3",
+    };
+    let resolution = match (ifo[0x201] >> 3) & 0x7 {
+        | 0 => format!("720x{lines}"),
+        | 1 => format!("704x{lines}"),
+        | 2 => format!("352x{lines}"),
+        | _ => format!("352x{}", lines / 2),
+    };
+
+    let audio_streams = (0..be_u16(ifo, 0x202).min(8) as usize)
+        .map(|i| audio_stream(&ifo[0x204 + i * 8..0x20C + i * 8]))
+        .collect();
+
+    let subpicture_streams = (0..be_u16(ifo, 0x254).min(32) as usize)
+        .map(|i| {
+            let attributes = &ifo[0x256 + i * 6..0x25C + i * 6];
+            DvdSubpictureStream {
+                language: language(attributes[0] & 0x3, &attributes[2..4]),
+            }
+        })
+        .collect();
+
+    let title_set = DvdTitleSet {
+        number,
+        video_coding: video_coding.to_string(),
+        video_standard: video_standard.to_string(),
+        aspect_ratio: aspect_ratio.to_string(),
+        resolution,
+        audio_streams,
+        subpicture_streams,
+    };
+
+    Ok((title_set, title_durations(ifo)?))
+}
+
+/// The running time of each title in the set, from the program chain its
+/// first chapter starts.
+fn title_durations(
+    ifo: &[u8]
+) -> Result<Vec<Option<RunningTime>>, Box<dyn Error>>
+{
+    let ptt_offset = be_u32(ifo, 0xC8) as usize * IFO_SECTOR_SIZE;
+    let ptt = table_at(ifo, be_u32(ifo, 0xC8), 8)?;
+    let pgci_offset = be_u32(ifo, 0xCC) as usize * IFO_SECTOR_SIZE;
+    let pgci = table_at(ifo, be_u32(ifo, 0xCC), 8)?;
+
+    let pgc_count = be_u16(pgci, 0) as usize;
+    let pgc_duration = |pgcn: usize| -> Option<RunningTime> {
+        if pgcn == 0 || pgcn > pgc_count {
+            return None;
+        }
+
+        let at = pgci_offset
+            + be_u32(ifo.get(pgci_offset + pgcn * 8..)?, 4) as usize;
+        playback_time(ifo.get(at + 4..at + 8)?)
+    };
+
+    let durations = (0..be_u16(ptt, 0) as usize)
+        .map(|i| {
+            let at = ptt_offset
+                + be_u32(ifo.get(ptt_offset + 8 + i * 4..)?, 0) as usize;
+            let pgcn = be_u16(ifo.get(at..at + 2)?, 0) as usize;
+            pgc_duration(pgcn)
+        })
+        .collect();
+
+    Ok(durations)
+}
+
+fn assemble(
+    vmg: VideoManager,
+    title_sets: Vec<TitleSetInfo>,
+) -> DvdVideo
+{
+    let titles = vmg
+        .titles
+        .iter()
+        .enumerate()
+        .map(|(i, (title_set, vts_title, chapters, angles))| {
+            let time = title_sets
+                .iter()
+                .find(|(s, _)| s.number == *title_set)
+                .and_then(|(_, times)| {
+                    times.get((*vts_title as usize).checked_sub(1)?)
+                })
+                .cloned()
+                .flatten();
+
+            DvdTitle {
+                number: i + 1,
+                title_set: *title_set,
+                chapters: *chapters,
+                angles: *angles,
+                duration_seconds: time.as_ref().map(|(s, _)| *s),
+                duration: time.map(|(_, text)| text),
+            }
+        })
+        .collect();
+
+    DvdVideo {
+        regions: vmg.regions,
+        provider_id: vmg.provider_id,
+        titles,
+        title_sets: title_sets.into_iter().map(|(s, _)| s).collect(),
+    }
+}
+
+fn audio_stream(attributes: &[u8]) -> DvdAudioStream
+{
+    let coding = match attributes[0] >> 5 {
+        | 0 => "AC-3",
+        | 2 => "MPEG-1",
+        | 3 => "MPEG-2",
+        | 4 => "LPCM",
+        | 6 => "DTS",
+        | _ => "Unknown",
+    };
+
+    DvdAudioStream {
+        coding: coding.to_string(),
+        channels: (attributes[1] & 0x7) + 1,
+        sample_rate: match (attributes[1] >> 4) & 0x3 {
+            | 1 => 96_000,
+            | _ => 48_000,
+        },
+        language: language((attributes[0] >> 2) & 0x3, &attributes[2..4]),
+    }
+}
+
+/// The language code of a stream whose language type is 1 (present).
+fn language(
+    language_type: u8,
+    code: &[u8],
+) -> Option<String>
+{
+    match language_type == 1 && code.iter().all(u8::is_ascii_alphabetic) {
+        | true => Some(String::from_utf8_lossy(code).to_lowercase()),
+        | false => None,
+    }
+}
+
+/// Decodes a program chain's BCD playback time, whose last byte holds the
+/// frame rate in its top two bits.
+fn playback_time(b: &[u8]) -> Option<RunningTime>
+{
+    let bcd = |b: u8| -> Option<u32> {
+        match (b >> 4, b & 0xF) {
+            | (tens, ones) if tens < 10 && ones < 10 => {
+                Some((tens * 10 + ones) as u32)
+            }
+            | _ => None,
+        }
+    };
+
+    let (hours, minutes, seconds) = (bcd(b[0])?, bcd(b[1])?, bcd(b[2])?);
+
+    Some((
+        hours * 3600 + minutes * 60 + seconds,
+        format!("{hours}:{minutes:02}:{seconds:02}"),
+    ))
+}
+
+/// Checks an IFO's identifier and that it is long enough to hold the fixed
+/// fields before `min_len`.
+fn check_ifo(
+    ifo: &[u8],
+    identifier: &[u8],
+    min_len: usize,
+) -> Result<(), Box<dyn Error>>
+{
+    if !ifo.starts_with(identifier) {
+        return Err(format!(
+            "The IFO does not start with {}.",
+            String::from_utf8_lossy(identifier)
+        )
+        .into());
+    }
+
+    if ifo.len() < min_len {
+        return Err(format!("The IFO is only {} bytes long.", ifo.len()).into());
+    }
+
+    Ok(())
+}
+
+/// The table starting at `sector` of the IFO, which must hold at least
+/// `min_len` bytes.
+fn table_at(
+    ifo: &[u8],
+    sector: u32,
+    min_len: usize,
+) -> Result<&[u8], Box<dyn Error>>
+{
+    let at = sector as usize * IFO_SECTOR_SIZE;
+
+    match ifo.get(at..) {
+        | Some(table) if sector > 0 && table.len() >= min_len => Ok(table),
+        | _ => {
+            Err(format!("The IFO table at sector {sector} is missing.").into())
+        }
+    }
+}
+
+/// Reads a ratio such as "16:9", "1.78:1" or "1.33".
+fn parse_ratio(text: &str) -> Option<f64>
+{
+    match text.trim().split_once([':', 'x', '/']) {
+        | Some((w, h)) => {
+            let (w, h): (f64, f64) =
+                (w.trim().parse().ok()?, h.trim().parse().ok()?);
+            (h > 0.0).then(|| w / h)
+        }
+        | None => text.trim().parse().ok(),
+    }
+}
+
+/// Whether the ISO 639-1 `code` from the disc is the catalog's `language`,
+/// given as an ISO 639-1 or 639-2 code.
+fn same_language(
+    code: &str,
+    language: &str,
+) -> bool
+{
+    let language = language.to_lowercase();
+
+    code == language
+        || LANGUAGES
+            .iter()
+            .any(|(two, three)| *two == code && three.contains(&&*language))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// A `VIDEO_TS.IFO` for a region 1 disc with two titles in title set 1
+    /// and one in title set 2.
+    fn video_manager_ifo() -> Vec<u8>
+    {
+        let mut ifo = vec![0; 2 * IFO_SECTOR_SIZE];
+        ifo[..12].copy_from_slice(VMG_IDENTIFIER);
+        ifo[0x23] = 0xFE;
+        ifo[0x3E..0x40].copy_from_slice(&2u16.to_be_bytes());
+        ifo[0x40..0x4A].copy_from_slice(b"CALREVEAL ");
+        ifo[0xC4..0xC8].copy_from_slice(&1u32.to_be_bytes());
+
+        let table = &mut ifo[IFO_SECTOR_SIZE..];
+        table[0..2].copy_from_slice(&3u16.to_be_bytes());
+        for (i, (vts, vts_title, chapters)) in
+            [(1, 1, 12u16), (1, 2, 1), (2, 1, 3)]
+                .into_iter()
+                .enumerate()
+        {
+            let entry = &mut table[8 + i * 12..20 + i * 12];
+            entry[1] = 1;
+            entry[2..4].copy_from_slice(&chapters.to_be_bytes());
+            entry[6] = vts;
+            entry[7] = vts_title;
+        }
+
+        ifo
+    }
+
+    /// A `VTS_01_0.IFO` holding 16:9 NTSC video, English AC-3 5.1 and
+    /// Spanish stereo LPCM audio, English subtitles, and two titles running
+    /// 1:30:07 and 0:02:30.
+    fn title_set_ifo() -> Vec<u8>
+    {
+        let mut ifo = vec![0; 3 * IFO_SECTOR_SIZE];
+        ifo[..12].copy_from_slice(VTS_IDENTIFIER);
+        ifo[0xC8..0xCC].copy_from_slice(&1u32.to_be_bytes());
+        ifo[0xCC..0xD0].copy_from_slice(&2u32.to_be_bytes());
+
+        ifo[0x200] = 0b0100_1100;
+        ifo[0x202..0x204].copy_from_slice(&2u16.to_be_bytes());
+        ifo[0x204..0x20C]
+            .copy_from_slice(&[0x04, 0x05, b'e', b'n', 0, 0, 0, 0]);
+        ifo[0x20C..0x214]
+            .copy_from_slice(&[0x84, 0x01, b'e', b's', 0, 0, 0, 0]);
+        ifo[0x254..0x256].copy_from_slice(&1u16.to_be_bytes());
+        ifo[0x256..0x25C].copy_from_slice(&[0x01, 0, b'e', b'n', 0, 0]);
+
+        // Title 1 starts in program chain 2, and title 2 in chain 1.
+        let ptt = IFO_SECTOR_SIZE;
+        ifo[ptt..ptt + 2].copy_from_slice(&2u16.to_be_bytes());
+        ifo[ptt + 8..ptt + 12].copy_from_slice(&16u32.to_be_bytes());
+        ifo[ptt + 12..ptt + 16].copy_from_slice(&20u32.to_be_bytes());
+        ifo[ptt + 16..ptt + 18].copy_from_slice(&2u16.to_be_bytes());
+        ifo[ptt + 20..ptt + 22].copy_from_slice(&1u16.to_be_bytes());
+
+        let pgci = 2 * IFO_SECTOR_SIZE;
+        ifo[pgci..pgci + 2].copy_from_slice(&2u16.to_be_bytes());
+        ifo[pgci + 12..pgci + 16].copy_from_slice(&100u32.to_be_bytes());
+        ifo[pgci + 20..pgci + 24].copy_from_slice(&200u32.to_be_bytes());
+        ifo[pgci + 104..pgci + 108].copy_from_slice(&[0x00, 0x02, 0x30, 0xC0]);
+        ifo[pgci + 204..pgci + 208].copy_from_slice(&[0x01, 0x30, 0x07, 0xD5]);
+
+        ifo
+    }
+
+    #[test]
+    fn test_video_manager()
+    {
+        let vmg = parse_video_manager(&video_manager_ifo()).unwrap();
+
+        assert_eq!(vec![1], vmg.regions);
+        assert_eq!("CALREVEAL", vmg.provider_id);
+        assert_eq!(2, vmg.title_set_count);
+        assert_eq!(vec![(1, 1, 12, 1), (1, 2, 1, 1), (2, 1, 3, 1)], vmg.titles);
+
+        assert!(parse_video_manager(&title_set_ifo()).is_err());
+        assert!(parse_video_manager(&video_manager_ifo()[..300]).is_err());
+    }
+
+    #[test]
+    fn test_title_set()
+    {
+        let (set, durations) = parse_title_set(1, &title_set_ifo()).unwrap();
+
+        assert_eq!("MPEG-2", set.video_coding);
+        assert_eq!("NTSC", set.video_standard);
+        assert_eq!("16:9", set.aspect_ratio);
+        assert_eq!("720x480", set.resolution);
+        assert_eq!(
+            vec![
+                DvdAudioStream {
+                    coding: "AC-3".to_string(),
+                    channels: 6,
+                    sample_rate: 48_000,
+                    language: Some("en".to_string()),
+                },
+                DvdAudioStream {
+                    coding: "LPCM".to_string(),
+                    channels: 2,
+                    sample_rate: 48_000,
+                    language: Some("es".to_string()),
+                },
+            ],
+            set.audio_streams
+        );
+        assert_eq!(
+            vec![DvdSubpictureStream {
+                language: Some("en".to_string())
+            }],
+            set.subpicture_streams
+        );
+        assert_eq!(
+            vec![
+                Some((5407, "1:30:07".to_string())),
+                Some((150, "0:02:30".to_string())),
+            ],
+            durations
+        );
+    }
+
+    #[test]
+    fn test_assemble()
+    {
+        let vmg = parse_video_manager(&video_manager_ifo()).unwrap();
+        let dvd =
+            assemble(vmg, vec![parse_title_set(1, &title_set_ifo()).unwrap()]);
+
+        assert_eq!(3, dvd.titles.len());
+        assert_eq!(Some(5407), dvd.titles[0].duration_seconds);
+        assert_eq!(Some("0:02:30".to_string()), dvd.titles[1].duration);
+        assert_eq!(12, dvd.titles[0].chapters);
+        // Title set 2's IFO is missing.
+        assert_eq!(None, dvd.titles[2].duration);
+        assert_eq!(1, dvd.title_sets.len());
+    }
+
+    #[test]
+    fn test_check_against()
+    {
+        let vmg = parse_video_manager(&video_manager_ifo()).unwrap();
+        let dvd =
+            assemble(vmg, vec![parse_title_set(1, &title_set_ifo()).unwrap()]);
+        let expected = |aspect_ratio: &str, language: &str| {
+            ExpectedContent {
+                aspect_ratio: Some(aspect_ratio.to_string()),
+                language: Some(language.to_string()),
+                duration: None,
+            }
+        };
+
+        assert!(check_against(&dvd, &expected("16:9", "eng")).is_empty());
+        let widescreen = expected("1.78:1", "eng; spa");
+        assert!(check_against(&dvd, &widescreen).is_empty());
+        assert!(check_against(&dvd, &ExpectedContent::default()).is_empty());
+
+        let problems = check_against(&dvd, &expected("4:3", "fre"));
+        assert_eq!(2, problems.len());
+        assert!(problems[0].contains("aspect ratio of 4:3"));
+        assert!(problems[1].contains("language fre"));
+    }
+}
@@ -0,0 +1,832 @@
+use super::{
+    civil_time,
+    le_u16,
+    le_u32,
+    path_component,
+    DiscEntry,
+    DiscFilesystem,
+    DiscImage,
+    Extent,
+    VolumeInfo,
+};
+use log::{
+    debug,
+    warn,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+const ATTR_VOLUME_LABEL: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+/// Marks the last of the long filename entries for a file.
+const LAST_LONG_ENTRY: u8 = 0x40;
+
+/// Set in the reserved byte when the short name's base or extension is
+/// stored in upper case but should be shown in lower case.
+const LOWER_CASE_BASE: u8 = 0x08;
+const LOWER_CASE_EXTENSION: u8 = 0x10;
+
+/// MBR partition types which hold FAT filesystems.
+const FAT_PARTITION_TYPES: [u8; 6] = [0x01, 0x04, 0x06, 0x0B, 0x0C, 0x0E];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType
+{
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// A FAT12, FAT16 or FAT32 volume, as found on floppy disks and flash
+/// drives.
+pub struct FatVolume
+{
+    image: DiscImage,
+    fat_type: FatType,
+    volume_name: String,
+    /// The first copy of the file allocation table.
+    fat: Vec<u8>,
+    cluster_size: u64,
+    /// Byte offset of cluster 2, the first data cluster.
+    data_start: u64,
+    cluster_count: u32,
+    root: RootDirectory,
+}
+
+enum RootDirectory
+{
+    /// FAT12 and FAT16 keep the root directory in a fixed region.
+    Fixed(Extent),
+    /// FAT32 keeps it in a cluster chain like any other directory.
+    Chain(u32),
+}
+
+/// The values of a boot sector's BIOS parameter block needed to read the
+/// volume.
+struct BiosParameterBlock
+{
+    bytes_per_sector: u64,
+    sectors_per_cluster: u64,
+    reserved_sectors: u64,
+    fat_count: u64,
+    root_entries: u64,
+    total_sectors: u64,
+    sectors_per_fat: u64,
+    root_cluster: u32,
+    label: String,
+}
+
+impl BiosParameterBlock
+{
+    fn parse(sector: &[u8]) -> Option<BiosParameterBlock>
+    {
+        let bytes_per_sector = le_u16(sector, 11) as u64;
+        let sectors_per_cluster = sector[13] as u64;
+        let reserved_sectors = le_u16(sector, 14) as u64;
+        let fat_count = sector[16] as u64;
+
+        let valid = matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            && sectors_per_cluster.is_power_of_two()
+            && reserved_sectors > 0
+            && matches!(fat_count, 1 | 2)
+            && (sector[21] == 0xF0 || sector[21] >= 0xF8);
+
+        if !valid {
+            return None;
+        }
+
+        let total_sectors = match le_u16(sector, 19) {
+            | 0 => le_u32(sector, 32) as u64,
+            | t => t as u64,
+        };
+
+        let (sectors_per_fat, root_cluster, label_at) = match le_u16(sector, 22)
+        {
+            | 0 => (le_u32(sector, 36) as u64, le_u32(sector, 44), 71),
+            | s => (s as u64, 0, 43),
+        };
+
+        let signature_at = label_at - 5;
+        let label = match sector[signature_at] {
+            | 0x29 => {
+                String::from_utf8_lossy(&sector[label_at..label_at + 11])
+                    .trim_end()
+                    .to_string()
+            }
+            | _ => String::new(),
+        };
+
+        Some(BiosParameterBlock {
+            bytes_per_sector,
+            sectors_per_cluster,
+            reserved_sectors,
+            fat_count,
+            root_entries: le_u16(sector, 17) as u64,
+            total_sectors,
+            sectors_per_fat,
+            root_cluster,
+            label,
+        })
+    }
+}
+
+impl FatVolume
+{
+    /// Finds a FAT volume in the image, either at the very start (as on
+    /// floppy disks) or in the first FAT partition of an MBR partition
+    /// table (as on most flash drives).
+    pub fn find(image: &mut DiscImage) -> Option<u64>
+    {
+        let sector = image.read_at(0, 512).ok()?;
+
+        if BiosParameterBlock::parse(&sector).is_some() {
+            return Some(0);
+        }
+
+        if sector[510..512] != [0x55, 0xAA] {
+            return None;
+        }
+
+        (0..4)
+            .map(|i| &sector[446 + i * 16..446 + (i + 1) * 16])
+            .filter(|p| FAT_PARTITION_TYPES.contains(&p[4]))
+            .map(|p| le_u32(p, 8) as u64 * 512)
+            .find(|offset| {
+                image
+                    .read_at(*offset, 512)
+                    .ok()
+                    .and_then(|s| BiosParameterBlock::parse(&s))
+                    .is_some()
+            })
+    }
+
+    pub fn open(
+        mut image: DiscImage,
+        offset: u64,
+    ) -> Result<FatVolume, Box<dyn Error>>
+    {
+        let sector = image.read_at(offset, 512)?;
+
+        let bpb = BiosParameterBlock::parse(&sector)
+            .ok_or(format!("No FAT boot sector found at offset {offset}."))?;
+
+        let sector_size = bpb.bytes_per_sector;
+        let root_sectors = (bpb.root_entries * DIRECTORY_ENTRY_SIZE as u64)
+            .div_ceil(sector_size);
+        let fat_start = offset + bpb.reserved_sectors * sector_size;
+        let root_start =
+            fat_start + bpb.fat_count * bpb.sectors_per_fat * sector_size;
+        let data_start = root_start + root_sectors * sector_size;
+
+        let metadata_sectors = bpb.reserved_sectors
+            + bpb.fat_count * bpb.sectors_per_fat
+            + root_sectors;
+        let cluster_count = (bpb.total_sectors.saturating_sub(metadata_sectors)
+            / bpb.sectors_per_cluster) as u32;
+
+        // The FAT type is determined by the number of clusters alone.
+        let fat_type = match cluster_count {
+            | c if c < 4085 => FatType::Fat12,
+            | c if c < 65525 => FatType::Fat16,
+            | _ => FatType::Fat32,
+        };
+
+        debug!("Found {fat_type:?} volume with {cluster_count} clusters");
+
+        let fat = image
+            .read_at(fat_start, (bpb.sectors_per_fat * sector_size) as usize)?;
+
+        let root = match fat_type {
+            | FatType::Fat32 => RootDirectory::Chain(bpb.root_cluster),
+            | _ => {
+                RootDirectory::Fixed(Extent {
+                    offset: root_start,
+                    length: root_sectors * sector_size,
+                })
+            }
+        };
+
+        let mut volume = FatVolume {
+            image,
+            fat_type,
+            volume_name: bpb.label,
+            fat,
+            cluster_size: bpb.sectors_per_cluster * sector_size,
+            data_start,
+            cluster_count,
+            root,
+        };
+
+        // The label in the root directory is the one users see and edit;
+        // the boot sector copy is often stale.
+        if let Some(label) = volume.root_label()? {
+            volume.volume_name = label;
+        }
+        if volume.volume_name.eq("NO NAME") {
+            volume.volume_name = String::new();
+        }
+
+        Ok(volume)
+    }
+
+    pub fn fat_type(&self) -> FatType
+    {
+        self.fat_type
+    }
+
+    /// The cluster following `cluster` in its chain, if any.
+    fn next_cluster(
+        &self,
+        cluster: u32,
+    ) -> Option<u32>
+    {
+        let n = cluster as usize;
+
+        let next = match self.fat_type {
+            | FatType::Fat12 => {
+                let at = n + n / 2;
+                let v = *self.fat.get(at)? as u32
+                    | (*self.fat.get(at + 1)? as u32) << 8;
+                if n.is_multiple_of(2) {
+                    v & 0xFFF
+                } else {
+                    v >> 4
+                }
+            }
+            | FatType::Fat16 => {
+                self.fat.get(n * 2 + 1)?;
+                le_u16(&self.fat, n * 2) as u32
+            }
+            | FatType::Fat32 => {
+                self.fat.get(n * 4 + 3)?;
+                le_u32(&self.fat, n * 4) & 0x0FFF_FFFF
+            }
+        };
+
+        // Zero (free), reserved, bad, and end-of-chain values all end the
+        // chain.
+        match next {
+            | c if c >= 2 && c < self.cluster_count + 2 => Some(c),
+            | _ => None,
+        }
+    }
+
+    /// The extents covered by the cluster chain starting at `first`, with
+    /// adjacent clusters merged.
+    fn chain_extents(
+        &self,
+        first: u32,
+    ) -> Vec<Extent>
+    {
+        let mut extents: Vec<Extent> = vec![];
+        let mut cluster = Some(first).filter(|c| *c >= 2);
+        let mut visited = 0;
+
+        while let Some(c) = cluster {
+            visited += 1;
+            if visited > self.cluster_count {
+                warn!("FAT cluster chain starting at {first} loops.");
+                break;
+            }
+
+            let offset = self.data_start + (c as u64 - 2) * self.cluster_size;
+
+            match extents.last_mut() {
+                | Some(last) if last.offset + last.length == offset => {
+                    last.length += self.cluster_size;
+                }
+                | _ => {
+                    extents.push(Extent {
+                        offset,
+                        length: self.cluster_size,
+                    })
+                }
+            }
+
+            cluster = self.next_cluster(c);
+        }
+
+        extents
+    }
+
+    fn read_directory(
+        &mut self,
+        extents: &[Extent],
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        let size = extents.iter().map(|e| e.length).sum();
+        self.image.read_extents(extents, size)
+    }
+
+    fn root_extents(&self) -> Vec<Extent>
+    {
+        match self.root {
+            | RootDirectory::Fixed(extent) => vec![extent],
+            | RootDirectory::Chain(cluster) => self.chain_extents(cluster),
+        }
+    }
+
+    fn root_label(&mut self) -> Result<Option<String>, Box<dyn Error>>
+    {
+        let root = self.read_directory(&self.root_extents())?;
+
+        Ok(root
+            .chunks_exact(DIRECTORY_ENTRY_SIZE)
+            .take_while(|e| e[0] != 0)
+            .find(|e| {
+                e[0] != 0xE5
+                    && e[11] & ATTR_LONG_NAME != ATTR_LONG_NAME
+                    && e[11] & ATTR_VOLUME_LABEL != 0
+            })
+            .map(|e| String::from_utf8_lossy(&e[0..11]).trim_end().to_string()))
+    }
+}
+
+impl DiscFilesystem for FatVolume
+{
+    fn filesystem_name(&self) -> &'static str
+    {
+        match self.fat_type {
+            | FatType::Fat12 => "FAT12",
+            | FatType::Fat16 => "FAT16",
+            | FatType::Fat32 => "FAT32",
+        }
+    }
+
+    fn volume_name(&self) -> String
+    {
+        self.volume_name.clone()
+    }
+
+    /// FAT records no volume dates outside of the label's directory entry,
+    /// so only the cluster layout is reported.
+    fn volume_info(&self) -> VolumeInfo
+    {
+        VolumeInfo {
+            created: None,
+            modified: None,
+            block_size: self.cluster_size,
+            block_count: self.cluster_count as u64,
+        }
+    }
+
+    fn entries(&mut self) -> Result<Vec<DiscEntry>, Box<dyn Error>>
+    {
+        let mut entries = vec![];
+        let mut visited = HashSet::new();
+        let mut pending = vec![(PathBuf::new(), self.root_extents())];
+
+        while let Some((parent, extents)) = pending.pop() {
+            // Guard against directories which loop back on themselves or a
+            // directory above them.
+            if !visited.insert(extents.first().map(|e| e.offset)) {
+                warn!("FAT directory {parent:?} was already read.");
+                continue;
+            }
+
+            let directory = self.read_directory(&extents)?;
+
+            for (name, record) in parse_directory(&directory) {
+                let path = parent.join(path_component(&name));
+                let first_cluster = (le_u16(record, 20) as u32) << 16
+                    | le_u16(record, 26) as u32;
+                let is_dir = record[11] & ATTR_DIRECTORY != 0;
+
+                if is_dir {
+                    pending.push((
+                        path.clone(),
+                        self.chain_extents(first_cluster),
+                    ));
+                }
+
+                entries.push(DiscEntry {
+                    path,
+                    is_dir,
+                    size: if is_dir { 0 } else { le_u32(record, 28) as u64 },
+                    data: if is_dir {
+                        vec![]
+                    } else {
+                        self.chain_extents(first_cluster)
+                    },
+                    resource_size: 0,
+                    resource: vec![],
+                    created: fat_time(
+                        le_u16(record, 16),
+                        le_u16(record, 14),
+                        record[13],
+                    ),
+                    modified: fat_time(
+                        le_u16(record, 24),
+                        le_u16(record, 22),
+                        0,
+                    ),
+                    finder_info: None,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(entries)
+    }
+
+    fn image(&mut self) -> &mut DiscImage
+    {
+        &mut self.image
+    }
+}
+
+/// Parses the live entries of a directory into (name, short entry) pairs,
+/// using the VFAT long filename where one is present and valid.
+fn parse_directory(directory: &[u8]) -> Vec<(String, &[u8])>
+{
+    let mut found = vec![];
+    let mut long_name: Vec<(u8, Vec<u16>)> = vec![];
+    let mut long_checksum = None;
+
+    for record in directory.chunks_exact(DIRECTORY_ENTRY_SIZE) {
+        match record[0] {
+            | 0x00 => break,
+            | 0xE5 => {
+                long_name.clear();
+                continue;
+            }
+            | _ => (),
+        }
+
+        let attributes = record[11];
+
+        if attributes & ATTR_LONG_NAME == ATTR_LONG_NAME {
+            if record[0] & LAST_LONG_ENTRY != 0 {
+                long_name.clear();
+                long_checksum = Some(record[13]);
+            }
+
+            let mut units = vec![];
+            for (start, count) in [(1, 5), (14, 6), (28, 2)] {
+                for i in 0..count {
+                    units.push(le_u16(record, start + i * 2));
+                }
+            }
+            long_name.push((record[0] & 0x1F, units));
+            continue;
+        }
+
+        let pending_long_name = std::mem::take(&mut long_name);
+
+        if attributes & ATTR_VOLUME_LABEL != 0 {
+            continue;
+        }
+
+        let short = short_name(record);
+        if short.eq(".") || short.eq("..") {
+            continue;
+        }
+
+        let name = match long_checksum.take() {
+            | Some(c) if c == short_name_checksum(&record[0..11]) => {
+                assemble_long_name(pending_long_name).unwrap_or(short)
+            }
+            | _ => short,
+        };
+
+        found.push((name, record));
+    }
+
+    found
+}
+
+/// Joins long filename pieces, which are stored last piece first.
+fn assemble_long_name(mut pieces: Vec<(u8, Vec<u16>)>) -> Option<String>
+{
+    pieces.sort_by_key(|(sequence, _)| *sequence);
+
+    let expected: Vec<u8> = (1..=pieces.len() as u8).collect();
+    let found: Vec<u8> = pieces.iter().map(|(s, _)| *s).collect();
+    if pieces.is_empty() || expected != found {
+        return None;
+    }
+
+    let units: Vec<u16> = pieces
+        .into_iter()
+        .flat_map(|(_, u)| u)
+        .take_while(|u| *u != 0x0000)
+        .collect();
+
+    Some(String::from_utf16_lossy(&units))
+}
+
+fn short_name_checksum(name: &[u8]) -> u8
+{
+    name.iter()
+        .fold(0u8, |sum, b| sum.rotate_right(1).wrapping_add(*b))
+}
+
+/// The 8.3 name of an entry as DOS would show it, honoring the Windows NT
+/// lower case flags.
+fn short_name(record: &[u8]) -> String
+{
+    let mut base: Vec<u8> = record[0..8].to_vec();
+    if base[0] == 0x05 {
+        base[0] = 0xE5;
+    }
+
+    let decode = |b: &[u8], lower: bool| {
+        let s: String = b.iter().map(|c| *c as char).collect();
+        let s = s.trim_end().to_string();
+        if lower {
+            s.to_lowercase()
+        } else {
+            s
+        }
+    };
+
+    let base = decode(&base, record[12] & LOWER_CASE_BASE != 0);
+    let extension =
+        decode(&record[8..11], record[12] & LOWER_CASE_EXTENSION != 0);
+
+    match extension.is_empty() {
+        | true => base,
+        | false => format!("{base}.{extension}"),
+    }
+}
+
+/// Converts a FAT date and time, plus the creation time's hundredths of a
+/// second, to a `SystemTime`.  FAT stores local time without a zone, so the
+/// result is only as accurate as the clock of the machine that wrote it.
+fn fat_time(
+    date: u16,
+    time: u16,
+    hundredths: u8,
+) -> Option<SystemTime>
+{
+    if date == 0 {
+        return None;
+    }
+
+    civil_time(
+        1980 + (date >> 9) as i64,
+        ((date >> 5) & 0x0F) as u32,
+        (date & 0x1F) as u32,
+        (time >> 11) as u32,
+        ((time >> 5) & 0x3F) as u32,
+        (time & 0x1F) as u32 * 2 + hundredths as u32 / 100,
+    )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::{
+        io::Write,
+        time::{
+            Duration,
+            UNIX_EPOCH,
+        },
+    };
+    use tempfile::NamedTempFile;
+
+    fn short_entry(
+        name: &[u8; 11],
+        attributes: u8,
+        cluster: u16,
+        size: u32,
+    ) -> Vec<u8>
+    {
+        let mut e = vec![0; 32];
+        e[0..11].copy_from_slice(name);
+        e[11] = attributes;
+        // 2001-02-03 04:05:06
+        e[22..24].copy_from_slice(&((4 << 11) | (5 << 5) | 3u16).to_le_bytes());
+        e[24..26].copy_from_slice(&((21 << 9) | (2 << 5) | 3u16).to_le_bytes());
+        e[26..28].copy_from_slice(&cluster.to_le_bytes());
+        e[28..32].copy_from_slice(&size.to_le_bytes());
+        e
+    }
+
+    fn long_entries(
+        name: &str,
+        short: &[u8; 11],
+    ) -> Vec<u8>
+    {
+        let mut units: Vec<u16> = name.encode_utf16().collect();
+        units.push(0);
+        while !units.len().is_multiple_of(13) {
+            units.push(0xFFFF);
+        }
+
+        let pieces: Vec<&[u16]> = units.chunks(13).collect();
+        let checksum = short_name_checksum(short);
+
+        let mut out = vec![];
+        for (i, piece) in pieces.iter().enumerate().rev() {
+            let mut e = vec![0; 32];
+            e[0] = (i + 1) as u8;
+            if i == pieces.len() - 1 {
+                e[0] |= LAST_LONG_ENTRY;
+            }
+            e[11] = ATTR_LONG_NAME;
+            e[13] = checksum;
+            for (j, u) in piece.iter().enumerate() {
+                let at = match j {
+                    | 0..=4 => 1 + j * 2,
+                    | 5..=10 => 14 + (j - 5) * 2,
+                    | _ => 28 + (j - 11) * 2,
+                };
+                e[at..at + 2].copy_from_slice(&u.to_le_bytes());
+            }
+            out.extend(e);
+        }
+        out
+    }
+
+    /// A 1.44MB floppy layout (FAT12, 224 root entries, one sector
+    /// clusters) holding a directory with a long-named file.
+    fn build_floppy() -> Vec<u8>
+    {
+        let mut image = vec![0; 2880 * 512];
+
+        let boot = &mut image[0..512];
+        boot[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+        boot[13] = 1;
+        boot[14..16].copy_from_slice(&1u16.to_le_bytes());
+        boot[16] = 2;
+        boot[17..19].copy_from_slice(&224u16.to_le_bytes());
+        boot[19..21].copy_from_slice(&2880u16.to_le_bytes());
+        boot[21] = 0xF0;
+        boot[22..24].copy_from_slice(&9u16.to_le_bytes());
+        boot[38] = 0x29;
+        boot[43..54].copy_from_slice(b"NO NAME    ");
+        boot[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        // FAT: media byte, then clusters 2 (directory) and 3-4 (file) end
+        // their chains.
+        let fat = &mut image[512..512 + 9 * 512];
+        let entries: [u16; 5] = [0xFF0, 0xFFF, 0xFFF, 4, 0xFFF];
+        for (n, v) in entries.iter().enumerate() {
+            let at = n + n / 2;
+            if n % 2 == 0 {
+                fat[at] = *v as u8;
+                fat[at + 1] = (fat[at + 1] & 0xF0) | (v >> 8) as u8;
+            } else {
+                fat[at] = (fat[at] & 0x0F) | ((v & 0x0F) << 4) as u8;
+                fat[at + 1] = (v >> 4) as u8;
+            }
+        }
+
+        let root_start = 19 * 512;
+        let data_start = 33 * 512;
+
+        let mut root = short_entry(b"FLOPPY 01  ", ATTR_VOLUME_LABEL, 0, 0);
+        root.extend(short_entry(b"LETTERS    ", ATTR_DIRECTORY, 2, 0));
+        image[root_start..root_start + root.len()].copy_from_slice(&root);
+
+        let mut letters = short_entry(b".          ", ATTR_DIRECTORY, 2, 0);
+        letters.extend(short_entry(b"..         ", ATTR_DIRECTORY, 0, 0));
+        letters
+            .extend(long_entries("Letter to the Editor.txt", b"LETTER~1TXT"));
+        letters.extend(short_entry(b"LETTER~1TXT", 0x20, 3, 600));
+        let mut lower = short_entry(b"README  TXT", 0x20, 0, 0);
+        lower[12] = LOWER_CASE_BASE | LOWER_CASE_EXTENSION;
+        letters.extend(lower);
+        image[data_start..data_start + letters.len()].copy_from_slice(&letters);
+
+        let file = data_start + 512;
+        for i in 0..600 {
+            image[file + i] = b'a' + (i % 26) as u8;
+        }
+
+        image
+    }
+
+    fn open(image: Vec<u8>) -> (NamedTempFile, FatVolume)
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&image).unwrap();
+
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        let offset = FatVolume::find(&mut image).unwrap();
+        let volume = FatVolume::open(image, offset).unwrap();
+
+        (file, volume)
+    }
+
+    #[test]
+    fn test_floppy()
+    {
+        let (_file, mut volume) = open(build_floppy());
+
+        assert_eq!(FatType::Fat12, volume.fat_type());
+        assert_eq!("FAT12", volume.filesystem_name());
+        assert_eq!("FLOPPY 01", volume.volume_name());
+
+        let entries = volume.entries().unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("LETTERS"),
+                PathBuf::from("LETTERS/Letter to the Editor.txt"),
+                PathBuf::from("LETTERS/readme.txt"),
+            ]
+        );
+
+        let letter = &entries[1];
+        assert_eq!(600, letter.size);
+        assert_eq!(1, letter.data.len());
+        assert_eq!(1024, letter.data[0].length);
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(981_173_106)),
+            letter.modified
+        );
+
+        let contents = volume.read_data(letter).unwrap();
+        assert_eq!(600, contents.len());
+        assert_eq!(b"abc", &contents[0..3]);
+        assert_eq!(Vec::<u8>::new(), volume.read_data(&entries[2]).unwrap());
+    }
+
+    /// Adds entries after those of the LETTERS directory.
+    fn add_to_letters(
+        image: &mut [u8],
+        entries: &[u8],
+    )
+    {
+        let at = 33 * 512 + 6 * DIRECTORY_ENTRY_SIZE;
+        image[at..at + entries.len()].copy_from_slice(entries);
+    }
+
+    #[test]
+    fn test_directory_loop()
+    {
+        // LOOP is LETTERS again, so would be read forever.
+        let mut image = build_floppy();
+        add_to_letters(&mut image, &short_entry(b"LOOP       ", 0x10, 2, 0));
+
+        let (_file, mut volume) = open(image);
+        let entries = volume.entries().unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+
+        assert_eq!(4, paths.len());
+        assert!(paths.contains(&PathBuf::from("LETTERS/LOOP")));
+    }
+
+    #[test]
+    fn test_unsafe_names()
+    {
+        let mut names = long_entries("..", b"DOTDOT     ");
+        names.extend(short_entry(b"DOTDOT     ", 0x20, 0, 0));
+        names.extend(long_entries("../a/b.txt", b"AB      TXT"));
+        names.extend(short_entry(b"AB      TXT", 0x20, 0, 0));
+
+        let mut image = build_floppy();
+        add_to_letters(&mut image, &names);
+
+        let (_file, mut volume) = open(image);
+        let paths: Vec<_> = volume
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+
+        assert!(paths.contains(&PathBuf::from("LETTERS/_..")));
+        assert!(paths.contains(&PathBuf::from("LETTERS/.._a_b.txt")));
+        assert!(paths
+            .iter()
+            .all(|p| p.starts_with("LETTERS") || p == "LETTERS"));
+    }
+
+    #[test]
+    fn test_partitioned()
+    {
+        let floppy = build_floppy();
+
+        let mut image = vec![0; 63 * 512];
+        image[446 + 4] = 0x01;
+        image[446 + 8..446 + 12].copy_from_slice(&63u32.to_le_bytes());
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+        image.extend(floppy);
+
+        let (_file, mut volume) = open(image);
+
+        assert_eq!("FLOPPY 01", volume.volume_name());
+        assert_eq!(3, volume.entries().unwrap().len());
+    }
+
+    #[test]
+    fn test_checksum()
+    {
+        assert_eq!(116, short_name_checksum(b"LETTER~1TXT"));
+    }
+}
@@ -0,0 +1,604 @@
+use super::{
+    be_u16,
+    be_u32,
+    btree::BTree,
+    mac_roman,
+    mac_time,
+    DiscEntry,
+    DiscFilesystem,
+    DiscImage,
+    Extent,
+    FinderInfo,
+    VolumeInfo,
+};
+use log::warn;
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// "BD", the signature of an HFS master directory block.
+const HFS_SIGNATURE: u16 = 0x4244;
+
+/// "H+", the signature of an HFS+ volume embedded in an HFS wrapper.
+const EMBEDDED_HFS_PLUS_SIGNATURE: u16 = 0x482B;
+
+/// Catalog node ID of the catalog file itself.
+const CATALOG_FILE_ID: u32 = 4;
+
+/// Catalog node ID of the root folder.
+pub(crate) const ROOT_FOLDER_ID: u32 = 2;
+
+const FOLDER_RECORD: u8 = 1;
+const FILE_RECORD: u8 = 2;
+
+/// Fork type used in extents overflow keys.
+pub(crate) const DATA_FORK: u8 = 0x00;
+pub(crate) const RESOURCE_FORK: u8 = 0xFF;
+
+/// Extents beyond those stored in the catalog, keyed by catalog node ID and
+/// fork type, in allocation block (start, count) pairs.
+pub(crate) type OverflowExtents = HashMap<(u32, u8), Vec<(u32, u32)>>;
+
+/// A folder's parent and name, used to rebuild paths from the catalog.
+pub(crate) struct CatalogFolder
+{
+    pub(crate) parent: u32,
+    pub(crate) name: String,
+}
+
+/// A classic HFS ("Mac OS Standard") volume.
+pub struct HfsVolume
+{
+    image: DiscImage,
+    /// Byte offset of the first allocation block.
+    allocation_start: u64,
+    block_size: u64,
+    volume_name: String,
+    info: VolumeInfo,
+    embedded_volume: Option<u64>,
+    catalog_size: u64,
+    catalog_extents: Vec<(u32, u32)>,
+    overflow_size: u64,
+    overflow_extents: Vec<(u32, u32)>,
+}
+
+impl HfsVolume
+{
+    /// Whether an HFS master directory block is present for a volume
+    /// starting at `offset`.
+    pub fn detect(
+        image: &mut DiscImage,
+        offset: u64,
+    ) -> bool
+    {
+        image
+            .read_at(offset + 1024, 2)
+            .map(|b| be_u16(&b, 0) == HFS_SIGNATURE)
+            .unwrap_or(false)
+    }
+
+    pub fn open(
+        mut image: DiscImage,
+        offset: u64,
+    ) -> Result<HfsVolume, Box<dyn Error>>
+    {
+        let mdb = image.read_at(offset + 1024, 512)?;
+
+        if be_u16(&mdb, 0) != HFS_SIGNATURE {
+            return Err(format!(
+                "No HFS master directory block found at offset {offset}."
+            )
+            .into());
+        }
+
+        let block_size = be_u32(&mdb, 20) as u64;
+        if block_size == 0 || !block_size.is_multiple_of(512) {
+            return Err(format!(
+                "Invalid HFS allocation block size {block_size}."
+            )
+            .into());
+        }
+
+        let allocation_start = offset + be_u16(&mdb, 28) as u64 * 512;
+
+        let name_len = (mdb[36] as usize).min(27);
+        let volume_name = mac_roman::decode(&mdb[37..37 + name_len]);
+
+        let embedded_volume = match be_u16(&mdb, 124) {
+            | EMBEDDED_HFS_PLUS_SIGNATURE => {
+                Some(allocation_start + be_u16(&mdb, 126) as u64 * block_size)
+            }
+            | _ => None,
+        };
+
+        Ok(HfsVolume {
+            image,
+            allocation_start,
+            block_size,
+            volume_name,
+            info: VolumeInfo {
+                created: mac_time(be_u32(&mdb, 2)),
+                modified: mac_time(be_u32(&mdb, 6)),
+                block_size,
+                block_count: be_u16(&mdb, 18) as u64,
+            },
+            embedded_volume,
+            overflow_size: be_u32(&mdb, 130) as u64,
+            overflow_extents: small_extent_record(&mdb[134..146]),
+            catalog_size: be_u32(&mdb, 146) as u64,
+            catalog_extents: small_extent_record(&mdb[150..162]),
+        })
+    }
+
+    /// Byte offset of an HFS+ volume wrapped by this volume, if any.
+    pub fn embedded_volume_offset(&self) -> Option<u64>
+    {
+        self.embedded_volume
+    }
+
+    pub fn into_image(self) -> DiscImage
+    {
+        self.image
+    }
+
+    fn to_extents(
+        &self,
+        blocks: &[(u32, u32)],
+    ) -> Vec<Extent>
+    {
+        blocks
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(start, count)| {
+                Extent {
+                    offset: self.allocation_start
+                        + *start as u64 * self.block_size,
+                    length: *count as u64 * self.block_size,
+                }
+            })
+            .collect()
+    }
+
+    fn read_overflow(&mut self) -> Result<OverflowExtents, Box<dyn Error>>
+    {
+        let mut overflow = OverflowExtents::new();
+
+        if self.overflow_size == 0 {
+            return Ok(overflow);
+        }
+
+        let extents = self.to_extents(&self.overflow_extents);
+        let tree = BTree::parse(
+            self.image.read_extents(&extents, self.overflow_size)?,
+        )?;
+
+        for record in tree.leaf_records()? {
+            // Key: length, fork type, file ID, starting allocation block.
+            if record.len() < 20 || record[0] != 7 {
+                warn!("Skipping malformed HFS extents overflow record.");
+                continue;
+            }
+
+            overflow
+                .entry((be_u32(record, 2), record[1]))
+                .or_default()
+                .extend(small_extent_record(&record[8..20]));
+        }
+
+        Ok(overflow)
+    }
+
+    fn fork_extents(
+        &self,
+        first: &[u8],
+        file_id: u32,
+        fork: u8,
+        overflow: &OverflowExtents,
+    ) -> Vec<Extent>
+    {
+        let mut blocks = small_extent_record(first);
+
+        if let Some(more) = overflow.get(&(file_id, fork)) {
+            blocks.extend(more);
+        }
+
+        self.to_extents(&blocks)
+    }
+}
+
+impl DiscFilesystem for HfsVolume
+{
+    fn filesystem_name(&self) -> &'static str
+    {
+        "HFS"
+    }
+
+    fn volume_name(&self) -> String
+    {
+        self.volume_name.clone()
+    }
+
+    fn volume_info(&self) -> VolumeInfo
+    {
+        self.info.clone()
+    }
+
+    fn entries(&mut self) -> Result<Vec<DiscEntry>, Box<dyn Error>>
+    {
+        let overflow = self.read_overflow()?;
+
+        let mut catalog_blocks = self.catalog_extents.clone();
+        if let Some(more) = overflow.get(&(CATALOG_FILE_ID, DATA_FORK)) {
+            catalog_blocks.extend(more);
+        }
+        let catalog_extents = self.to_extents(&catalog_blocks);
+        let catalog = BTree::parse(
+            self.image
+                .read_extents(&catalog_extents, self.catalog_size)?,
+        )?;
+
+        let mut folders = HashMap::new();
+        let mut entries = vec![];
+        let mut files = vec![];
+
+        for record in catalog.leaf_records()? {
+            // Key: length, reserved, parent ID, Pascal string name.
+            let key_len = record[0] as usize;
+            if key_len < 6 || record.len() < key_len + 2 {
+                warn!("Skipping malformed HFS catalog record.");
+                continue;
+            }
+
+            let parent = be_u32(record, 2);
+            let name_len = (record[6] as usize).min(key_len - 6);
+            let name = mac_roman::decode(&record[7..7 + name_len]);
+
+            let data_start = (key_len + 2) & !1;
+            let data = &record[data_start..];
+
+            match data.first() {
+                | Some(&FOLDER_RECORD) if data.len() >= 70 => {
+                    let folder_id = be_u32(data, 6);
+
+                    folders.insert(
+                        folder_id,
+                        CatalogFolder {
+                            parent,
+                            name: name.clone(),
+                        },
+                    );
+
+                    if folder_id != ROOT_FOLDER_ID {
+                        entries.push((
+                            parent,
+                            name,
+                            folder_entry(
+                                mac_time(be_u32(data, 10)),
+                                mac_time(be_u32(data, 14)),
+                            ),
+                        ));
+                    }
+                }
+                | Some(&FILE_RECORD) if data.len() >= 102 => {
+                    files.push((parent, name, data.to_vec()));
+                }
+                | _ => (),
+            }
+        }
+
+        for (parent, name, data) in files {
+            let file_id = be_u32(&data, 20);
+
+            let mut raw = [0; 32];
+            raw[..16].copy_from_slice(&data[4..20]);
+            raw[16..].copy_from_slice(&data[56..72]);
+
+            entries.push((
+                parent,
+                name,
+                DiscEntry {
+                    path: PathBuf::new(),
+                    is_dir: false,
+                    size: be_u32(&data, 26) as u64,
+                    data: self.fork_extents(
+                        &data[74..86],
+                        file_id,
+                        DATA_FORK,
+                        &overflow,
+                    ),
+                    resource_size: be_u32(&data, 36) as u64,
+                    resource: self.fork_extents(
+                        &data[86..98],
+                        file_id,
+                        RESOURCE_FORK,
+                        &overflow,
+                    ),
+                    created: mac_time(be_u32(&data, 44)),
+                    modified: mac_time(be_u32(&data, 48)),
+                    finder_info: Some(FinderInfo { raw }),
+                },
+            ));
+        }
+
+        Ok(resolve_paths(&folders, entries))
+    }
+
+    fn image(&mut self) -> &mut DiscImage
+    {
+        &mut self.image
+    }
+}
+
+/// Reads an HFS extent record: three (start block, block count) pairs.
+fn small_extent_record(b: &[u8]) -> Vec<(u32, u32)>
+{
+    (0..3)
+        .map(|i| (be_u16(b, i * 4) as u32, be_u16(b, i * 4 + 2) as u32))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+pub(crate) fn folder_entry(
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> DiscEntry
+{
+    DiscEntry {
+        path: PathBuf::new(),
+        is_dir: true,
+        size: 0,
+        data: vec![],
+        resource_size: 0,
+        resource: vec![],
+        created,
+        modified,
+        finder_info: None,
+    }
+}
+
+/// Fills in the path of each (parent ID, name, entry) from the catalog
+/// folders and sorts the result so parents precede their children.  Entries
+/// which cannot be connected to the root folder are dropped.
+pub(crate) fn resolve_paths(
+    folders: &HashMap<u32, CatalogFolder>,
+    entries: Vec<(u32, String, DiscEntry)>,
+) -> Vec<DiscEntry>
+{
+    let mut resolved: Vec<DiscEntry> = entries
+        .into_iter()
+        .filter_map(|(parent, name, mut entry)| {
+            let mut components = vec![path_component(&name)];
+            let mut current = parent;
+
+            while current != ROOT_FOLDER_ID {
+                // Guard against cycles in a damaged catalog.
+                if components.len() > folders.len() + 1 {
+                    warn!("HFS catalog folder chain for {name} loops.");
+                    return None;
+                }
+
+                match folders.get(&current) {
+                    | Some(folder) => {
+                        components.push(path_component(&folder.name));
+                        current = folder.parent;
+                    }
+                    | None => {
+                        warn!("HFS catalog entry {name} has no parent folder.");
+                        return None;
+                    }
+                }
+            }
+
+            entry.path = components.iter().rev().collect();
+            Some(entry)
+        })
+        .collect();
+
+    resolved.sort_by(|a, b| a.path.cmp(&b.path));
+
+    resolved
+}
+
+/// Mac names may contain "/", which the Finder showed as-is.  Use ":" on the
+/// host, matching how macOS presents these names.  Names such as ".." are
+/// made safe as for every other filesystem.
+fn path_component(name: &str) -> String
+{
+    super::path_component(&name.replace('/', ":"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+    use crate::disc_image::{
+        btree::tests::build_btree,
+        extract,
+    };
+    use std::{
+        fs,
+        io::Write,
+    };
+    use tempfile::{
+        NamedTempFile,
+        TempDir,
+    };
+
+    fn catalog_key(
+        parent: u32,
+        name: &[u8],
+    ) -> Vec<u8>
+    {
+        let mut key = vec![(6 + name.len()) as u8, 0];
+        key.extend(parent.to_be_bytes());
+        key.push(name.len() as u8);
+        key.extend(name);
+        if key.len() % 2 == 1 {
+            key.push(0);
+        }
+        key
+    }
+
+    fn folder_record(
+        parent: u32,
+        name: &[u8],
+        id: u32,
+    ) -> Vec<u8>
+    {
+        let mut r = catalog_key(parent, name);
+        let mut data = vec![0; 70];
+        data[0] = FOLDER_RECORD;
+        data[6..10].copy_from_slice(&id.to_be_bytes());
+        r.extend(data);
+        r
+    }
+
+    fn file_record(
+        parent: u32,
+        name: &[u8],
+        id: u32,
+        data_block: u16,
+        data_len: u32,
+        rsrc_block: u16,
+        rsrc_len: u32,
+    ) -> Vec<u8>
+    {
+        let mut r = catalog_key(parent, name);
+        let mut data = vec![0; 102];
+        data[0] = FILE_RECORD;
+        data[4..12].copy_from_slice(b"TEXTttxt");
+        data[20..24].copy_from_slice(&id.to_be_bytes());
+        data[26..30].copy_from_slice(&data_len.to_be_bytes());
+        data[36..40].copy_from_slice(&rsrc_len.to_be_bytes());
+        data[48..52].copy_from_slice(&(2_082_844_800u32 + 60).to_be_bytes());
+        data[74..76].copy_from_slice(&data_block.to_be_bytes());
+        data[76..78].copy_from_slice(&1u16.to_be_bytes());
+        if rsrc_len > 0 {
+            data[86..88].copy_from_slice(&rsrc_block.to_be_bytes());
+            data[88..90].copy_from_slice(&1u16.to_be_bytes());
+        }
+        r.extend(data);
+        r
+    }
+
+    /// A bare HFS volume with 512 byte allocation blocks starting at byte
+    /// 2048.  Blocks 0-1 hold the catalog, block 2 a data fork and block 3
+    /// a resource fork.
+    pub(crate) fn build_hfs_volume() -> Vec<u8>
+    {
+        let mut image = vec![0; 2048 + 512 * 4];
+
+        let mdb = &mut image[1024..1536];
+        mdb[0..2].copy_from_slice(&HFS_SIGNATURE.to_be_bytes());
+        mdb[20..24].copy_from_slice(&512u32.to_be_bytes());
+        mdb[28..30].copy_from_slice(&4u16.to_be_bytes());
+        mdb[36] = 9;
+        mdb[37..46].copy_from_slice(b"Test Disc");
+        mdb[146..150].copy_from_slice(&1024u32.to_be_bytes());
+        mdb[150..152].copy_from_slice(&0u16.to_be_bytes());
+        mdb[152..154].copy_from_slice(&2u16.to_be_bytes());
+
+        let catalog = build_btree(
+            512,
+            &[
+                folder_record(1, b"Test Disc", ROOT_FOLDER_ID),
+                folder_record(ROOT_FOLDER_ID, b"Docs", 16),
+                file_record(16, b"Read/Me", 17, 2, 5, 3, 4),
+                file_record(ROOT_FOLDER_ID, b"Caf\x8E", 18, 2, 3, 0, 0),
+            ],
+        );
+        image[2048..3072].copy_from_slice(&catalog);
+        image[3072..3077].copy_from_slice(b"hello");
+        image[3584..3588].copy_from_slice(b"rsrc");
+
+        image
+    }
+
+    fn open_test_volume() -> (NamedTempFile, HfsVolume)
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_hfs_volume()).unwrap();
+
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        assert!(HfsVolume::detect(&mut image, 0));
+
+        let volume = HfsVolume::open(image, 0).unwrap();
+
+        (file, volume)
+    }
+
+    #[test]
+    fn test_list()
+    {
+        let (_file, mut volume) = open_test_volume();
+
+        assert_eq!("Test Disc", volume.volume_name());
+        assert_eq!(None, volume.embedded_volume_offset());
+
+        let entries = volume.entries().unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("Café"),
+                PathBuf::from("Docs"),
+                PathBuf::from("Docs/Read:Me"),
+            ]
+        );
+
+        let read_me = &entries[2];
+        assert_eq!(5, read_me.size);
+        assert_eq!(4, read_me.resource_size);
+        assert_eq!(b"TEXT", &read_me.finder_info.as_ref().unwrap().file_type());
+        assert_eq!(b"hello".to_vec(), volume.read_data(read_me).unwrap());
+        assert_eq!(b"rsrc".to_vec(), volume.read_resource(read_me).unwrap());
+    }
+
+    #[test]
+    fn test_unsafe_names()
+    {
+        let folders = HashMap::from([(
+            16,
+            CatalogFolder {
+                parent: ROOT_FOLDER_ID,
+                name: "..".to_string(),
+            },
+        )]);
+        let entries = vec![
+            (16, ".".to_string(), folder_entry(None, None)),
+            (16, "a/../b".to_string(), folder_entry(None, None)),
+        ];
+
+        let paths: Vec<_> = resolve_paths(&folders, entries)
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(
+            vec![PathBuf::from("_../_."), PathBuf::from("_../a:..:b")],
+            paths
+        );
+    }
+
+    #[test]
+    fn test_extract()
+    {
+        let (_file, mut volume) = open_test_volume();
+        let out = TempDir::new().unwrap();
+        let to = out.path().to_path_buf();
+
+        assert!(extract(&mut volume, &to).unwrap().is_empty());
+
+        assert_eq!(
+            "hello",
+            fs::read_to_string(to.join("Docs/Read:Me")).unwrap()
+        );
+
+        let header = fs::read(to.join("Docs/._Read:Me")).unwrap();
+        assert_eq!(&header[0..4], &[0x00, 0x05, 0x16, 0x07]);
+        assert!(header.ends_with(b"rsrc"));
+    }
+}
@@ -0,0 +1,462 @@
+use super::{
+    be_u16,
+    be_u32,
+    be_u64,
+    btree::BTree,
+    hfs::{
+        folder_entry,
+        resolve_paths,
+        CatalogFolder,
+        OverflowExtents,
+        DATA_FORK,
+        RESOURCE_FORK,
+        ROOT_FOLDER_ID,
+    },
+    mac_time,
+    DiscEntry,
+    DiscFilesystem,
+    DiscImage,
+    Extent,
+    FinderInfo,
+    VolumeInfo,
+};
+use log::warn;
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::PathBuf,
+};
+
+/// "H+", the signature of an HFS+ volume header.
+const HFS_PLUS_SIGNATURE: u16 = 0x482B;
+
+/// "HX", the signature of an HFSX (case-sensitive HFS+) volume header.
+const HFSX_SIGNATURE: u16 = 0x4858;
+
+const CATALOG_FILE_ID: u32 = 4;
+
+const FOLDER_RECORD: u16 = 1;
+const FILE_RECORD: u16 = 2;
+
+/// An HFS+ ("Mac OS Extended") or HFSX volume.
+pub struct HfsPlusVolume
+{
+    image: DiscImage,
+    /// Byte offset of the volume, which is also allocation block zero.
+    offset: u64,
+    block_size: u64,
+    volume_name: Option<String>,
+    info: VolumeInfo,
+    extents_fork: ForkData,
+    catalog_fork: ForkData,
+}
+
+/// An HFS+ `HFSPlusForkData` structure.
+struct ForkData
+{
+    logical_size: u64,
+    extents: Vec<(u32, u32)>,
+}
+
+impl ForkData
+{
+    fn parse(b: &[u8]) -> ForkData
+    {
+        ForkData {
+            logical_size: be_u64(b, 0),
+            extents: large_extent_record(&b[16..80]),
+        }
+    }
+}
+
+impl HfsPlusVolume
+{
+    /// Whether an HFS+ or HFSX volume header is present for a volume
+    /// starting at `offset`.
+    pub fn detect(
+        image: &mut DiscImage,
+        offset: u64,
+    ) -> bool
+    {
+        image
+            .read_at(offset + 1024, 2)
+            .map(|b| {
+                matches!(be_u16(&b, 0), HFS_PLUS_SIGNATURE | HFSX_SIGNATURE)
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn open(
+        mut image: DiscImage,
+        offset: u64,
+    ) -> Result<HfsPlusVolume, Box<dyn Error>>
+    {
+        let header = image.read_at(offset + 1024, 512)?;
+
+        if !matches!(be_u16(&header, 0), HFS_PLUS_SIGNATURE | HFSX_SIGNATURE) {
+            return Err(format!(
+                "No HFS+ volume header found at offset {offset}."
+            )
+            .into());
+        }
+
+        let block_size = be_u32(&header, 40) as u64;
+        if block_size == 0 || !block_size.is_multiple_of(512) {
+            return Err(format!(
+                "Invalid HFS+ allocation block size {block_size}."
+            )
+            .into());
+        }
+
+        Ok(HfsPlusVolume {
+            image,
+            offset,
+            block_size,
+            volume_name: None,
+            info: VolumeInfo {
+                created: mac_time(be_u32(&header, 16)),
+                modified: mac_time(be_u32(&header, 20)),
+                block_size,
+                block_count: be_u32(&header, 44) as u64,
+            },
+            extents_fork: ForkData::parse(&header[192..272]),
+            catalog_fork: ForkData::parse(&header[272..352]),
+        })
+    }
+
+    fn to_extents(
+        &self,
+        blocks: &[(u32, u32)],
+    ) -> Result<Vec<Extent>, Box<dyn Error>>
+    {
+        blocks
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(start, count)| {
+                let offset = (*start as u64)
+                    .checked_mul(self.block_size)
+                    .and_then(|start| start.checked_add(self.offset));
+                let length = (*count as u64).checked_mul(self.block_size);
+
+                match (offset, length) {
+                    | (Some(offset), Some(length)) => {
+                        Ok(Extent { offset, length })
+                    }
+                    | _ => {
+                        Err(format!(
+                            "HFS+ extent of {count} blocks at block {start} \
+                             is out of range."
+                        )
+                        .into())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn read_overflow(&mut self) -> Result<OverflowExtents, Box<dyn Error>>
+    {
+        let mut overflow = OverflowExtents::new();
+
+        if self.extents_fork.logical_size == 0 {
+            return Ok(overflow);
+        }
+
+        let extents = self.to_extents(&self.extents_fork.extents)?;
+        let tree = BTree::parse(
+            self.image
+                .read_extents(&extents, self.extents_fork.logical_size)?,
+        )?;
+
+        for record in tree.leaf_records()? {
+            // Key: length, fork type, pad, file ID, starting block.
+            if record.len() < 76 || be_u16(record, 0) != 10 {
+                warn!("Skipping malformed HFS+ extents overflow record.");
+                continue;
+            }
+
+            overflow
+                .entry((be_u32(record, 4), record[2]))
+                .or_default()
+                .extend(large_extent_record(&record[12..76]));
+        }
+
+        Ok(overflow)
+    }
+
+    fn fork_extents(
+        &self,
+        fork: &ForkData,
+        file_id: u32,
+        fork_type: u8,
+        overflow: &OverflowExtents,
+    ) -> Result<Vec<Extent>, Box<dyn Error>>
+    {
+        let mut blocks = fork.extents.clone();
+
+        if let Some(more) = overflow.get(&(file_id, fork_type)) {
+            blocks.extend(more);
+        }
+
+        self.to_extents(&blocks)
+    }
+}
+
+impl DiscFilesystem for HfsPlusVolume
+{
+    fn filesystem_name(&self) -> &'static str
+    {
+        "HFS+"
+    }
+
+    /// HFS+ keeps the volume name only as the name of the root folder, so
+    /// it is not known until the catalog has been read.
+    fn volume_name(&self) -> String
+    {
+        self.volume_name.clone().unwrap_or_default()
+    }
+
+    fn volume_info(&self) -> VolumeInfo
+    {
+        self.info.clone()
+    }
+
+    fn entries(&mut self) -> Result<Vec<DiscEntry>, Box<dyn Error>>
+    {
+        let overflow = self.read_overflow()?;
+
+        let catalog_extents = self.fork_extents(
+            &self.catalog_fork,
+            CATALOG_FILE_ID,
+            DATA_FORK,
+            &overflow,
+        )?;
+        let catalog =
+            BTree::parse(self.image.read_extents(
+                &catalog_extents,
+                self.catalog_fork.logical_size,
+            )?)?;
+
+        let mut folders = HashMap::new();
+        let mut entries = vec![];
+
+        for record in catalog.leaf_records()? {
+            // Key: length, parent ID, UTF-16 name with its length.
+            if record.len() < 8 {
+                warn!("Skipping malformed HFS+ catalog record.");
+                continue;
+            }
+
+            let key_len = be_u16(record, 0) as usize;
+            let name_len = be_u16(record, 6) as usize;
+            if key_len < 6 + name_len * 2 || record.len() < key_len + 4 {
+                warn!("Skipping malformed HFS+ catalog record.");
+                continue;
+            }
+
+            let parent = be_u32(record, 2);
+            let name = String::from_utf16_lossy(
+                &(0..name_len)
+                    .map(|i| be_u16(record, 8 + i * 2))
+                    .collect::<Vec<_>>(),
+            );
+            let data = &record[key_len + 2..];
+
+            match be_u16(data, 0) {
+                | FOLDER_RECORD if data.len() >= 88 => {
+                    let folder_id = be_u32(data, 8);
+
+                    if folder_id == ROOT_FOLDER_ID {
+                        self.volume_name = Some(name.clone());
+                    } else if !is_private(&name) {
+                        entries.push((
+                            parent,
+                            name.clone(),
+                            folder_entry(
+                                mac_time(be_u32(data, 12)),
+                                mac_time(be_u32(data, 16)),
+                            ),
+                        ));
+                    }
+
+                    folders.insert(folder_id, CatalogFolder { parent, name });
+                }
+                | FILE_RECORD if data.len() >= 248 => {
+                    if is_private(&name) {
+                        continue;
+                    }
+
+                    let file_id = be_u32(data, 8);
+                    let data_fork = ForkData::parse(&data[88..168]);
+                    let resource_fork = ForkData::parse(&data[168..248]);
+
+                    let mut raw = [0; 32];
+                    raw.copy_from_slice(&data[48..80]);
+
+                    entries.push((
+                        parent,
+                        name,
+                        DiscEntry {
+                            path: PathBuf::new(),
+                            is_dir: false,
+                            size: data_fork.logical_size,
+                            data: self.fork_extents(
+                                &data_fork, file_id, DATA_FORK, &overflow,
+                            )?,
+                            resource_size: resource_fork.logical_size,
+                            resource: self.fork_extents(
+                                &resource_fork,
+                                file_id,
+                                RESOURCE_FORK,
+                                &overflow,
+                            )?,
+                            created: mac_time(be_u32(data, 12)),
+                            modified: mac_time(be_u32(data, 16)),
+                            finder_info: Some(FinderInfo { raw }),
+                        },
+                    ));
+                }
+                | _ => (),
+            }
+        }
+
+        Ok(resolve_paths(&folders, entries))
+    }
+
+    fn image(&mut self) -> &mut DiscImage
+    {
+        &mut self.image
+    }
+}
+
+/// Reads an HFS+ extent record: eight (start block, block count) pairs.
+fn large_extent_record(b: &[u8]) -> Vec<(u32, u32)>
+{
+    (0..8)
+        .map(|i| (be_u32(b, i * 8), be_u32(b, i * 8 + 4)))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// The hard link and journal bookkeeping entries have names beginning with
+/// NUL characters or ".HFS+ Private Directory Data", and are not user files.
+fn is_private(name: &str) -> bool
+{
+    name.starts_with('\0')
+        || name.starts_with(".HFS+ Private Directory Data")
+        || name.eq(".journal")
+        || name.eq(".journal_info_block")
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::disc_image::btree::tests::build_btree;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn catalog_key(
+        parent: u32,
+        name: &str,
+    ) -> Vec<u8>
+    {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let mut key = ((6 + units.len() * 2) as u16).to_be_bytes().to_vec();
+        key.extend(parent.to_be_bytes());
+        key.extend((units.len() as u16).to_be_bytes());
+        for u in units {
+            key.extend(u.to_be_bytes());
+        }
+        key
+    }
+
+    fn folder_record(
+        parent: u32,
+        name: &str,
+        id: u32,
+    ) -> Vec<u8>
+    {
+        let mut r = catalog_key(parent, name);
+        let mut data = vec![0; 88];
+        data[0..2].copy_from_slice(&FOLDER_RECORD.to_be_bytes());
+        data[8..12].copy_from_slice(&id.to_be_bytes());
+        r.extend(data);
+        r
+    }
+
+    fn file_record(
+        parent: u32,
+        name: &str,
+        id: u32,
+        block: u32,
+        len: u64,
+    ) -> Vec<u8>
+    {
+        let mut r = catalog_key(parent, name);
+        let mut data = vec![0; 248];
+        data[0..2].copy_from_slice(&FILE_RECORD.to_be_bytes());
+        data[8..12].copy_from_slice(&id.to_be_bytes());
+        data[48..56].copy_from_slice(b"APPLdemo");
+        data[88..96].copy_from_slice(&len.to_be_bytes());
+        data[104..108].copy_from_slice(&block.to_be_bytes());
+        data[108..112].copy_from_slice(&1u32.to_be_bytes());
+        r.extend(data);
+        r
+    }
+
+    /// A bare HFS+ volume with 4096 byte allocation blocks.  Blocks 1-2 hold
+    /// the catalog and block 3 the contents of the only file.
+    fn build_hfs_plus_volume() -> Vec<u8>
+    {
+        let mut image = vec![0; 4096 * 4];
+
+        let header = &mut image[1024..1536];
+        header[0..2].copy_from_slice(&HFS_PLUS_SIGNATURE.to_be_bytes());
+        header[40..44].copy_from_slice(&4096u32.to_be_bytes());
+        header[272..280].copy_from_slice(&8192u64.to_be_bytes());
+        header[288..292].copy_from_slice(&1u32.to_be_bytes());
+        header[292..296].copy_from_slice(&2u32.to_be_bytes());
+
+        let catalog = build_btree(
+            4096,
+            &[
+                folder_record(1, "Plus Disc", ROOT_FOLDER_ID),
+                file_record(
+                    ROOT_FOLDER_ID,
+                    "\0\0\0\0HFS+ Private Data",
+                    20,
+                    3,
+                    0,
+                ),
+                file_record(ROOT_FOLDER_ID, "Player", 21, 3, 6),
+            ],
+        );
+        image[4096..12288].copy_from_slice(&catalog);
+        image[12288..12294].copy_from_slice(b"binary");
+
+        image
+    }
+
+    #[test]
+    fn test_list()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_hfs_plus_volume()).unwrap();
+
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        assert!(HfsPlusVolume::detect(&mut image, 0));
+
+        let mut volume = HfsPlusVolume::open(image, 0).unwrap();
+        let entries = volume.entries().unwrap();
+
+        assert_eq!("Plus Disc", volume.volume_name());
+        assert_eq!(1, entries.len());
+        assert_eq!(PathBuf::from("Player"), entries[0].path);
+        assert_eq!(
+            b"demo",
+            &entries[0].finder_info.as_ref().unwrap().creator()
+        );
+        assert_eq!(b"binary".to_vec(), volume.read_data(&entries[0]).unwrap());
+    }
+}
@@ -0,0 +1,1265 @@
+use super::{
+    civil_time,
+    le_u16,
+    le_u32,
+    path_component,
+    DiscEntry,
+    DiscFilesystem,
+    DiscImage,
+    Extent,
+    VolumeInfo,
+};
+use log::{
+    debug,
+    warn,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    path::PathBuf,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+/// ISO 9660 logical sectors are always 2048 bytes on optical media.
+pub const SECTOR_SIZE: u64 = 2048;
+
+/// The volume descriptor set starts at sector 16, after the system area.
+const FIRST_DESCRIPTOR_SECTOR: u64 = 16;
+
+/// The most sectors between the end of one session's volume and the start
+/// of the next on a multisession CD: the first session's lead-out (6750),
+/// the next lead-in (4500) and pregap (150), with room for run-out blocks and
+/// padding.  Later sessions have a shorter lead-out.
+const MAX_SESSION_GAP: u64 = 6750 + 4500 + 150 + 600;
+
+/// Sectors read at once while looking for the next session.
+const SESSION_SEARCH_CHUNK: u64 = 64;
+
+/// Upper bound on descriptors read before giving up on a terminator.
+const MAX_DESCRIPTORS: u64 = 64;
+
+const STANDARD_IDENTIFIER: &[u8; 5] = b"CD001";
+
+const BOOT_RECORD: u8 = 0;
+const PRIMARY_VOLUME_DESCRIPTOR: u8 = 1;
+const SUPPLEMENTARY_VOLUME_DESCRIPTOR: u8 = 2;
+const VOLUME_DESCRIPTOR_SET_TERMINATOR: u8 = 255;
+
+const EL_TORITO_IDENTIFIER: &[u8] = b"EL TORITO SPECIFICATION";
+
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_ASSOCIATED: u8 = 0x04;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// The location and size of a directory or file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectoryExtent
+{
+    pub sector: u32,
+    pub size: u32,
+}
+
+/// A primary or supplementary (Joliet) volume descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeDescriptor
+{
+    /// The Joliet level (1-3) for supplementary descriptors using UCS-2
+    /// names, or `None` for the primary descriptor.
+    pub joliet_level: Option<u8>,
+    pub system_id: String,
+    pub volume_id: String,
+    pub volume_set_id: String,
+    pub publisher_id: String,
+    pub preparer_id: String,
+    pub application_id: String,
+    pub volume_space_size: u32,
+    pub logical_block_size: u16,
+    pub root: DirectoryExtent,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+}
+
+impl VolumeDescriptor
+{
+    fn parse(
+        sector: &[u8],
+        joliet_level: Option<u8>,
+    ) -> VolumeDescriptor
+    {
+        let text = |at: usize, len: usize| {
+            identifier(&sector[at..at + len], joliet_level.is_some())
+        };
+
+        let root = &sector[156..190];
+
+        VolumeDescriptor {
+            joliet_level,
+            system_id: text(8, 32),
+            volume_id: text(40, 32),
+            volume_set_id: text(190, 128),
+            publisher_id: text(318, 128),
+            preparer_id: text(446, 128),
+            application_id: text(574, 128),
+            volume_space_size: le_u32(sector, 80),
+            logical_block_size: le_u16(sector, 128),
+            root: DirectoryExtent {
+                sector: le_u32(root, 2),
+                size: le_u32(root, 10),
+            },
+            created: descriptor_time(&sector[813..830]),
+            modified: descriptor_time(&sector[830..847]),
+        }
+    }
+}
+
+/// The El Torito boot record and the default entry of its boot catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElTorito
+{
+    pub catalog_sector: u32,
+    pub platform: String,
+    pub bootable: bool,
+    pub media_type: String,
+    pub load_sector: u32,
+    pub sector_count: u16,
+}
+
+/// Everything found in the volume descriptor set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeDescriptors
+{
+    pub primary: Option<VolumeDescriptor>,
+    pub joliet: Option<VolumeDescriptor>,
+    pub el_torito: Option<ElTorito>,
+}
+
+/// One session of a multisession disc.  Each session records the whole
+/// filesystem as it stood when the session was written, so files which were
+/// later replaced or deleted can still be found in earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session
+{
+    /// Counted from 1.
+    pub number: usize,
+    /// The sector the session starts at, whose volume descriptors are 16
+    /// sectors in.
+    pub start: u64,
+    /// The sector after the session's volume, from its volume space size.
+    /// Sector numbers are from the start of the disc in every session.
+    pub end: u64,
+    pub volume_id: String,
+    pub created: Option<SystemTime>,
+}
+
+/// Reads the ISO 9660 volume descriptor set of the first (or only) session.
+/// Returns `None` when the image has no ISO 9660 descriptors at all.
+pub fn read_volume_descriptors(
+    image: &mut DiscImage
+) -> Result<Option<VolumeDescriptors>, Box<dyn Error>>
+{
+    read_volume_descriptors_at(image, 0)
+}
+
+/// Reads the volume descriptor set of the session starting at sector
+/// `session_start`.
+pub fn read_volume_descriptors_at(
+    image: &mut DiscImage,
+    session_start: u64,
+) -> Result<Option<VolumeDescriptors>, Box<dyn Error>>
+{
+    let mut found = VolumeDescriptors::default();
+    let mut any = false;
+
+    for n in 0..MAX_DESCRIPTORS {
+        let at = (session_start + FIRST_DESCRIPTOR_SECTOR + n) * SECTOR_SIZE;
+
+        if at + SECTOR_SIZE > image.size() {
+            break;
+        }
+
+        let sector = image.read_at(at, SECTOR_SIZE as usize)?;
+
+        if &sector[1..6] != STANDARD_IDENTIFIER {
+            break;
+        }
+        any = true;
+
+        match sector[0] {
+            | BOOT_RECORD if sector[7..].starts_with(EL_TORITO_IDENTIFIER) => {
+                found.el_torito = read_boot_catalog(image, le_u32(&sector, 71))
+                    .map_err(|e| debug!("Unreadable El Torito catalog: {e}"))
+                    .ok();
+            }
+            | PRIMARY_VOLUME_DESCRIPTOR if found.primary.is_none() => {
+                found.primary = Some(VolumeDescriptor::parse(&sector, None));
+            }
+            | SUPPLEMENTARY_VOLUME_DESCRIPTOR if found.joliet.is_none() => {
+                if let Some(level) = joliet_level(&sector[88..120]) {
+                    found.joliet =
+                        Some(VolumeDescriptor::parse(&sector, Some(level)));
+                }
+            }
+            | VOLUME_DESCRIPTOR_SET_TERMINATOR => break,
+            | _ => (),
+        }
+    }
+
+    Ok(any.then_some(found))
+}
+
+/// Finds each session of the ISO 9660 filesystem in the image, as a
+/// multisession CD-R appends them.  The next session is looked for in the gap
+/// after each session's volume, so an image of a single-session disc is not
+/// read past its volume.  Returns no sessions when there is no primary volume
+/// descriptor.
+pub fn read_sessions(
+    image: &mut DiscImage
+) -> Result<Vec<Session>, Box<dyn Error>>
+{
+    let mut sessions: Vec<Session> = vec![];
+    let mut start = Some(0);
+
+    while let Some(session_start) = start {
+        let Some(primary) = read_volume_descriptors_at(image, session_start)?
+            .and_then(|d| d.primary)
+        else {
+            break;
+        };
+
+        let end = primary.volume_space_size as u64;
+        if end <= session_start {
+            debug!(
+                "Session at sector {session_start} ends before it starts, at \
+                 {end}."
+            );
+            break;
+        }
+
+        sessions.push(Session {
+            number: sessions.len() + 1,
+            start: session_start,
+            end,
+            volume_id: primary.volume_id,
+            created: primary.created,
+        });
+
+        start = find_next_session(image, end)?;
+    }
+
+    Ok(sessions)
+}
+
+/// The start of the session whose primary volume descriptor lies in the gap
+/// after a session ending at `end`, if any.
+fn find_next_session(
+    image: &mut DiscImage,
+    end: u64,
+) -> Result<Option<u64>, Box<dyn Error>>
+{
+    let first = end + FIRST_DESCRIPTOR_SECTOR;
+    let last = (end + MAX_SESSION_GAP + FIRST_DESCRIPTOR_SECTOR)
+        .min(image.size() / SECTOR_SIZE);
+
+    let mut sector = first;
+    while sector < last {
+        let count = SESSION_SEARCH_CHUNK.min(last - sector);
+        let data = image
+            .read_at(sector * SECTOR_SIZE, (count * SECTOR_SIZE) as usize)?;
+
+        if let Some(i) = data.chunks(SECTOR_SIZE as usize).position(|d| {
+            d[0] == PRIMARY_VOLUME_DESCRIPTOR && &d[1..6] == STANDARD_IDENTIFIER
+        }) {
+            return Ok(Some(sector + i as u64 - FIRST_DESCRIPTOR_SECTOR));
+        }
+
+        sector += count;
+    }
+
+    Ok(None)
+}
+
+fn read_boot_catalog(
+    image: &mut DiscImage,
+    catalog_sector: u32,
+) -> Result<ElTorito, Box<dyn Error>>
+{
+    let catalog = image.read_at(catalog_sector as u64 * SECTOR_SIZE, 64)?;
+
+    // The validation entry must have header ID 1 and end in 55 AA.
+    if catalog[0] != 1 || catalog[30..32] != [0x55, 0xAA] {
+        return Err("Boot catalog validation entry is invalid.".into());
+    }
+
+    let platform = match catalog[1] {
+        | 0x00 => "x86".to_string(),
+        | 0x01 => "PowerPC".to_string(),
+        | 0x02 => "Mac".to_string(),
+        | 0xEF => "UEFI".to_string(),
+        | p => format!("Unknown (0x{p:02X})"),
+    };
+
+    let entry = &catalog[32..64];
+
+    let media_type = match entry[1] & 0x0F {
+        | 0 => "No emulation",
+        | 1 => "1.2MB floppy",
+        | 2 => "1.44MB floppy",
+        | 3 => "2.88MB floppy",
+        | 4 => "Hard disk",
+        | _ => "Unknown",
+    }
+    .to_string();
+
+    Ok(ElTorito {
+        catalog_sector,
+        platform,
+        bootable: entry[0] == 0x88,
+        media_type,
+        load_sector: le_u32(entry, 8),
+        sector_count: le_u16(entry, 6),
+    })
+}
+
+/// Whether the root directory's "." record carries Rock Ridge System Use
+/// entries, which give POSIX names, permissions, and links.
+pub fn has_rock_ridge(
+    image: &mut DiscImage,
+    root: DirectoryExtent,
+) -> Result<bool, Box<dyn Error>>
+{
+    let sector = image.read_at(root.sector as u64 * SECTOR_SIZE, 255)?;
+    let record = &sector[..(sector[0] as usize).max(33)];
+
+    let mut sharing_protocol = false;
+
+    for (signature, data) in system_use_entries(record) {
+        match signature {
+            | b"SP" if data.starts_with(&[0xBE, 0xEF]) => {
+                sharing_protocol = true
+            }
+            | b"RR" | b"ER" | b"PX" | b"NM" if sharing_protocol => {
+                return Ok(true)
+            }
+            | _ => (),
+        }
+    }
+
+    Ok(false)
+}
+
+/// The System Use Sharing Protocol entries (signature, data after the four
+/// byte header) of a directory record.  Continuation areas are not followed.
+fn system_use_entries(record: &[u8]) -> Vec<(&[u8], &[u8])>
+{
+    let name_length = record[32] as usize;
+
+    // The System Use area follows the name, padded to an even offset.
+    let mut at = 33 + name_length + (name_length + 1) % 2;
+    let mut entries = vec![];
+
+    while at + 4 <= record.len() {
+        let length = record[at + 2] as usize;
+        if length < 4 || at + length > record.len() {
+            break;
+        }
+
+        let signature = &record[at..at + 2];
+        if signature == b"ST" {
+            break;
+        }
+
+        entries.push((signature, &record[at + 4..at + length]));
+        at += length;
+    }
+
+    entries
+}
+
+/// An ISO 9660 volume read through its Joliet tree where present, or the
+/// primary tree with Rock Ridge names where those are recorded.
+pub struct Iso9660Volume
+{
+    image: DiscImage,
+    descriptor: VolumeDescriptor,
+    rock_ridge: bool,
+}
+
+impl Iso9660Volume
+{
+    /// Opens the ISO 9660 volume in the image, if it has one.  On a
+    /// multisession disc this is the last session's, as a mount would show.
+    pub fn open(
+        mut image: DiscImage
+    ) -> Result<Option<Iso9660Volume>, Box<dyn Error>>
+    {
+        let start = read_sessions(&mut image)?.last().map_or(0, |s| s.start);
+
+        Iso9660Volume::open_session(image, start)
+    }
+
+    /// Opens the volume of the session starting at sector `session_start`.
+    pub fn open_session(
+        mut image: DiscImage,
+        session_start: u64,
+    ) -> Result<Option<Iso9660Volume>, Box<dyn Error>>
+    {
+        let Some(descriptors) =
+            read_volume_descriptors_at(&mut image, session_start)?
+        else {
+            return Ok(None);
+        };
+
+        let Some(primary) = descriptors.primary else {
+            return Ok(None);
+        };
+
+        // Rock Ridge names are the most faithful, being the originals from
+        // the Unix system which mastered the disc.  Joliet names come next.
+        let rock_ridge = has_rock_ridge(&mut image, primary.root)
+            .unwrap_or_else(|e| {
+                warn!("Could not check for Rock Ridge: {e}");
+                false
+            });
+
+        let descriptor = match (rock_ridge, descriptors.joliet) {
+            | (false, Some(joliet)) => joliet,
+            | _ => primary,
+        };
+
+        Ok(Some(Iso9660Volume {
+            image,
+            descriptor,
+            rock_ridge,
+        }))
+    }
+
+    fn read_directory(
+        &mut self,
+        extent: DirectoryExtent,
+    ) -> Result<Vec<DirectoryRecord>, Box<dyn Error>>
+    {
+        let data = self.image.read_at(
+            extent.sector as u64 * SECTOR_SIZE,
+            extent.size as usize,
+        )?;
+
+        let mut records: Vec<DirectoryRecord> = vec![];
+        let mut at = 0;
+
+        while at < data.len() {
+            let length = data[at] as usize;
+
+            // Records do not span sectors; a zero length pads to the next.
+            if length == 0 {
+                at = (at / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+                continue;
+            }
+
+            if length < 34 || at + length > data.len() {
+                return Err(format!(
+                    "Directory record at sector {} offset {at} is invalid.",
+                    extent.sector
+                )
+                .into());
+            }
+
+            let record = &data[at..at + length];
+            at += length;
+
+            let name_length = record[32] as usize;
+            let raw_name = &record[33..(33 + name_length).min(length)];
+            let flags = record[25];
+
+            // Skip "." and "..", and associated files (such as the Apple
+            // extensions' resource forks).
+            if raw_name == [0]
+                || raw_name == [1]
+                || flags & FLAG_ASSOCIATED != 0
+            {
+                continue;
+            }
+
+            let name = match self.rock_ridge {
+                | true => rock_ridge_name(record),
+                | false => None,
+            }
+            .unwrap_or_else(|| {
+                file_name(raw_name, self.descriptor.joliet_level.is_some())
+            });
+
+            let extent = Extent {
+                offset: le_u32(record, 2) as u64 * SECTOR_SIZE,
+                length: le_u32(record, 10) as u64,
+            };
+
+            // Files over 4GB are split across records with the same name,
+            // all but the last flagged as multi-extent.
+            match records.last_mut() {
+                | Some(previous)
+                    if previous.continues && previous.name == name =>
+                {
+                    previous.extents.push(extent);
+                    previous.continues = flags & FLAG_MULTI_EXTENT != 0;
+                }
+                | _ => {
+                    records.push(DirectoryRecord {
+                        name,
+                        is_dir: flags & FLAG_DIRECTORY != 0,
+                        extents: vec![extent],
+                        continues: flags & FLAG_MULTI_EXTENT != 0,
+                        recorded: record_time(&record[18..25]),
+                    })
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+impl DiscFilesystem for Iso9660Volume
+{
+    fn filesystem_name(&self) -> &'static str
+    {
+        match (self.rock_ridge, self.descriptor.joliet_level) {
+            | (true, _) => "ISO 9660 (Rock Ridge)",
+            | (false, Some(_)) => "ISO 9660 (Joliet)",
+            | (false, None) => "ISO 9660",
+        }
+    }
+
+    fn volume_name(&self) -> String
+    {
+        self.descriptor.volume_id.clone()
+    }
+
+    fn volume_info(&self) -> VolumeInfo
+    {
+        VolumeInfo {
+            created: self.descriptor.created,
+            modified: self.descriptor.modified,
+            block_size: self.descriptor.logical_block_size as u64,
+            block_count: self.descriptor.volume_space_size as u64,
+        }
+    }
+
+    fn entries(&mut self) -> Result<Vec<DiscEntry>, Box<dyn Error>>
+    {
+        let mut entries = vec![];
+        let mut visited = HashSet::new();
+        let mut pending = vec![(PathBuf::new(), self.descriptor.root)];
+
+        while let Some((path, extent)) = pending.pop() {
+            // Guard against directories which loop back on themselves.
+            if !visited.insert(extent.sector) {
+                warn!("ISO 9660 directory {path:?} was already read.");
+                continue;
+            }
+
+            let mut children = vec![];
+
+            for record in self.read_directory(extent)? {
+                let entry = DiscEntry {
+                    path: path.join(&record.name),
+                    is_dir: record.is_dir,
+                    size: record.extents.iter().map(|e| e.length).sum(),
+                    data: record.extents,
+                    resource_size: 0,
+                    resource: vec![],
+                    created: None,
+                    modified: record.recorded,
+                    finder_info: None,
+                };
+
+                if entry.is_dir {
+                    children.push((
+                        entry.path.clone(),
+                        DirectoryExtent {
+                            sector: (entry.data[0].offset / SECTOR_SIZE) as u32,
+                            size: entry.size as u32,
+                        },
+                    ));
+                }
+
+                entries.push(entry);
+            }
+
+            // Read subdirectories in name order.
+            pending.extend(children.into_iter().rev());
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(entries)
+    }
+
+    fn image(&mut self) -> &mut DiscImage
+    {
+        &mut self.image
+    }
+}
+
+struct DirectoryRecord
+{
+    name: String,
+    is_dir: bool,
+    extents: Vec<Extent>,
+    continues: bool,
+    recorded: Option<SystemTime>,
+}
+
+/// The Rock Ridge alternate name (NM entries) of a directory record.
+fn rock_ridge_name(record: &[u8]) -> Option<String>
+{
+    let name: Vec<u8> = system_use_entries(record)
+        .into_iter()
+        .filter(|(signature, data)| *signature == b"NM" && data.len() > 1)
+        .flat_map(|(_, data)| data[1..].to_vec())
+        .collect();
+
+    match name.is_empty() {
+        | true => None,
+        | false => Some(path_component(&String::from_utf8_lossy(&name))),
+    }
+}
+
+/// Decodes a file identifier, dropping the ";1" version number and the
+/// trailing "." of names without an extension.
+fn file_name(
+    raw: &[u8],
+    ucs2: bool,
+) -> String
+{
+    let name = identifier(raw, ucs2);
+    let name = match name.rsplit_once(';') {
+        | Some((base, version))
+            if version.bytes().all(|c| c.is_ascii_digit()) =>
+        {
+            base
+        }
+        | _ => &name,
+    };
+
+    path_component(name.strip_suffix('.').unwrap_or(name))
+}
+
+/// The Joliet level indicated by a supplementary descriptor's escape
+/// sequences, if it is a Joliet descriptor.
+fn joliet_level(escape_sequences: &[u8]) -> Option<u8>
+{
+    match &escape_sequences[0..3] {
+        | b"%/@" => Some(1),
+        | b"%/C" => Some(2),
+        | b"%/E" => Some(3),
+        | _ => None,
+    }
+}
+
+/// Decodes a space-padded identifier, which is UCS-2 big-endian for Joliet.
+fn identifier(
+    b: &[u8],
+    ucs2: bool,
+) -> String
+{
+    let s = match ucs2 {
+        | true => {
+            String::from_utf16_lossy(
+                &b.chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        | false => String::from_utf8_lossy(b).to_string(),
+    };
+
+    s.trim_end_matches([' ', '\0']).to_string()
+}
+
+/// Parses the 17 byte "YYYYMMDDHHMMSScc" plus time zone format used in
+/// volume descriptors.  All zeros means "not specified".
+fn descriptor_time(b: &[u8]) -> Option<SystemTime>
+{
+    let digits = std::str::from_utf8(&b[0..16]).ok()?;
+    if !digits.bytes().all(|c| c.is_ascii_digit()) || digits[0..4].eq("0000") {
+        return None;
+    }
+
+    let field = |r: std::ops::Range<usize>| digits[r].parse::<u32>().ok();
+
+    let local = civil_time(
+        field(0..4)? as i64,
+        field(4..6)?,
+        field(6..8)?,
+        field(8..10)?,
+        field(10..12)?,
+        field(12..14)?,
+    )?;
+
+    Some(apply_offset(local, b[16] as i8))
+}
+
+/// Parses the 7 byte time used in directory records.
+fn record_time(b: &[u8]) -> Option<SystemTime>
+{
+    if b[0..6].iter().all(|c| *c == 0) {
+        return None;
+    }
+
+    let local = civil_time(
+        1900 + b[0] as i64,
+        b[1] as u32,
+        b[2] as u32,
+        b[3] as u32,
+        b[4] as u32,
+        b[5] as u32,
+    )?;
+
+    Some(apply_offset(local, b[6] as i8))
+}
+
+/// Converts a local time to UTC using an offset in 15 minute intervals.
+fn apply_offset(
+    local: SystemTime,
+    quarter_hours: i8,
+) -> SystemTime
+{
+    let offset = Duration::from_secs(quarter_hours.unsigned_abs() as u64 * 900);
+
+    match quarter_hours >= 0 {
+        | true => local - offset,
+        | false => local + offset,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+    use crate::disc_image::{
+        extract,
+        format_time,
+    };
+    use std::{
+        fs,
+        io::Write,
+    };
+    use tempfile::{
+        NamedTempFile,
+        TempDir,
+    };
+
+    fn put(
+        sector: &mut [u8],
+        at: usize,
+        len: usize,
+        text: &str,
+    )
+    {
+        let mut field = text.as_bytes().to_vec();
+        field.resize(len, b' ');
+        sector[at..at + len].copy_from_slice(&field);
+    }
+
+    /// A volume descriptor with a root directory at sector 20.
+    pub(crate) fn volume_descriptor(
+        kind: u8,
+        volume_id: &str,
+        sectors: u32,
+    ) -> Vec<u8>
+    {
+        let mut d = vec![0; SECTOR_SIZE as usize];
+        d[0] = kind;
+        d[1..6].copy_from_slice(STANDARD_IDENTIFIER);
+        d[6] = 1;
+        put(&mut d, 8, 32, "");
+        put(&mut d, 40, 32, volume_id);
+        d[80..84].copy_from_slice(&sectors.to_le_bytes());
+        d[84..88].copy_from_slice(&sectors.to_be_bytes());
+        d[128..130].copy_from_slice(&2048u16.to_le_bytes());
+        d[156] = 34;
+        d[158..162].copy_from_slice(&20u32.to_le_bytes());
+        d[166..170].copy_from_slice(&2048u32.to_le_bytes());
+        d[181] = 2;
+        d[188] = 1;
+        put(&mut d, 190, 128, "");
+        put(&mut d, 318, 128, "CALIFORNIA REVEALED");
+        put(&mut d, 446, 128, "");
+        put(&mut d, 574, 128, "TOAST");
+        d[813..830].copy_from_slice(b"1996040112000000\x00");
+        d[830..847].copy_from_slice(b"1996040213000000\x04");
+        d
+    }
+
+    pub(crate) fn joliet_descriptor(volume_id: &str) -> Vec<u8>
+    {
+        let mut d = volume_descriptor(SUPPLEMENTARY_VOLUME_DESCRIPTOR, "", 100);
+        d[88..91].copy_from_slice(b"%/E");
+        let mut units: Vec<u8> = volume_id
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        units.resize(32, 0);
+        d[40..72].copy_from_slice(&units);
+        d
+    }
+
+    pub(crate) fn terminator() -> Vec<u8>
+    {
+        let mut d = vec![0; SECTOR_SIZE as usize];
+        d[0] = VOLUME_DESCRIPTOR_SET_TERMINATOR;
+        d[1..6].copy_from_slice(STANDARD_IDENTIFIER);
+        d
+    }
+
+    fn record(
+        name: &[u8],
+        sector: u32,
+        size: u32,
+        flags: u8,
+        system_use: &[u8],
+    ) -> Vec<u8>
+    {
+        let mut r = vec![0; 33];
+        r[2..6].copy_from_slice(&sector.to_le_bytes());
+        r[6..10].copy_from_slice(&sector.to_be_bytes());
+        r[10..14].copy_from_slice(&size.to_le_bytes());
+        r[14..18].copy_from_slice(&size.to_be_bytes());
+        r[18..25].copy_from_slice(&[96, 4, 1, 12, 0, 0, 0]);
+        r[25] = flags;
+        r[28] = 1;
+        r[32] = name.len() as u8;
+        r.extend(name);
+        if r.len() % 2 == 1 {
+            r.push(0);
+        }
+        r.extend(system_use);
+        if r.len() % 2 == 1 {
+            r.push(0);
+        }
+        r[0] = r.len() as u8;
+        r
+    }
+
+    fn directory(records: Vec<Vec<u8>>) -> Vec<u8>
+    {
+        let mut d: Vec<u8> = records.concat();
+        d.resize(SECTOR_SIZE as usize, 0);
+        d
+    }
+
+    fn nm(name: &str) -> Vec<u8>
+    {
+        let mut e = vec![b'N', b'M', 5 + name.len() as u8, 1, 0];
+        e.extend(name.as_bytes());
+        e
+    }
+
+    fn ucs2(name: &str) -> Vec<u8>
+    {
+        name.encode_utf16().flat_map(|u| u.to_be_bytes()).collect()
+    }
+
+    /// A disc with a "README.TXT" file and a "DOCS" directory holding
+    /// "NOTES.TXT", recorded at 1996-04-01 12:00:00 UTC.  The primary tree
+    /// also has "BIG.BIN", split over two extents, and "BROKEN.DAT", whose
+    /// data lies past the end of the image.  With `joliet`, a Joliet tree
+    /// names them "Read Me.txt", "Docs" and "Notes.txt".  With
+    /// `rock_ridge`, the primary tree carries lowercase Rock Ridge names.
+    pub(crate) fn build_iso(
+        joliet: bool,
+        rock_ridge: bool,
+    ) -> Vec<u8>
+    {
+        let su = |name: &str| {
+            match rock_ridge {
+                | true => nm(name),
+                | false => vec![],
+            }
+        };
+        let dot = |sector, system_use: &[u8]| {
+            vec![
+                record(&[0], sector, 2048, FLAG_DIRECTORY, system_use),
+                record(&[1], 20, 2048, FLAG_DIRECTORY, &[]),
+            ]
+        };
+        let sharing_protocol = match rock_ridge {
+            | true => {
+                vec![
+                    b'S', b'P', 7, 1, 0xBE, 0xEF, 0, b'E', b'R', 8, 1, 0, 0, 0,
+                    1,
+                ]
+            }
+            | false => vec![],
+        };
+
+        let mut sectors = vec![vec![0; SECTOR_SIZE as usize]; 26];
+
+        sectors[16] = volume_descriptor(PRIMARY_VOLUME_DESCRIPTOR, "DISC", 26);
+        sectors[17] = terminator();
+        if joliet {
+            sectors[17] = joliet_descriptor("Disc");
+            sectors[17][158..162].copy_from_slice(&24u32.to_le_bytes());
+            sectors[18] = terminator();
+        }
+
+        let mut root = dot(20, &sharing_protocol);
+        root.extend([
+            record(b"BIG.BIN;1", 22, 2048, FLAG_MULTI_EXTENT, &su("big.bin")),
+            record(b"BIG.BIN;1", 23, 5, 0, &su("big.bin")),
+            record(b"BROKEN.DAT;1", 1000, 5, 0, &su("broken.dat")),
+            record(b"DOCS", 21, 2048, FLAG_DIRECTORY, &su("docs")),
+            record(b"README.TXT;1", 22, 5, 0, &su("read me.txt")),
+        ]);
+        sectors[20] = directory(root);
+
+        let mut docs = dot(21, &[]);
+        docs.push(record(b"NOTES.TXT;1", 23, 5, 0, &su("notes.txt")));
+        sectors[21] = directory(docs);
+
+        sectors[22][0..5].copy_from_slice(b"hello");
+        sectors[23][0..5].copy_from_slice(b"notes");
+
+        let mut root = dot(24, &[]);
+        root.extend([
+            record(&ucs2("Docs"), 25, 2048, FLAG_DIRECTORY, &[]),
+            record(&ucs2("Read Me.txt;1"), 22, 5, 0, &[]),
+        ]);
+        sectors[24] = directory(root);
+
+        let mut docs = dot(25, &[]);
+        docs.push(record(&ucs2("Notes.txt;1"), 23, 5, 0, &[]));
+        sectors[25] = directory(docs);
+
+        sectors.concat()
+    }
+
+    /// The ISO 9660 track of a Video CD, with its "VCD/INFO.VCD" file.
+    pub(crate) fn build_video_cd() -> Vec<u8>
+    {
+        let dot = |sector| {
+            vec![
+                record(&[0], sector, 2048, FLAG_DIRECTORY, &[]),
+                record(&[1], 20, 2048, FLAG_DIRECTORY, &[]),
+            ]
+        };
+
+        let mut sectors = vec![vec![0; SECTOR_SIZE as usize]; 23];
+
+        sectors[16] =
+            volume_descriptor(PRIMARY_VOLUME_DESCRIPTOR, "VIDEOCD", 23);
+        sectors[17] = terminator();
+
+        let mut root = dot(20);
+        root.push(record(b"VCD", 21, 2048, FLAG_DIRECTORY, &[]));
+        sectors[20] = directory(root);
+
+        let mut vcd = dot(21);
+        vcd.push(record(b"INFO.VCD;1", 22, 8, 0, &[]));
+        sectors[21] = directory(vcd);
+
+        sectors[22][0..8].copy_from_slice(b"VIDEO_CD");
+
+        sectors.concat()
+    }
+
+    /// A multisession disc: `build_iso` as the first session, and a second
+    /// session starting at sector 176 whose volume, "DISC_2", keeps
+    /// "README.TXT" from the first session, drops the rest and adds
+    /// "NEW.TXT".
+    pub(crate) fn build_multisession() -> Vec<u8>
+    {
+        let start = 176;
+        let mut sectors: Vec<Vec<u8>> = build_iso(false, false)
+            .chunks(SECTOR_SIZE as usize)
+            .map(|c| c.to_vec())
+            .collect();
+        sectors.resize(start as usize + 22, vec![0; SECTOR_SIZE as usize]);
+
+        let mut descriptor =
+            volume_descriptor(PRIMARY_VOLUME_DESCRIPTOR, "DISC_2", start + 22);
+        descriptor[158..162].copy_from_slice(&(start + 20).to_le_bytes());
+        sectors[start as usize + 16] = descriptor;
+        sectors[start as usize + 17] = terminator();
+
+        sectors[start as usize + 20] = directory(vec![
+            record(&[0], start + 20, 2048, FLAG_DIRECTORY, &[]),
+            record(&[1], start + 20, 2048, FLAG_DIRECTORY, &[]),
+            record(b"NEW.TXT;1", start + 21, 3, 0, &[]),
+            record(b"README.TXT;1", 22, 5, 0, &[]),
+        ]);
+        sectors[start as usize + 21][0..3].copy_from_slice(b"new");
+
+        sectors.concat()
+    }
+
+    fn open_volume(image: Vec<u8>) -> (NamedTempFile, Iso9660Volume)
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&image).unwrap();
+
+        let image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        (file, Iso9660Volume::open(image).unwrap().unwrap())
+    }
+
+    fn paths(entries: &[DiscEntry]) -> Vec<String>
+    {
+        entries
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn open(sectors: Vec<Vec<u8>>) -> (NamedTempFile, DiscImage)
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&vec![0; 16 * SECTOR_SIZE as usize]).unwrap();
+        for s in sectors {
+            file.write_all(&s).unwrap();
+        }
+
+        let image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        (file, image)
+    }
+
+    #[test]
+    fn test_primary_and_joliet()
+    {
+        let (_file, mut image) = open(vec![
+            volume_descriptor(PRIMARY_VOLUME_DESCRIPTOR, "TORCH_RELAY", 100),
+            joliet_descriptor("Torch Relay"),
+            terminator(),
+        ]);
+
+        let found = read_volume_descriptors(&mut image).unwrap().unwrap();
+
+        let primary = found.primary.unwrap();
+        assert_eq!("TORCH_RELAY", primary.volume_id);
+        assert_eq!("CALIFORNIA REVEALED", primary.publisher_id);
+        assert_eq!("TOAST", primary.application_id);
+        assert_eq!(100, primary.volume_space_size);
+        assert_eq!(20, primary.root.sector);
+        assert_eq!(
+            "1996-04-01T12:00:00Z",
+            format_time(primary.created.unwrap())
+        );
+        // One hour ahead of UTC.
+        assert_eq!(
+            "1996-04-02T12:00:00Z",
+            format_time(primary.modified.unwrap())
+        );
+
+        let joliet = found.joliet.unwrap();
+        assert_eq!(Some(3), joliet.joliet_level);
+        assert_eq!("Torch Relay", joliet.volume_id);
+
+        assert_eq!(None, found.el_torito);
+    }
+
+    #[test]
+    fn test_el_torito()
+    {
+        let mut boot = vec![0; SECTOR_SIZE as usize];
+        boot[0] = BOOT_RECORD;
+        boot[1..6].copy_from_slice(STANDARD_IDENTIFIER);
+        boot[7..7 + EL_TORITO_IDENTIFIER.len()]
+            .copy_from_slice(EL_TORITO_IDENTIFIER);
+        boot[71..75].copy_from_slice(&19u32.to_le_bytes());
+
+        let mut catalog = vec![0; SECTOR_SIZE as usize];
+        catalog[0] = 1;
+        catalog[30..32].copy_from_slice(&[0x55, 0xAA]);
+        catalog[32] = 0x88;
+        catalog[38..40].copy_from_slice(&4u16.to_le_bytes());
+        catalog[40..44].copy_from_slice(&30u32.to_le_bytes());
+
+        let (_file, mut image) = open(vec![
+            volume_descriptor(PRIMARY_VOLUME_DESCRIPTOR, "BOOT", 100),
+            boot,
+            terminator(),
+            catalog,
+        ]);
+
+        let el_torito = read_volume_descriptors(&mut image)
+            .unwrap()
+            .unwrap()
+            .el_torito
+            .unwrap();
+
+        assert_eq!(
+            ElTorito {
+                catalog_sector: 19,
+                platform: "x86".to_string(),
+                bootable: true,
+                media_type: "No emulation".to_string(),
+                load_sector: 30,
+                sector_count: 4,
+            },
+            el_torito
+        );
+    }
+
+    #[test]
+    fn test_not_iso()
+    {
+        let (_file, mut image) = open(vec![vec![0; SECTOR_SIZE as usize]]);
+
+        assert_eq!(None, read_volume_descriptors(&mut image).unwrap());
+    }
+
+    #[test]
+    fn test_rock_ridge()
+    {
+        let mut root = vec![0; SECTOR_SIZE as usize];
+        let system_use = [b'S', b'P', 7, 1, 0xBE, 0xEF, 0, b'P', b'X', 4, 1];
+        root[0] = 34 + system_use.len() as u8;
+        root[32] = 1;
+        root[34..34 + system_use.len()].copy_from_slice(&system_use);
+
+        let mut plain = vec![0; SECTOR_SIZE as usize];
+        plain[0] = 34;
+        plain[32] = 1;
+
+        let (_file, mut image) = open(vec![root, plain]);
+        let extent = |sector| DirectoryExtent { sector, size: 2048 };
+
+        assert!(has_rock_ridge(&mut image, extent(16)).unwrap());
+        assert!(!has_rock_ridge(&mut image, extent(17)).unwrap());
+    }
+
+    #[test]
+    fn test_sessions()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_multisession()).unwrap();
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+
+        let sessions = read_sessions(&mut image).unwrap();
+
+        assert_eq!(2, sessions.len());
+        assert_eq!(
+            (1, 0, 26),
+            (sessions[0].number, sessions[0].start, sessions[0].end)
+        );
+        assert_eq!("DISC", sessions[0].volume_id);
+        assert_eq!((176, 198), (sessions[1].start, sessions[1].end));
+        assert_eq!("DISC_2", sessions[1].volume_id);
+
+        let mut last = Iso9660Volume::open(image).unwrap().unwrap();
+        assert_eq!("DISC_2", last.volume_name());
+        let entries = last.entries().unwrap();
+        assert_eq!(vec!["NEW.TXT", "README.TXT"], paths(&entries));
+        assert_eq!(b"hello".to_vec(), last.read_data(&entries[1]).unwrap());
+
+        let image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        let mut first = Iso9660Volume::open_session(image, 0).unwrap().unwrap();
+        assert_eq!(5, first.entries().unwrap().len());
+
+        let (_file, mut single) = open_volume(build_iso(false, false));
+        assert_eq!(1, read_sessions(&mut single.image).unwrap().len());
+    }
+
+    #[test]
+    fn test_primary_entries()
+    {
+        let (_file, mut volume) = open_volume(build_iso(false, false));
+        let entries = volume.entries().unwrap();
+
+        assert_eq!("ISO 9660", volume.filesystem_name());
+        assert_eq!("DISC", volume.volume_name());
+        assert_eq!(
+            vec![
+                "BIG.BIN",
+                "BROKEN.DAT",
+                "DOCS",
+                "DOCS/NOTES.TXT",
+                "README.TXT"
+            ],
+            paths(&entries)
+        );
+
+        let big = &entries[0];
+        assert_eq!(2053, big.size);
+        let data = volume.read_data(big).unwrap();
+        assert!(data.starts_with(b"hello"));
+        assert!(data.ends_with(b"notes"));
+
+        assert!(entries[2].is_dir);
+        assert_eq!(
+            "1996-04-01T12:00:00Z",
+            format_time(entries[4].modified.unwrap())
+        );
+        assert_eq!(b"notes".to_vec(), volume.read_data(&entries[3]).unwrap());
+    }
+
+    #[test]
+    fn test_joliet_entries()
+    {
+        let (_file, mut volume) = open_volume(build_iso(true, false));
+        let entries = volume.entries().unwrap();
+
+        assert_eq!("ISO 9660 (Joliet)", volume.filesystem_name());
+        assert_eq!("Disc", volume.volume_name());
+        assert_eq!(
+            vec!["Docs", "Docs/Notes.txt", "Read Me.txt"],
+            paths(&entries)
+        );
+        assert_eq!(b"hello".to_vec(), volume.read_data(&entries[2]).unwrap());
+    }
+
+    #[test]
+    fn test_rock_ridge_entries()
+    {
+        let (_file, mut volume) = open_volume(build_iso(true, true));
+        let entries = volume.entries().unwrap();
+
+        assert_eq!("ISO 9660 (Rock Ridge)", volume.filesystem_name());
+        assert_eq!(
+            vec![
+                "big.bin",
+                "broken.dat",
+                "docs",
+                "docs/notes.txt",
+                "read me.txt"
+            ],
+            paths(&entries)
+        );
+    }
+
+    #[test]
+    fn test_extract()
+    {
+        let (_file, mut volume) = open_volume(build_iso(false, false));
+        let out = TempDir::new().unwrap();
+        let to = out.path().to_path_buf();
+
+        let failures = extract(&mut volume, &to).unwrap();
+
+        assert_eq!(1, failures.len());
+        assert_eq!(PathBuf::from("BROKEN.DAT"), failures[0].path);
+
+        assert_eq!("hello", fs::read_to_string(to.join("README.TXT")).unwrap());
+        assert_eq!(
+            "notes",
+            fs::read_to_string(to.join("DOCS/NOTES.TXT")).unwrap()
+        );
+
+        let recorded = civil_time(1996, 4, 1, 12, 0, 0).unwrap();
+        for path in ["README.TXT", "DOCS"] {
+            let modified = fs::metadata(to.join(path)).unwrap().modified();
+            assert_eq!(recorded, modified.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_file_name()
+    {
+        assert_eq!("README.TXT", file_name(b"README.TXT;1", false));
+        assert_eq!("MAKEFILE", file_name(b"MAKEFILE.;1", false));
+        assert_eq!("A;B", file_name(b"A;B", false));
+        assert_eq!("a_b", file_name(&ucs2("a/b"), true));
+        assert_eq!("_..", path_component(".."));
+    }
+}
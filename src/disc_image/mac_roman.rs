@@ -0,0 +1,48 @@
+/// Characters for bytes 0x80 through 0xFF in the Mac OS Roman encoding,
+/// which classic Mac OS used for HFS names and type/creator codes.
+const HIGH_CHARACTERS: [char; 128] = [
+    'Ä', 'Å', 'Ç', 'É', 'Ñ', 'Ö', 'Ü', 'á', 'à', 'â', 'ä', 'ã', 'å', 'ç', 'é',
+    'è', 'ê', 'ë', 'í', 'ì', 'î', 'ï', 'ñ', 'ó', 'ò', 'ô', 'ö', 'õ', 'ú', 'ù',
+    'û', 'ü', '†', '°', '¢', '£', '§', '•', '¶', 'ß', '®', '©', '™', '´', '¨',
+    '≠', 'Æ', 'Ø', '∞', '±', '≤', '≥', '¥', 'µ', '∂', '∑', '∏', 'π', '∫', 'ª',
+    'º', 'Ω', 'æ', 'ø', '¿', '¡', '¬', '√', 'ƒ', '≈', '∆', '«', '»', '…',
+    '\u{a0}', 'À', 'Ã', 'Õ', 'Œ', 'œ', '–', '—', '“', '”', '‘', '’', '÷', '◊',
+    'ÿ', 'Ÿ', '⁄', '€', '‹', '›', 'ﬁ', 'ﬂ', '‡', '·', '‚', '„', '‰', 'Â', 'Ê',
+    'Á', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', 'Ó', 'Ô', '\u{f8ff}', 'Ò', 'Ú', 'Û',
+    'Ù', 'ı', 'ˆ', '˜', '¯', '˘', '˙', '˚', '¸', '˝', '˛', 'ˇ',
+];
+
+/// Decodes Mac OS Roman text into a `String`.
+pub fn decode(bytes: &[u8]) -> String
+{
+    bytes
+        .iter()
+        .map(|b| {
+            match b {
+                | 0x00..=0x7F => *b as char,
+                | _ => HIGH_CHARACTERS[(b - 0x80) as usize],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_ascii()
+    {
+        assert_eq!("TEXT", decode(b"TEXT"));
+    }
+
+    #[test]
+    fn test_high_characters()
+    {
+        assert_eq!("Ä", decode(&[0x80]));
+        assert_eq!("©", decode(&[0xA9]));
+        assert_eq!("ˇ", decode(&[0xFF]));
+        assert_eq!("Café", decode(&[b'C', b'a', b'f', 0x8E]));
+    }
+}
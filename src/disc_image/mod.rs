@@ -0,0 +1,904 @@
+pub mod apple_double;
+pub mod apple_partition_map;
+pub mod blu_ray;
+pub mod btree;
+pub mod dvd_video;
+pub mod fat;
+pub mod hfs;
+pub mod hfs_plus;
+pub mod iso9660;
+pub mod mac_roman;
+pub mod probe;
+pub mod udf;
+pub mod verify;
+pub mod video_cd;
+
+use log::{
+    debug,
+    warn,
+};
+use std::{
+    error::Error,
+    fs::{
+        self,
+        File,
+        FileTimes,
+    },
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+/// Size of the buffer used when streaming file contents out of an image.
+const COPY_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A disc or media image opened for random-access reads.
+pub struct DiscImage
+{
+    file: File,
+    size: u64,
+}
+
+impl DiscImage
+{
+    pub fn open(path: &PathBuf) -> Result<DiscImage, Box<dyn Error>>
+    {
+        let mut file = File::open(path)
+            .map_err(|e| format!("Could not open image {path:?}: {e}"))?;
+
+        // Block devices report a zero length in their metadata, so seek to
+        // the end instead.
+        let size = file.seek(SeekFrom::End(0))?;
+
+        Ok(DiscImage { file, size })
+    }
+
+    /// Total size of the image in bytes.
+    pub fn size(&self) -> u64
+    {
+        self.size
+    }
+
+    /// Reads exactly `len` bytes starting at `offset`.
+    pub fn read_at(
+        &mut self,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        if offset
+            .checked_add(len as u64)
+            .is_none_or(|end| end > self.size)
+        {
+            return Err(format!(
+                "Read of {len} bytes at offset {offset} is past the end of \
+                 the image ({} bytes).",
+                self.size
+            )
+            .into());
+        }
+
+        let mut buf = vec![0; len];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    /// Reads the first `size` bytes covered by `extents` into memory.
+    pub fn read_extents(
+        &mut self,
+        extents: &[Extent],
+        size: u64,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        // The size comes from the image, so may be corrupt.  No file can be
+        // larger than the image holding it.
+        if size > self.size {
+            return Err(format!(
+                "A recorded size of {size} bytes is larger than the image ({} \
+                 bytes).",
+                self.size
+            )
+            .into());
+        }
+
+        let mut out = Vec::with_capacity(size as usize);
+        self.copy_extents(extents, size, &mut out)?;
+
+        Ok(out)
+    }
+
+    /// Streams the first `size` bytes covered by `extents` to `out`.
+    pub fn copy_extents(
+        &mut self,
+        extents: &[Extent],
+        size: u64,
+        out: &mut dyn Write,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let mut remaining = size;
+
+        for extent in extents {
+            let mut pos = extent.offset;
+            let mut extent_remaining = extent.length.min(remaining);
+
+            while extent_remaining > 0 {
+                let chunk = extent_remaining.min(COPY_CHUNK_SIZE);
+                out.write_all(&self.read_at(pos, chunk as usize)?)?;
+
+                pos += chunk;
+                extent_remaining -= chunk;
+                remaining -= chunk;
+            }
+
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        if remaining > 0 {
+            return Err(format!(
+                "File extents ended {remaining} bytes before the recorded \
+                 file size."
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+/// A contiguous run of bytes within an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent
+{
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Classic Mac OS Finder information for a file: `FInfo` followed by
+/// `FXInfo`, as stored on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinderInfo
+{
+    pub raw: [u8; 32],
+}
+
+impl FinderInfo
+{
+    pub fn file_type(&self) -> [u8; 4]
+    {
+        [self.raw[0], self.raw[1], self.raw[2], self.raw[3]]
+    }
+
+    pub fn creator(&self) -> [u8; 4]
+    {
+        [self.raw[4], self.raw[5], self.raw[6], self.raw[7]]
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.raw.iter().all(|b| *b == 0)
+    }
+}
+
+/// A single file or directory found on a filesystem within an image.
+#[derive(Debug, Clone)]
+pub struct DiscEntry
+{
+    /// Location relative to the root of the volume.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Logical size of the data fork (or the only stream) in bytes.
+    pub size: u64,
+    pub data: Vec<Extent>,
+    /// Logical size of the resource fork in bytes, if the filesystem has
+    /// them.
+    pub resource_size: u64,
+    pub resource: Vec<Extent>,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    pub finder_info: Option<FinderInfo>,
+}
+
+/// Dates and sizes recorded in a volume's header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeInfo
+{
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    /// Size of the filesystem's allocation unit (block or cluster) in bytes.
+    pub block_size: u64,
+    pub block_count: u64,
+}
+
+/// A filesystem found within an image which can list and read its files.
+pub trait DiscFilesystem
+{
+    /// Short name of the filesystem, such as "HFS+".
+    fn filesystem_name(&self) -> &'static str;
+
+    fn volume_name(&self) -> String;
+
+    fn volume_info(&self) -> VolumeInfo;
+
+    /// All files and directories on the volume, parents before children.
+    fn entries(&mut self) -> Result<Vec<DiscEntry>, Box<dyn Error>>;
+
+    fn image(&mut self) -> &mut DiscImage;
+
+    fn read_data(
+        &mut self,
+        entry: &DiscEntry,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        self.image().read_extents(&entry.data, entry.size)
+    }
+
+    fn read_resource(
+        &mut self,
+        entry: &DiscEntry,
+    ) -> Result<Vec<u8>, Box<dyn Error>>
+    {
+        self.image()
+            .read_extents(&entry.resource, entry.resource_size)
+    }
+}
+
+/// Opens the filesystem in the image which carroh can read directly.  FAT
+/// media is preferred, followed by the Mac side of the image, and then the
+/// ISO 9660 volume.
+pub fn open_filesystem(
+    path: &PathBuf
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    if let Some(volume) = open_fat_volume(path)? {
+        return Ok(Some(volume));
+    }
+
+    if let Some(volume) = open_mac_volume(path)? {
+        return Ok(Some(volume));
+    }
+
+    open_iso9660_volume(path)
+}
+
+/// Opens the files common to every platform on the media: the FAT volume
+/// of a floppy or flash drive, or else the ISO 9660 volume of a disc.
+pub fn open_file_copy_volume(
+    path: &PathBuf
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    if let Some(volume) = open_fat_volume(path)? {
+        return Ok(Some(volume));
+    }
+
+    open_iso9660_volume(path)
+}
+
+/// Opens the ISO 9660 volume in the image, if it has one, using Rock Ridge
+/// or Joliet names where they are recorded.
+pub fn open_iso9660_volume(
+    path: &PathBuf
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    let image = DiscImage::open(path)?;
+
+    match iso9660::Iso9660Volume::open(image)? {
+        | Some(volume) => {
+            debug!("Found an {} volume", volume.filesystem_name());
+            Ok(Some(Box::new(volume)))
+        }
+        | None => Ok(None),
+    }
+}
+
+/// Opens the ISO 9660 volume of the session of a multisession image which
+/// starts at sector `session_start`.
+pub fn open_iso9660_session(
+    path: &PathBuf,
+    session_start: u64,
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    let image = DiscImage::open(path)?;
+
+    match iso9660::Iso9660Volume::open_session(image, session_start)? {
+        | Some(volume) => Ok(Some(Box::new(volume))),
+        | None => Ok(None),
+    }
+}
+
+/// Lists the sessions of the ISO 9660 filesystem in the image, oldest first.
+pub fn read_iso9660_sessions(
+    path: &PathBuf
+) -> Result<Vec<iso9660::Session>, Box<dyn Error>>
+{
+    iso9660::read_sessions(&mut DiscImage::open(path)?)
+}
+
+/// Opens the FAT12/16/32 volume in the image, if it has one.  Both bare
+/// (floppy) and MBR-partitioned (flash drive) images are recognized.
+pub fn open_fat_volume(
+    path: &PathBuf
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    let mut image = DiscImage::open(path)?;
+
+    match fat::FatVolume::find(&mut image) {
+        | Some(offset) => {
+            debug!("Found a FAT volume at offset {offset}");
+            Ok(Some(Box::new(fat::FatVolume::open(image, offset)?)))
+        }
+        | None => Ok(None),
+    }
+}
+
+/// Opens the Mac side (HFS or HFS+) of the image, if it has one.  Both
+/// partitioned (Apple partition map) and bare volumes are recognized.
+pub fn open_mac_volume(
+    path: &PathBuf
+) -> Result<Option<Box<dyn DiscFilesystem>>, Box<dyn Error>>
+{
+    let mut image = DiscImage::open(path)?;
+
+    let mut offsets: Vec<u64> =
+        apple_partition_map::read_partitions(&mut image)?
+            .into_iter()
+            .filter(|p| p.is_hfs())
+            .map(|p| p.offset)
+            .collect();
+    offsets.push(0);
+
+    for offset in offsets {
+        if hfs_plus::HfsPlusVolume::detect(&mut image, offset) {
+            debug!("Found an HFS+ volume at offset {offset}");
+            let volume = hfs_plus::HfsPlusVolume::open(image, offset)?;
+            return Ok(Some(Box::new(volume)));
+        }
+
+        if hfs::HfsVolume::detect(&mut image, offset) {
+            debug!("Found an HFS volume at offset {offset}");
+            let volume = hfs::HfsVolume::open(image, offset)?;
+
+            // HFS+ volumes are commonly wrapped in an HFS volume for the
+            // benefit of older systems.  Prefer the wrapped volume.
+            return match volume.embedded_volume_offset() {
+                | Some(embedded) => {
+                    debug!("Using HFS+ volume embedded at offset {embedded}");
+                    Ok(Some(Box::new(hfs_plus::HfsPlusVolume::open(
+                        volume.into_image(),
+                        embedded,
+                    )?)))
+                }
+                | None => Ok(Some(Box::new(volume))),
+            };
+        }
+    }
+
+    Ok(None)
+}
+
+/// Produces a human-readable listing of the entries, including the Mac
+/// type and creator codes where available.
+pub fn format_listing(entries: &[DiscEntry]) -> String
+{
+    entries
+        .iter()
+        .map(|e| {
+            let (file_type, creator) = match &e.finder_info {
+                | Some(fi) if !e.is_dir => {
+                    (
+                        mac_roman::decode(&fi.file_type()),
+                        mac_roman::decode(&fi.creator()),
+                    )
+                }
+                | _ => ("    ".to_string(), "    ".to_string()),
+            };
+
+            let path = e.path.to_string_lossy();
+
+            if e.is_dir {
+                format!("{file_type} {creator} {:>12} {:>12} {path}/", "", "")
+            } else {
+                format!(
+                    "{file_type} {creator} {:>12} {:>12} {path}",
+                    e.size, e.resource_size
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A file or directory which could not be extracted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractFailure
+{
+    /// Location relative to the root of the volume.
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Writes every entry of the filesystem below `to`, keeping the recorded
+/// modification times.  Resource forks and Finder information, where the
+/// filesystem has them, are written alongside each file as AppleDouble
+/// (`._name`) files.
+///
+/// A file which cannot be read or written does not stop the extraction.  It
+/// is reported in the returned list instead.
+pub fn extract(
+    fs: &mut dyn DiscFilesystem,
+    to: &PathBuf,
+) -> Result<Vec<ExtractFailure>, Box<dyn Error>>
+{
+    let entries = fs.entries()?;
+
+    fs::create_dir_all(to)?;
+
+    let mut failures = vec![];
+    let mut directories = vec![];
+
+    for entry in entries {
+        let mut target = to.clone();
+        target.push(&entry.path);
+
+        let result = match entry.is_dir {
+            | true => fs::create_dir_all(&target).map_err(|e| e.into()),
+            | false => extract_file(fs, &entry, &target),
+        };
+
+        if let Err(e) = result {
+            warn!("Could not extract {:?}: {e}", entry.path);
+            failures.push(ExtractFailure {
+                path: entry.path,
+                error: e.to_string(),
+            });
+            continue;
+        }
+
+        match entry.is_dir {
+            | true => directories.push((target, entry.modified)),
+            | false => set_modified(&target, entry.modified),
+        }
+    }
+
+    // Writing a directory's contents changes its modification time, so
+    // directories are dated last, deepest first.
+    for (target, modified) in directories.into_iter().rev() {
+        set_modified(&target, modified);
+    }
+
+    Ok(failures)
+}
+
+/// Copies the files and directories below `from`, such as a mounted disc,
+/// to `to`, keeping their modification times.  Symbolic links are copied as
+/// links.
+///
+/// As with `extract`, an entry which cannot be copied does not stop the
+/// copy, and is reported in the returned list instead.
+pub fn copy_tree(
+    from: &Path,
+    to: &Path,
+) -> Result<Vec<ExtractFailure>, Box<dyn Error>>
+{
+    fs::create_dir_all(to)?;
+
+    let mut failures = vec![];
+    let mut directories = vec![];
+    let mut pending = vec![PathBuf::new()];
+
+    while let Some(dir) = pending.pop() {
+        let listing = match fs::read_dir(from.join(&dir)) {
+            | Ok(listing) => listing,
+            | Err(e) => {
+                warn!("Could not list {dir:?}: {e}");
+                failures.push(ExtractFailure {
+                    path: dir,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        for entry in listing {
+            let path = match entry {
+                | Ok(entry) => dir.join(entry.file_name()),
+                | Err(e) => {
+                    warn!("Could not list {dir:?}: {e}");
+                    failures.push(ExtractFailure {
+                        path: dir.clone(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let target = to.join(&path);
+
+            match copy_entry(&from.join(&path), &target) {
+                | Ok(Some(modified)) => {
+                    directories.push((target, modified));
+                    pending.push(path);
+                }
+                | Ok(None) => (),
+                | Err(e) => {
+                    warn!("Could not copy {path:?}: {e}");
+                    failures.push(ExtractFailure {
+                        path,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // As in `extract`, directories are dated last, deepest first.
+    for (target, modified) in directories.into_iter().rev() {
+        set_modified(&target, modified);
+    }
+
+    Ok(failures)
+}
+
+/// Copies a file or symbolic link, or creates a directory, returning the
+/// directory's modification time so that it can be set once it is filled.
+fn copy_entry(
+    source: &Path,
+    target: &PathBuf,
+) -> Result<Option<Option<SystemTime>>, Box<dyn Error>>
+{
+    let metadata = fs::symlink_metadata(source)?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_dir() {
+        fs::create_dir_all(target)?;
+        return Ok(Some(metadata.modified().ok()));
+    }
+
+    if file_type.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+    } else {
+        fs::copy(source, target)?;
+        set_modified(target, metadata.modified().ok());
+    }
+
+    Ok(None)
+}
+
+fn extract_file(
+    fs: &mut dyn DiscFilesystem,
+    entry: &DiscEntry,
+    target: &PathBuf,
+) -> Result<(), Box<dyn Error>>
+{
+    debug!("Extracting {:?} to {target:?}", entry.path);
+
+    let mut out = File::create(target)?;
+    fs.image().copy_extents(&entry.data, entry.size, &mut out)?;
+
+    let finder_info = entry.finder_info.clone().filter(|fi| !fi.is_empty());
+
+    if finder_info.is_none() && entry.resource_size == 0 {
+        return Ok(());
+    }
+
+    let resource = fs.read_resource(entry)?;
+    let header_path = apple_double::header_path(target)
+        .ok_or(format!("No AppleDouble path for {target:?}"))?;
+
+    apple_double::write(
+        &header_path,
+        finder_info.as_ref(),
+        &resource,
+        entry.created,
+        entry.modified,
+    )
+}
+
+/// Sets the modification time of an extracted file or directory.  Failing to
+/// do so is not worth failing the extraction over.
+fn set_modified(
+    target: &PathBuf,
+    modified: Option<SystemTime>,
+)
+{
+    let Some(modified) = modified else {
+        return;
+    };
+
+    File::open(target)
+        .and_then(|f| f.set_times(FileTimes::new().set_modified(modified)))
+        .unwrap_or_else(|e| {
+            warn!("Could not set the modification time of {target:?}: {e}")
+        });
+}
+
+/// Makes a name read from an image safe to use as a single component of a
+/// path on the host, so that extracting it cannot write outside the target:
+/// names may not contain "/" (or NUL), nor be "." or "..".
+pub(crate) fn path_component(name: &str) -> String
+{
+    match name {
+        | "" | "." | ".." => format!("_{name}"),
+        | _ => name.replace(['/', '\0'], "_"),
+    }
+}
+
+/// Converts seconds since 1904-01-01, as used by classic Mac OS, to a
+/// `SystemTime`.  Zero is treated as "not recorded".
+pub(crate) fn mac_time(seconds: u32) -> Option<SystemTime>
+{
+    const MAC_TO_UNIX_EPOCH: u64 = 2_082_844_800;
+
+    let seconds = seconds as u64;
+
+    match seconds {
+        | 0 => None,
+        | s if s >= MAC_TO_UNIX_EPOCH => {
+            Some(UNIX_EPOCH + Duration::from_secs(s - MAC_TO_UNIX_EPOCH))
+        }
+        | s => Some(UNIX_EPOCH - Duration::from_secs(MAC_TO_UNIX_EPOCH - s)),
+    }
+}
+
+/// Converts a calendar date and time, taken as UTC, to a `SystemTime`.
+/// Returns `None` for dates which do not exist.
+pub(crate) fn civil_time(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<SystemTime>
+{
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // Days since 1970-01-01, from Howard Hinnant's days_from_civil.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let seconds = days * 86_400
+        + hour as i64 * 3_600
+        + minute as i64 * 60
+        + second as i64;
+
+    match seconds {
+        | s if s >= 0 => Some(UNIX_EPOCH + Duration::from_secs(s as u64)),
+        | s => Some(UNIX_EPOCH - Duration::from_secs(s.unsigned_abs())),
+    }
+}
+
+/// Formats a `SystemTime` as an RFC 3339 UTC timestamp, such as
+/// "1996-04-01T12:00:00Z".
+pub fn format_time(time: SystemTime) -> String
+{
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        | Ok(d) => d.as_secs() as i64,
+        | Err(e) => -(e.duration().as_secs() as i64),
+    };
+
+    let days = seconds.div_euclid(86_400);
+    let of_day = seconds.rem_euclid(86_400);
+
+    // Howard Hinnant's civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        of_day / 3_600,
+        of_day % 3_600 / 60,
+        of_day % 60
+    )
+}
+
+pub(crate) fn le_u16(
+    b: &[u8],
+    at: usize,
+) -> u16
+{
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+pub(crate) fn le_u32(
+    b: &[u8],
+    at: usize,
+) -> u32
+{
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+pub(crate) fn be_u16(
+    b: &[u8],
+    at: usize,
+) -> u16
+{
+    u16::from_be_bytes([b[at], b[at + 1]])
+}
+
+pub(crate) fn be_u32(
+    b: &[u8],
+    at: usize,
+) -> u32
+{
+    u32::from_be_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+pub(crate) fn be_u64(
+    b: &[u8],
+    at: usize,
+) -> u64
+{
+    ((be_u32(b, at) as u64) << 32) | be_u32(b, at + 4) as u64
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_read_extents()
+    {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"hello").unwrap();
+        let mut image = DiscImage::open(&file.path().to_path_buf()).unwrap();
+        let extents = [Extent {
+            offset: 1,
+            length: 4,
+        }];
+
+        assert_eq!(b"ell".to_vec(), image.read_extents(&extents, 3).unwrap());
+        assert!(image.read_extents(&extents, 5).is_err());
+        assert!(image.read_extents(&extents, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_mac_time()
+    {
+        assert_eq!(None, mac_time(0));
+        assert_eq!(Some(UNIX_EPOCH), mac_time(2_082_844_800));
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(86_400)),
+            mac_time(2_082_844_800 + 86_400)
+        );
+    }
+
+    #[test]
+    fn test_civil_time()
+    {
+        assert_eq!(Some(UNIX_EPOCH), civil_time(1970, 1, 1, 0, 0, 0));
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            civil_time(2000, 2, 29, 0, 0, 0)
+        );
+        assert_eq!(
+            Some(UNIX_EPOCH - Duration::from_secs(86_400)),
+            civil_time(1969, 12, 31, 0, 0, 0)
+        );
+        assert_eq!(None, civil_time(1995, 13, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_format_time()
+    {
+        assert_eq!("1970-01-01T00:00:00Z", format_time(UNIX_EPOCH));
+        assert_eq!(
+            "2000-02-29T01:02:03Z",
+            format_time(civil_time(2000, 2, 29, 1, 2, 3).unwrap())
+        );
+        assert_eq!(
+            "1904-01-01T00:00:00Z",
+            format_time(mac_time(1).unwrap() - Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_listing()
+    {
+        let mut raw = [0; 32];
+        raw[..8].copy_from_slice(b"TEXTttxt");
+
+        let entries = vec![
+            DiscEntry {
+                path: PathBuf::from("Folder"),
+                is_dir: true,
+                size: 0,
+                data: vec![],
+                resource_size: 0,
+                resource: vec![],
+                created: None,
+                modified: None,
+                finder_info: None,
+            },
+            DiscEntry {
+                path: PathBuf::from("Folder/Read Me"),
+                is_dir: false,
+                size: 12,
+                data: vec![],
+                resource_size: 286,
+                resource: vec![],
+                created: None,
+                modified: None,
+                finder_info: Some(FinderInfo { raw }),
+            },
+        ];
+
+        assert_eq!(
+            format_listing(&entries).lines().collect::<Vec<_>>(),
+            vec![
+                format!("{:36}Folder/", ""),
+                format!("TEXT ttxt {:>12} {:>12} Folder/Read Me", 12, 286),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copy_tree()
+    {
+        let from = tempfile::TempDir::new().unwrap();
+        let to = tempfile::TempDir::new().unwrap();
+        let dated = UNIX_EPOCH + Duration::from_secs(946_684_800);
+
+        fs::create_dir_all(from.path().join("VIDEO_TS/EXTRA")).unwrap();
+        fs::write(from.path().join("VIDEO_TS/VTS_01_0.IFO"), "IFO").unwrap();
+        fs::write(from.path().join("README.TXT"), "Read me").unwrap();
+        File::open(from.path().join("README.TXT"))
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(dated))
+            .unwrap();
+        std::os::unix::fs::symlink("README.TXT", from.path().join("LINK"))
+            .unwrap();
+
+        assert!(copy_tree(from.path(), to.path()).unwrap().is_empty());
+
+        let copied = |path: &str| fs::read_to_string(to.path().join(path));
+        assert_eq!("IFO", copied("VIDEO_TS/VTS_01_0.IFO").unwrap());
+        assert_eq!("Read me", copied("LINK").unwrap());
+        assert!(to.path().join("VIDEO_TS/EXTRA").is_dir());
+        assert!(fs::symlink_metadata(to.path().join("LINK"))
+            .unwrap()
+            .is_symlink());
+        assert_eq!(
+            dated,
+            fs::metadata(to.path().join("README.TXT"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        );
+    }
+}
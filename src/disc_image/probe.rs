@@ -0,0 +1,410 @@
+use super::{
+    format_time,
+    iso9660,
+    open_fat_volume,
+    open_mac_volume,
+    udf,
+    video_cd,
+    DiscFilesystem,
+    DiscImage,
+};
+use log::warn;
+use serde::Serialize;
+use std::{
+    error::Error,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// How much of the start of an image must be zeros for it to count as blank
+/// when no filesystem is found.
+const BLANK_CHECK_BYTES: u64 = 64 * 2048;
+
+/// The technical metadata of every filesystem found in an image, as written
+/// to the `.fsinfo.json` sidecar.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FilesystemReport
+{
+    pub image_size: u64,
+    /// True when no filesystem was found and the image starts with zeros.
+    pub blank: bool,
+    /// Every signature found, such as "ISO 9660", "Joliet", "Rock Ridge",
+    /// "UDF", "HFS+", "FAT16" and "El Torito".
+    pub signatures: Vec<String>,
+    pub filesystems: Vec<FilesystemInfo>,
+    pub el_torito: Option<ElToritoInfo>,
+    /// Each ISO 9660 session, oldest first.  The filesystems above are
+    /// those of the last session.
+    pub sessions: Vec<SessionInfo>,
+    /// "VCD" or "SVCD" when the image is of a Video CD.
+    pub video_cd: Option<String>,
+    /// Problems with the image itself, such as a Video CD captured as an
+    /// ISO.
+    pub warnings: Vec<String>,
+}
+
+/// Identification of one filesystem.  Fields the filesystem does not record
+/// are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FilesystemInfo
+{
+    pub filesystem: String,
+    pub volume_id: String,
+    pub volume_set_id: Option<String>,
+    pub system_id: Option<String>,
+    pub publisher_id: Option<String>,
+    pub preparer_id: Option<String>,
+    pub application_id: Option<String>,
+    pub implementation_id: Option<String>,
+    /// RFC 3339 UTC timestamps.
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub block_size: u64,
+    pub block_count: Option<u64>,
+    pub file_count: Option<u64>,
+    pub directory_count: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElToritoInfo
+{
+    pub platform: String,
+    pub bootable: bool,
+    pub media_type: String,
+    pub load_sector: u32,
+    pub sector_count: u16,
+}
+
+/// Where a session of a multisession disc lies, in sectors from the start of
+/// the disc.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionInfo
+{
+    pub number: usize,
+    pub start: u64,
+    pub end: u64,
+    pub volume_id: String,
+    pub created: Option<String>,
+}
+
+/// Probes the image for each filesystem carroh recognizes.  A probe which
+/// fails is logged and left out of the report rather than failing the rest.
+pub fn probe(path: &PathBuf) -> Result<FilesystemReport, Box<dyn Error>>
+{
+    let mut image = DiscImage::open(path)?;
+
+    let mut report = FilesystemReport {
+        image_size: image.size(),
+        ..Default::default()
+    };
+
+    let sessions = iso9660::read_sessions(&mut image).unwrap_or_else(|e| {
+        warn!("Could not look for sessions in {path:?}: {e}");
+        vec![]
+    });
+    let last_session = sessions.last().map_or(0, |s| s.start);
+
+    match iso9660::read_volume_descriptors_at(&mut image, last_session) {
+        | Ok(Some(descriptors)) => {
+            add_iso9660(&mut image, descriptors, &mut report)
+        }
+        | Ok(None) => (),
+        | Err(e) => warn!("Could not probe {path:?} for ISO 9660: {e}"),
+    }
+
+    report.sessions = sessions
+        .into_iter()
+        .map(|s| {
+            SessionInfo {
+                number: s.number,
+                start: s.start,
+                end: s.end,
+                volume_id: s.volume_id,
+                created: time(s.created),
+            }
+        })
+        .collect();
+
+    match udf::read_volume(&mut image) {
+        | Ok(Some(volume)) => add_udf(volume, &mut report),
+        | Ok(None) => (),
+        | Err(e) => warn!("Could not probe {path:?} for UDF: {e}"),
+    }
+
+    for (name, open) in [
+        ("HFS/HFS+", open_mac_volume as fn(&PathBuf) -> _),
+        ("FAT", open_fat_volume),
+    ] {
+        match open(path).and_then(|v| v.map(add_volume).transpose()) {
+            | Ok(Some(info)) => {
+                report.signatures.push(info.filesystem.clone());
+                report.filesystems.push(info);
+            }
+            | Ok(None) => (),
+            | Err(e) => warn!("Could not probe {path:?} for {name}: {e}"),
+        }
+    }
+
+    if report.signatures.contains(&"ISO 9660".to_string()) {
+        match video_cd::kind_in_image(path) {
+            | Ok(Some(kind)) => {
+                report.video_cd = Some(kind.to_string());
+                report.warnings.push(video_cd::image_warning(kind));
+            }
+            | Ok(None) => (),
+            | Err(e) => warn!("Could not probe {path:?} for a Video CD: {e}"),
+        }
+    }
+
+    if report.signatures.is_empty() {
+        let len = image.size().min(BLANK_CHECK_BYTES);
+        report.blank = image.read_at(0, len as usize)?.iter().all(|b| *b == 0);
+    }
+
+    Ok(report)
+}
+
+fn add_iso9660(
+    image: &mut DiscImage,
+    descriptors: iso9660::VolumeDescriptors,
+    report: &mut FilesystemReport,
+)
+{
+    if let Some(primary) = descriptors.primary {
+        report.signatures.push("ISO 9660".to_string());
+
+        match iso9660::has_rock_ridge(image, primary.root) {
+            | Ok(true) => report.signatures.push("Rock Ridge".to_string()),
+            | Ok(false) => (),
+            | Err(e) => warn!("Could not check for Rock Ridge: {e}"),
+        }
+
+        report
+            .filesystems
+            .push(iso9660_info("ISO 9660".to_string(), primary));
+    }
+
+    if let Some(joliet) = descriptors.joliet {
+        report.signatures.push("Joliet".to_string());
+
+        let name =
+            format!("Joliet (level {})", joliet.joliet_level.unwrap_or(1));
+        report.filesystems.push(iso9660_info(name, joliet));
+    }
+
+    if let Some(el_torito) = descriptors.el_torito {
+        report.signatures.push("El Torito".to_string());
+        report.el_torito = Some(ElToritoInfo {
+            platform: el_torito.platform,
+            bootable: el_torito.bootable,
+            media_type: el_torito.media_type,
+            load_sector: el_torito.load_sector,
+            sector_count: el_torito.sector_count,
+        });
+    }
+}
+
+fn iso9660_info(
+    filesystem: String,
+    d: iso9660::VolumeDescriptor,
+) -> FilesystemInfo
+{
+    FilesystemInfo {
+        filesystem,
+        volume_id: d.volume_id,
+        volume_set_id: Some(d.volume_set_id),
+        system_id: Some(d.system_id),
+        publisher_id: Some(d.publisher_id),
+        preparer_id: Some(d.preparer_id),
+        application_id: Some(d.application_id),
+        created: time(d.created),
+        modified: time(d.modified),
+        block_size: d.logical_block_size as u64,
+        block_count: Some(d.volume_space_size as u64),
+        ..Default::default()
+    }
+}
+
+fn add_udf(
+    volume: udf::UdfVolume,
+    report: &mut FilesystemReport,
+)
+{
+    report.signatures.push("UDF".to_string());
+    report.filesystems.push(FilesystemInfo {
+        filesystem: format!("UDF (NSR0{})", volume.nsr_revision),
+        volume_id: match volume.logical_volume_id.is_empty() {
+            | true => volume.volume_id,
+            | false => volume.logical_volume_id,
+        },
+        volume_set_id: Some(volume.volume_set_id),
+        application_id: Some(volume.application_id),
+        implementation_id: Some(volume.implementation_id),
+        created: time(volume.recorded),
+        block_size: volume.block_size as u64,
+        ..Default::default()
+    });
+}
+
+fn add_volume(
+    mut volume: Box<dyn DiscFilesystem>
+) -> Result<FilesystemInfo, Box<dyn Error>>
+{
+    // Some volumes, such as HFS+, only know their name once the catalog has
+    // been read.
+    let entries = volume.entries()?;
+    let info = volume.volume_info();
+    let directories = entries.iter().filter(|e| e.is_dir).count() as u64;
+
+    Ok(FilesystemInfo {
+        filesystem: volume.filesystem_name().to_string(),
+        volume_id: volume.volume_name(),
+        created: time(info.created),
+        modified: time(info.modified),
+        block_size: info.block_size,
+        block_count: Some(info.block_count),
+        file_count: Some(entries.len() as u64 - directories),
+        directory_count: Some(directories),
+        ..Default::default()
+    })
+}
+
+fn time(t: Option<SystemTime>) -> Option<String>
+{
+    t.map(format_time)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::disc_image::{
+        hfs::tests::build_hfs_volume,
+        iso9660::tests::{
+            build_multisession,
+            build_video_cd,
+            joliet_descriptor,
+            terminator,
+            volume_descriptor,
+        },
+        udf::tests::udf_sectors,
+    };
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_image(sectors: Vec<Vec<u8>>) -> NamedTempFile
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        for s in sectors {
+            file.write_all(&s).unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_iso9660_and_udf()
+    {
+        // A UDF bridge disc: ISO 9660 and Joliet descriptors followed by the
+        // UDF recognition sequence.
+        let mut sectors = udf_sectors("BRIDGE");
+        sectors[16] = volume_descriptor(1, "BRIDGE", 300);
+        sectors[17] = joliet_descriptor("Bridge");
+        sectors[18] = terminator();
+        sectors[19][1..6].copy_from_slice(b"BEA01");
+        sectors[20][1..6].copy_from_slice(b"NSR02");
+        sectors[21][1..6].copy_from_slice(b"TEA01");
+        while sectors.len() < 300 {
+            sectors.push(vec![0; 2048]);
+        }
+
+        let file = write_image(sectors);
+        let report = probe(&PathBuf::from(file.path())).unwrap();
+
+        assert!(!report.blank);
+        assert_eq!(vec!["ISO 9660", "Joliet", "UDF"], report.signatures);
+        assert_eq!(3, report.filesystems.len());
+
+        let iso = &report.filesystems[0];
+        assert_eq!("BRIDGE", iso.volume_id);
+        assert_eq!(Some(300), iso.block_count);
+        assert_eq!(Some("TOAST".to_string()), iso.application_id);
+        assert_eq!(Some("1996-04-01T12:00:00Z".to_string()), iso.created);
+
+        assert_eq!("Joliet (level 3)", report.filesystems[1].filesystem);
+        assert_eq!("Bridge", report.filesystems[1].volume_id);
+
+        assert_eq!("UDF (NSR02)", report.filesystems[2].filesystem);
+        assert_eq!("BRIDGE", report.filesystems[2].volume_id);
+    }
+
+    #[test]
+    fn test_hfs()
+    {
+        let file = write_image(vec![build_hfs_volume()]);
+        let report = probe(&PathBuf::from(file.path())).unwrap();
+
+        assert_eq!(vec!["HFS"], report.signatures);
+        assert_eq!("Test Disc", report.filesystems[0].volume_id);
+        assert_eq!(512, report.filesystems[0].block_size);
+        assert_eq!(Some(1), report.filesystems[0].directory_count);
+        assert_eq!(Some(2), report.filesystems[0].file_count);
+    }
+
+    #[test]
+    fn test_video_cd()
+    {
+        let file = write_image(vec![build_video_cd()]);
+        let report = probe(&PathBuf::from(file.path())).unwrap();
+
+        assert_eq!(Some("VCD".to_string()), report.video_cd);
+        assert_eq!(1, report.warnings.len());
+        assert!(report.warnings[0].contains("Mode 2 Form 2"));
+    }
+
+    #[test]
+    fn test_multisession()
+    {
+        let file = write_image(vec![build_multisession()]);
+        let report = probe(&PathBuf::from(file.path())).unwrap();
+
+        assert_eq!(2, report.sessions.len());
+        assert_eq!(176, report.sessions[1].start);
+        assert_eq!("DISC_2", report.sessions[1].volume_id);
+        assert_eq!("DISC_2", report.filesystems[0].volume_id);
+    }
+
+    #[test]
+    fn test_blank()
+    {
+        let file = write_image(vec![vec![0; 2048]; 20]);
+        let report = probe(&PathBuf::from(file.path())).unwrap();
+
+        assert!(report.blank);
+        assert!(report.signatures.is_empty());
+        assert_eq!(20 * 2048, report.image_size);
+    }
+
+    #[test]
+    fn test_json()
+    {
+        let report = FilesystemReport {
+            image_size: 2048,
+            blank: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::json!({
+                "image_size": 2048,
+                "blank": true,
+                "signatures": [],
+                "filesystems": [],
+                "el_torito": null,
+                "sessions": [],
+                "video_cd": null,
+                "warnings": [],
+            }),
+            serde_json::to_value(&report).unwrap()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use super::{
+    civil_time,
+    le_u16,
+    le_u32,
+    DiscImage,
+};
+use std::{
+    error::Error,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+/// UDF on optical media uses 2048 byte sectors.
+pub const SECTOR_SIZE: u64 = 2048;
+
+/// The Volume Recognition Sequence starts at byte 32768, the same place as
+/// the ISO 9660 volume descriptors it shares the area with.
+const VOLUME_RECOGNITION_SECTOR: u64 = 16;
+
+/// Upper bound on Volume Recognition Sequence descriptors examined.
+const MAX_RECOGNITION_DESCRIPTORS: u64 = 32;
+
+/// The first Anchor Volume Descriptor Pointer is always at sector 256.
+const ANCHOR_SECTOR: u64 = 256;
+
+const PRIMARY_VOLUME_DESCRIPTOR_TAG: u16 = 1;
+const ANCHOR_VOLUME_DESCRIPTOR_POINTER_TAG: u16 = 2;
+const LOGICAL_VOLUME_DESCRIPTOR_TAG: u16 = 6;
+const TERMINATING_DESCRIPTOR_TAG: u16 = 8;
+
+/// Identification found in a UDF volume's descriptor sequence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UdfVolume
+{
+    /// The highest NSR revision in the recognition sequence (2 or 3).
+    pub nsr_revision: u8,
+    pub volume_id: String,
+    pub volume_set_id: String,
+    pub logical_volume_id: String,
+    pub application_id: String,
+    pub implementation_id: String,
+    pub recorded: Option<SystemTime>,
+    pub block_size: u32,
+}
+
+/// Reads the UDF volume descriptors.  Returns `None` when the image has no
+/// UDF Volume Recognition Sequence.
+pub fn read_volume(
+    image: &mut DiscImage
+) -> Result<Option<UdfVolume>, Box<dyn Error>>
+{
+    let Some(nsr_revision) = nsr_revision(image)? else {
+        return Ok(None);
+    };
+
+    let mut volume = UdfVolume {
+        nsr_revision,
+        ..Default::default()
+    };
+
+    let anchor = image.read_at(ANCHOR_SECTOR * SECTOR_SIZE, 512)?;
+    if le_u16(&anchor, 0) != ANCHOR_VOLUME_DESCRIPTOR_POINTER_TAG {
+        return Err("UDF anchor volume descriptor pointer not found.".into());
+    }
+
+    let length = le_u32(&anchor, 16) as u64;
+    let location = le_u32(&anchor, 20) as u64;
+
+    for n in 0..length.div_ceil(SECTOR_SIZE) {
+        let d = image.read_at((location + n) * SECTOR_SIZE, 512)?;
+
+        match le_u16(&d, 0) {
+            | PRIMARY_VOLUME_DESCRIPTOR_TAG => {
+                volume.volume_id = dstring(&d[24..56]);
+                volume.volume_set_id = dstring(&d[72..200]);
+                volume.application_id = regid(&d[344..376]);
+                volume.recorded = timestamp(&d[376..388]);
+                volume.implementation_id = regid(&d[388..420]);
+            }
+            | LOGICAL_VOLUME_DESCRIPTOR_TAG => {
+                volume.logical_volume_id = dstring(&d[84..212]);
+                volume.block_size = le_u32(&d, 212);
+            }
+            | TERMINATING_DESCRIPTOR_TAG => break,
+            | _ => (),
+        }
+    }
+
+    Ok(Some(volume))
+}
+
+/// Finds the NSR descriptor between BEA01 and TEA01 in the Volume
+/// Recognition Sequence.
+fn nsr_revision(image: &mut DiscImage) -> Result<Option<u8>, Box<dyn Error>>
+{
+    let mut extended_area = false;
+
+    for n in 0..MAX_RECOGNITION_DESCRIPTORS {
+        let at = (VOLUME_RECOGNITION_SECTOR + n) * SECTOR_SIZE;
+        if at + SECTOR_SIZE > image.size() {
+            break;
+        }
+
+        let d = image.read_at(at, 7)?;
+
+        match &d[1..6] {
+            | b"BEA01" => extended_area = true,
+            | b"NSR02" if extended_area => return Ok(Some(2)),
+            | b"NSR03" if extended_area => return Ok(Some(3)),
+            | b"TEA01" => break,
+            | b"CD001" | b"BOOT2" | b"CDW02" => (),
+            | _ => break,
+        }
+    }
+
+    Ok(None)
+}
+
+/// Decodes an OSTA compressed Unicode `dstring`, whose last byte is the
+/// used length.
+fn dstring(b: &[u8]) -> String
+{
+    let length = (b[b.len() - 1] as usize).min(b.len() - 1);
+    if length == 0 {
+        return String::new();
+    }
+
+    let bytes = &b[1..length];
+
+    match b[0] {
+        | 16 => {
+            String::from_utf16_lossy(
+                &bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        | _ => bytes.iter().map(|c| *c as char).collect(),
+    }
+}
+
+/// The identifier of an entity identifier (`regid`).
+fn regid(b: &[u8]) -> String
+{
+    String::from_utf8_lossy(&b[1..24])
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+/// Parses a 12 byte UDF timestamp, converting it to UTC when it records a
+/// time zone.
+fn timestamp(b: &[u8]) -> Option<SystemTime>
+{
+    let type_and_zone = le_u16(b, 0);
+    let year = le_u16(b, 2);
+
+    if year == 0 {
+        return None;
+    }
+
+    let local = civil_time(
+        year as i64,
+        b[4] as u32,
+        b[5] as u32,
+        b[6] as u32,
+        b[7] as u32,
+        b[8] as u32,
+    )?;
+
+    // Sign-extend the 12 bit offset in minutes.  -2047 means unspecified.
+    let offset = ((type_and_zone << 4) as i16) >> 4;
+    if type_and_zone >> 12 != 1 || offset == -2047 {
+        return Some(local);
+    }
+
+    let minutes = Duration::from_secs(offset.unsigned_abs() as u64 * 60);
+
+    Some(match offset >= 0 {
+        | true => local - minutes,
+        | false => local + minutes,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+    use crate::disc_image::format_time;
+    use std::{
+        io::Write,
+        path::PathBuf,
+    };
+    use tempfile::NamedTempFile;
+
+    fn tag(
+        sector: &mut [u8],
+        id: u16,
+    )
+    {
+        sector[0..2].copy_from_slice(&id.to_le_bytes());
+    }
+
+    fn put_dstring(
+        field: &mut [u8],
+        text: &str,
+    )
+    {
+        field[0] = 8;
+        field[1..1 + text.len()].copy_from_slice(text.as_bytes());
+        let last = field.len() - 1;
+        field[last] = text.len() as u8 + 1;
+    }
+
+    /// An image holding only a UDF volume, with its descriptor sequence at
+    /// sector 257.
+    pub(crate) fn udf_sectors(volume_id: &str) -> Vec<Vec<u8>>
+    {
+        let sector = || vec![0; SECTOR_SIZE as usize];
+        let mut sectors = vec![sector(); 16];
+
+        for id in [b"BEA01", b"NSR02", b"TEA01"] {
+            let mut s = sector();
+            s[1..6].copy_from_slice(id);
+            s[6] = 1;
+            sectors.push(s);
+        }
+        sectors.resize(ANCHOR_SECTOR as usize, sector());
+
+        let mut anchor = sector();
+        tag(&mut anchor, ANCHOR_VOLUME_DESCRIPTOR_POINTER_TAG);
+        anchor[16..20].copy_from_slice(&(3 * 2048u32).to_le_bytes());
+        anchor[20..24].copy_from_slice(&257u32.to_le_bytes());
+        sectors.push(anchor);
+
+        let mut primary = sector();
+        tag(&mut primary, PRIMARY_VOLUME_DESCRIPTOR_TAG);
+        put_dstring(&mut primary[24..56], volume_id);
+        put_dstring(&mut primary[72..200], "3F2A0000 DVD");
+        primary[345..356].copy_from_slice(b"mkisofs 2.0");
+        // 2004-05-06 07:08:09, two hours ahead of UTC.
+        primary[376..378].copy_from_slice(&(0x1000u16 | 120).to_le_bytes());
+        primary[378..380].copy_from_slice(&2004u16.to_le_bytes());
+        primary[380..385].copy_from_slice(&[5, 6, 7, 8, 9]);
+        primary[389..403].copy_from_slice(b"*Sonic Solutns");
+        sectors.push(primary);
+
+        let mut logical = sector();
+        tag(&mut logical, LOGICAL_VOLUME_DESCRIPTOR_TAG);
+        put_dstring(&mut logical[84..212], volume_id);
+        logical[212..216].copy_from_slice(&2048u32.to_le_bytes());
+        sectors.push(logical);
+
+        let mut terminator = sector();
+        tag(&mut terminator, TERMINATING_DESCRIPTOR_TAG);
+        sectors.push(terminator);
+
+        sectors
+    }
+
+    #[test]
+    fn test_read_volume()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        for s in udf_sectors("ELECTRIC_DVD") {
+            file.write_all(&s).unwrap();
+        }
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+
+        let volume = read_volume(&mut image).unwrap().unwrap();
+
+        assert_eq!(2, volume.nsr_revision);
+        assert_eq!("ELECTRIC_DVD", volume.volume_id);
+        assert_eq!("ELECTRIC_DVD", volume.logical_volume_id);
+        assert_eq!("3F2A0000 DVD", volume.volume_set_id);
+        assert_eq!("mkisofs 2.0", volume.application_id);
+        assert_eq!("*Sonic Solutns", volume.implementation_id);
+        assert_eq!(2048, volume.block_size);
+        assert_eq!(
+            "2004-05-06T05:08:09Z",
+            format_time(volume.recorded.unwrap())
+        );
+    }
+
+    #[test]
+    fn test_no_udf()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&vec![0; 40 * SECTOR_SIZE as usize]).unwrap();
+        let mut image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+
+        assert_eq!(None, read_volume(&mut image).unwrap());
+    }
+
+    #[test]
+    fn test_dstring()
+    {
+        assert_eq!("", dstring(&[0; 8]));
+        assert_eq!("AB", dstring(&[8, b'A', b'B', 0, 0, 0, 0, 3]));
+        assert_eq!("é", dstring(&[16, 0, 0xE9, 0, 0, 0, 0, 3]));
+    }
+}
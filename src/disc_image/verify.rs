@@ -0,0 +1,275 @@
+use super::{
+    DiscEntry,
+    DiscFilesystem,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    fs::{
+        self,
+        File,
+    },
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The result of comparing a file-system copy with the volume it was
+/// extracted from.  Paths are relative to the root of the volume.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport
+{
+    pub matched: usize,
+    /// On the volume, but not in the copy.
+    pub missing: Vec<PathBuf>,
+    /// In the copy, but not on the volume.
+    pub extra: Vec<PathBuf>,
+    /// In both, but with a different type, size or content.
+    pub differing: Vec<(PathBuf, String)>,
+}
+
+impl VerifyReport
+{
+    pub fn is_ok(&self) -> bool
+    {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.differing.is_empty()
+    }
+}
+
+impl fmt::Display for VerifyReport
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result
+    {
+        write!(
+            f,
+            "{} matched, {} missing, {} extra, {} differing.",
+            self.matched,
+            self.missing.len(),
+            self.extra.len(),
+            self.differing.len()
+        )?;
+
+        for path in &self.missing {
+            write!(f, "\n  Missing: {path:?}")?;
+        }
+        for path in &self.extra {
+            write!(f, "\n  Extra: {path:?}")?;
+        }
+        for (path, reason) in &self.differing {
+            write!(f, "\n  Differs: {path:?} ({reason})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares the names, sizes and SHA-256 hashes of every file on the volume
+/// with the copy in `dir`.  Top-level names for which `ignore` returns true,
+/// such as the image itself and its sidecars, are not reported as extra.
+pub fn verify(
+    fs: &mut dyn DiscFilesystem,
+    dir: &Path,
+    ignore: &dyn Fn(&str) -> bool,
+) -> Result<VerifyReport, Box<dyn Error>>
+{
+    let entries = fs.entries()?;
+
+    let mut report = VerifyReport::default();
+    let mut expected = HashSet::new();
+
+    for entry in entries {
+        let target = dir.join(&entry.path);
+        expected.insert(entry.path.clone());
+
+        let metadata = match fs::symlink_metadata(&target) {
+            | Ok(m) => m,
+            | Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                report.missing.push(entry.path);
+                continue;
+            }
+            | Err(e) => {
+                report.differing.push((entry.path, e.to_string()));
+                continue;
+            }
+        };
+
+        let difference = match (entry.is_dir, metadata.is_dir()) {
+            | (true, true) => None,
+            | (true, false) => Some("a folder on the disc".to_string()),
+            | (false, true) => Some("a file on the disc".to_string()),
+            | (false, false) if metadata.len() != entry.size => {
+                Some(format!(
+                    "{} bytes on the disc, {} bytes copied",
+                    entry.size,
+                    metadata.len()
+                ))
+            }
+            | (false, false) => {
+                match (hash_entry(fs, &entry), hash_file(&target)) {
+                    | (Err(e), _) => {
+                        Some(format!("unreadable on the disc: {e}"))
+                    }
+                    | (_, Err(e)) => Some(format!("unreadable copy: {e}")),
+                    | (Ok(disc), Ok(copy)) if disc != copy => {
+                        Some("contents differ".to_string())
+                    }
+                    | _ => None,
+                }
+            }
+        };
+
+        match difference {
+            | Some(reason) => report.differing.push((entry.path, reason)),
+            | None => report.matched += 1,
+        }
+    }
+
+    find_extra(dir, Path::new(""), &expected, ignore, &mut report.extra)?;
+    report.extra.sort();
+
+    Ok(report)
+}
+
+/// The SHA-256 hash of a file's data on the volume, as lowercase hex.
+pub fn hash_entry(
+    fs: &mut dyn DiscFilesystem,
+    entry: &DiscEntry,
+) -> Result<String, Box<dyn Error>>
+{
+    let mut hasher = Sha256::new();
+    fs.image()
+        .copy_extents(&entry.data, entry.size, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The SHA-256 hash of a file, as lowercase hex.
+pub fn hash_file(path: &Path) -> Result<String, Box<dyn Error>>
+{
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn find_extra(
+    dir: &Path,
+    relative: &Path,
+    expected: &HashSet<PathBuf>,
+    ignore: &dyn Fn(&str) -> bool,
+    extra: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>>
+{
+    for child in fs::read_dir(dir.join(relative))? {
+        let child = child?;
+        let path = relative.join(child.file_name());
+
+        if relative.as_os_str().is_empty()
+            && ignore(&child.file_name().to_string_lossy())
+        {
+            continue;
+        }
+
+        if !expected.contains(&path) {
+            extra.push(path);
+            continue;
+        }
+
+        if child.file_type()?.is_dir() {
+            find_extra(dir, &path, expected, ignore, extra)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::disc_image::{
+        extract,
+        iso9660::{
+            tests::build_iso,
+            Iso9660Volume,
+        },
+        DiscImage,
+    };
+    use std::io::Write;
+    use tempfile::{
+        NamedTempFile,
+        TempDir,
+    };
+
+    fn open_volume() -> (NamedTempFile, Iso9660Volume)
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_iso(true, false)).unwrap();
+
+        let image = DiscImage::open(&PathBuf::from(file.path())).unwrap();
+        (file, Iso9660Volume::open(image).unwrap().unwrap())
+    }
+
+    #[test]
+    fn test_matching_copy()
+    {
+        let (_file, mut volume) = open_volume();
+        let out = TempDir::new().unwrap();
+        extract(&mut volume, &out.path().to_path_buf()).unwrap();
+        fs::write(out.path().join("item.iso"), b"image").unwrap();
+
+        let report =
+            verify(&mut volume, out.path(), &|name| name == "item.iso")
+                .unwrap();
+
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(3, report.matched);
+    }
+
+    #[test]
+    fn test_damaged_copy()
+    {
+        let (_file, mut volume) = open_volume();
+        let out = TempDir::new().unwrap();
+        extract(&mut volume, &out.path().to_path_buf()).unwrap();
+
+        fs::write(out.path().join("Read Me.txt"), b"jello").unwrap();
+        fs::remove_file(out.path().join("Docs/Notes.txt")).unwrap();
+        fs::write(out.path().join("Docs/Stray.txt"), b"stray").unwrap();
+
+        let report = verify(&mut volume, out.path(), &|_| false).unwrap();
+
+        assert!(!report.is_ok());
+        assert_eq!(1, report.matched);
+        assert_eq!(vec![PathBuf::from("Docs/Notes.txt")], report.missing);
+        assert_eq!(vec![PathBuf::from("Docs/Stray.txt")], report.extra);
+        assert_eq!(
+            vec![(PathBuf::from("Read Me.txt"), "contents differ".to_string())],
+            report.differing
+        );
+    }
+
+    #[test]
+    fn test_hash_file()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"abc").unwrap();
+
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            hash_file(file.path()).unwrap()
+        );
+    }
+}
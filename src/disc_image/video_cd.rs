@@ -0,0 +1,136 @@
+use super::{
+    open_iso9660_volume,
+    DiscEntry,
+};
+use std::{
+    error::Error,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The info file each Video CD standard requires, and the name of the
+/// standard.
+const SIGNATURES: [(&str, &str); 2] =
+    [("VCD/INFO.VCD", "VCD"), ("SVCD/INFO.SVD", "SVCD")];
+
+/// The warning given for a Video CD found in a 2048-byte image.
+pub fn image_warning(kind: &str) -> String
+{
+    format!(
+        "This image holds a {kind}.  Its MPEG tracks are Mode 2 Form 2 \
+         sectors, which a 2048-byte image drops or corrupts.  Capture the \
+         disc again as a raw BIN/CUE."
+    )
+}
+
+/// The Video CD standard ("VCD" or "SVCD") whose info file is among
+/// `entries`, if any.
+pub fn kind_from_entries(entries: &[DiscEntry]) -> Option<&'static str>
+{
+    SIGNATURES
+        .iter()
+        .find(|(path, _)| {
+            entries.iter().any(|e| {
+                !e.is_dir && e.path.to_string_lossy().eq_ignore_ascii_case(path)
+            })
+        })
+        .map(|(_, kind)| *kind)
+}
+
+/// The Video CD standard of the ISO 9660 filesystem in the image or device
+/// at `path`, if it is a Video CD.
+pub fn kind_in_image(
+    path: &PathBuf
+) -> Result<Option<&'static str>, Box<dyn Error>>
+{
+    match open_iso9660_volume(path)? {
+        | Some(mut volume) => Ok(kind_from_entries(&volume.entries()?)),
+        | None => Ok(None),
+    }
+}
+
+/// The Video CD standard of a disc mounted at `dir`, if it is a Video CD.
+pub fn kind_in_directory(dir: &Path) -> Option<&'static str>
+{
+    SIGNATURES
+        .iter()
+        .find(|(path, _)| dir.join(path).is_file())
+        .map(|(_, kind)| *kind)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::disc_image::iso9660::tests::{
+        build_iso,
+        build_video_cd,
+    };
+    use std::{
+        fs,
+        io::Write,
+    };
+    use tempfile::{
+        NamedTempFile,
+        TempDir,
+    };
+
+    fn entry(path: &str) -> DiscEntry
+    {
+        DiscEntry {
+            path: PathBuf::from(path),
+            is_dir: false,
+            size: 0,
+            data: vec![],
+            resource_size: 0,
+            resource: vec![],
+            created: None,
+            modified: None,
+            finder_info: None,
+        }
+    }
+
+    #[test]
+    fn test_kind_from_entries()
+    {
+        assert_eq!(
+            Some("VCD"),
+            kind_from_entries(&[
+                entry("MPEGAV/AVSEQ01.DAT"),
+                entry("VCD/INFO.VCD")
+            ])
+        );
+        assert_eq!(Some("SVCD"), kind_from_entries(&[entry("svcd/info.svd")]));
+        assert_eq!(None, kind_from_entries(&[entry("INFO.VCD")]));
+    }
+
+    #[test]
+    fn test_kind_in_image()
+    {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_iso(false, false)).unwrap();
+
+        assert_eq!(None, kind_in_image(&PathBuf::from(file.path())).unwrap());
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&build_video_cd()).unwrap();
+
+        assert_eq!(
+            Some("VCD"),
+            kind_in_image(&PathBuf::from(file.path())).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_kind_in_directory()
+    {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(None, kind_in_directory(dir.path()));
+
+        fs::create_dir(dir.path().join("SVCD")).unwrap();
+        fs::write(dir.path().join("SVCD/INFO.SVD"), "SUPERVCD").unwrap();
+        assert_eq!(Some("SVCD"), kind_in_directory(dir.path()));
+    }
+}
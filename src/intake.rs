@@ -0,0 +1,308 @@
+//! Sharing the items of an import between several drives, so that each drive
+//! takes the next item not yet started as soon as it is free.
+
+use crate::{
+    audio_cd::ItemTags,
+    metadata::ExpectedContent,
+};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+        MutexGuard,
+        PoisonError,
+    },
+    thread,
+};
+
+/// A single disc to import: one semi-colon-separated value of an item's
+/// identifier, with what its row says about it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Item
+{
+    /// The identifier value, which names the disc's files.
+    pub cvp: String,
+    pub tags: ItemTags,
+    pub expected: ExpectedContent,
+}
+
+/// One drive's share of an import.
+pub struct Lane<'a>
+{
+    /// The drive this lane images from.
+    pub dev: String,
+    /// The number of items in the whole import.
+    pub total: usize,
+    lanes: usize,
+    prompt: &'a Mutex<()>,
+    stopped: &'a AtomicBool,
+}
+
+impl Lane<'_>
+{
+    /// Starts messages from this lane, such as "[sr0] ", so that the lanes
+    /// can be told apart.  With only one drive, this is empty.
+    pub fn label(&self) -> String
+    {
+        match self.lanes {
+            | 1 => String::new(),
+            | _ => format!("[{}] ", self.dev),
+        }
+    }
+
+    /// Waits until no other lane is prompting the user, and keeps them from
+    /// doing so until the guard is dropped.  Work which must not race with
+    /// another lane, such as claiming an output directory, is also done
+    /// while holding it.
+    pub fn prompt(&self) -> MutexGuard<'_, ()>
+    {
+        // A lane which panicked while prompting leaves nothing to clean up.
+        self.prompt.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether another lane has failed, so that this one should take no
+    /// more work.
+    pub fn stopped(&self) -> bool
+    {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// Works through `items` in order with one lane per drive in `devices`, each
+/// on its own thread, handing each item with its position (from 1) to `work`
+/// on whichever lane is free first.  Once an item fails, the lanes finish
+/// the items they have started and take no more; every failure is then
+/// returned together.
+pub fn run_lanes<T, F>(
+    devices: &[String],
+    items: Vec<T>,
+    work: F,
+) -> Result<(), Box<dyn Error>>
+where
+    T: Send,
+    F: Fn(&Lane, usize, T) -> Result<(), Box<dyn Error>> + Sync,
+{
+    let total = items.len();
+    let queue =
+        Mutex::new(items.into_iter().enumerate().collect::<VecDeque<_>>());
+
+    let next = |lane: &Lane| {
+        if lane.stopped() {
+            return None;
+        }
+
+        queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
+    };
+
+    spawn_lanes(devices, total, |lane| {
+        while let Some((i, item)) = next(lane) {
+            work(lane, i + 1, item)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Runs `work` over and over with one lane per drive in `devices`, each on
+/// its own thread, until it returns false.  Rather than being handed an
+/// item, `work` finds its own, such as from the disc it waits for.  Once a
+/// lane fails, the others stop when `work` next returns; `work` should
+/// check `Lane::stopped` while it waits.  Every failure is then returned
+/// together.
+pub fn run_watch_lanes<F>(
+    devices: &[String],
+    total: usize,
+    work: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(&Lane) -> Result<bool, Box<dyn Error>> + Sync,
+{
+    spawn_lanes(devices, total, |lane| {
+        while !lane.stopped() && work(lane)? {}
+
+        Ok(())
+    })
+}
+
+/// Runs `body` on one lane per drive in `devices`, each on its own thread,
+/// stopping the other lanes once one fails.
+fn spawn_lanes<F>(
+    devices: &[String],
+    total: usize,
+    body: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(&Lane) -> Result<(), Box<dyn Error>> + Sync,
+{
+    if devices.is_empty() {
+        return Err("No drives were given to import with.".into());
+    }
+
+    let stopped = AtomicBool::new(false);
+    let prompt = Mutex::new(());
+
+    let errors: Vec<String> = thread::scope(|s| {
+        let handles: Vec<_> = devices
+            .iter()
+            .map(|dev| {
+                let lane = Lane {
+                    dev: dev.clone(),
+                    total,
+                    lanes: devices.len(),
+                    prompt: &prompt,
+                    stopped: &stopped,
+                };
+                let body = &body;
+
+                // Errors are passed back as text, as they may not be sent
+                // between threads.
+                thread::Builder::new().name(dev.clone()).spawn_scoped(
+                    s,
+                    move || -> Result<(), String> {
+                        body(&lane).map_err(|e| {
+                            lane.stopped.store(true, Ordering::SeqCst);
+                            format!("{}{e}", lane.label())
+                        })
+                    },
+                )
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .zip(devices)
+            .filter_map(|(handle, dev)| {
+                let result = match handle {
+                    | Ok(handle) => {
+                        handle.join().unwrap_or_else(|_| {
+                            Err(format!("The lane for {dev} panicked."))
+                        })
+                    }
+                    | Err(e) => {
+                        Err(format!("Could not start a lane for {dev}: {e}"))
+                    }
+                };
+
+                result.err()
+            })
+            .collect()
+    });
+
+    match errors.is_empty() {
+        | true => Ok(()),
+        | false => Err(errors.join("\n").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::{
+        collections::HashMap,
+        time::Duration,
+    };
+
+    fn devices(names: &[&str]) -> Vec<String>
+    {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_run_lanes()
+    {
+        let done = Mutex::new(HashMap::new());
+
+        run_lanes(
+            &devices(&["sr0", "sr1", "sr2"]),
+            (1..=30).collect(),
+            |lane, position, item: usize| {
+                assert_eq!(position, item);
+                assert_eq!(30, lane.total);
+                assert_eq!(format!("[{}] ", lane.dev), lane.label());
+
+                thread::sleep(Duration::from_millis(1));
+                let previous =
+                    done.lock().unwrap().insert(item, lane.dev.clone());
+                assert_eq!(None, previous);
+
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let done = done.into_inner().unwrap();
+        assert_eq!(30, done.len());
+        assert!(done.values().any(|dev| dev != "sr0"));
+    }
+
+    #[test]
+    fn test_run_lanes_stops_after_failure()
+    {
+        let done = Mutex::new(Vec::new());
+
+        let error = run_lanes(
+            &devices(&["sr0"]),
+            vec!["a", "b", "c"],
+            |lane, _, item| {
+                assert_eq!("", lane.label());
+                done.lock().unwrap().push(item);
+
+                match item {
+                    | "b" => Err(format!("{item} was not found.").into()),
+                    | _ => Ok(()),
+                }
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!("b was not found.", error.to_string());
+        assert_eq!(vec!["a", "b"], done.into_inner().unwrap());
+
+        assert!(run_lanes(&[], vec![1], |_, _, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_run_watch_lanes()
+    {
+        let remaining = Mutex::new((1..=10).collect::<Vec<usize>>());
+        let done = Mutex::new(Vec::new());
+
+        run_watch_lanes(&devices(&["sr0", "sr1"]), 10, |lane| {
+            assert_eq!(10, lane.total);
+
+            let Some(item) = remaining.lock().unwrap().pop() else {
+                return Ok(false);
+            };
+            done.lock().unwrap().push(item);
+
+            Ok(true)
+        })
+        .unwrap();
+
+        let mut done = done.into_inner().unwrap();
+        done.sort();
+        assert_eq!((1..=10).collect::<Vec<_>>(), done);
+
+        // The failing lane stops the other, which checks while it waits.
+        let error = run_watch_lanes(&devices(&["sr0", "sr1"]), 1, |lane| {
+            match lane.dev.as_str() {
+                | "sr0" => Err("The disc could not be read.".into()),
+                | _ => {
+                    thread::sleep(Duration::from_millis(1));
+                    Ok(!lane.stopped())
+                }
+            }
+        })
+        .unwrap_err();
+        assert_eq!("[sr0] The disc could not be read.", error.to_string());
+    }
+}
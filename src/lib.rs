@@ -1,7 +1,14 @@
 #![feature(stmt_expr_attributes)]
 #![feature(absolute_path)]
 pub mod agent;
+pub mod audio_cd;
 pub mod cli;
+pub mod cli_handler;
+pub mod config;
 pub mod csv_processor;
+pub mod disc_image;
+pub mod intake;
 pub mod integration_tests;
-pub mod cli_handler;
+pub mod metadata;
+pub mod preflight;
+pub mod watch;
@@ -1,23 +1,42 @@
 #![feature(absolute_path)]
 use carroh::{
     agent::Agent,
+    audio_cd::{
+        media::{
+            MediaStatus,
+            POLL_INTERVAL,
+        },
+        ItemTags,
+        AUDIO_CD_LABEL,
+    },
     cli::Cli,
+    cli_handler::drive_identity::DriveIdentity,
     csv_processor::common::{
         header_searcher::HeaderSearcher,
         path_reader::PathReader,
     },
+    intake::{
+        self,
+        Item,
+        Lane,
+    },
+    metadata::ExpectedContent,
+    watch,
 };
 use clap::Parser;
-use inquire::{
-    Confirm,
-    Select,
-};
+use inquire::Select;
 use log::info;
 use std::{
     error::Error,
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Mutex,
+        PoisonError,
+    },
     thread,
-    time,
 };
 
 fn main() -> Result<(), Box<dyn Error>>
@@ -30,6 +49,10 @@ fn main() -> Result<(), Box<dyn Error>>
 
     let mut agent = Agent::new(args)?;
 
+    if let Some(command) = agent.command() {
+        return agent.run_command(command);
+    }
+
     // Take the first argument as the csv location.
     let input_path = agent.get_input_csv_path();
     info!("Input CSV: {input_path:?}",);
@@ -83,6 +106,19 @@ fn main() -> Result<(), Box<dyn Error>>
     let marc = agent.first_value(&"marc".to_string(), &input_path)?;
     let gcd = ogc.replace("/", "-");
 
+    // Prompt the user to select the imaging devices (imd) from the local
+    // system devices. Use the third and later arguments, or the config
+    // file's, as defaults, and find each as the system names it now.
+    let drives = agent.select_rom_devices()?;
+    for drive in &drives {
+        println!("Using {} ({}) for imaging.", drive.name, drive.given);
+    }
+
+    // Before creating anything, or asking about existing folders, check that
+    // each drive can be read, that the output parent can be written, and
+    // that the tools used can be found.
+    agent.preflight(&ofp, &drives)?;
+
     // Compute the parent directory location (pdl) as ofp/gcd + "_" + marc
     let mut pdl = ofp.clone();
     pdl.push(format!("{gcd}_{marc}"));
@@ -98,153 +134,549 @@ fn main() -> Result<(), Box<dyn Error>>
     // Handle a potentially existing rdl.
     agent.create_dir_or_prompt_if_exists(&rdl)?;
 
-    // Prompt the user to select the imaging device (imd) from the local
-    // system devices. Use third argument as default.
-    let dev = agent.select_rom_device()?;
-    info!("Using device '{dev}' for imaging.");
-
-    // For every line in the CSV:
+    let devices: Vec<String> = drives.iter().map(|d| d.name.clone()).collect();
+
+    // Find the descriptive columns used to tag audio tracks.  These are
+    // optional, so a missing column only leaves its tag out.
+    let optional_col_i =
+        |col: &str| input_path.find_single_header_index(&col.to_string()).ok();
+    let label_col_i = optional_col_i("label");
+    let partner_col_i = optional_col_i("obj_partner_name");
+    let ocn_col_i = optional_col_i(&ocn_col);
+    let date_col_i = optional_col_i("obj_created_date__date_free");
+    let aspect_ratio_col_i =
+        optional_col_i("obj_av_item_parts__ip_aspect_ratio");
+    let language_col_i = optional_col_i("obj_language__value");
+    let duration_col_i = optional_col_i("obj_prsv_duration_string");
+
+    // For every line in the CSV, queue each semi-colon-separated value in
+    // the pit (cvp) as an item to import.
+    let mut items = Vec::new();
     for row in input_path.csv()?.records() {
-        // For each semi-colon-separated value in the pit (cvp):
-        let pit_value = row?[pit_col_i].to_string();
+        let row = row?;
+
+        let pit_value = row[pit_col_i].to_string();
+
+        // Collect the row's descriptive metadata for tagging audio tracks.
+        let row_value = |i: Option<usize>| {
+            i.and_then(|i| row.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+        };
+        let tags = ItemTags {
+            title: row_value(label_col_i),
+            organization: row_value(partner_col_i),
+            call_number: row_value(ocn_col_i),
+            date: row_value(date_col_i),
+        };
+
+        // Collect what the row says about the content, to check the disc
+        // against.
+        let expected = ExpectedContent {
+            aspect_ratio: row_value(aspect_ratio_col_i),
+            language: row_value(language_col_i),
+            duration: row_value(duration_col_i),
+        };
 
         info!("All row identifiers: {pit_value}");
 
-        let all_cvps = pit_value.split(";");
+        for cvp in pit_value.split(";") {
+            items.push(Item {
+                cvp: cvp.to_string(),
+                tags: tags.clone(),
+                expected: expected.clone(),
+            });
+        }
+    }
 
-        for cvp in all_cvps {
-            info!("Working on item identifier: {cvp}");
-            // Prompt the user to locate and insert the disc associated with the
-            // cvp.
-            loop {
-                println!("Please insert disk associated with {cvp}.");
+    let drive_of = |lane: &Lane| {
+        drives
+            .iter()
+            .find(|d| d.name == lane.dev)
+            .ok_or(format!("{} is not a selected drive.", lane.dev))
+    };
+
+    // With --watch, image whichever disk is inserted into any drive as the
+    // remaining item its label matches, until none remain.
+    if agent.watching() {
+        let total = items.len();
+        let remaining = Mutex::new(items);
+
+        return intake::run_watch_lanes(&devices, total, |lane| {
+            watch_drive(&agent, lane, drive_of(lane)?, &remaining, &rdl)
+        });
+    }
 
-                // Wait for the user to press enter to continue.
-                if Confirm::new(&format!(
-                    "Is the disk associated with {cvp} inserted into {dev}? \
-                     (Yes/No)"
-                ))
-                .prompt()?
-                {
-                    break;
-                }
+    // Import the items, with each drive taking the next one as it becomes
+    // free.
+    intake::run_lanes(&devices, items, |lane, position, item| {
+        import_item(&agent, lane, drive_of(lane)?, position, item, &rdl)
+    })
+}
 
-                agent.eject_tray()?;
-            }
+/// Waits for a disk in the lane's `drive`, and imports it as the remaining
+/// item its label matches, or ejects it if it matches none.  Returns false
+/// once no items remain.
+fn watch_drive(
+    agent: &Agent,
+    lane: &Lane,
+    drive: &DriveIdentity,
+    remaining: &Mutex<Vec<Item>>,
+    rdl: &Path,
+) -> Result<bool, Box<dyn Error>>
+{
+    let dev = &lane.dev;
+    let label = lane.label();
+    let done = || {
+        lane.stopped()
+            || remaining
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_empty()
+    };
+
+    println!("{label}Waiting for a disk in {dev}.");
+    while !agent.media_ready(dev) {
+        if done() {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
 
-            // Retain the system's disk label (sdl) from the imd.
-            let sdl = {
-                let temp_sdl = agent.get_rom_device_label(&dev);
+    // Audio CDs have no label to match, so are left for an import without
+    // --watch.
+    let sdl = match agent.is_audio_disc(dev) {
+        | true => Err("it is an audio CD".into()),
+        | false => agent.wait_for_rom_device_label(dev),
+    };
+    let claimed = match sdl {
+        | Ok(sdl) => claim_item(agent, lane, &sdl, remaining)?,
+        | Err(e) => {
+            println!(
+                "{label}The disk in {dev} has no label to match ({e}), so it \
+                 will be ejected.  Import it without --watch."
+            );
+            None
+        }
+    };
 
-                match temp_sdl {
-                    | Err(_) => {
-                        let wait_sec = 5;
+    match claimed {
+        | Some((position, item)) => {
+            println!("{label}Item {position} of {}: {}", lane.total, item.cvp);
+            image_item(agent, lane, drive, item, rdl)?;
+        }
+        | None => agent.eject_tray(dev)?,
+    }
 
-                        println!(
-                            "Could not find the disk label.  Assuming the \
-                             disk is not in the drive, and waiting {wait_sec} \
-                             seconds before retrying..."
-                        );
+    wait_for_removal(agent, lane)?;
 
-                        thread::sleep(time::Duration::from_secs(wait_sec));
+    Ok(!done())
+}
 
-                        agent.get_rom_device_label(&dev)?
-                    }
-                    | Ok(sdl) => sdl,
+/// Waits for the disk in the lane's drive to be taken out, so that a disk
+/// which could not be ejected, or whose tray was pushed back in, is not
+/// imported again as the next item.  A drive which cannot tell whether it
+/// holds a disk is asked about instead.
+fn wait_for_removal(
+    agent: &Agent,
+    lane: &Lane,
+) -> Result<(), Box<dyn Error>>
+{
+    let dev = &lane.dev;
+    let label = lane.label();
+    let mut asked = false;
+
+    loop {
+        match agent.media_status(dev) {
+            | MediaStatus::Ready | MediaStatus::NotReady => {
+                if !asked {
+                    println!("{label}Please take the disk out of {dev}.");
+                    asked = true;
                 }
-            };
-
-            // Calculate the mounted location to copy from
-            #[cfg(target_os = "linux")]
-            let mount_point = {
-                let mut dev_path = PathBuf::from("/dev");
-                dev_path.push(&dev);
-                dev_path
-            };
-
-            #[cfg(target_os = "macos")]
-            let mount_point = {
-                let mut dev_path = PathBuf::from("/Volumes");
-                dev_path.push(&sdl);
-                dev_path
-            };
-
-            // Compute the cvp's file location (cfl) as rdl/cvp_sdl.
-            let mut cfl = rdl.clone();
-            cfl.push(format!("{cvp}_{sdl}"));
-
-            if cfl.exists() {
-                let cfl_s = cfl
-                    .to_str()
-                    .ok_or(format!("File dump path could not be generated."))?;
-
-                let skip_option =
-                    format!("Skip {cvp} and continue to the next identifier.");
-
-                if Select::new(
+            }
+            | MediaStatus::Unknown => {
+                let removed = "The disk has been taken out.";
+
+                // Only one drive may prompt at a time.
+                let _prompt = lane.prompt();
+
+                return match Select::new(
                     &format!(
-                        "The file dump location, {cfl_s} already exists, so \
-                         importing {cvp} cannot continue.  Would you like to \
-                         skip importing {cvp} and move on to the remaining \
-                         records?",
+                        "{label}{dev} cannot tell whether the disk is still \
+                         in it.  Please take it out."
                     ),
-                    vec!["Cancel Import and Exit Program", &skip_option],
+                    vec![removed, "Cancel Import and Exit Program"],
                 )
                 .prompt()?
-                .eq(&skip_option)
                 {
-                    continue;
-                } else {
-                    return Err("The import encountered existing files which \
-                                cannot not be overwritten, and the user \
-                                elected to cancel the import."
-                        .into());
-                }
+                    | choice if choice == removed => Ok(()),
+                    | _ => {
+                        Err("The disk was not taken out, and the user elected \
+                             to cancel the import."
+                            .into())
+                    }
+                };
             }
+            | MediaStatus::NoDisc | MediaStatus::TrayOpen => return Ok(()),
+        }
 
-            // Extract the contents of the disk to the cfl.
-            agent.copy_rec(&mount_point, &cfl)?;
+        if lane.stopped() {
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
 
-            // Compute the cvp's iso location (cil) as rdl/cvp_sdl + ".iso"
-            let mut cil = cfl.clone();
-            cil.push(format!("{cvp}_{sdl}.iso"));
+/// Takes the item which the disk label `sdl` matches from the `remaining`
+/// items, with its position in the import, asking which it is when several
+/// match.  Returns none if no item matches, or the user chooses none.
+fn claim_item(
+    agent: &Agent,
+    lane: &Lane,
+    sdl: &str,
+    remaining: &Mutex<Vec<Item>>,
+) -> Result<Option<(usize, Item)>, Box<dyn Error>>
+{
+    let label = lane.label();
+
+    // No other lane may claim an item while this one is choosing.
+    let _prompt = lane.prompt();
+    let mut remaining =
+        remaining.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let keys: Vec<Vec<&str>> = remaining.iter().map(watch::item_keys).collect();
+    let matches = watch::match_label(sdl, &keys, agent.label_pattern());
+    let cvp = |i: usize| remaining[i].cvp.clone();
+
+    let chosen = match matches[..] {
+        | [] => {
+            println!(
+                "{label}No remaining identifier matches the label {sdl:?}, so \
+                 the disk will be ejected."
+            );
+            return Ok(None);
+        }
+        | [i] => i,
+        | _ => {
+            let none = "None of these.  Eject the disk.".to_string();
+            let mut choices: Vec<String> =
+                matches.iter().map(|&i| cvp(i)).collect();
+            choices.push(none);
+
+            let choice = Select::new(
+                &format!(
+                    "{label}The label {sdl:?} matches several identifiers.  \
+                     Which is the disk?"
+                ),
+                choices,
+            )
+            .prompt()?;
+
+            match matches.iter().find(|&&i| cvp(i) == choice) {
+                | Some(&i) => i,
+                | None => return Ok(None),
+            }
+        }
+    };
 
-            if cil.exists() {
-                let cil_s = cil
-                    .to_str()
-                    .ok_or(format!("ISO path could not be generated."))?;
+    let position = lane.total - remaining.len() + 1;
+    let item = remaining.remove(chosen);
+    println!("{label}Matched the label {sdl:?} to {}.", item.cvp);
 
-                let skip_option =
-                    format!("Skip {cvp} and continue to the next identifier.");
+    Ok(Some((position, item)))
+}
 
-                if Select::new(
-                    &format!(
-                        "The iso write location, {cil_s} already exists, so \
-                         importing {cvp} cannot continue.  Would you like to \
-                         skip importing {cvp} and move on to the remaining \
-                         records?",
-                    ),
-                    vec!["Cancel Import and Exit Program", &skip_option],
+/// Prompts for the disk of `item` in the lane's `drive`, images it into the
+/// raw file directory location (rdl), ejects it, and waits for it to be
+/// taken out.
+fn import_item(
+    agent: &Agent,
+    lane: &Lane,
+    drive: &DriveIdentity,
+    position: usize,
+    item: Item,
+    rdl: &Path,
+) -> Result<(), Box<dyn Error>>
+{
+    let cvp = &item.cvp;
+    let dev = &lane.dev;
+    let label = lane.label();
+
+    info!("Working on item identifier: {cvp}");
+    println!("{label}Item {position} of {}: {cvp}", lane.total);
+
+    // Prompt the user to locate and insert the disc associated with the
+    // cvp, and carry on once the drive reports it ready.
+    loop {
+        println!("{label}Please insert disk associated with {cvp} into {dev}.");
+
+        let Err(e) = agent.wait_for_media(dev) else {
+            break;
+        };
+        println!("{label}{e}");
+
+        let wait = "Keep waiting for the disk.";
+        let eject = "Eject the tray and keep waiting.";
+        let close = "Close the tray and keep waiting.";
+
+        // Only one drive may prompt at a time, and the others wait for the
+        // answer.
+        let _prompt = lane.prompt();
+
+        match Select::new(
+            &format!("{label}The disk associated with {cvp} was not found."),
+            vec![wait, eject, close, "Cancel Import and Exit Program"],
+        )
+        .prompt()?
+        {
+            | choice if choice == wait => (),
+            | choice if choice == eject => agent.eject_tray(dev)?,
+            | choice if choice == close => agent.close_tray(dev)?,
+            | _ => {
+                return Err(format!(
+                    "No disk was inserted for {cvp}, and the user elected to \
+                     cancel the import."
                 )
-                .prompt()?
-                .eq(&skip_option)
-                {
-                    continue;
-                } else {
-                    return Err("The import encountered existing files which \
-                                cannot not be overwritten, and the user \
-                                elected to cancel the import."
-                        .into());
-                }
+                .into())
             }
+        }
+    }
+
+    image_item(agent, lane, drive, item, rdl)?;
+
+    // The next item's disk is only waited for once this one is out of the
+    // drive, so that it is not imported a second time.
+    wait_for_removal(agent, lane)
+}
 
-            // Write the imd's ISO and to cil.
-            agent.dump_iso(&mount_point, &cil)?;
+/// Images the disk of `item`, which is in the lane's `drive`, into the raw
+/// file directory location (rdl), and ejects it.
+fn image_item(
+    agent: &Agent,
+    lane: &Lane,
+    drive: &DriveIdentity,
+    item: Item,
+    rdl: &Path,
+) -> Result<(), Box<dyn Error>>
+{
+    let Item {
+        cvp,
+        tags,
+        expected,
+    } = item;
+    let dev = &lane.dev;
+    let label = lane.label();
+
+    // Audio CDs have no filesystem, and so no label, and cannot be
+    // imaged as an ISO.  Recognise them from the table of contents.
+    let is_audio = agent.is_audio_disc(dev);
+
+    // Enhanced CDs and mixed-mode discs have a filesystem, but also
+    // audio which an ISO would lose.
+    let is_mixed = !is_audio && agent.is_mixed_disc(dev);
+
+    // Retain the system's disk label (sdl) from the imd.
+    let sdl = if is_audio {
+        AUDIO_CD_LABEL.to_string()
+    } else {
+        agent.wait_for_rom_device_label(dev)?
+    };
+
+    // Keep the disc in the drive until it has been imaged, or until
+    // imaging fails.
+    let door = agent.lock_door(dev);
+
+    // Calculate the location to image from.
+    #[cfg(target_os = "linux")]
+    let mount_point = {
+        let mut dev_path = PathBuf::from("/dev");
+        dev_path.push(dev);
+        dev_path
+    };
+
+    #[cfg(target_os = "macos")]
+    let mount_point = {
+        let mut dev_path = PathBuf::from("/Volumes");
+        dev_path.push(&sdl);
+        dev_path
+    };
+
+    // Compute the cvp's file location (cfl) as rdl/cvp_sdl.
+    let mut cfl = rdl.to_path_buf();
+    cfl.push(format!("{cvp}_{sdl}"));
+
+    // Check for and create the cfl while no other drive can, so that two
+    // drives never import into the same location.
+    let prompt = lane.prompt();
+
+    if cfl.exists() {
+        let cfl_s = cfl
+            .to_str()
+            .ok_or(format!("File dump path could not be generated."))?;
+
+        // Watching, the disk is set aside without asking.
+        if agent.watching() {
+            println!(
+                "{label}The file dump location, {cfl_s} already exists, so \
+                 {cvp} is skipped."
+            );
+            drop(door);
+            agent.eject_tray(dev)?;
+            return Ok(());
+        }
 
-            // Eject the disk.
-            agent.eject_tray()?;
+        let skip_option =
+            format!("Skip {cvp} and continue to the next identifier.");
+
+        if Select::new(
+            &format!(
+                "{label}The file dump location, {cfl_s} already exists, so \
+                 importing {cvp} cannot continue.  Would you like to skip \
+                 importing {cvp} and move on to the remaining records?",
+            ),
+            vec!["Cancel Import and Exit Program", &skip_option],
+        )
+        .prompt()?
+        .eq(&skip_option)
+        {
+            drop(door);
+            agent.eject_tray(dev)?;
+            return Ok(());
+        } else {
+            return Err("The import encountered existing files which cannot \
+                        not be overwritten, and the user elected to cancel \
+                        the import."
+                .into());
         }
     }
 
+    // Create the cfl, which holds both the ISO and the file copy.
+    agent.create_directory(&cfl)?;
+    drop(prompt);
+
+    // Compute the item's metadata sidecar location as
+    // cfl/cvp_sdl + ".metadata.json"
+    let mut metadata = cfl.clone();
+    metadata.push(format!("{cvp}_{sdl}.metadata.json"));
+
+    // Record the drive the disk was read in, as named when the session
+    // started, in case its name changes by the next.
+    agent.write_provenance(&metadata, drive)?;
+
+    // Write audio CDs as cfl/cvp_sdl.bin, with a cue sheet at
+    // cfl/cvp_sdl.cue, record their checksums, and split them into
+    // cfl/cvp_sdl_tNN.wav (or .flac) as the listenable copy, then move
+    // on to the next disk.
+    if is_audio {
+        let mut bin = cfl.clone();
+        bin.push(format!("{cvp}_{sdl}.bin"));
+        let mut cue = cfl.clone();
+        cue.push(format!("{cvp}_{sdl}.cue"));
+
+        agent.rip_raw(dev, &bin, &cue, &metadata)?;
+        agent.write_audio_checksums(dev, &cue, &metadata)?;
+        agent.check_running_time(&metadata, &expected, false)?;
+        agent.split_audio(&cue, &cfl, agent.audio_format(), &tags)?;
+        drop(door);
+        agent.eject_tray(dev)?;
+        return Ok(());
+    }
+
+    // Video CDs and other discs with Mode 2 Form 2 sectors lose data
+    // in an ISO, so are written as a raw BIN/CUE instead.  Their
+    // files cannot be copied from the BIN, so move on to the next
+    // disk.
+    if let Some(reason) = agent.raw_capture_reason(dev, &mount_point) {
+        println!(
+            "WARNING: {reason}  Reading the disc as a raw BIN/CUE instead of \
+             an ISO, without a file copy."
+        );
+
+        let mut bin = cfl.clone();
+        bin.push(format!("{cvp}_{sdl}.bin"));
+        let mut cue = cfl.clone();
+        cue.push(format!("{cvp}_{sdl}.cue"));
+
+        agent.rip_raw(dev, &bin, &cue, &metadata)?;
+
+        // A VCD's MPEG tracks play at a fixed rate, so their length
+        // gives their running time.
+        let is_vcd = agent.video_cd_kind(dev, &mount_point) == Some("VCD");
+        agent.check_running_time(&metadata, &expected, is_vcd)?;
+
+        drop(door);
+        agent.eject_tray(dev)?;
+        return Ok(());
+    }
+
+    // Compute the cvp's iso location (cil) as rdl/cvp_sdl + ".iso"
+    let mut cil = cfl.clone();
+    cil.push(format!("{cvp}_{sdl}.iso"));
+
+    if is_mixed {
+        // Write the whole disc as a BIN/CUE, as for audio CDs, and
+        // then take the ISO from its data track.
+        let mut bin = cfl.clone();
+        bin.push(format!("{cvp}_{sdl}.bin"));
+        let mut cue = cfl.clone();
+        cue.push(format!("{cvp}_{sdl}.cue"));
+
+        agent.rip_raw(dev, &bin, &cue, &metadata)?;
+        agent.write_audio_checksums(dev, &cue, &metadata)?;
+        agent.split_audio(&cue, &cfl, agent.audio_format(), &tags)?;
+        agent.extract_data_track(&cue, &cil)?;
+    } else {
+        // Write the imd's ISO and to cil.
+        agent.dump_iso(dev, &mount_point, &cil)?;
+    }
+
+    // Extract the contents of the disk to the cfl, straight from the
+    // image so that every platform behaves the same and the media
+    // does not need to be mounted.  Filesystems the image cannot be
+    // read for, such as UDF alone, are copied from the mounted media
+    // instead.
+    if !agent.copy_files(&cil, &cfl)? {
+        agent.copy_mounted_files(dev, &cfl)?;
+    }
+
+    // A multisession disc's file copy is of its last session, as a
+    // mount shows.  List (or extract) the earlier sessions too, as
+    // they may hold files which were later replaced or deleted.
+    agent.copy_earlier_sessions(&cil, &cfl)?;
+
+    // Check the extracted files against the ISO, so that a bad copy
+    // is noticed while the disc is still to hand.
+    agent.verify_files(&cfl)?;
+
+    // Record the titles and streams of DVD-Video discs in the
+    // item's metadata, and check them against the row.
+    agent.write_dvd_video(&cil, &metadata, &expected)?;
+
+    // Likewise record the titles and playlists of Blu-ray discs.
+    agent.write_blu_ray(&cil, &metadata)?;
+
+    // Check the running time of DVD-Video or Blu-ray titles, or of a
+    // mixed-mode disc's audio, against the row, as a mismatch
+    // suggests the wrong disc or wrong metadata.
+    agent.check_running_time(&metadata, &expected, false)?;
+
+    // Hybrid and Mac-only discs carry an HFS or HFS+ volume which the
+    // file copy above cannot see on every platform.  Extract it, with
+    // resource forks as AppleDouble files, to cfl/cvp_sdl_hfs.
+    let mut mac_dir = cfl.clone();
+    mac_dir.push(format!("{cvp}_{sdl}_hfs"));
+    agent.extract_mac_volume(&cil, &mac_dir)?;
+
+    // Record the filesystems found in the image, with their volume
+    // identifiers and dates, at cfl/cvp_sdl.fsinfo.json.
+    let mut fsinfo = cfl.clone();
+    fsinfo.push(format!("{cvp}_{sdl}.fsinfo.json"));
+    agent.write_filesystem_report(&cil, &fsinfo)?;
+
+    // Unlock the door and eject the disk.
+    drop(door);
+    agent.eject_tray(dev)?;
+
     Ok(())
 }
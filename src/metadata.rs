@@ -0,0 +1,339 @@
+use crate::{
+    audio_cd::{
+        checksums::DiscChecksums,
+        toc::{
+            TrackLayout,
+            FRAMES_PER_SECOND,
+        },
+    },
+    cli_handler::drive_identity::DriveIdentity,
+    disc_image::{
+        blu_ray::BluRay,
+        dvd_video::DvdVideo,
+    },
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+
+/// What the program learned about an item while harvesting it, kept next to
+/// its files as `<identifier>_<label>.metadata.json`.  Each step fills in its
+/// own section, so the sidecar is read, updated and written back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemMetadata
+{
+    /// How the item was harvested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Checksums and disc IDs of an audio CD rip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_checksums: Option<DiscChecksums>,
+    /// The tracks of a disc read as raw sectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_layout: Option<Vec<TrackLayout>>,
+    /// The titles and streams of a DVD-Video disc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dvd_video: Option<DvdVideo>,
+    /// The titles and playlists of a Blu-ray disc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blu_ray: Option<BluRay>,
+    /// Where the disc disagrees with the item's row.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance
+{
+    /// The drive the disc was read in.
+    pub drive: DriveIdentity,
+}
+
+/// What an item's CSV row says about its content, to be checked against what
+/// is found on the disc.  Values the row leaves out are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedContent
+{
+    /// From the `obj_av_item_parts__ip_aspect_ratio` column, such as "4:3".
+    pub aspect_ratio: Option<String>,
+    /// From the `obj_language__value` column, as ISO 639-2 codes such as
+    /// "eng", separated by semicolons.
+    pub language: Option<String>,
+    /// From the `obj_prsv_duration_string` column, such as "0:30:07".
+    pub duration: Option<String>,
+}
+
+impl ItemMetadata
+{
+    /// Reads the sidecar at `path`, or starts an empty one if there is none.
+    pub fn load(path: &PathBuf) -> Result<ItemMetadata, Box<dyn Error>>
+    {
+        if !path.exists() {
+            return Ok(ItemMetadata::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {path:?}: {e}"))?;
+
+        serde_json::from_str(&text)
+            .map_err(|e| format!("Could not parse {path:?}: {e}").into())
+    }
+
+    pub fn save(
+        &self,
+        path: &PathBuf,
+    ) -> Result<(), Box<dyn Error>>
+    {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .map_err(|e| format!("Could not write {path:?}: {e}").into())
+    }
+
+    /// Reads the sidecar at `path`, applies `change` and writes it back.
+    pub fn update(
+        path: &PathBuf,
+        change: impl FnOnce(&mut ItemMetadata),
+    ) -> Result<(), Box<dyn Error>>
+    {
+        let mut metadata = ItemMetadata::load(path)?;
+        change(&mut metadata);
+        metadata.save(path)
+    }
+
+    /// The running times, in seconds, of the titles or tracks recorded so
+    /// far: a DVD-Video's titles, a Blu-ray's playlists, or else a CD's audio
+    /// tracks.  When `video_cd` is set, a CD's data tracks after the first
+    /// are taken to be Video CD MPEG tracks, which play at the CD's 75
+    /// sectors a second.
+    pub fn running_times(
+        &self,
+        video_cd: bool,
+    ) -> Vec<u32>
+    {
+        if let Some(dvd) = &self.dvd_video {
+            return dvd
+                .titles
+                .iter()
+                .filter_map(|t| t.duration_seconds)
+                .collect();
+        }
+
+        if let Some(blu_ray) = &self.blu_ray {
+            return blu_ray
+                .playlists
+                .iter()
+                .map(|p| p.duration_seconds)
+                .collect();
+        }
+
+        let Some(layout) = &self.track_layout else {
+            return vec![];
+        };
+
+        let first = layout.first().map(|t| t.number);
+        let is_audio = layout.iter().any(|t| t.track_type == "AUDIO");
+
+        layout
+            .iter()
+            .filter(|t| {
+                match is_audio {
+                    | true => t.track_type == "AUDIO",
+                    | false => video_cd && Some(t.number) != first,
+                }
+            })
+            .map(|t| t.end.saturating_sub(t.start) / FRAMES_PER_SECOND)
+            .collect()
+    }
+}
+
+impl ExpectedContent
+{
+    /// Compares the row's running time with `running_times`, those of the
+    /// disc's titles or tracks in seconds.  The disc matches if either its
+    /// total or its longest title is within `tolerance` seconds of the row,
+    /// as a feature may share the disc with extras.  Returns a description of
+    /// the disagreement, if there is one.
+    pub fn check_running_time(
+        &self,
+        running_times: &[u32],
+        tolerance: u32,
+    ) -> Option<String>
+    {
+        let expected = parse_duration(self.duration.as_deref()?)?;
+        let longest = *running_times.iter().max()?;
+        let total: u32 = running_times.iter().sum();
+
+        let close = |seconds: u32| seconds.abs_diff(expected) <= tolerance;
+
+        match close(total) || close(longest) {
+            | true => None,
+            | false => {
+                Some(format!(
+                    "The row gives a running time of {}, but the disc runs {} \
+                     in all, and its longest title {}.",
+                    format_duration(expected),
+                    format_duration(total),
+                    format_duration(longest)
+                ))
+            }
+        }
+    }
+}
+
+/// Parses a running time such as "1:02:03", "62:03" or "3723" as seconds.
+/// Fractions of a second are dropped.
+pub fn parse_duration(text: &str) -> Option<u32>
+{
+    let text = text.trim();
+    let whole = text.split_once('.').map_or(text, |(whole, _)| whole);
+
+    let parts: Vec<u32> = whole
+        .split(':')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    match parts[..] {
+        | [h, m, s] if m < 60 && s < 60 => Some(h * 3600 + m * 60 + s),
+        | [m, s] if s < 60 => Some(m * 60 + s),
+        | [s] => Some(s),
+        | _ => None,
+    }
+}
+
+/// Formats seconds as "H:MM:SS", as in `obj_prsv_duration_string`.
+pub fn format_duration(seconds: u32) -> String
+{
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::audio_cd::checksums::TrackChecksums;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_update()
+    {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("CR001_AUDIO_CD.metadata.json");
+
+        assert_eq!(ItemMetadata::default(), ItemMetadata::load(&path).unwrap());
+
+        let checksums = DiscChecksums {
+            accuraterip_id: "dBAR-001-00000096-00000097-02000201".to_string(),
+            freedb_id: "02000201".to_string(),
+            musicbrainz_id: "x".to_string(),
+            ctdb_toc_id: "y".to_string(),
+            ctdb_crc: "00000000".to_string(),
+            tracks: vec![TrackChecksums {
+                number: 1,
+                accuraterip_v1: "00000000".to_string(),
+                accuraterip_v2: "00000000".to_string(),
+            }],
+        };
+
+        ItemMetadata::update(&path, |m| {
+            m.audio_checksums = Some(checksums.clone())
+        })
+        .unwrap();
+
+        assert_eq!(
+            Some(checksums),
+            ItemMetadata::load(&path).unwrap().audio_checksums
+        );
+
+        fs::write(&path, "{}").unwrap();
+        assert_eq!(ItemMetadata::default(), ItemMetadata::load(&path).unwrap());
+    }
+
+    #[test]
+    fn test_parse_duration()
+    {
+        assert_eq!(Some(1807), parse_duration("0:30:07"));
+        assert_eq!(Some(3723), parse_duration(" 1:02:03.5 "));
+        assert_eq!(Some(1807), parse_duration("30:07"));
+        assert_eq!(Some(90), parse_duration("90"));
+        assert_eq!(None, parse_duration("0:75:00"));
+        assert_eq!(None, parse_duration("about half an hour"));
+        assert_eq!("1:02:03", format_duration(3723));
+    }
+
+    #[test]
+    fn test_running_times()
+    {
+        let track = |number: u8, track_type: &str, start: u32, end: u32| {
+            TrackLayout {
+                number,
+                track_type: track_type.to_string(),
+                pregap_start: None,
+                start,
+                end,
+            }
+        };
+
+        let enhanced = ItemMetadata {
+            track_layout: Some(vec![
+                track(1, "AUDIO", 0, 75 * 200),
+                track(2, "AUDIO", 75 * 200, 75 * 300),
+                track(3, "MODE1/2352", 75 * 400, 75 * 500),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(vec![200, 100], enhanced.running_times(false));
+
+        let video_cd = ItemMetadata {
+            track_layout: Some(vec![
+                track(1, "MODE2/2352", 0, 1000),
+                track(2, "MODE2/2352", 1150, 1150 + 75 * 1807),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(vec![1807], video_cd.running_times(true));
+        assert!(video_cd.running_times(false).is_empty());
+    }
+
+    #[test]
+    fn test_check_running_time()
+    {
+        let expected = ExpectedContent {
+            duration: Some("0:30:07".to_string()),
+            ..Default::default()
+        };
+
+        // The feature and its extras.
+        assert_eq!(None, expected.check_running_time(&[1800, 600], 60));
+        // Episodes which make up the running time between them.
+        assert_eq!(None, expected.check_running_time(&[900, 907], 60));
+        assert_eq!(None, expected.check_running_time(&[], 60));
+        assert_eq!(
+            Some(
+                "The row gives a running time of 0:30:07, but the disc runs \
+                 1:00:00 in all, and its longest title 0:45:00."
+                    .to_string()
+            ),
+            expected.check_running_time(&[2700, 900], 60)
+        );
+
+        let unknown = ExpectedContent {
+            duration: Some("unknown".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(None, unknown.check_running_time(&[2700], 60));
+        assert_eq!(
+            None,
+            ExpectedContent::default().check_running_time(&[2700], 60)
+        );
+    }
+}
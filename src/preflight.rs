@@ -0,0 +1,312 @@
+//! Checks made before the first item is imported, so that a missing
+//! permission or program is reported, with how to fix it, before any disc is
+//! inserted.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fmt,
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::ErrorKind,
+    os::unix::fs::{
+        MetadataExt,
+        OpenOptionsExt,
+        PermissionsExt,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Where the names of the system's groups are kept.
+pub const GROUP_PATH: &str = "/etc/group";
+
+/// An external program which a `CliHandler` runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tool
+{
+    /// The program's name, or its path.
+    pub program: String,
+    /// The operation the program is run for, as named in the config file's
+    /// `[tools]` table, such as "image".
+    pub operation: &'static str,
+    /// What the program is needed for, such as "imaging data discs".
+    pub purpose: String,
+    /// Whether every import needs the program, rather than only some discs.
+    pub required: bool,
+    /// How to install the program.
+    pub fix: String,
+}
+
+/// Something which would stop, or hamper, the import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem
+{
+    pub what: String,
+    pub fix: String,
+    /// Whether the import cannot go ahead until it is fixed.
+    pub blocking: bool,
+}
+
+impl fmt::Display for Problem
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result
+    {
+        write!(f, "{}\n  Fix: {}", self.what, self.fix)
+    }
+}
+
+/// The programs in `tools` which cannot be found in the folders of
+/// `path_var` (normally `$PATH`).
+pub fn check_tools(
+    tools: &[Tool],
+    path_var: Option<&OsStr>,
+) -> Vec<Problem>
+{
+    tools
+        .iter()
+        .filter(|tool| find_program(&tool.program, path_var).is_none())
+        .map(|tool| {
+            Problem {
+                what: format!(
+                    "{} was not found on PATH.  It is needed for {}.",
+                    tool.program, tool.purpose
+                ),
+                fix: tool.fix.clone(),
+                blocking: tool.required,
+            }
+        })
+        .collect()
+}
+
+/// Where `program` would be run from: itself, if it is a path, or else the
+/// first folder in `path_var` holding an executable file of that name.
+pub fn find_program(
+    program: &str,
+    path_var: Option<&OsStr>,
+) -> Option<PathBuf>
+{
+    let is_executable = |path: &Path| {
+        fs::metadata(path)
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    };
+
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+
+    env::split_paths(path_var?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+/// Whether the drive at `path` can be opened for reading.  Drives which can
+/// be opened, but hold no media, pass.
+pub fn check_device(path: &Path) -> Option<Problem>
+{
+    let error = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .err()?;
+
+    match error.kind() {
+        | ErrorKind::NotFound => {
+            Some(Problem {
+                what: format!("Drive {path:?} does not exist."),
+                fix: "Check that the drive is connected, and give one of the \
+                      drives the program lists when no ROM Device is given."
+                    .to_string(),
+                blocking: true,
+            })
+        }
+        | ErrorKind::PermissionDenied => {
+            let group = fs::metadata(path).ok().and_then(|m| {
+                let groups = fs::read_to_string(GROUP_PATH).ok()?;
+                group_name(m.gid(), &groups)
+            });
+
+            Some(Problem {
+                what: format!("Drive {path:?} cannot be opened for reading."),
+                fix: device_access_fix(path, group.as_deref()),
+                blocking: true,
+            })
+        }
+        | _ => None,
+    }
+}
+
+/// Whether files can be created in `dir`, or, if it has not been made yet,
+/// in the nearest folder above it.
+pub fn check_writable(dir: &Path) -> Option<Problem>
+{
+    let dir = dir.ancestors().find(|d| d.exists()).unwrap_or(dir);
+    let error = tempfile::Builder::new()
+        .prefix(".carroh-preflight")
+        .tempfile_in(dir)
+        .err()?;
+
+    let fix = match error.kind() {
+        | ErrorKind::PermissionDenied => {
+            format!(
+                "Give your user write access to {dir:?}, for example with \
+                 `sudo chown -R $USER {dir:?}`, or choose another output \
+                 parent directory."
+            )
+        }
+        | ErrorKind::ReadOnlyFilesystem => {
+            format!(
+                "{dir:?} is on a read-only filesystem.  Mount it read-write, \
+                 or choose another output parent directory."
+            )
+        }
+        | _ => {
+            "Check that the output parent directory exists and has room."
+                .to_string()
+        }
+    };
+
+    Some(Problem {
+        what: format!("Files cannot be created in {dir:?}: {error}"),
+        fix,
+        blocking: true,
+    })
+}
+
+/// The name of the group `gid`, from the text of a group file.
+pub fn group_name(
+    gid: u32,
+    group_file: &str,
+) -> Option<String>
+{
+    group_file.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id = fields.nth(1)?.parse::<u32>().ok()?;
+
+        (id == gid).then(|| name.to_string())
+    })
+}
+
+/// How to be allowed to read the drive at `path`, which the group `group`
+/// owns.  Drives owned by root's group, and drives on macOS, can only be
+/// read as root.
+fn device_access_fix(
+    path: &Path,
+    group: Option<&str>,
+) -> String
+{
+    match group {
+        | Some(group) if group != "root" && cfg!(target_os = "linux") => {
+            format!(
+                "Add your user to the `{group}` group, which owns {path:?}, \
+                 with `sudo usermod -aG {group} $USER`, then log out and back \
+                 in."
+            )
+        }
+        | _ => {
+            format!(
+                "Run the program with sudo, or give your user read access to \
+                 {path:?}."
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_tools()
+    {
+        let bin = TempDir::new().unwrap();
+        for (name, mode) in [("dd", 0o755), ("lsblk", 0o644)] {
+            let path = bin.path().join(name);
+            fs::write(&path, "").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode))
+                .unwrap();
+        }
+
+        let tool = |program: &str, required| {
+            Tool {
+                program: program.to_string(),
+                operation: "image",
+                purpose: "testing".to_string(),
+                required,
+                fix: format!("Install {program}."),
+            }
+        };
+        let path_var =
+            env::join_paths(["/nonexistent", bin.path().to_str().unwrap()])
+                .unwrap();
+
+        assert_eq!(
+            Some(bin.path().join("dd")),
+            find_program("dd", Some(&path_var))
+        );
+        assert_eq!(None, find_program("dd", None));
+
+        let problems = check_tools(
+            &[
+                tool("dd", true),
+                tool("lsblk", true),
+                tool("udisksctl", false),
+            ],
+            Some(&path_var),
+        );
+        assert_eq!(2, problems.len());
+        assert!(problems[0].blocking && !problems[1].blocking);
+        assert_eq!(
+            "lsblk was not found on PATH.  It is needed for testing.\n  Fix: \
+             Install lsblk.",
+            problems[0].to_string()
+        );
+
+        let dd = bin.path().join("dd");
+        assert!(
+            check_tools(&[tool(dd.to_str().unwrap(), true)], None).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_check_writable_and_device()
+    {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(None, check_writable(dir.path()));
+        assert_eq!(0, fs::read_dir(dir.path()).unwrap().count());
+        assert_eq!(None, check_writable(&dir.path().join("new/Raw")));
+
+        let file = dir.path().join("sr0");
+        fs::write(&file, "").unwrap();
+        assert_eq!(None, check_device(&file));
+        assert!(check_writable(&file.join("Raw")).is_some());
+
+        let missing = check_device(&dir.path().join("sr9")).unwrap();
+        assert!(missing.what.contains("does not exist"));
+    }
+
+    #[test]
+    fn test_group_name()
+    {
+        let groups = "root:x:0:\ndisk:x:6:\ncdrom:x:24:archivist\nbad line\n";
+
+        assert_eq!(Some("cdrom".to_string()), group_name(24, groups));
+        assert_eq!(None, group_name(25, groups));
+
+        let fix = device_access_fix(Path::new("/dev/sr0"), Some("cdrom"));
+        assert!(fix.contains("sudo usermod -aG cdrom $USER"));
+        let fix = device_access_fix(Path::new("/dev/sr0"), Some("root"));
+        assert!(fix.starts_with("Run the program with sudo"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Matching the label of whatever disc is inserted to the identifier it
+//! belongs to, so that `--watch` can import discs in any order without
+//! being told which is which.
+
+use crate::intake::Item;
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+
+/// How `--watch` matches disc labels, from the config file's `[watch]`
+/// table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchSettings
+{
+    /// Finds the identifier within a label, where the exact and substring
+    /// matches cannot.
+    pub label_pattern: Option<LabelPattern>,
+}
+
+/// A regular expression matched against a disc's label.  The part captured
+/// by its group named `id`, or without one, the whole match, is the
+/// identifier, such as "CSJ_(?P<id>\d+)_D\d" for the label "CSJ_0042_D1"
+/// and the identifier "0042".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct LabelPattern(Regex);
+
+impl LabelPattern
+{
+    pub fn parse(pattern: &str) -> Result<LabelPattern, Box<dyn Error>>
+    {
+        let regex = Regex::new(pattern).map_err(|e| {
+            format!("The label pattern \"{pattern}\" is invalid: {e}")
+        })?;
+
+        Ok(LabelPattern(regex))
+    }
+
+    /// The identifier the pattern finds in `label`, if it matches.
+    pub fn find<'a>(
+        &self,
+        label: &'a str,
+    ) -> Option<&'a str>
+    {
+        let captures = self.0.captures(label)?;
+
+        captures.name("id").or(captures.get(0)).map(|m| m.as_str())
+    }
+}
+
+impl PartialEq for LabelPattern
+{
+    fn eq(
+        &self,
+        other: &LabelPattern,
+    ) -> bool
+    {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for LabelPattern {}
+
+impl TryFrom<String> for LabelPattern
+{
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<LabelPattern, String>
+    {
+        LabelPattern::parse(&pattern).map_err(|e| e.to_string())
+    }
+}
+
+/// What a disc's label may be matched with: the item's identifier, and its
+/// row's call number, which may differ when the import is by temporary id.
+pub fn item_keys(item: &Item) -> Vec<&str>
+{
+    let mut keys = vec![item.cvp.as_str()];
+    keys.extend(item.tags.call_number.as_deref());
+    keys
+}
+
+/// The positions in `keys` (each item's identifiers) of the items `label`
+/// matches best, trying in turn:
+/// - an identifier which is the label,
+/// - one which is the label, ignoring case and anything but letters and digits,
+///   as disc labels are often upper case, with underscores for spaces and
+///   punctuation,
+/// - one which is what `pattern` finds in the label, compared likewise, and
+/// - the longest which are within the label, compared likewise.
+///
+/// Empty if no item matches, and several if the best match is ambiguous.
+pub fn match_label(
+    label: &str,
+    keys: &[Vec<&str>],
+    pattern: Option<&LabelPattern>,
+) -> Vec<usize>
+{
+    let positions = |matches: &dyn Fn(&str) -> bool| -> Vec<usize> {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, keys)| keys.iter().any(|k| matches(k)))
+            .map(|(i, _)| i)
+            .collect()
+    };
+
+    let exact = positions(&|key| key == label);
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    let wanted = normalize(label);
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+
+    let alike = positions(&|key| normalize(key) == wanted);
+    if !alike.is_empty() {
+        return alike;
+    }
+
+    if let Some(found) = pattern.and_then(|p| p.find(label)) {
+        let found = normalize(found);
+        let patterned = positions(&|key| normalize(key) == found);
+        if !patterned.is_empty() {
+            return patterned;
+        }
+    }
+
+    // A label holding "CSJ12" holds "CSJ1" too, so only the longest
+    // identifiers within it are taken.
+    let within = |key: &str| {
+        let key = normalize(key);
+        (!key.is_empty() && wanted.contains(&key)).then_some(key.len())
+    };
+    let longest = keys
+        .iter()
+        .flatten()
+        .filter_map(|k| within(k))
+        .max()
+        .unwrap_or(0);
+
+    positions(&|key| within(key) == Some(longest))
+}
+
+/// `text` in upper case, without anything but letters and digits.
+fn normalize(text: &str) -> String
+{
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn keys<'a>(ids: &[&'a [&'a str]]) -> Vec<Vec<&'a str>>
+    {
+        ids.iter().map(|k| k.to_vec()).collect()
+    }
+
+    #[test]
+    fn test_match_label()
+    {
+        let ids = keys(&[
+            &["csj_1"],
+            &["csj_12", "2019.0042"],
+            &["csj_3"],
+            &["CSJ_3"],
+        ]);
+
+        assert_eq!(vec![3], match_label("CSJ_3", &ids, None));
+        assert_eq!(vec![2, 3], match_label("Csj 3", &ids, None));
+        assert_eq!(vec![1], match_label("CSJ12_DISC", &ids, None));
+        assert_eq!(vec![1], match_label("2019_0042", &ids, None));
+        assert_eq!(vec![0], match_label("CSJ1", &ids, None));
+        assert!(match_label("UNTITLED", &ids, None).is_empty());
+        assert!(match_label("__", &ids, None).is_empty());
+
+        // The pattern finds CSJ_1, where 2019.0042 is the longest
+        // identifier within the label.
+        let label = "CSJ_1_2019_0042";
+        let pattern = LabelPattern::parse(r"^(?P<id>CSJ_\d+)").unwrap();
+        assert_eq!(vec![1], match_label(label, &ids, None));
+        assert_eq!(vec![0], match_label(label, &ids, Some(&pattern)));
+        assert_eq!(vec![1], match_label("CSJ_12", &ids, Some(&pattern)));
+    }
+
+    #[test]
+    fn test_label_pattern()
+    {
+        let pattern = LabelPattern::parse(r"CSJ_(?P<id>\d+)_D\d").unwrap();
+        assert_eq!(Some("0042"), pattern.find("CSJ_0042_D1"));
+        assert_eq!(None, pattern.find("UNTITLED"));
+
+        let whole = LabelPattern::parse(r"\d{4}").unwrap();
+        assert_eq!(Some("0042"), whole.find("DISC_0042"));
+
+        assert!(LabelPattern::parse("(").is_err());
+        assert_eq!(whole, LabelPattern::parse(r"\d{4}").unwrap());
+    }
+}